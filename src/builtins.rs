@@ -0,0 +1,187 @@
+//! Functions every program can call with `I IZ`, unless it declares a function with the same
+//! name. They work on BUKKITs used as lists, whose items are in the slots named `0`, `1` and so
+//! on, up to the first missing index. Other slots, such as `parent`, are not items:
+//!
+//! - `I IZ LEN YR <bukkit> MKAY` returns the number of items of the BUKKIT.
+//! - `I IZ APPEND YR <bukkit> AN YR <value> MKAY` adds the value in the slot after the last item,
+//!   and returns the index of that slot.
+//! - `I IZ REMOVE YR <bukkit> AN YR <slot> MKAY` removes the slot, named by a YARN or indexed by a
+//!   NUMBR, and returns its value. The items after a removed item move down by one, so the list
+//!   has no holes.
+//!
+//! Like other function calls, they put their result in `IT`.
+//!
+//! The methods of the native libraries imported with `CAN HAS` are built in too, named
+//! `<library>'Z <method>`, which no LOLCODE function can be named. The tree-walking interpreter
+//! calls them as the methods of the library's BUKKIT, and the other engines, which have no
+//! objects, call them by that name:
+//!
+//! - `STRING IZ LEN YR <yarn> MKAY` returns the number of characters of the YARN.
+//! - `STRING IZ AT YR <yarn> AN YR <index> MKAY` returns the character at the index of the YARN,
+//!   counting from 0.
+
+use std::rc::Rc;
+
+use crate::runtime::Runtime;
+use crate::types::{BukkitRef, RuntimeErrorKind, RuntimeResult, Value};
+use crate::variable_access::expect_bukkit;
+
+type BuiltinFunction = fn(&Rc<Runtime>, Vec<Value>) -> RuntimeResult<Value>;
+
+pub(crate) struct Builtin {
+    pub name: &'static str,
+    pub arity: usize,
+    function: BuiltinFunction,
+}
+
+const BUILTINS: [Builtin; 5] = [
+    Builtin {
+        name: "LEN",
+        arity: 1,
+        function: len,
+    },
+    Builtin {
+        name: "APPEND",
+        arity: 2,
+        function: append,
+    },
+    Builtin {
+        name: "REMOVE",
+        arity: 2,
+        function: remove,
+    },
+    Builtin {
+        name: "STRING'Z LEN",
+        arity: 1,
+        function: string_len,
+    },
+    Builtin {
+        name: "STRING'Z AT",
+        arity: 2,
+        function: string_at,
+    },
+];
+
+/// Returns the built-in function with the given name, if there is one.
+pub(crate) fn find(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+/// Returns the methods of the native library, by their name in the library.
+pub(crate) fn library_methods(
+    library: &str,
+) -> impl Iterator<Item = (&'static str, &'static Builtin)> + '_ {
+    BUILTINS.iter().filter_map(move |builtin| {
+        let (builtin_library, method) = builtin.name.split_once("'Z ")?;
+        (builtin_library == library).then_some((method, builtin))
+    })
+}
+
+impl Builtin {
+    pub fn call(&self, runtime: &Rc<Runtime>, arguments: Vec<Value>) -> RuntimeResult<Value> {
+        if arguments.len() != self.arity {
+            return Err(RuntimeErrorKind::WrongNumberOfArguments {
+                function: self.name.to_string(),
+                expected: self.arity,
+                found: arguments.len(),
+            }
+            .into());
+        }
+        (self.function)(runtime, arguments)
+    }
+}
+
+fn length(bukkit: &BukkitRef) -> i32 {
+    bukkit.borrow().length() as i32
+}
+
+fn len(_: &Rc<Runtime>, arguments: Vec<Value>) -> RuntimeResult<Value> {
+    let [bukkit]: [Value; 1] = arguments.try_into().unwrap();
+    Ok(Value::Numbr(length(&expect_bukkit(bukkit)?)))
+}
+
+fn append(runtime: &Rc<Runtime>, arguments: Vec<Value>) -> RuntimeResult<Value> {
+    let [bukkit, value]: [Value; 2] = arguments.try_into().unwrap();
+    let bukkit = expect_bukkit(bukkit)?;
+    let index = length(&bukkit);
+    bukkit.set_slot(runtime, &index.to_string(), value)?;
+    Ok(Value::Numbr(index))
+}
+
+fn remove(runtime: &Rc<Runtime>, arguments: Vec<Value>) -> RuntimeResult<Value> {
+    let [bukkit, slot]: [Value; 2] = arguments.try_into().unwrap();
+    let bukkit = expect_bukkit(bukkit)?;
+    let (slot_name, index) = match slot {
+        Value::Yarn(yarn) => {
+            let index = yarn
+                .parse::<i32>()
+                .ok()
+                .filter(|index| index.to_string() == yarn);
+            (yarn, index)
+        }
+        Value::Numbr(index) => (index.to_string(), Some(index)),
+        value => return Err(RuntimeErrorKind::InvalidSlotName(value.type_name()).into()),
+    };
+
+    let slot = |name: &str| bukkit.borrow().slots.get(name).cloned();
+    let removed =
+        slot(&slot_name).ok_or_else(|| RuntimeErrorKind::SlotNotFound(slot_name.clone()))?;
+
+    // The items after a removed item move down in place, and the last one is removed, so the
+    // other slots keep their order.
+    let mut last = slot_name;
+    let length = length(&bukkit);
+    if let Some(mut index) = index.filter(|index| (0..length).contains(index)) {
+        while index + 1 < length {
+            let next = slot(&(index + 1).to_string()).unwrap();
+            bukkit.set_slot(runtime, &index.to_string(), next)?;
+            index += 1;
+        }
+        last = index.to_string();
+    }
+    bukkit.remove_slot(runtime, &last);
+    Ok(removed)
+}
+
+fn expect_yarn(value: Value) -> RuntimeResult<String> {
+    match value {
+        Value::Yarn(yarn) => Ok(yarn),
+        value => Err(RuntimeErrorKind::InvalidType {
+            expected: "YARN",
+            found: value.type_name(),
+        }
+        .into()),
+    }
+}
+
+fn string_len(_: &Rc<Runtime>, arguments: Vec<Value>) -> RuntimeResult<Value> {
+    let [yarn]: [Value; 1] = arguments.try_into().unwrap();
+    Ok(Value::Numbr(expect_yarn(yarn)?.chars().count() as i32))
+}
+
+fn string_at(_: &Rc<Runtime>, arguments: Vec<Value>) -> RuntimeResult<Value> {
+    let [yarn, index]: [Value; 2] = arguments.try_into().unwrap();
+    let yarn = expect_yarn(yarn)?;
+    let index = match index {
+        Value::Numbr(index) => index,
+        value => {
+            return Err(RuntimeErrorKind::InvalidType {
+                expected: "NUMBR",
+                found: value.type_name(),
+            }
+            .into())
+        }
+    };
+    usize::try_from(index)
+        .ok()
+        .and_then(|index| yarn.chars().nth(index))
+        .map(|character| Value::Yarn(character.to_string()))
+        .ok_or_else(|| {
+            RuntimeErrorKind::GenericError(format!(
+                "index {} is out of range for a YARN of {} characters",
+                index,
+                yarn.chars().count()
+            ))
+            .into()
+        })
+}
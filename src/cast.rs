@@ -0,0 +1,121 @@
+use lolcode_ast::parser::expression::ASTType;
+
+use crate::error::type_name;
+use crate::types::{RuntimeErrorKind, RuntimeResult, Value};
+
+/// Formats a NUMBAR the way the spec wants it when cast to a YARN: truncated to two decimal
+/// places.
+pub fn numbar_to_yarn(numbar: f32) -> String {
+    // From 2^23 on, NUMBARs have no fractional part, and scaling them could overflow.
+    let truncated = if numbar.abs() < 8_388_608.0 {
+        (numbar * 100.0).trunc() / 100.0
+    } else {
+        numbar
+    };
+    // Adding zero turns the negative zero that -0.001 truncates to into a positive one.
+    format!("{:.2}", truncated + 0.0)
+}
+
+fn yarn_to_numbr(yarn: &str) -> Option<i32> {
+    let yarn = yarn.trim();
+    match yarn.parse::<i32>() {
+        Ok(numbr) => Some(numbr),
+        Err(_) => yarn_to_numbar(yarn).map(|numbar| numbar as i32),
+    }
+}
+
+/// Parses a YARN as a NUMBAR. Only finite numbers are accepted, so `NAN` and `INF` are not
+/// numbers, and neither are values too large for a NUMBAR.
+fn yarn_to_numbar(yarn: &str) -> Option<f32> {
+    yarn.trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|numbar| numbar.is_finite())
+}
+
+impl Value {
+    /// Explicit cast, as done by `MAEK` and `IS NOW A`. Unlike implicit casts, a NOOB can be
+    /// explicitly cast to any type, resulting in that type's default value.
+    pub fn cast(&self, to: &ASTType) -> RuntimeResult<Value> {
+        match self {
+            Value::Noob => Ok(to.into()),
+            _ => self.implicit_cast(to),
+        }
+    }
+
+    /// Implicit cast, used when an operator expects a given type. A NOOB can only be implicitly
+    /// cast to a TROOF.
+    pub fn implicit_cast(&self, to: &ASTType) -> RuntimeResult<Value> {
+        let illegal_cast = || RuntimeErrorKind::IllegalCast {
+            from: self.type_name(),
+            to: type_name(to),
+        };
+        Ok(match (self, to) {
+            (_, ASTType::Noob) => Value::Noob,
+            (_, ASTType::Troof) => Value::Troof(self.to_boolean()),
+
+            (Value::Noob, _) => return Err(illegal_cast().into()),
+
+            (Value::Troof(troof), ASTType::Numbr) => Value::Numbr(*troof as i32),
+            (Value::Troof(troof), ASTType::Numbar) => Value::Numbar(*troof as i32 as f32),
+            (Value::Troof(troof), ASTType::Yarn) => {
+                Value::Yarn(if *troof { "WIN" } else { "FAIL" }.to_string())
+            }
+
+            (Value::Numbr(numbr), ASTType::Numbr) => Value::Numbr(*numbr),
+            (Value::Numbr(numbr), ASTType::Numbar) => Value::Numbar(*numbr as f32),
+            (Value::Numbr(numbr), ASTType::Yarn) => Value::Yarn(numbr.to_string()),
+
+            (Value::Numbar(numbar), ASTType::Numbr) => Value::Numbr(*numbar as i32),
+            (Value::Numbar(numbar), ASTType::Numbar) => Value::Numbar(*numbar),
+            (Value::Numbar(numbar), ASTType::Yarn) => Value::Yarn(numbar_to_yarn(*numbar)),
+
+            (Value::Yarn(yarn), ASTType::Numbr) => {
+                Value::Numbr(yarn_to_numbr(yarn).ok_or_else(illegal_cast)?)
+            }
+            (Value::Yarn(yarn), ASTType::Numbar) => {
+                Value::Numbar(yarn_to_numbar(yarn).ok_or_else(illegal_cast)?)
+            }
+            (Value::Yarn(yarn), ASTType::Yarn) => Value::Yarn(yarn.clone()),
+
+            (Value::Bukkit(bukkit), ASTType::Bukkit) => Value::Bukkit(bukkit.clone()),
+            (Value::Bukkit(_), _) | (_, ASTType::Bukkit) => return Err(illegal_cast().into()),
+        })
+    }
+
+    /// Casts a value to either a NUMBR or a NUMBAR, as done by the math operators. A YARN becomes
+    /// a NUMBAR if it has a decimal point, and a NUMBR otherwise.
+    pub fn to_number(&self) -> RuntimeResult<Value> {
+        match self {
+            Value::Numbr(_) | Value::Numbar(_) => Ok(self.clone()),
+            Value::Yarn(yarn) if yarn.contains('.') => self.implicit_cast(&ASTType::Numbar),
+            _ => self.implicit_cast(&ASTType::Numbr),
+        }
+    }
+
+    /// Equality as done by `BOTH SAEM` and `DIFFRINT`. There is no implicit casting, except for
+    /// comparisons between a NUMBR and a NUMBAR, which are compared as NUMBARs.
+    pub fn is_saem(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Numbr(l), Value::Numbar(r)) => *l as f32 == *r,
+            (Value::Numbar(l), Value::Numbr(r)) => *l == *r as f32,
+            _ => self == other,
+        }
+    }
+
+    pub fn to_yarn(&self) -> RuntimeResult<String> {
+        match self.implicit_cast(&ASTType::Yarn)? {
+            Value::Yarn(yarn) => Ok(yarn),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Cast done by `VISIBLE`. This is the implicit YARN cast, so printing a NOOB is an error,
+    /// except that BUKKITs are printed with their [`Display`](std::fmt::Display) rendering.
+    pub fn to_visible_yarn(&self) -> RuntimeResult<String> {
+        match self {
+            Value::Bukkit(bukkit) => Ok(bukkit.to_string()),
+            _ => self.to_yarn(),
+        }
+    }
+}
@@ -0,0 +1,488 @@
+//! Static type checking. Types of variables and expressions are inferred through the program,
+//! and the operations that would always fail at runtime are reported with the same errors the
+//! interpreter raises.
+//!
+//! A type is `None` when it cannot be inferred, for example after branches that assign different
+//! types to a variable. Only definite errors are reported, so unknown types never cause one.
+//! `IS NOW A` is checked like the `MAEK` assignment it stands for. The slots of objects, and the
+//! values returned by their methods, are not inferred.
+
+use std::collections::HashMap;
+
+use lolcode_ast::parser::expression::{ASTExpression, ASTType, Identifier, VariableAccess};
+use lolcode_ast::parser::statements::assignment::VariableAssignment;
+use lolcode_ast::parser::statements::bukkit_set_slot::BukkitSetSlot;
+use lolcode_ast::parser::statements::how_iz_i::HowIzI;
+use lolcode_ast::parser::statements::i_has_a::{IHasA, IHasAInitialValue};
+use lolcode_ast::parser::statements::i_is::IIz;
+use lolcode_ast::parser::statements::im_in_yr::{ImInYr, LoopCondition, LoopIterationOperation};
+use lolcode_ast::parser::statements::o_rly::ORly;
+use lolcode_ast::parser::statements::visible::Visible;
+use lolcode_ast::parser::statements::wtf::Wtf;
+use lolcode_ast::parser::statements::ASTNode;
+use lolcode_ast::parser::ASTBlock;
+
+use crate::builtins;
+use crate::error::{expression_span, type_name, Span};
+use crate::infer::{expression_type, Inference, Operand, Type};
+use crate::operations::literal_value;
+use crate::source::{parse, Extension, Source};
+use crate::types::{RuntimeError, RuntimeErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FunctionType {
+    arity: usize,
+    returns: Type,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct CheckScope {
+    variables: HashMap<String, Type>,
+    functions: HashMap<String, FunctionType>,
+    it: Type,
+}
+
+struct Checker<'a> {
+    /// The statements of LOLCODE 1.3 that were rewritten before parsing.
+    parsed: &'a Source,
+    scopes: Vec<CheckScope>,
+    /// Types returned by each function being checked, innermost last.
+    returns: Vec<Vec<Type>>,
+    errors: Vec<RuntimeError>,
+}
+
+/// The type of a value that has one of the given types.
+fn join(types: impl IntoIterator<Item = Type>) -> Type {
+    let mut types = types.into_iter();
+    let first = types.next()??;
+    types.all(|other| other == Some(first)).then_some(first)
+}
+
+/// Merges the scopes resulting from each path the program could have taken.
+fn merge(outcomes: Vec<Vec<CheckScope>>) -> Vec<CheckScope> {
+    let mut merged = outcomes[0].clone();
+    for (index, scope) in merged.iter_mut().enumerate() {
+        for (name, value_type) in scope.variables.iter_mut() {
+            *value_type = join(
+                outcomes
+                    .iter()
+                    .map(|outcome| outcome[index].variables.get(name).copied().flatten()),
+            );
+        }
+        scope.it = join(outcomes.iter().map(|outcome| outcome[index].it));
+    }
+    merged
+}
+
+/// Checks the program, returning the errors it would definitely raise if the code containing
+/// them runs. Fails if the source does not parse.
+pub fn check(source: &str) -> Result<Vec<RuntimeError>, RuntimeError> {
+    let (ast, parsed) = parse(source)?;
+
+    let mut checker = Checker {
+        parsed: &parsed,
+        scopes: vec![CheckScope::default()],
+        returns: vec![],
+        errors: vec![],
+    };
+    checker.block(&ast);
+
+    let mut errors = checker.errors;
+    errors.sort_by_key(|error| error.span.map(|span| span.start));
+    Ok(errors)
+}
+
+impl Checker<'_> {
+    fn error(&mut self, kind: RuntimeErrorKind, span: Option<Span>) {
+        self.errors.push(RuntimeError { kind, span });
+    }
+
+    fn variable_type(&self, name: &str) -> Type {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.variables.get(name))
+            .copied()
+            .flatten()
+    }
+
+    /// Returns the type of the declared function, or else of the built-in function, with the name.
+    fn function_type(&self, name: &str) -> Option<FunctionType> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.functions.get(name))
+            .copied()
+            .or_else(|| {
+                builtins::find(name).map(|builtin| FunctionType {
+                    arity: builtin.arity,
+                    returns: None,
+                })
+            })
+    }
+
+    fn set_it(&mut self, value_type: Type) {
+        self.scopes.last_mut().unwrap().it = value_type;
+    }
+
+    /// Returns the name the identifier refers to, if it is known before running.
+    fn identifier_name(&mut self, identifier: &Identifier) -> Option<String> {
+        let name = identifier.to_string_slice();
+        if !identifier.is_srs {
+            return Some(name.to_string());
+        }
+        if let Some(found) = self.variable_type(name).filter(|found| *found != "YARN") {
+            self.error(
+                RuntimeErrorKind::CannotSRSNonYarn(found),
+                Some(identifier.into()),
+            );
+        }
+        None
+    }
+
+    fn access_type(&mut self, variable_access: &VariableAccess) -> Type {
+        let name = self.identifier_name(&variable_access.name)?;
+        let value_type = self.variable_type(&name);
+        if variable_access.accesses.is_empty() {
+            return value_type;
+        }
+        if let Some(found) = value_type.filter(|found| *found != "BUKKIT") {
+            self.error(
+                RuntimeErrorKind::InvalidType {
+                    expected: "BUKKIT",
+                    found,
+                },
+                Some(variable_access.into()),
+            );
+        }
+        None
+    }
+
+    /// Records that the variable now holds a value of the given type.
+    fn assign(&mut self, variable_access: &VariableAccess, value_type: Type) {
+        // Only the types of whole variables are tracked.
+        if variable_access.name.is_srs || !variable_access.accesses.is_empty() {
+            self.access_type(variable_access);
+            return;
+        }
+        let name = variable_access.name.to_string_slice().to_string();
+        if let Some(scope) = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.variables.contains_key(&name))
+        {
+            scope.variables.insert(name, value_type);
+        }
+    }
+
+    fn block(&mut self, block: &ASTBlock) {
+        for node in block.0.iter() {
+            self.statement(node);
+        }
+    }
+
+    fn scoped_block(&mut self, block: &ASTBlock) {
+        self.scopes.push(CheckScope::default());
+        self.block(block);
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, node: &ASTNode) {
+        match node {
+            ASTNode::HAI(_) | ASTNode::KTHXBYE(_) | ASTNode::ASTError(_) => {}
+            ASTNode::IHasA(IHasA {
+                identifier,
+                initial_value,
+            }) => {
+                let value_type = match initial_value {
+                    Some(IHasAInitialValue::Expression(expression)) => self.expression(expression),
+                    Some(IHasAInitialValue::Type(ast_type)) => Some(type_name(ast_type)),
+                    None => Some("NOOB"),
+                };
+                if let Some(name) = self.identifier_name(identifier) {
+                    let scope = self.scopes.last_mut().unwrap();
+                    scope.variables.insert(name, value_type);
+                }
+            }
+            ASTNode::ImInYr(ImInYr {
+                on_iteration,
+                condition,
+                code_block,
+                ..
+            }) => {
+                // The loop may run any number of times, including none.
+                let before = self.scopes.clone();
+                self.scopes.push(CheckScope::default());
+                if let Some(LoopCondition::TIL(expression) | LoopCondition::WILE(expression)) =
+                    condition
+                {
+                    self.expression(expression);
+                }
+                self.block(code_block);
+                if let Some(LoopIterationOperation { operand, .. }) = on_iteration {
+                    let operand_type = self.access_type(operand);
+                    if let Some(found) =
+                        operand_type.filter(|found| !matches!(*found, "NUMBR" | "NUMBAR"))
+                    {
+                        self.error(
+                            RuntimeErrorKind::InvalidType {
+                                expected: "NUMBR",
+                                found,
+                            },
+                            Some(operand.into()),
+                        );
+                    }
+                }
+                self.scopes.pop();
+                let after = std::mem::take(&mut self.scopes);
+                self.scopes = merge(vec![before, after]);
+            }
+            ASTNode::BukkitSetSlot(BukkitSetSlot { bukkit, value, .. }) => {
+                self.expression(value);
+                let bukkit_type = self.access_type(bukkit);
+                if let Some(found) = bukkit_type.filter(|found| *found != "BUKKIT") {
+                    self.error(
+                        RuntimeErrorKind::InvalidType {
+                            expected: "BUKKIT",
+                            found,
+                        },
+                        Some(bukkit.into()),
+                    );
+                }
+            }
+            ASTNode::VariableAssignment(VariableAssignment {
+                variable_access,
+                expression,
+            }) => {
+                let value_type = self.expression(expression);
+                self.assign(variable_access, value_type);
+            }
+            ASTNode::Visible(Visible(expressions, _)) => {
+                for expression in expressions.iter() {
+                    if self.expression(expression) == Some("NOOB") {
+                        self.error(
+                            RuntimeErrorKind::IllegalCast {
+                                from: "NOOB",
+                                to: "YARN",
+                            },
+                            expression_span(expression),
+                        );
+                    }
+                }
+            }
+            ASTNode::FoundYr(expression) => {
+                let value_type = self.expression(expression);
+                if let Some(returns) = self.returns.last_mut() {
+                    returns.push(value_type);
+                }
+            }
+            ASTNode::Gtfo(_) => {
+                if let Some(returns) = self.returns.last_mut() {
+                    returns.push(Some("NOOB"));
+                }
+            }
+            ASTNode::Wtf(Wtf { omg, omg_wtf }) => {
+                let before = self.scopes.clone();
+                let mut outcomes = vec![before.clone()];
+                for (expression, block) in omg.iter() {
+                    self.expression(expression);
+                    self.scoped_block(block);
+                    outcomes.push(std::mem::replace(&mut self.scopes, before.clone()));
+                }
+                if let Some(block) = omg_wtf {
+                    self.scoped_block(block);
+                    outcomes.push(std::mem::replace(&mut self.scopes, before.clone()));
+                }
+                self.scopes = merge(outcomes);
+            }
+            ASTNode::ORly(ORly {
+                if_true,
+                if_false,
+                mebbes,
+            }) => {
+                let before = self.scopes.clone();
+                let mut outcomes = vec![];
+                if let Some(block) = if_true {
+                    self.scoped_block(block);
+                    outcomes.push(std::mem::replace(&mut self.scopes, before.clone()));
+                }
+                for (expression, block) in mebbes.iter() {
+                    self.expression(expression);
+                    self.scoped_block(block);
+                    outcomes.push(std::mem::replace(&mut self.scopes, before.clone()));
+                }
+                match if_false {
+                    Some(block) => {
+                        self.scoped_block(block);
+                        outcomes.push(std::mem::take(&mut self.scopes));
+                    }
+                    None => outcomes.push(before),
+                }
+                self.scopes = merge(outcomes);
+            }
+            ASTNode::IIz(IIz { name, arguments }) => {
+                for argument in arguments.iter() {
+                    self.expression(argument);
+                }
+                if let Some(Extension::Call(_)) = self.parsed.extension(Some(name.into())) {
+                    self.set_it(None);
+                    return;
+                }
+                let function_name = name.to_string_slice();
+                let returns = match self.function_type(function_name) {
+                    Some(function) if function.arity != arguments.len() => {
+                        self.error(
+                            RuntimeErrorKind::WrongNumberOfArguments {
+                                function: function_name.to_string(),
+                                expected: function.arity,
+                                found: arguments.len(),
+                            },
+                            Some(name.into()),
+                        );
+                        None
+                    }
+                    Some(function) => function.returns,
+                    None => None,
+                };
+                self.set_it(returns);
+            }
+            ASTNode::HowIzI(how_iz_i) => {
+                let is_method = matches!(
+                    self.parsed.extension(Some((&how_iz_i.name).into())),
+                    Some(Extension::Method(_))
+                );
+                self.function(how_iz_i, !is_method);
+            }
+            ASTNode::Gimmeh(variable_access) => self.assign(variable_access, Some("YARN")),
+            ASTNode::Expression(expression) => {
+                let parsed = self.parsed;
+                match (parsed.extension(expression_span(expression)), expression) {
+                    (Some(Extension::Import(_)), _) => {}
+                    (
+                        Some(Extension::Object { body, .. }),
+                        ASTExpression::VariableAccess(variable_access),
+                    ) => {
+                        self.scoped_block(body);
+                        if let Some(name) = self.identifier_name(&variable_access.name) {
+                            let scope = self.scopes.last_mut().unwrap();
+                            scope.variables.insert(name, Some("BUKKIT"));
+                        }
+                    }
+                    _ => {
+                        let value_type = self.expression(expression);
+                        self.set_it(value_type);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks the body of the function, and declares it unless it is the method of an object.
+    fn function(&mut self, how_iz_i: &HowIzI, declare: bool) {
+        let HowIzI {
+            name,
+            arguments,
+            body,
+        } = how_iz_i;
+        let name = name.to_string_slice().to_string();
+        // Declared before checking the body, so recursive calls are known.
+        let mut function = FunctionType {
+            arity: arguments.len(),
+            returns: None,
+        };
+        if declare {
+            self.scopes
+                .last_mut()
+                .unwrap()
+                .functions
+                .insert(name.clone(), function);
+        }
+
+        let outer = self.scopes.clone();
+        let mut function_scope = CheckScope::default();
+        for argument in arguments.iter() {
+            function_scope
+                .variables
+                .insert(argument.to_string_slice().to_string(), None);
+        }
+        self.scopes.push(function_scope);
+        self.returns.push(vec![]);
+        self.block(body);
+
+        let mut returns = self.returns.pop().unwrap_or_default();
+        // Without a FOUND YR or GTFO at the end, the function returns IT.
+        if !matches!(body.0.last(), Some(ASTNode::FoundYr(_) | ASTNode::Gtfo(_))) {
+            returns.push(self.scopes.last().unwrap().it);
+        }
+        self.scopes = outer;
+
+        function.returns = join(returns);
+        if declare {
+            self.scopes
+                .last_mut()
+                .unwrap()
+                .functions
+                .insert(name, function);
+        }
+    }
+
+    /// Returns the type of the value of the expression, if it can be inferred.
+    fn expression(&mut self, expression: &ASTExpression) -> Type {
+        expression_type(self, expression)
+    }
+}
+
+impl Inference for Checker<'_> {
+    fn variable_access(&mut self, variable_access: &VariableAccess) -> Type {
+        self.access_type(variable_access)
+    }
+
+    /// Reports the operands that cannot be cast the way the operator casts them.
+    fn operand(&mut self, operand: &ASTExpression, value_type: Type, usage: Operand) {
+        let Some(from) = value_type else {
+            return;
+        };
+        let literal = match operand {
+            ASTExpression::LiteralValue(token) => Some(literal_value(token)),
+            _ => None,
+        };
+        let span = expression_span(operand);
+        match usage {
+            Operand::Compared | Operand::Troof => {}
+            Operand::Number => match from {
+                "NOOB" | "BUKKIT" => self.error(
+                    RuntimeErrorKind::InvalidType {
+                        expected: "NUMBR",
+                        found: from,
+                    },
+                    span,
+                ),
+                "YARN" if literal.is_some_and(|value| value.to_number().is_err()) => {
+                    self.error(RuntimeErrorKind::IllegalCast { from, to: "NUMBR" }, span)
+                }
+                _ => {}
+            },
+            Operand::Yarn => {
+                if matches!(from, "NOOB" | "BUKKIT") {
+                    self.error(RuntimeErrorKind::IllegalCast { from, to: "YARN" }, span);
+                }
+            }
+            Operand::Maek(ast_type) => {
+                let illegal = match (from, ast_type) {
+                    ("NOOB", _) | (_, ASTType::Noob | ASTType::Troof) => false,
+                    ("BUKKIT", ASTType::Bukkit) => false,
+                    ("BUKKIT", _) | (_, ASTType::Bukkit) => true,
+                    ("YARN", ASTType::Numbr | ASTType::Numbar) => {
+                        literal.is_some_and(|value| value.cast(ast_type).is_err())
+                    }
+                    _ => false,
+                };
+                if illegal {
+                    let to = type_name(ast_type);
+                    self.error(RuntimeErrorKind::IllegalCast { from, to }, span);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,772 @@
+//! Translation to a standalone C file.
+//!
+//! The generated code calls into the runtime in `runtime.c`, which is copied at the top of the
+//! file. Scopes are kept at runtime like in the tree-walking interpreter, so `SRS`, functions
+//! reading variables declared after them and shadowing behave the same. Every expression is
+//! evaluated into its own temporary, so operands are evaluated left to right.
+
+use std::collections::HashSet;
+
+use lolcode_ast::parser::expression::{ASTExpression, ASTType, Identifier, VariableAccess};
+use lolcode_ast::parser::statements::assignment::VariableAssignment;
+use lolcode_ast::parser::statements::bukkit_set_slot::BukkitSetSlot;
+use lolcode_ast::parser::statements::how_iz_i::HowIzI;
+use lolcode_ast::parser::statements::i_has_a::{IHasA, IHasAInitialValue};
+use lolcode_ast::parser::statements::i_is::IIz;
+use lolcode_ast::parser::statements::im_in_yr::{
+    ImInYr, LoopCondition, LoopIterationOperation, LoopOperation,
+};
+use lolcode_ast::parser::statements::o_rly::ORly;
+use lolcode_ast::parser::statements::visible::Visible;
+use lolcode_ast::parser::statements::wtf::Wtf;
+use lolcode_ast::parser::statements::ASTNode;
+use lolcode_ast::parser::ASTBlock;
+
+use crate::error::{expression_span, node_span, Span};
+use crate::operations::{literal_value, BinaryOperation};
+use crate::source::LibraryCalls;
+use crate::types::Value;
+use crate::yarn::{parse_yarn, yarn_literal, YarnPart};
+
+const RUNTIME: &str = include_str!("runtime.c");
+const INDENT: &str = "    ";
+
+/// Where `GTFO` and `FOUND YR` jump to. `depth` is the number of scopes still open at the target,
+/// so the scopes opened after it are released before jumping.
+enum Exit {
+    /// `GTFO` jumps to the label at the end of the loop.
+    Loop { label: String, depth: usize },
+    /// `GTFO` jumps to the label at the end of the `WTF?`.
+    Wtf { label: String, depth: usize },
+    /// `GTFO` and `FOUND YR` return from the function.
+    Function { depth: usize },
+    /// At the top level, `GTFO` and `FOUND YR` only skip the rest of the current statement.
+    TopLevel { label: String, depth: usize },
+}
+
+struct Generator<'a> {
+    source: &'a str,
+    library_calls: &'a LibraryCalls,
+    /// Body of the C function being generated.
+    code: String,
+    indent: usize,
+    /// The C variables of the scopes open in the function being generated, outermost first.
+    scopes: Vec<String>,
+    exits: Vec<Exit>,
+    used_labels: HashSet<String>,
+    /// Definitions of the functions declared with `HOW IZ I`.
+    functions: Vec<String>,
+    /// Used to give unique names to temporaries, scopes and labels.
+    counter: usize,
+}
+
+/// Quotes the text as a C string literal. Anything but printable ASCII is escaped in octal, so
+/// the literal does not depend on the encoding of the C file.
+fn c_string(text: &str) -> String {
+    let mut literal = String::from("\"");
+    for byte in text.bytes() {
+        match byte {
+            b'"' => literal.push_str("\\\""),
+            b'\\' => literal.push_str("\\\\"),
+            // Avoids trigraphs.
+            b'?' => literal.push_str("\\?"),
+            b' '..=b'~' => literal.push(byte as char),
+            _ => literal.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+fn c_type(ast_type: &ASTType) -> &'static str {
+    match ast_type {
+        ASTType::Yarn => "LOL_YARN",
+        ASTType::Bukkit => "LOL_BUKKIT",
+        ASTType::Numbr => "LOL_NUMBR",
+        ASTType::Numbar => "LOL_NUMBAR",
+        ASTType::Troof => "LOL_TROOF",
+        ASTType::Noob => "LOL_NOOB",
+    }
+}
+
+fn c_operation(operation: BinaryOperation) -> &'static str {
+    match operation {
+        BinaryOperation::SumOf => "LOL_SUM_OF",
+        BinaryOperation::DiffOf => "LOL_DIFF_OF",
+        BinaryOperation::ProduktOf => "LOL_PRODUKT_OF",
+        BinaryOperation::QuoshuntOf => "LOL_QUOSHUNT_OF",
+        BinaryOperation::ModOf => "LOL_MOD_OF",
+        BinaryOperation::BiggrOf => "LOL_BIGGR_OF",
+        BinaryOperation::SmallrOf => "LOL_SMALLR_OF",
+        BinaryOperation::BothSaem => "LOL_BOTH_SAEM",
+        BinaryOperation::Diffrint => "LOL_DIFFRINT",
+        BinaryOperation::WonOf => "LOL_WON_OF",
+    }
+}
+
+fn c_literal(value: &Value) -> String {
+    match value {
+        Value::Noob => "lol_noob()".to_string(),
+        Value::Troof(troof) => format!("lol_troof({})", *troof as i32),
+        Value::Numbr(i32::MIN) => "lol_numbr(INT32_MIN)".to_string(),
+        Value::Numbr(numbr) => format!("lol_numbr({})", numbr),
+        // The exponent notation keeps every digit needed to get the same float back.
+        Value::Numbar(numbar) => format!("lol_numbar({:e}f)", numbar),
+        Value::Yarn(yarn) => format!("lol_yarn({})", c_string(yarn)),
+        Value::Bukkit(_) => "lol_bukkit_new()".to_string(),
+    }
+}
+
+/// Translates the program to C. `file_name` is the name runtime errors refer to.
+pub(super) fn generate(
+    ast: &ASTBlock,
+    library_calls: &LibraryCalls,
+    source: &str,
+    file_name: &str,
+) -> Result<String, String> {
+    let mut generator = Generator {
+        source,
+        library_calls,
+        code: String::new(),
+        indent: 1,
+        scopes: vec!["s0".to_string()],
+        exits: vec![],
+        used_labels: HashSet::new(),
+        functions: vec![],
+        counter: 1,
+    };
+
+    generator.line(format!("lol_file_name = {};", c_string(file_name)));
+    generator.line("LOL_SCOPE(s0, NULL);");
+    generator.line("lol_define_builtins(s0);");
+    for node in ast.0.iter() {
+        let label = generator.fresh("lol_statement_end_");
+        generator.exits.push(Exit::TopLevel {
+            label: label.clone(),
+            depth: 1,
+        });
+        generator.line("{");
+        generator.indent += 1;
+        generator.statement(node)?;
+        generator.exits.pop();
+        generator.label(&label);
+        generator.indent -= 1;
+        generator.line("}");
+    }
+    generator.line("lol_scope_release(s0);");
+    generator.line("return 0;");
+
+    let mut output = String::from(RUNTIME);
+    for function in generator.functions.iter() {
+        output.push('\n');
+        output.push_str(function);
+    }
+    output.push_str("\nint main(void) {\n");
+    output.push_str(&generator.code);
+    output.push_str("}\n");
+    Ok(output)
+}
+
+impl Generator<'_> {
+    fn line(&mut self, line: impl AsRef<str>) {
+        for _ in 0..self.indent {
+            self.code.push_str(INDENT);
+        }
+        self.code.push_str(line.as_ref());
+        self.code.push('\n');
+    }
+
+    fn fresh(&mut self, prefix: &str) -> String {
+        self.counter += 1;
+        format!("{}{}", prefix, self.counter)
+    }
+
+    /// Emits the label, if anything jumps to it.
+    fn label(&mut self, label: &str) {
+        if self.used_labels.contains(label) {
+            self.line(format!("{}:;", label));
+        }
+    }
+
+    fn scope(&self) -> &str {
+        self.scopes.last().unwrap()
+    }
+
+    /// Sets the position reported by the runtime errors raised after this point.
+    fn at(&mut self, span: Option<Span>) {
+        let (line, column) = match span {
+            Some(span) => span.line_and_column(self.source),
+            None => (0, 0),
+        };
+        self.line(format!("lol_at({}, {});", line, column));
+    }
+
+    /// Returns a C expression evaluating to the name the identifier refers to.
+    fn name(&self, identifier: &Identifier) -> String {
+        let name = c_string(identifier.to_string_slice());
+        match identifier.is_srs {
+            true => format!("lol_srs({}, {})", self.scope(), name),
+            false => name,
+        }
+    }
+
+    /// Returns a C expression evaluating to the name of the slot accessed with `'Z`. `SRS` slot
+    /// names can also be NUMBRs.
+    fn slot_name(&self, identifier: &Identifier) -> String {
+        let name = c_string(identifier.to_string_slice());
+        match identifier.is_srs {
+            true => format!("lol_srs_slot({}, {})", self.scope(), name),
+            false => name,
+        }
+    }
+
+    /// Returns a C expression evaluating to a pointer to the accessed value. A slot that is written
+    /// to is copied into its BUKKIT first when it is inherited from a parent.
+    fn access(&self, variable_access: &VariableAccess, write: bool) -> String {
+        let mut pointer = format!(
+            "lol_lookup({}, {})",
+            self.scope(),
+            self.name(&variable_access.name)
+        );
+        let last = variable_access.accesses.len().saturating_sub(1);
+        for (index, access) in variable_access.accesses.iter().enumerate() {
+            let slot = if write && index == last {
+                "lol_own_slot"
+            } else {
+                "lol_slot"
+            };
+            pointer = format!("{}({}, {})", slot, pointer, self.slot_name(access));
+        }
+        pointer
+    }
+
+    /// Declares a temporary holding the given value, returning its name.
+    fn temporary(&mut self, value: String) -> String {
+        let temporary = self.fresh("t");
+        self.line(format!("lol_value {} = {};", temporary, value));
+        temporary
+    }
+
+    /// Releases the scopes opened after the given depth, before jumping out of them.
+    fn release_to(&mut self, depth: usize) {
+        let releases: Vec<String> = self.scopes[depth..]
+            .iter()
+            .rev()
+            .map(|scope| format!("lol_scope_release({});", scope))
+            .collect();
+        for release in releases {
+            self.line(release);
+        }
+    }
+
+    /// Runs the block in a new child scope. The header, such as an `if`, is put before the
+    /// opening brace.
+    fn scoped_block(&mut self, header: &str, block: &ASTBlock) -> Result<(), String> {
+        match header.is_empty() {
+            true => self.line("{"),
+            false => self.line(format!("{} {{", header)),
+        }
+        self.indent += 1;
+        let scope = self.fresh("s");
+        self.line(format!("LOL_SCOPE({}, {});", scope, self.scope()));
+        self.scopes.push(scope.clone());
+        self.block(block)?;
+        self.scopes.pop();
+        self.line(format!("lol_scope_release({});", scope));
+        self.indent -= 1;
+        self.line("}");
+        Ok(())
+    }
+
+    fn block(&mut self, block: &ASTBlock) -> Result<(), String> {
+        for node in block.0.iter() {
+            self.statement(node)?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, node: &ASTNode) -> Result<(), String> {
+        let span = node_span(node);
+        match node {
+            ASTNode::HAI(_) | ASTNode::KTHXBYE(_) => {}
+            ASTNode::IHasA(IHasA {
+                identifier,
+                initial_value,
+            }) => {
+                let value = match initial_value {
+                    Some(IHasAInitialValue::Expression(expression)) => {
+                        self.expression(expression)?
+                    }
+                    Some(IHasAInitialValue::Type(ast_type)) => {
+                        self.temporary(format!("lol_default({})", c_type(ast_type)))
+                    }
+                    None => self.temporary("lol_noob()".to_string()),
+                };
+                self.at(span);
+                let name = self.name(identifier);
+                self.line(format!(
+                    "lol_declare({}, {}, {});",
+                    self.scope(),
+                    name,
+                    value
+                ));
+            }
+            ASTNode::ImInYr(ImInYr {
+                on_iteration,
+                condition,
+                code_block,
+                ..
+            }) => {
+                self.line("{");
+                self.indent += 1;
+                let scope = self.fresh("s");
+                self.line(format!("LOL_SCOPE({}, {});", scope, self.scope()));
+                self.scopes.push(scope.clone());
+
+                let label = self.fresh("lol_loop_end_");
+                self.line("for (;;) {");
+                self.indent += 1;
+                match condition {
+                    None => {}
+                    Some(LoopCondition::TIL(expression)) => {
+                        let value = self.expression(expression)?;
+                        self.line(format!("if (lol_test({})) break;", value));
+                    }
+                    Some(LoopCondition::WILE(expression)) => {
+                        let value = self.expression(expression)?;
+                        self.line(format!("if (!lol_test({})) break;", value));
+                    }
+                }
+                self.exits.push(Exit::Loop {
+                    label: label.clone(),
+                    depth: self.scopes.len(),
+                });
+                self.block(code_block)?;
+                self.exits.pop();
+                if let Some(LoopIterationOperation { operation, operand }) = on_iteration {
+                    let amount = match operation {
+                        LoopOperation::UPPIN(_) => 1,
+                        LoopOperation::NERFIN(_) => -1,
+                    };
+                    self.at(span);
+                    self.line(format!(
+                        "lol_increment({}, {});",
+                        self.access(operand, true),
+                        amount
+                    ));
+                }
+                self.indent -= 1;
+                self.line("}");
+                self.label(&label);
+
+                self.scopes.pop();
+                self.line(format!("lol_scope_release({});", scope));
+                self.indent -= 1;
+                self.line("}");
+            }
+            ASTNode::BukkitSetSlot(BukkitSetSlot {
+                bukkit,
+                slot_name,
+                value,
+            }) => {
+                let value = self.expression(value)?;
+                self.at(span);
+                self.line(format!(
+                    "lol_set_slot({}, {}, {});",
+                    self.access(bukkit, true),
+                    self.slot_name(slot_name),
+                    value
+                ));
+            }
+            ASTNode::VariableAssignment(VariableAssignment {
+                variable_access,
+                expression,
+            }) => {
+                let value = self.expression(expression)?;
+                self.at(span);
+                self.line(format!(
+                    "lol_assign({}, {});",
+                    self.access(variable_access, true),
+                    value
+                ));
+            }
+            ASTNode::Visible(Visible(expressions, has_exclamation)) => {
+                for (index, expression) in expressions.iter().enumerate() {
+                    let value = self.expression(expression)?;
+                    self.at(expression_span(expression).or(span));
+                    self.line(format!("lol_print({}, {});", value, (index > 0) as i32));
+                }
+                if has_exclamation.is_none() {
+                    self.line("putchar('\\n');");
+                }
+                self.line("fflush(stdout);");
+            }
+            ASTNode::FoundYr(expression) => {
+                let value = self.expression(expression)?;
+                let exit = self
+                    .exits
+                    .iter()
+                    .rev()
+                    .find(|exit| matches!(exit, Exit::Function { .. } | Exit::TopLevel { .. }));
+                match exit {
+                    Some(&Exit::Function { depth }) => {
+                        self.release_to(depth);
+                        self.line(format!("return {};", value));
+                    }
+                    Some(Exit::TopLevel { label, depth }) => {
+                        let (label, depth) = (label.clone(), *depth);
+                        self.line(format!("lol_drop({});", value));
+                        self.jump(&label, depth);
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            ASTNode::Gtfo(_) => match self.exits.last() {
+                Some(&Exit::Function { depth }) => {
+                    self.release_to(depth);
+                    self.line("return lol_noob();");
+                }
+                Some(
+                    Exit::Loop { label, depth }
+                    | Exit::Wtf { label, depth }
+                    | Exit::TopLevel { label, depth },
+                ) => {
+                    let (label, depth) = (label.clone(), *depth);
+                    self.jump(&label, depth);
+                }
+                None => unreachable!(),
+            },
+            ASTNode::Wtf(Wtf { omg, omg_wtf }) => {
+                // Once a case matches, every following case is executed as well, until a GTFO
+                // is found.
+                self.line("{");
+                self.indent += 1;
+                let found = self.fresh("found");
+                self.line(format!("int {} = 0;", found));
+                let label = self.fresh("lol_wtf_end_");
+                self.exits.push(Exit::Wtf {
+                    label: label.clone(),
+                    depth: self.scopes.len(),
+                });
+                for (expression, block) in omg.iter() {
+                    self.line(format!("if (!{}) {{", found));
+                    self.indent += 1;
+                    let value = self.expression(expression)?;
+                    self.line(format!(
+                        "{} = lol_matches({}, {});",
+                        found,
+                        self.scope(),
+                        value
+                    ));
+                    self.indent -= 1;
+                    self.line("}");
+                    self.scoped_block(&format!("if ({})", found), block)?;
+                }
+                if let Some(block) = omg_wtf {
+                    self.scoped_block("", block)?;
+                }
+                self.exits.pop();
+                self.label(&label);
+                self.indent -= 1;
+                self.line("}");
+            }
+            ASTNode::ORly(ORly {
+                if_true,
+                if_false,
+                mebbes,
+            }) => {
+                let mut branches = 0;
+                if let Some(block) = if_true {
+                    let header = format!("if (lol_to_troof(&{}->it))", self.scope());
+                    self.scoped_block(&header, block)?;
+                    self.line("else {");
+                    self.indent += 1;
+                    branches += 1;
+                }
+                for (expression, block) in mebbes.iter() {
+                    let value = self.expression(expression)?;
+                    self.scoped_block(&format!("if (lol_test({}))", value), block)?;
+                    self.line("else {");
+                    self.indent += 1;
+                    branches += 1;
+                }
+                if let Some(block) = if_false {
+                    self.scoped_block("", block)?;
+                }
+                for _ in 0..branches {
+                    self.indent -= 1;
+                    self.line("}");
+                }
+            }
+            ASTNode::IIz(IIz { name, arguments }) => {
+                self.at(span);
+                let function = self.fresh("f");
+                self.line(format!(
+                    "lol_function {} = lol_enter_function({}, {}, {});",
+                    function,
+                    self.scope(),
+                    c_string(&self.library_calls.function_name(name)),
+                    arguments.len()
+                ));
+                self.line("{");
+                self.indent += 1;
+                let function_scope = self.fresh("s");
+                self.line(format!(
+                    "LOL_SCOPE({}, {}.scope);",
+                    function_scope, function
+                ));
+                // The arguments are evaluated in the caller's scope.
+                for (index, argument) in arguments.iter().enumerate() {
+                    let value = self.expression(argument)?;
+                    self.line(format!(
+                        "lol_declare({}, {}.parameters[{}], {});",
+                        function_scope, function, index, value
+                    ));
+                }
+                let result = self.temporary(format!("{}.body({})", function, function_scope));
+                self.line(format!("lol_scope_release({});", function_scope));
+                self.line("lol_leave_function();");
+                self.line(format!("lol_set_it({}, {});", self.scope(), result));
+                self.indent -= 1;
+                self.line("}");
+            }
+            ASTNode::HowIzI(how_iz_i) => self.function(how_iz_i)?,
+            ASTNode::Gimmeh(variable_access) => {
+                let value = self.temporary("lol_read_line()".to_string());
+                self.at(span);
+                self.line(format!(
+                    "lol_assign({}, {});",
+                    self.access(variable_access, true),
+                    value
+                ));
+            }
+            ASTNode::Expression(expression) => {
+                let value = self.expression(expression)?;
+                self.line(format!("lol_set_it({}, {});", self.scope(), value));
+            }
+            ASTNode::ASTError(error) => return Err(format!("{:?}", error)),
+        };
+        Ok(())
+    }
+
+    fn jump(&mut self, label: &str, depth: usize) {
+        self.release_to(depth);
+        self.line(format!("goto {};", label));
+        self.used_labels.insert(label.to_string());
+    }
+
+    /// Generates the C function for a `HOW IZ I`, and defines it in the current scope.
+    fn function(&mut self, how_iz_i: &HowIzI) -> Result<(), String> {
+        let HowIzI {
+            name,
+            arguments,
+            body,
+            ..
+        } = how_iz_i;
+        let function = self.fresh("lol_function_");
+        let scope = self.fresh("s");
+
+        let code = std::mem::take(&mut self.code);
+        let indent = std::mem::replace(&mut self.indent, 1);
+        let scopes = std::mem::replace(&mut self.scopes, vec![scope.clone()]);
+        let exits = std::mem::replace(&mut self.exits, vec![Exit::Function { depth: 1 }]);
+        let result = self.block(body);
+        self.line(format!("return lol_take_it({});", scope));
+        let function_body = std::mem::replace(&mut self.code, code);
+        self.indent = indent;
+        self.scopes = scopes;
+        self.exits = exits;
+        result?;
+
+        let parameters = match arguments.is_empty() {
+            true => "NULL".to_string(),
+            false => {
+                let names: Vec<String> = arguments
+                    .iter()
+                    .map(|argument| c_string(argument.to_string_slice()))
+                    .collect();
+                self.functions.push(format!(
+                    "static const char *const {}_parameters[] = {{{}}};\n",
+                    function,
+                    names.join(", ")
+                ));
+                format!("{}_parameters", function)
+            }
+        };
+        self.functions.push(format!(
+            "/* HOW IZ I {} */\nstatic lol_value {}(lol_scope *const {}) {{\n{}}}\n",
+            name.to_string_slice().replace("*/", "* /"),
+            function,
+            scope,
+            function_body
+        ));
+
+        self.line(format!(
+            "lol_define({}, {}, {}, {}, {});",
+            self.scope(),
+            c_string(name.to_string_slice()),
+            function,
+            arguments.len(),
+            parameters
+        ));
+        Ok(())
+    }
+
+    /// Evaluates the expression into a new temporary, returning its name.
+    fn expression(&mut self, expression: &ASTExpression) -> Result<String, String> {
+        let span = expression_span(expression);
+        Ok(match expression {
+            ASTExpression::LiteralValue(token) => match yarn_literal(token) {
+                Some(raw) => self.yarn(raw, span),
+                None => self.temporary(c_literal(&literal_value(token))),
+            },
+            ASTExpression::VariableAccess(variable_access) => {
+                self.at(span);
+                self.temporary(format!("lol_copy({})", self.access(variable_access, false)))
+            }
+            ASTExpression::BothOf(left, right) => {
+                self.short_circuit(&[left.as_ref(), right.as_ref()], true)?
+            }
+            ASTExpression::EitherOf(left, right) => {
+                self.short_circuit(&[left.as_ref(), right.as_ref()], false)?
+            }
+            ASTExpression::AllOf(values) => {
+                self.short_circuit(&values.iter().collect::<Vec<_>>(), true)?
+            }
+            ASTExpression::AnyOf(values) => {
+                self.short_circuit(&values.iter().collect::<Vec<_>>(), false)?
+            }
+            ASTExpression::Not(expression) => {
+                let value = self.expression(expression)?;
+                self.temporary(format!("lol_not({})", value))
+            }
+            ASTExpression::WonOf(left, right) => {
+                self.binary(BinaryOperation::WonOf, left, right, span)?
+            }
+            ASTExpression::SumOf(left, right) => {
+                self.binary(BinaryOperation::SumOf, left, right, span)?
+            }
+            ASTExpression::DiffOf(left, right) => {
+                self.binary(BinaryOperation::DiffOf, left, right, span)?
+            }
+            ASTExpression::ProduktOf(left, right) => {
+                self.binary(BinaryOperation::ProduktOf, left, right, span)?
+            }
+            ASTExpression::QuoshuntOf(left, right) => {
+                self.binary(BinaryOperation::QuoshuntOf, left, right, span)?
+            }
+            ASTExpression::ModOf(left, right) => {
+                self.binary(BinaryOperation::ModOf, left, right, span)?
+            }
+            ASTExpression::BiggrOf(left, right) => {
+                self.binary(BinaryOperation::BiggrOf, left, right, span)?
+            }
+            ASTExpression::SmallrOf(left, right) => {
+                self.binary(BinaryOperation::SmallrOf, left, right, span)?
+            }
+            ASTExpression::BothSaem(left, right) => {
+                self.binary(BinaryOperation::BothSaem, left, right, span)?
+            }
+            ASTExpression::Diffrint(left, right) => {
+                self.binary(BinaryOperation::Diffrint, left, right, span)?
+            }
+            ASTExpression::Smoosh(values) => {
+                let result = self.temporary("lol_yarn(\"\")".to_string());
+                for value in values.iter() {
+                    let value = self.expression(value)?;
+                    self.at(span);
+                    self.line(format!("{} = lol_smoosh({}, {});", result, result, value));
+                }
+                result
+            }
+            ASTExpression::Maek(expression, ast_type) => {
+                let value = self.expression(expression)?;
+                self.at(span);
+                self.temporary(format!("lol_cast({}, {})", value, c_type(ast_type)))
+            }
+        })
+    }
+
+    fn binary(
+        &mut self,
+        operation: BinaryOperation,
+        left: &ASTExpression,
+        right: &ASTExpression,
+        span: Option<Span>,
+    ) -> Result<String, String> {
+        let left = self.expression(left)?;
+        let right = self.expression(right)?;
+        self.at(span);
+        Ok(self.temporary(format!(
+            "lol_binary({}, {}, {})",
+            c_operation(operation),
+            left,
+            right
+        )))
+    }
+
+    /// Evaluates the operands of `ALL OF` (when `all` is set) or `ANY OF`, stopping at the first
+    /// one that decides the result.
+    fn short_circuit(&mut self, values: &[&ASTExpression], all: bool) -> Result<String, String> {
+        let troof = self.fresh("c");
+        self.line(format!("int {} = {};", troof, all as i32));
+        let mut branches = 0;
+        for (index, value) in values.iter().enumerate() {
+            let value = self.expression(value)?;
+            self.line(format!("{} = lol_test({});", troof, value));
+            if index + 1 < values.len() {
+                match all {
+                    true => self.line(format!("if ({}) {{", troof)),
+                    false => self.line(format!("if (!{}) {{", troof)),
+                }
+                self.indent += 1;
+                branches += 1;
+            }
+        }
+        for _ in 0..branches {
+            self.indent -= 1;
+            self.line("}");
+        }
+        Ok(self.temporary(format!("lol_troof({})", troof)))
+    }
+
+    /// Evaluates a YARN literal. Escape sequences are resolved now, and interpolated variables
+    /// are read at runtime.
+    fn yarn(&mut self, raw: &str, span: Option<Span>) -> String {
+        let parts = match parse_yarn(raw) {
+            Ok(parts) => parts,
+            Err(error) => {
+                // Like in the interpreter, an invalid literal only fails when it is evaluated.
+                self.at(span);
+                self.line(format!(
+                    "lol_fail(\"%s\", {});",
+                    c_string(&error.to_string())
+                ));
+                return self.temporary("lol_noob()".to_string());
+            }
+        };
+
+        let mut result: Option<String> = None;
+        for part in parts {
+            let value = match part {
+                YarnPart::Text(text) => self.temporary(format!("lol_yarn({})", c_string(&text))),
+                YarnPart::Variable(name) => {
+                    self.at(span);
+                    self.temporary(format!(
+                        "lol_interpolate({}, {})",
+                        self.scope(),
+                        c_string(&name)
+                    ))
+                }
+            };
+            result = Some(match result {
+                None => value,
+                Some(result) => {
+                    self.line(format!("{} = lol_smoosh({}, {});", result, result, value));
+                    result
+                }
+            });
+        }
+        result.unwrap()
+    }
+}
@@ -0,0 +1,646 @@
+//! Translation to a self-contained ES module.
+//!
+//! The module starts with the runtime in `runtime.js`, and exports an async `run` function taking
+//! the callbacks used by `VISIBLE` and `GIMMEH`. Scopes are kept at runtime like in the
+//! tree-walking interpreter, and JavaScript evaluates arguments left to right, so expressions are
+//! translated to nested calls into the runtime.
+
+use lolcode_ast::parser::expression::{ASTExpression, ASTType, Identifier, VariableAccess};
+use lolcode_ast::parser::statements::assignment::VariableAssignment;
+use lolcode_ast::parser::statements::bukkit_set_slot::BukkitSetSlot;
+use lolcode_ast::parser::statements::how_iz_i::HowIzI;
+use lolcode_ast::parser::statements::i_has_a::{IHasA, IHasAInitialValue};
+use lolcode_ast::parser::statements::i_is::IIz;
+use lolcode_ast::parser::statements::im_in_yr::{
+    ImInYr, LoopCondition, LoopIterationOperation, LoopOperation,
+};
+use lolcode_ast::parser::statements::o_rly::ORly;
+use lolcode_ast::parser::statements::visible::Visible;
+use lolcode_ast::parser::statements::wtf::Wtf;
+use lolcode_ast::parser::statements::ASTNode;
+use lolcode_ast::parser::ASTBlock;
+
+use crate::error::{expression_span, node_span, type_name, Span};
+use crate::operations::literal_value;
+use crate::source::LibraryCalls;
+use crate::types::Value;
+use crate::yarn::{parse_yarn, yarn_literal, YarnPart};
+
+const RUNTIME: &str = include_str!("runtime.js");
+const INDENT: &str = "    ";
+
+/// Where `GTFO` and `FOUND YR` jump to.
+enum Exit {
+    /// `GTFO` breaks out of the labeled loop.
+    Loop(String),
+    /// `GTFO` breaks out of the labeled `WTF?` block.
+    Wtf(String),
+    /// `GTFO` and `FOUND YR` return from the function.
+    Function,
+    /// At the top level, `GTFO` and `FOUND YR` only skip the rest of the current statement.
+    TopLevel(String),
+}
+
+struct Generator<'a> {
+    source: &'a str,
+    library_calls: &'a LibraryCalls,
+    /// Body of the JavaScript function being generated.
+    code: String,
+    indent: usize,
+    /// The variables of the scopes open in the function being generated, outermost first.
+    scopes: Vec<String>,
+    exits: Vec<Exit>,
+    /// Declarations of the functions declared with `HOW IZ I`.
+    functions: Vec<String>,
+    /// Used to give unique names to scopes and labels.
+    counter: usize,
+}
+
+/// Quotes the text as a JavaScript string literal. JSON strings are valid JavaScript.
+fn js_string(text: &str) -> String {
+    serde_json::to_string(text).unwrap()
+}
+
+fn js_literal(value: &Value) -> String {
+    match value {
+        Value::Noob => "null".to_string(),
+        Value::Troof(troof) => troof.to_string(),
+        Value::Numbr(numbr) => numbr.to_string(),
+        // The exponent notation keeps every digit needed to get the same float back.
+        Value::Numbar(numbar) => format!("new Numbar({:e})", numbar),
+        Value::Yarn(yarn) => js_string(yarn),
+        Value::Bukkit(_) => "new Map()".to_string(),
+    }
+}
+
+fn js_type(ast_type: &ASTType) -> String {
+    js_string(type_name(ast_type))
+}
+
+/// Translates the program to an ES module. `file_name` is the name runtime errors refer to.
+pub(super) fn generate(
+    ast: &ASTBlock,
+    library_calls: &LibraryCalls,
+    source: &str,
+    file_name: &str,
+) -> Result<String, String> {
+    let mut generator = Generator {
+        source,
+        library_calls,
+        code: String::new(),
+        indent: 1,
+        scopes: vec!["s0".to_string()],
+        exits: vec![],
+        functions: vec![],
+        counter: 0,
+    };
+
+    generator.line("const s0 = new Scope(null);");
+    generator.line("$.defineBuiltins(s0);");
+    for node in ast.0.iter() {
+        let label = generator.fresh("lol_statement_");
+        generator.exits.push(Exit::TopLevel(label.clone()));
+        generator.line(format!("{}: {{", label));
+        generator.indent += 1;
+        generator.statement(node)?;
+        generator.indent -= 1;
+        generator.line("}");
+        generator.exits.pop();
+    }
+
+    let mut output = String::from(RUNTIME);
+    output.push_str(
+        "
+/**
+ * Runs the program. `visible` is called with the text printed by VISIBLE, and `gimmeh` returns
+ * the next line of input, or null at the end of the input. Both may return promises. Runtime
+ * errors reject the returned promise with a `LolcodeError`.
+ */
+export async function run({ visible, gimmeh, maxCallDepth } = {}) {
+",
+    );
+    output.push_str(&format!(
+        "{}const $ = new Runtime({{ visible, gimmeh, maxCallDepth }}, {});\n",
+        INDENT,
+        js_string(file_name)
+    ));
+    for function in generator.functions.iter() {
+        output.push('\n');
+        output.push_str(function);
+    }
+    output.push('\n');
+    output.push_str(&generator.code);
+    output.push_str("}\n");
+    Ok(output)
+}
+
+impl Generator<'_> {
+    fn line(&mut self, line: impl AsRef<str>) {
+        for _ in 0..self.indent {
+            self.code.push_str(INDENT);
+        }
+        self.code.push_str(line.as_ref());
+        self.code.push('\n');
+    }
+
+    fn fresh(&mut self, prefix: &str) -> String {
+        self.counter += 1;
+        format!("{}{}", prefix, self.counter)
+    }
+
+    fn scope(&self) -> &str {
+        self.scopes.last().unwrap()
+    }
+
+    /// The position passed to the runtime, reported by the errors it raises.
+    fn position(&self, span: Option<Span>) -> String {
+        match span {
+            Some(span) => {
+                let (line, column) = span.line_and_column(self.source);
+                format!("\"{}:{}\"", line, column)
+            }
+            None => "null".to_string(),
+        }
+    }
+
+    /// Returns an expression evaluating to the name the identifier refers to.
+    fn name(&self, identifier: &Identifier, span: Option<Span>) -> String {
+        let name = js_string(identifier.to_string_slice());
+        match identifier.is_srs {
+            true => format!("$.srs({}, {}, {})", self.position(span), self.scope(), name),
+            false => name,
+        }
+    }
+
+    /// Returns an expression evaluating to the name of the slot accessed with `'Z`. `SRS` slot
+    /// names can also be NUMBRs.
+    fn slot_name(&self, identifier: &Identifier, span: Option<Span>) -> String {
+        let name = js_string(identifier.to_string_slice());
+        match identifier.is_srs {
+            true => format!(
+                "$.srsSlot({}, {}, {})",
+                self.position(span),
+                self.scope(),
+                name
+            ),
+            false => name,
+        }
+    }
+
+    /// Returns the arguments the runtime takes to find an accessed value.
+    fn access(&self, variable_access: &VariableAccess, span: Option<Span>) -> String {
+        let path: Vec<String> = variable_access
+            .accesses
+            .iter()
+            .map(|access| self.slot_name(access, span))
+            .collect();
+        format!(
+            "{}, {}, {}, [{}]",
+            self.position(span),
+            self.scope(),
+            self.name(&variable_access.name, span),
+            path.join(", ")
+        )
+    }
+
+    /// Runs the block in a new child scope. The header, such as an `if`, is put before the
+    /// opening brace.
+    fn scoped_block(&mut self, header: &str, block: &ASTBlock) -> Result<(), String> {
+        match header.is_empty() {
+            true => self.line("{"),
+            false => self.line(format!("{} {{", header)),
+        }
+        self.indent += 1;
+        let scope = self.fresh("s");
+        self.line(format!("const {} = new Scope({});", scope, self.scope()));
+        self.scopes.push(scope);
+        self.block(block)?;
+        self.scopes.pop();
+        self.indent -= 1;
+        self.line("}");
+        Ok(())
+    }
+
+    fn block(&mut self, block: &ASTBlock) -> Result<(), String> {
+        for node in block.0.iter() {
+            self.statement(node)?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, node: &ASTNode) -> Result<(), String> {
+        let span = node_span(node);
+        let position = self.position(span);
+        match node {
+            ASTNode::HAI(_) | ASTNode::KTHXBYE(_) => {}
+            ASTNode::IHasA(IHasA {
+                identifier,
+                initial_value,
+            }) => {
+                let value = match initial_value {
+                    Some(IHasAInitialValue::Expression(expression)) => {
+                        self.expression(expression)?
+                    }
+                    Some(IHasAInitialValue::Type(ast_type)) => js_literal(&Value::from(ast_type)),
+                    None => "null".to_string(),
+                };
+                self.line(format!(
+                    "$.declare({}, {}, {});",
+                    self.scope(),
+                    value,
+                    self.name(identifier, span)
+                ));
+            }
+            ASTNode::ImInYr(ImInYr {
+                on_iteration,
+                condition,
+                code_block,
+                ..
+            }) => {
+                self.line("{");
+                self.indent += 1;
+                let scope = self.fresh("s");
+                self.line(format!("const {} = new Scope({});", scope, self.scope()));
+                self.scopes.push(scope);
+
+                let label = self.fresh("lol_loop_");
+                self.line(format!("{}: for (;;) {{", label));
+                self.indent += 1;
+                match condition {
+                    None => {}
+                    Some(LoopCondition::TIL(expression)) => {
+                        let value = self.expression(expression)?;
+                        self.line(format!("if ($.test({})) break;", value));
+                    }
+                    Some(LoopCondition::WILE(expression)) => {
+                        let value = self.expression(expression)?;
+                        self.line(format!("if (!$.test({})) break;", value));
+                    }
+                }
+                self.exits.push(Exit::Loop(label));
+                self.block(code_block)?;
+                self.exits.pop();
+                if let Some(LoopIterationOperation { operation, operand }) = on_iteration {
+                    let amount = match operation {
+                        LoopOperation::UPPIN(_) => 1,
+                        LoopOperation::NERFIN(_) => -1,
+                    };
+                    self.line(format!(
+                        "$.increment({}, $.place({}), {});",
+                        position,
+                        self.access(operand, span),
+                        amount
+                    ));
+                }
+                self.indent -= 1;
+                self.line("}");
+
+                self.scopes.pop();
+                self.indent -= 1;
+                self.line("}");
+            }
+            ASTNode::BukkitSetSlot(BukkitSetSlot {
+                bukkit,
+                slot_name,
+                value,
+            }) => {
+                let value = self.expression(value)?;
+                self.line(format!(
+                    "$.setSlot({}, {}, $.place({}), {});",
+                    position,
+                    value,
+                    self.access(bukkit, span),
+                    self.slot_name(slot_name, span)
+                ));
+            }
+            ASTNode::VariableAssignment(VariableAssignment {
+                variable_access,
+                expression,
+            }) => {
+                let value = self.expression(expression)?;
+                self.line(format!(
+                    "$.assign({}, $.place({}));",
+                    value,
+                    self.access(variable_access, span)
+                ));
+            }
+            ASTNode::Visible(Visible(expressions, has_exclamation)) => {
+                for (index, expression) in expressions.iter().enumerate() {
+                    let value = self.expression(expression)?;
+                    self.line(format!(
+                        "await $.print({}, {}, {});",
+                        self.position(expression_span(expression).or(span)),
+                        value,
+                        index > 0
+                    ));
+                }
+                if has_exclamation.is_none() {
+                    self.line("await $.write(\"\\n\");");
+                }
+            }
+            ASTNode::FoundYr(expression) => {
+                let value = self.expression(expression)?;
+                let exit = self
+                    .exits
+                    .iter()
+                    .rev()
+                    .find(|exit| matches!(exit, Exit::Function | Exit::TopLevel(_)));
+                match exit {
+                    Some(Exit::Function) => self.line(format!("return {};", value)),
+                    Some(Exit::TopLevel(label)) => {
+                        let label = label.clone();
+                        self.line(format!("{};", value));
+                        self.line(format!("break {};", label));
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            ASTNode::Gtfo(_) => match self.exits.last() {
+                Some(Exit::Function) => self.line("return null;"),
+                Some(Exit::Loop(label) | Exit::Wtf(label) | Exit::TopLevel(label)) => {
+                    let label = label.clone();
+                    self.line(format!("break {};", label));
+                }
+                None => unreachable!(),
+            },
+            ASTNode::Wtf(Wtf { omg, omg_wtf }) => {
+                // Once a case matches, every following case is executed as well, until a GTFO
+                // is found.
+                let label = self.fresh("lol_wtf_");
+                self.line(format!("{}: {{", label));
+                self.indent += 1;
+                let found = self.fresh("found");
+                self.line(format!("let {} = false;", found));
+                self.exits.push(Exit::Wtf(label));
+                for (expression, block) in omg.iter() {
+                    let value = self.expression(expression)?;
+                    self.line(format!(
+                        "if (!{}) {} = $.matches({}, {});",
+                        found,
+                        found,
+                        self.scope(),
+                        value
+                    ));
+                    self.scoped_block(&format!("if ({})", found), block)?;
+                }
+                if let Some(block) = omg_wtf {
+                    self.scoped_block("", block)?;
+                }
+                self.exits.pop();
+                self.indent -= 1;
+                self.line("}");
+            }
+            ASTNode::ORly(ORly {
+                if_true,
+                if_false,
+                mebbes,
+            }) => {
+                let mut branches = 0;
+                if let Some(block) = if_true {
+                    let header = format!("if ($.test({}.it))", self.scope());
+                    self.scoped_block(&header, block)?;
+                    self.line("else {");
+                    self.indent += 1;
+                    branches += 1;
+                }
+                for (expression, block) in mebbes.iter() {
+                    let value = self.expression(expression)?;
+                    self.scoped_block(&format!("if ($.test({}))", value), block)?;
+                    self.line("else {");
+                    self.indent += 1;
+                    branches += 1;
+                }
+                if let Some(block) = if_false {
+                    self.scoped_block("", block)?;
+                }
+                for _ in 0..branches {
+                    self.indent -= 1;
+                    self.line("}");
+                }
+            }
+            ASTNode::IIz(IIz { name, arguments }) => {
+                self.line("{");
+                self.indent += 1;
+                let function = self.fresh("f");
+                self.line(format!(
+                    "const {} = $.enterFunction({}, {}, {}, {});",
+                    function,
+                    position,
+                    self.scope(),
+                    js_string(&self.library_calls.function_name(name)),
+                    arguments.len()
+                ));
+                let function_scope = self.fresh("s");
+                self.line(format!(
+                    "const {} = new Scope({}.scope);",
+                    function_scope, function
+                ));
+                // The arguments are evaluated in the caller's scope.
+                for (index, argument) in arguments.iter().enumerate() {
+                    let value = self.expression(argument)?;
+                    self.line(format!(
+                        "$.declare({}, {}, {}.parameters[{}]);",
+                        function_scope, value, function, index
+                    ));
+                }
+                self.line(format!(
+                    "{}.it = await {}.body({});",
+                    self.scope(),
+                    function,
+                    function_scope
+                ));
+                self.line("$.leaveFunction();");
+                self.indent -= 1;
+                self.line("}");
+            }
+            ASTNode::HowIzI(how_iz_i) => self.function(how_iz_i)?,
+            ASTNode::Gimmeh(variable_access) => {
+                self.line(format!(
+                    "$.assign(await $.readLine({}), $.place({}));",
+                    position,
+                    self.access(variable_access, span)
+                ));
+            }
+            ASTNode::Expression(expression) => {
+                let value = self.expression(expression)?;
+                self.line(format!("{}.it = {};", self.scope(), value));
+            }
+            ASTNode::ASTError(error) => return Err(format!("{:?}", error)),
+        };
+        Ok(())
+    }
+
+    /// Generates the JavaScript function for a `HOW IZ I`, and defines it in the current scope.
+    fn function(&mut self, how_iz_i: &HowIzI) -> Result<(), String> {
+        let HowIzI {
+            name,
+            arguments,
+            body,
+            ..
+        } = how_iz_i;
+        let function = self.fresh("lol_function_");
+        let scope = self.fresh("s");
+
+        let code = std::mem::take(&mut self.code);
+        let indent = std::mem::replace(&mut self.indent, 2);
+        let scopes = std::mem::replace(&mut self.scopes, vec![scope.clone()]);
+        let exits = std::mem::replace(&mut self.exits, vec![Exit::Function]);
+        let result = self.block(body);
+        self.line(format!("return {}.takeIt();", scope));
+        let function_body = std::mem::replace(&mut self.code, code);
+        self.indent = indent;
+        self.scopes = scopes;
+        self.exits = exits;
+        result?;
+
+        self.functions.push(format!(
+            "{indent}// HOW IZ I {}\n{indent}async function {}({}) {{\n{}{indent}}}\n",
+            name.to_string_slice(),
+            function,
+            scope,
+            function_body,
+            indent = INDENT
+        ));
+
+        let parameters: Vec<String> = arguments
+            .iter()
+            .map(|argument| js_string(argument.to_string_slice()))
+            .collect();
+        self.line(format!(
+            "{}.define({}, {}, [{}]);",
+            self.scope(),
+            js_string(name.to_string_slice()),
+            function,
+            parameters.join(", ")
+        ));
+        Ok(())
+    }
+
+    fn expression(&mut self, expression: &ASTExpression) -> Result<String, String> {
+        let span = expression_span(expression);
+        let position = self.position(span);
+        Ok(match expression {
+            ASTExpression::LiteralValue(token) => match yarn_literal(token) {
+                Some(raw) => self.yarn(raw, span),
+                None => js_literal(&literal_value(token)),
+            },
+            ASTExpression::VariableAccess(variable_access) => {
+                format!("$.read({})", self.access(variable_access, span))
+            }
+            ASTExpression::BothOf(left, right) => {
+                self.short_circuit(&[left.as_ref(), right.as_ref()], "&&")?
+            }
+            ASTExpression::EitherOf(left, right) => {
+                self.short_circuit(&[left.as_ref(), right.as_ref()], "||")?
+            }
+            ASTExpression::AllOf(values) => {
+                self.short_circuit(&values.iter().collect::<Vec<_>>(), "&&")?
+            }
+            ASTExpression::AnyOf(values) => {
+                self.short_circuit(&values.iter().collect::<Vec<_>>(), "||")?
+            }
+            ASTExpression::Not(expression) => format!("!$.test({})", self.expression(expression)?),
+            ASTExpression::WonOf(left, right) => self.binary("WON OF", left, right, &position)?,
+            ASTExpression::SumOf(left, right) => self.binary("SUM OF", left, right, &position)?,
+            ASTExpression::DiffOf(left, right) => self.binary("DIFF OF", left, right, &position)?,
+            ASTExpression::ProduktOf(left, right) => {
+                self.binary("PRODUKT OF", left, right, &position)?
+            }
+            ASTExpression::QuoshuntOf(left, right) => {
+                self.binary("QUOSHUNT OF", left, right, &position)?
+            }
+            ASTExpression::ModOf(left, right) => self.binary("MOD OF", left, right, &position)?,
+            ASTExpression::BiggrOf(left, right) => {
+                self.binary("BIGGR OF", left, right, &position)?
+            }
+            ASTExpression::SmallrOf(left, right) => {
+                self.binary("SMALLR OF", left, right, &position)?
+            }
+            ASTExpression::BothSaem(left, right) => {
+                self.binary("BOTH SAEM", left, right, &position)?
+            }
+            ASTExpression::Diffrint(left, right) => {
+                self.binary("DIFFRINT", left, right, &position)?
+            }
+            ASTExpression::Smoosh(values) => {
+                // Each value is cast before the next one is evaluated, like in the interpreter.
+                let mut result = "\"\"".to_string();
+                for value in values.iter() {
+                    let value = self.expression(value)?;
+                    result = format!("$.smoosh({}, {}, {})", position, result, value);
+                }
+                result
+            }
+            ASTExpression::Maek(expression, ast_type) => format!(
+                "$.cast({}, {}, {})",
+                position,
+                self.expression(expression)?,
+                js_type(ast_type)
+            ),
+        })
+    }
+
+    fn binary(
+        &mut self,
+        operation: &str,
+        left: &ASTExpression,
+        right: &ASTExpression,
+        position: &str,
+    ) -> Result<String, String> {
+        Ok(format!(
+            "$.binary({}, {}, {}, {})",
+            position,
+            js_string(operation),
+            self.expression(left)?,
+            self.expression(right)?
+        ))
+    }
+
+    /// Evaluates the operands of `ALL OF` and `ANY OF` with the given operator, which stops at
+    /// the first one that decides the result.
+    fn short_circuit(
+        &mut self,
+        values: &[&ASTExpression],
+        operator: &str,
+    ) -> Result<String, String> {
+        if values.is_empty() {
+            return Ok((operator == "&&").to_string());
+        }
+        let mut operands = vec![];
+        for value in values.iter() {
+            operands.push(format!("$.test({})", self.expression(value)?));
+        }
+        Ok(format!("({})", operands.join(&format!(" {} ", operator))))
+    }
+
+    /// Evaluates a YARN literal. Escape sequences are resolved now, and interpolated variables
+    /// are read at runtime.
+    fn yarn(&mut self, raw: &str, span: Option<Span>) -> String {
+        let position = self.position(span);
+        let parts = match parse_yarn(raw) {
+            Ok(parts) => parts,
+            // Like in the interpreter, an invalid literal only fails when it is evaluated.
+            Err(error) => {
+                return format!("$.failAt({}, {})", position, js_string(&error.to_string()))
+            }
+        };
+
+        let mut result: Option<String> = None;
+        for part in parts {
+            let value = match part {
+                YarnPart::Text(text) => js_string(&text),
+                YarnPart::Variable(name) => format!(
+                    "$.interpolate({}, {}, {})",
+                    position,
+                    self.scope(),
+                    js_string(&name)
+                ),
+            };
+            result = Some(match result {
+                None => value,
+                Some(result) => format!("$.smoosh({}, {}, {})", position, result, value),
+            });
+        }
+        result.unwrap()
+    }
+}
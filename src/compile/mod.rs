@@ -0,0 +1,29 @@
+//! Ahead-of-time compilation of LOLCODE programs to other languages.
+//!
+//! The generated programs bundle a small runtime implementing the same value model as the
+//! interpreter, and are expected to print the same output as the tree-walking engine.
+
+mod c;
+mod js;
+
+use crate::source::parse_without_extensions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// A standalone C file, which only needs the C standard library.
+    C,
+    /// A self-contained ES module exporting an async `run` function.
+    Js,
+}
+
+/// Translates the source to a program in the target language. Runtime errors of the program
+/// refer to `file_name`. Only the native libraries can be imported, whose methods the bundled
+/// runtime implements, and objects cannot be declared.
+pub fn compile(source: &str, file_name: &str, target: Target) -> Result<String, String> {
+    let (ast, library_calls) =
+        parse_without_extensions(source).map_err(|err| err.render(source, file_name))?;
+    match target {
+        Target::C => c::generate(&ast, &library_calls, source, file_name),
+        Target::Js => js::generate(&ast, &library_calls, source, file_name),
+    }
+}
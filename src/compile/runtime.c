@@ -0,0 +1,1274 @@
+/*
+ * Runtime bundled with the LOLCODE programs compiled to C.
+ *
+ * It mirrors the value model of the tree-walking interpreter: casts, operators and scoping follow
+ * `cast.rs`, `operations.rs` and `variable_access.rs`, so a compiled program prints the same
+ * output as `run` does. Values are owned: every function taking a `lol_value` by value consumes
+ * it, and every function returning one gives ownership to the caller.
+ */
+
+#include <math.h>
+#include <stdarg.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+/* Programs rarely use every function of the runtime. */
+#if defined(__GNUC__)
+#pragma GCC diagnostic ignored "-Wunused-function"
+#endif
+
+#ifndef LOL_MAX_CALL_DEPTH
+#define LOL_MAX_CALL_DEPTH 256
+#endif
+
+typedef enum { LOL_NOOB, LOL_TROOF, LOL_NUMBR, LOL_NUMBAR, LOL_YARN, LOL_BUKKIT } lol_type;
+
+typedef struct lol_bukkit lol_bukkit;
+
+typedef struct {
+    lol_type type;
+    union {
+        int troof;
+        int32_t numbr;
+        float numbar;
+        char *yarn;
+        lol_bukkit *bukkit;
+    } as;
+} lol_value;
+
+typedef struct {
+    char *name;
+    lol_value value;
+} lol_entry;
+
+/* Maps are small in practice, so entries are looked up linearly. */
+typedef struct {
+    lol_entry *entries;
+    size_t length;
+    size_t capacity;
+} lol_map;
+
+/* BUKKITs are shared by reference and freed when their last reference is dropped. BUKKITs that
+ * refer to themselves are never freed, which is fine for a program that exits when it ends. */
+struct lol_bukkit {
+    lol_map slots;
+    size_t references;
+    /* Set while the BUKKIT is being walked, to stop at cycles. */
+    int visiting;
+};
+
+typedef struct lol_scope lol_scope;
+typedef lol_value (*lol_body)(lol_scope *scope);
+
+typedef struct {
+    char *name;
+    lol_body body;
+    size_t arity;
+    const char *const *parameters;
+    /* Function scopes are children of the scope the function was declared in. */
+    lol_scope *scope;
+} lol_function;
+
+struct lol_scope {
+    lol_scope *parent;
+    lol_map variables;
+    lol_function *functions;
+    size_t function_count;
+    size_t function_capacity;
+    lol_value it;
+};
+
+static const char *lol_file_name = "";
+static int lol_line;
+static int lol_column;
+static size_t lol_call_depth;
+
+/* Sets the position reported by runtime errors. A line of 0 means the position is unknown. */
+static void lol_at(int line, int column) {
+    lol_line = line;
+    lol_column = column;
+}
+
+static void lol_fail(const char *format, ...) {
+    va_list arguments;
+    fflush(stdout);
+    fprintf(stderr, "error: ");
+    va_start(arguments, format);
+    vfprintf(stderr, format, arguments);
+    va_end(arguments);
+    if (lol_line > 0) {
+        fprintf(stderr, "\n --> %s:%d:%d\n", lol_file_name, lol_line, lol_column);
+    } else {
+        fprintf(stderr, "\n --> %s\n", lol_file_name);
+    }
+    exit(1);
+}
+
+static void *lol_alloc(size_t size) {
+    void *memory = malloc(size ? size : 1);
+    if (memory == NULL) {
+        lol_fail("out of memory");
+    }
+    return memory;
+}
+
+static void *lol_grow(void *memory, size_t *capacity, size_t element_size) {
+    *capacity = *capacity ? *capacity * 2 : 4;
+    memory = realloc(memory, *capacity * element_size);
+    if (memory == NULL) {
+        lol_fail("out of memory");
+    }
+    return memory;
+}
+
+static char *lol_strdup(const char *text) {
+    size_t length = strlen(text);
+    char *copy = lol_alloc(length + 1);
+    memcpy(copy, text, length + 1);
+    return copy;
+}
+
+static const char *lol_type_name(const lol_value *value) {
+    switch (value->type) {
+    case LOL_NOOB:
+        return "NOOB";
+    case LOL_TROOF:
+        return "TROOF";
+    case LOL_NUMBR:
+        return "NUMBR";
+    case LOL_NUMBAR:
+        return "NUMBAR";
+    case LOL_YARN:
+        return "YARN";
+    default:
+        return "BUKKIT";
+    }
+}
+
+static const char *lol_type_names[] = {"NOOB", "TROOF", "NUMBR", "NUMBAR", "YARN", "BUKKIT"};
+
+/* ---------------------------------------------------------------------------------------------
+ * Values
+ * ------------------------------------------------------------------------------------------ */
+
+static lol_value lol_noob(void) {
+    lol_value value;
+    value.type = LOL_NOOB;
+    value.as.numbr = 0;
+    return value;
+}
+
+static lol_value lol_troof(int troof) {
+    lol_value value;
+    value.type = LOL_TROOF;
+    value.as.troof = troof != 0;
+    return value;
+}
+
+static lol_value lol_numbr(int32_t numbr) {
+    lol_value value;
+    value.type = LOL_NUMBR;
+    value.as.numbr = numbr;
+    return value;
+}
+
+static lol_value lol_numbar(float numbar) {
+    lol_value value;
+    value.type = LOL_NUMBAR;
+    value.as.numbar = numbar;
+    return value;
+}
+
+/* Takes ownership of the string. */
+static lol_value lol_yarn_owned(char *yarn) {
+    lol_value value;
+    value.type = LOL_YARN;
+    value.as.yarn = yarn;
+    return value;
+}
+
+static lol_value lol_yarn(const char *yarn) {
+    return lol_yarn_owned(lol_strdup(yarn));
+}
+
+static lol_value lol_bukkit_new(void) {
+    lol_value value;
+    value.type = LOL_BUKKIT;
+    value.as.bukkit = lol_alloc(sizeof(lol_bukkit));
+    value.as.bukkit->slots.entries = NULL;
+    value.as.bukkit->slots.length = 0;
+    value.as.bukkit->slots.capacity = 0;
+    value.as.bukkit->references = 1;
+    value.as.bukkit->visiting = 0;
+    return value;
+}
+
+/* The default value of a type, as given by `ITZ A` and by casting a NOOB with `MAEK`. */
+static lol_value lol_default(lol_type type) {
+    switch (type) {
+    case LOL_TROOF:
+        return lol_troof(0);
+    case LOL_NUMBR:
+        return lol_numbr(0);
+    case LOL_NUMBAR:
+        return lol_numbar(0.0f);
+    case LOL_YARN:
+        return lol_yarn("");
+    case LOL_BUKKIT:
+        return lol_bukkit_new();
+    default:
+        return lol_noob();
+    }
+}
+
+static void lol_map_free(lol_map *map);
+
+static void lol_drop(lol_value value) {
+    if (value.type == LOL_YARN) {
+        free(value.as.yarn);
+    } else if (value.type == LOL_BUKKIT && --value.as.bukkit->references == 0) {
+        lol_map_free(&value.as.bukkit->slots);
+        free(value.as.bukkit);
+    }
+}
+
+/* Copies a value. A copy of a BUKKIT is another reference to the same BUKKIT. */
+static lol_value lol_copy(const lol_value *value) {
+    lol_value copy = *value;
+    if (value->type == LOL_YARN) {
+        copy.as.yarn = lol_strdup(value->as.yarn);
+    } else if (value->type == LOL_BUKKIT) {
+        value->as.bukkit->references++;
+    }
+    return copy;
+}
+
+/* ---------------------------------------------------------------------------------------------
+ * Maps
+ * ------------------------------------------------------------------------------------------ */
+
+static lol_value *lol_map_find(lol_map *map, const char *name) {
+    size_t index;
+    for (index = 0; index < map->length; index++) {
+        if (strcmp(map->entries[index].name, name) == 0) {
+            return &map->entries[index].value;
+        }
+    }
+    return NULL;
+}
+
+static void lol_map_insert(lol_map *map, const char *name, lol_value value) {
+    lol_value *existing = lol_map_find(map, name);
+    if (existing != NULL) {
+        lol_drop(*existing);
+        *existing = value;
+        return;
+    }
+    if (map->length == map->capacity) {
+        map->entries = lol_grow(map->entries, &map->capacity, sizeof(lol_entry));
+    }
+    map->entries[map->length].name = lol_strdup(name);
+    map->entries[map->length].value = value;
+    map->length++;
+}
+
+/* Removes an entry, keeping the others in order. */
+static void lol_map_remove(lol_map *map, const char *name) {
+    size_t index;
+    for (index = 0; index < map->length; index++) {
+        if (strcmp(map->entries[index].name, name) == 0) {
+            free(map->entries[index].name);
+            lol_drop(map->entries[index].value);
+            memmove(&map->entries[index], &map->entries[index + 1],
+                    (map->length - index - 1) * sizeof(lol_entry));
+            map->length--;
+            return;
+        }
+    }
+}
+
+static void lol_map_free(lol_map *map) {
+    size_t index;
+    for (index = 0; index < map->length; index++) {
+        free(map->entries[index].name);
+        lol_drop(map->entries[index].value);
+    }
+    free(map->entries);
+}
+
+/* ---------------------------------------------------------------------------------------------
+ * Casts
+ * ------------------------------------------------------------------------------------------ */
+
+static int lol_is_space(char c) {
+    return c == ' ' || c == '\t' || c == '\n' || c == '\r' || c == '\f' || c == '\v';
+}
+
+/* Returns a copy of the text without its leading and trailing whitespace. */
+static char *lol_trim(const char *text) {
+    size_t length;
+    char *trimmed;
+    while (lol_is_space(*text)) {
+        text++;
+    }
+    length = strlen(text);
+    while (length > 0 && lol_is_space(text[length - 1])) {
+        length--;
+    }
+    trimmed = lol_alloc(length + 1);
+    memcpy(trimmed, text, length);
+    trimmed[length] = '\0';
+    return trimmed;
+}
+
+static int lol_digits(const char **text) {
+    const char *start = *text;
+    while (**text >= '0' && **text <= '9') {
+        (*text)++;
+    }
+    return *text != start;
+}
+
+static int lol_parse_numbr(const char *text, int32_t *numbr) {
+    int negative = 0;
+    int64_t result = 0;
+    const char *digits;
+    if (*text == '+' || *text == '-') {
+        negative = *text == '-';
+        text++;
+    }
+    digits = text;
+    if (!lol_digits(&text) || *text != '\0') {
+        return 0;
+    }
+    for (; *digits; digits++) {
+        result = result * 10 + (*digits - '0');
+        if (result > (int64_t)INT32_MAX + 1) {
+            return 0;
+        }
+    }
+    if (negative) {
+        result = -result;
+    }
+    if (result > INT32_MAX) {
+        return 0;
+    }
+    *numbr = (int32_t)result;
+    return 1;
+}
+
+/* Accepts the same syntax as Rust's `f32::from_str`, which the interpreter uses, but only finite
+ * numbers, like the interpreter does. */
+static int lol_parse_numbar(const char *text, float *numbar) {
+    const char *rest = text;
+    int has_digits;
+    if (*rest == '+' || *rest == '-') {
+        rest++;
+    }
+    has_digits = lol_digits(&rest);
+    if (*rest == '.') {
+        rest++;
+        has_digits |= lol_digits(&rest);
+    }
+    if (!has_digits) {
+        return 0;
+    }
+    if (*rest == 'e' || *rest == 'E') {
+        rest++;
+        if (*rest == '+' || *rest == '-') {
+            rest++;
+        }
+        if (!lol_digits(&rest)) {
+            return 0;
+        }
+    }
+    if (*rest != '\0') {
+        return 0;
+    }
+    *numbar = strtof(text, NULL);
+    return isfinite(*numbar);
+}
+
+/* Converts like Rust's `as i32`: saturating, with NaN becoming 0. */
+static int32_t lol_numbar_to_numbr(float numbar) {
+    if (numbar != numbar) {
+        return 0;
+    }
+    if (numbar >= 2147483648.0f) {
+        return INT32_MAX;
+    }
+    if (numbar <= -2147483648.0f) {
+        return INT32_MIN;
+    }
+    return (int32_t)numbar;
+}
+
+/* NUMBARs are cast to YARNs truncated to two decimal places. */
+static char *lol_numbar_to_yarn(float numbar) {
+    char buffer[64];
+    /* From 2^23 on, NUMBARs have no fractional part, and scaling them could overflow. */
+    float truncated = fabsf(numbar) < 8388608.0f ? truncf(numbar * 100.0f) / 100.0f : numbar;
+    /* Adding zero turns the negative zero that -0.001 truncates to into a positive one. */
+    truncated += 0.0f;
+    if (truncated != truncated) {
+        return lol_strdup("NaN");
+    }
+    snprintf(buffer, sizeof(buffer), "%.2f", (double)truncated);
+    return lol_strdup(buffer);
+}
+
+static void lol_illegal_cast(const lol_value *value, lol_type to) {
+    lol_fail("cannot cast %s to %s", lol_type_name(value), lol_type_names[to]);
+}
+
+static int lol_to_troof(const lol_value *value) {
+    switch (value->type) {
+    case LOL_NOOB:
+        return 0;
+    case LOL_TROOF:
+        return value->as.troof;
+    case LOL_NUMBR:
+        return value->as.numbr != 0;
+    case LOL_NUMBAR:
+        return value->as.numbar != 0.0f;
+    case LOL_YARN:
+        return value->as.yarn[0] != '\0';
+    default:
+        return 1;
+    }
+}
+
+/* Returns the TROOF cast of the value, consuming it. */
+static int lol_test(lol_value value) {
+    int troof = lol_to_troof(&value);
+    lol_drop(value);
+    return troof;
+}
+
+/* Implicit cast, used when an operator expects a given type. A NOOB can only be implicitly cast
+ * to a TROOF. */
+static lol_value lol_implicit_cast(lol_value value, lol_type to) {
+    lol_value result;
+    char buffer[16];
+    if (to == LOL_NOOB) {
+        lol_drop(value);
+        return lol_noob();
+    }
+    if (to == LOL_TROOF) {
+        return lol_troof(lol_test(value));
+    }
+    if (value.type == to && to != LOL_NUMBAR) {
+        return value;
+    }
+    switch (value.type) {
+    case LOL_TROOF:
+        if (to == LOL_NUMBR) {
+            return lol_numbr(value.as.troof);
+        } else if (to == LOL_NUMBAR) {
+            return lol_numbar((float)value.as.troof);
+        } else if (to == LOL_YARN) {
+            return lol_yarn(value.as.troof ? "WIN" : "FAIL");
+        }
+        break;
+    case LOL_NUMBR:
+        if (to == LOL_NUMBAR) {
+            return lol_numbar((float)value.as.numbr);
+        } else if (to == LOL_YARN) {
+            snprintf(buffer, sizeof(buffer), "%ld", (long)value.as.numbr);
+            return lol_yarn(buffer);
+        }
+        break;
+    case LOL_NUMBAR:
+        if (to == LOL_NUMBR) {
+            return lol_numbr(lol_numbar_to_numbr(value.as.numbar));
+        } else if (to == LOL_NUMBAR) {
+            return value;
+        } else if (to == LOL_YARN) {
+            return lol_yarn_owned(lol_numbar_to_yarn(value.as.numbar));
+        }
+        break;
+    case LOL_YARN:
+        if (to == LOL_NUMBR || to == LOL_NUMBAR) {
+            char *trimmed = lol_trim(value.as.yarn);
+            int32_t numbr;
+            float numbar;
+            int valid;
+            if (to == LOL_NUMBR) {
+                valid = lol_parse_numbr(trimmed, &numbr);
+                if (!valid && lol_parse_numbar(trimmed, &numbar)) {
+                    valid = 1;
+                    numbr = lol_numbar_to_numbr(numbar);
+                }
+                result = lol_numbr(numbr);
+            } else {
+                valid = lol_parse_numbar(trimmed, &numbar);
+                result = lol_numbar(numbar);
+            }
+            free(trimmed);
+            if (!valid) {
+                lol_illegal_cast(&value, to);
+            }
+            lol_drop(value);
+            return result;
+        }
+        break;
+    default:
+        break;
+    }
+    lol_illegal_cast(&value, to);
+    return value;
+}
+
+/* Explicit cast, as done by `MAEK`. A NOOB can be cast to any type, becoming its default. */
+static lol_value lol_cast(lol_value value, lol_type to) {
+    if (value.type == LOL_NOOB) {
+        return lol_default(to);
+    }
+    return lol_implicit_cast(value, to);
+}
+
+/* Casts the value to a YARN, returning the string. */
+static char *lol_to_yarn(lol_value value) {
+    return lol_implicit_cast(value, LOL_YARN).as.yarn;
+}
+
+/* Casts a value to either a NUMBR or a NUMBAR, as done by the math operators. A YARN becomes a
+ * NUMBAR if it has a decimal point, and a NUMBR otherwise. */
+static lol_value lol_to_number(lol_value value) {
+    if (value.type == LOL_NUMBR || value.type == LOL_NUMBAR) {
+        return value;
+    }
+    if (value.type == LOL_YARN && strchr(value.as.yarn, '.') != NULL) {
+        return lol_implicit_cast(value, LOL_NUMBAR);
+    }
+    return lol_implicit_cast(value, LOL_NUMBR);
+}
+
+/* ---------------------------------------------------------------------------------------------
+ * Operators
+ * ------------------------------------------------------------------------------------------ */
+
+typedef enum {
+    LOL_SUM_OF,
+    LOL_DIFF_OF,
+    LOL_PRODUKT_OF,
+    LOL_QUOSHUNT_OF,
+    LOL_MOD_OF,
+    LOL_BIGGR_OF,
+    LOL_SMALLR_OF,
+    LOL_BOTH_SAEM,
+    LOL_DIFFRINT,
+    LOL_WON_OF
+} lol_operation;
+
+/* Whether the value can be cast to a number without an error. */
+static int lol_is_numeric(const lol_value *value) {
+    int32_t numbr;
+    float numbar;
+    char *trimmed;
+    int valid;
+    switch (value->type) {
+    case LOL_TROOF:
+    case LOL_NUMBR:
+    case LOL_NUMBAR:
+        return 1;
+    case LOL_YARN:
+        trimmed = lol_trim(value->as.yarn);
+        valid = strchr(value->as.yarn, '.') == NULL && lol_parse_numbr(trimmed, &numbr);
+        valid = valid || lol_parse_numbar(trimmed, &numbar);
+        free(trimmed);
+        return valid;
+    default:
+        return 0;
+    }
+}
+
+static void lol_invalid_operands(lol_operation operation, const lol_value *left,
+                                 const lol_value *right) {
+    const char *l = lol_type_name(left);
+    const char *r = lol_type_name(right);
+    switch (operation) {
+    case LOL_SUM_OF:
+        lol_fail("cannot add %s to %s", r, l);
+        break;
+    case LOL_DIFF_OF:
+        lol_fail("cannot subtract %s from %s", r, l);
+        break;
+    case LOL_PRODUKT_OF:
+        lol_fail("cannot multiply %s by %s", l, r);
+        break;
+    case LOL_QUOSHUNT_OF:
+        lol_fail("cannot divide %s by %s", l, r);
+        break;
+    case LOL_MOD_OF:
+        lol_fail("cannot take the remainder of dividing %s by %s", l, r);
+        break;
+    default:
+        lol_fail("cannot compare %s and %s", l, r);
+        break;
+    }
+}
+
+/* Equality as done by `WTF?` cases: same type and same value, without any casting. BUKKITs are
+ * only equal to themselves. */
+static int lol_equal(const lol_value *left, const lol_value *right) {
+    if (left->type != right->type) {
+        return 0;
+    }
+    switch (left->type) {
+    case LOL_NOOB:
+        return 1;
+    case LOL_TROOF:
+        return left->as.troof == right->as.troof;
+    case LOL_NUMBR:
+        return left->as.numbr == right->as.numbr;
+    case LOL_NUMBAR:
+        return left->as.numbar == right->as.numbar;
+    case LOL_YARN:
+        return strcmp(left->as.yarn, right->as.yarn) == 0;
+    default:
+        return left->as.bukkit == right->as.bukkit;
+    }
+}
+
+/* Equality as done by `BOTH SAEM`: NUMBRs and NUMBARs are compared as NUMBARs. */
+static int lol_is_saem(const lol_value *left, const lol_value *right) {
+    if (left->type == LOL_NUMBR && right->type == LOL_NUMBAR) {
+        return (float)left->as.numbr == right->as.numbar;
+    }
+    if (left->type == LOL_NUMBAR && right->type == LOL_NUMBR) {
+        return left->as.numbar == (float)right->as.numbr;
+    }
+    return lol_equal(left, right);
+}
+
+static int32_t lol_wrap(int64_t value) {
+    return (int32_t)(uint32_t)(uint64_t)value;
+}
+
+static lol_value lol_binary(lol_operation operation, lol_value left, lol_value right) {
+    lol_value result;
+    float l, r;
+    if (operation == LOL_BOTH_SAEM || operation == LOL_DIFFRINT) {
+        int saem = lol_is_saem(&left, &right);
+        lol_drop(left);
+        lol_drop(right);
+        return lol_troof(operation == LOL_BOTH_SAEM ? saem : !saem);
+    }
+    if (operation == LOL_WON_OF) {
+        int l_troof = lol_test(left);
+        return lol_troof(l_troof != lol_test(right));
+    }
+
+    if (!lol_is_numeric(&left) || !lol_is_numeric(&right)) {
+        lol_invalid_operands(operation, &left, &right);
+    }
+    left = lol_to_number(left);
+    right = lol_to_number(right);
+
+    if (left.type == LOL_NUMBR && right.type == LOL_NUMBR) {
+        int64_t a = left.as.numbr;
+        int64_t b = right.as.numbr;
+        switch (operation) {
+        case LOL_SUM_OF:
+            return lol_numbr(lol_wrap(a + b));
+        case LOL_DIFF_OF:
+            return lol_numbr(lol_wrap(a - b));
+        case LOL_PRODUKT_OF:
+            return lol_numbr(lol_wrap(a * b));
+        case LOL_QUOSHUNT_OF:
+            if (b == 0) {
+                lol_fail("division by zero");
+            }
+            return lol_numbr(lol_wrap(a / b));
+        case LOL_MOD_OF:
+            if (b == 0) {
+                lol_fail("division by zero");
+            }
+            return lol_numbr(lol_wrap(a % b));
+        case LOL_BIGGR_OF:
+            return lol_troof(a > b);
+        default:
+            return lol_troof(a < b);
+        }
+    }
+
+    l = left.type == LOL_NUMBR ? (float)left.as.numbr : left.as.numbar;
+    r = right.type == LOL_NUMBR ? (float)right.as.numbr : right.as.numbar;
+    switch (operation) {
+    case LOL_SUM_OF:
+        result = lol_numbar(l + r);
+        break;
+    case LOL_DIFF_OF:
+        result = lol_numbar(l - r);
+        break;
+    case LOL_PRODUKT_OF:
+        result = lol_numbar(l * r);
+        break;
+    case LOL_QUOSHUNT_OF:
+        result = lol_numbar(l / r);
+        break;
+    case LOL_MOD_OF:
+        result = lol_numbar(fmodf(l, r));
+        break;
+    case LOL_BIGGR_OF:
+        result = lol_troof(l > r);
+        break;
+    default:
+        result = lol_troof(l < r);
+        break;
+    }
+    return result;
+}
+
+static lol_value lol_not(lol_value value) {
+    return lol_troof(!lol_test(value));
+}
+
+/* Appends the YARN cast of the value to the YARN, as done by `SMOOSH`. */
+static lol_value lol_smoosh(lol_value yarn, lol_value value) {
+    char *text = lol_to_yarn(value);
+    size_t length = strlen(yarn.as.yarn);
+    char *joined = lol_alloc(length + strlen(text) + 1);
+    memcpy(joined, yarn.as.yarn, length);
+    strcpy(joined + length, text);
+    free(text);
+    lol_drop(yarn);
+    return lol_yarn_owned(joined);
+}
+
+/* ---------------------------------------------------------------------------------------------
+ * Output and input
+ * ------------------------------------------------------------------------------------------ */
+
+typedef struct {
+    char *data;
+    size_t length;
+    size_t capacity;
+} lol_buffer;
+
+static void lol_buffer_push(lol_buffer *buffer, const char *text) {
+    size_t length = strlen(text);
+    while (buffer->length + length + 1 > buffer->capacity) {
+        buffer->data = lol_grow(buffer->data, &buffer->capacity, 1);
+    }
+    memcpy(buffer->data + buffer->length, text, length + 1);
+    buffer->length += length;
+}
+
+/* Quotes a YARN inside a BUKKIT rendering, escaping it like Rust's `{:?}` does. */
+static void lol_buffer_push_quoted(lol_buffer *buffer, const char *text) {
+    char escaped[16];
+    lol_buffer_push(buffer, "\"");
+    for (; *text; text++) {
+        unsigned char c = (unsigned char)*text;
+        switch (c) {
+        case '"':
+            lol_buffer_push(buffer, "\\\"");
+            break;
+        case '\\':
+            lol_buffer_push(buffer, "\\\\");
+            break;
+        case '\n':
+            lol_buffer_push(buffer, "\\n");
+            break;
+        case '\r':
+            lol_buffer_push(buffer, "\\r");
+            break;
+        case '\t':
+            lol_buffer_push(buffer, "\\t");
+            break;
+        default:
+            if (c < 0x20 || c == 0x7f) {
+                snprintf(escaped, sizeof(escaped), "\\u{%x}", c);
+            } else {
+                escaped[0] = (char)c;
+                escaped[1] = '\0';
+            }
+            lol_buffer_push(buffer, escaped);
+            break;
+        }
+    }
+    lol_buffer_push(buffer, "\"");
+}
+
+/* Renders a value like its `Display` implementation in the interpreter. BUKKITs list their slots
+ * in the order they were added, and a BUKKIT found inside itself is rendered as `{...}`. */
+static void lol_buffer_push_value(lol_buffer *buffer, const lol_value *value) {
+    lol_entry *slots;
+    size_t index;
+    char *text;
+    switch (value->type) {
+    case LOL_NOOB:
+        lol_buffer_push(buffer, "NOOB");
+        break;
+    case LOL_BUKKIT:
+        if (value->as.bukkit->visiting) {
+            lol_buffer_push(buffer, "{...}");
+            break;
+        }
+        value->as.bukkit->visiting = 1;
+        slots = value->as.bukkit->slots.entries;
+        lol_buffer_push(buffer, "{");
+        for (index = 0; index < value->as.bukkit->slots.length; index++) {
+            if (index > 0) {
+                lol_buffer_push(buffer, ", ");
+            }
+            lol_buffer_push(buffer, slots[index].name);
+            lol_buffer_push(buffer, ": ");
+            if (slots[index].value.type == LOL_YARN) {
+                lol_buffer_push_quoted(buffer, slots[index].value.as.yarn);
+            } else {
+                lol_buffer_push_value(buffer, &slots[index].value);
+            }
+        }
+        lol_buffer_push(buffer, "}");
+        value->as.bukkit->visiting = 0;
+        break;
+    default:
+        text = lol_to_yarn(lol_copy(value));
+        lol_buffer_push(buffer, text);
+        free(text);
+        break;
+    }
+}
+
+/* Prints a value for `VISIBLE`. This is the YARN cast, except that BUKKITs are rendered. */
+static void lol_print(lol_value value, int separator) {
+    lol_buffer buffer = {NULL, 0, 0};
+    char *text;
+    if (value.type == LOL_BUKKIT) {
+        lol_buffer_push_value(&buffer, &value);
+        lol_drop(value);
+        text = buffer.data;
+    } else {
+        text = lol_to_yarn(value);
+    }
+    if (separator) {
+        fputc(' ', stdout);
+    }
+    fputs(text, stdout);
+    free(text);
+}
+
+static lol_value lol_read_line(void) {
+    lol_buffer line = {NULL, 0, 0};
+    char character[2] = {0, 0};
+    int c;
+    fflush(stdout);
+    lol_buffer_push(&line, "");
+    while ((c = fgetc(stdin)) != EOF) {
+        if (c == '\n') {
+            break;
+        }
+        character[0] = (char)c;
+        lol_buffer_push(&line, character);
+    }
+    if (c == EOF && line.length == 0) {
+        lol_fail("I/O error: reached the end of the input");
+    }
+    if (c == '\n' && line.length > 0 && line.data[line.length - 1] == '\r') {
+        line.data[--line.length] = '\0';
+    }
+    return lol_yarn_owned(line.data);
+}
+
+/* ---------------------------------------------------------------------------------------------
+ * Scopes
+ * ------------------------------------------------------------------------------------------ */
+
+#define LOL_SCOPE(name, parent)                                                                \
+    lol_scope name##_data;                                                                     \
+    lol_scope *const name = lol_scope_init(&name##_data, parent)
+
+static lol_scope *lol_scope_init(lol_scope *scope, lol_scope *parent) {
+    scope->parent = parent;
+    scope->variables.entries = NULL;
+    scope->variables.length = 0;
+    scope->variables.capacity = 0;
+    scope->functions = NULL;
+    scope->function_count = 0;
+    scope->function_capacity = 0;
+    scope->it = lol_noob();
+    return scope;
+}
+
+static void lol_scope_release(lol_scope *scope) {
+    size_t index;
+    lol_map_free(&scope->variables);
+    for (index = 0; index < scope->function_count; index++) {
+        free(scope->functions[index].name);
+    }
+    free(scope->functions);
+    lol_drop(scope->it);
+}
+
+static lol_value *lol_find_variable(lol_scope *scope, const char *name) {
+    for (; scope != NULL; scope = scope->parent) {
+        lol_value *value = lol_map_find(&scope->variables, name);
+        if (value != NULL) {
+            return value;
+        }
+    }
+    return NULL;
+}
+
+/* Returns the variable with the given name. `IT` is the one of the current scope. */
+static lol_value *lol_lookup(lol_scope *scope, const char *name) {
+    lol_value *value;
+    if (strcmp(name, "IT") == 0) {
+        return &scope->it;
+    }
+    value = lol_find_variable(scope, name);
+    if (value == NULL) {
+        lol_fail("variable \"%s\" was not declared", name);
+    }
+    return value;
+}
+
+/* Returns the slot of a BUKKIT, as done by `'Z`. Slots missing from the BUKKIT are looked up in
+ * the BUKKIT held by its `parent` slot, and so on up the chain. */
+static lol_value *lol_slot(lol_value *bukkit, const char *name) {
+    lol_value *start = bukkit, *slot = NULL;
+    if (bukkit->type != LOL_BUKKIT) {
+        lol_fail("expected BUKKIT, but found %s", lol_type_name(bukkit));
+    }
+    while (bukkit != NULL && bukkit->type == LOL_BUKKIT && !bukkit->as.bukkit->visiting) {
+        slot = lol_map_find(&bukkit->as.bukkit->slots, name);
+        if (slot != NULL) {
+            break;
+        }
+        bukkit->as.bukkit->visiting = 1;
+        bukkit = lol_map_find(&bukkit->as.bukkit->slots, "parent");
+    }
+    for (bukkit = start; bukkit != NULL && bukkit->type == LOL_BUKKIT && bukkit->as.bukkit->visiting;
+         bukkit = lol_map_find(&bukkit->as.bukkit->slots, "parent")) {
+        bukkit->as.bukkit->visiting = 0;
+    }
+    if (slot == NULL) {
+        lol_fail("BUKKIT has no slot named \"%s\"", name);
+    }
+    return slot;
+}
+
+/* Returns the slot of a BUKKIT for writing. A slot inherited from a parent is copied into the
+ * BUKKIT first, so writes to it never change the parent. */
+static lol_value *lol_own_slot(lol_value *bukkit, const char *name) {
+    lol_value *slot = lol_slot(bukkit, name);
+    if (lol_map_find(&bukkit->as.bukkit->slots, name) == NULL) {
+        lol_map_insert(&bukkit->as.bukkit->slots, name, lol_copy(slot));
+    }
+    return lol_map_find(&bukkit->as.bukkit->slots, name);
+}
+
+/* Names resolved through `SRS` are interned, so they live as long as the program. */
+static char **lol_names;
+static size_t lol_name_count;
+static size_t lol_name_capacity;
+
+static const char *lol_intern(const char *name) {
+    size_t index;
+    for (index = 0; index < lol_name_count; index++) {
+        if (strcmp(lol_names[index], name) == 0) {
+            return lol_names[index];
+        }
+    }
+    if (lol_name_count == lol_name_capacity) {
+        lol_names = lol_grow(lol_names, &lol_name_capacity, sizeof(char *));
+    }
+    lol_names[lol_name_count] = lol_strdup(name);
+    return lol_names[lol_name_count++];
+}
+
+/* Returns the name an `SRS` identifier refers to, which is the value of the variable. */
+static const char *lol_srs(lol_scope *scope, const char *name) {
+    lol_value *value = lol_lookup(scope, name);
+    if (value->type != LOL_YARN) {
+        lol_fail("SRS expects a YARN, but found %s", lol_type_name(value));
+    }
+    return lol_intern(value->as.yarn);
+}
+
+/* Returns the name of the slot an `SRS` identifier accesses with `'Z`. NUMBRs index the slots of
+ * a BUKKIT used as a list. */
+static const char *lol_srs_slot(lol_scope *scope, const char *name) {
+    lol_value *value = lol_lookup(scope, name);
+    char buffer[16];
+    if (value->type == LOL_NUMBR) {
+        snprintf(buffer, sizeof(buffer), "%ld", (long)value->as.numbr);
+        return lol_intern(buffer);
+    }
+    if (value->type != LOL_YARN) {
+        lol_fail("slot names must be YARNs or NUMBRs, but found %s", lol_type_name(value));
+    }
+    return lol_intern(value->as.yarn);
+}
+
+static void lol_declare(lol_scope *scope, const char *name, lol_value value) {
+    lol_map_insert(&scope->variables, name, value);
+}
+
+static void lol_assign(lol_value *variable, lol_value value) {
+    lol_drop(*variable);
+    *variable = value;
+}
+
+static void lol_set_slot(lol_value *bukkit, const char *name, lol_value value) {
+    if (bukkit->type != LOL_BUKKIT) {
+        lol_fail("expected BUKKIT, but found %s", lol_type_name(bukkit));
+    }
+    lol_map_insert(&bukkit->as.bukkit->slots, name, value);
+}
+
+/* Adds one or subtracts one from a variable, as done by `UPPIN` and `NERFIN`. */
+static void lol_increment(lol_value *variable, int amount) {
+    if (variable->type == LOL_NUMBR) {
+        variable->as.numbr = lol_wrap((int64_t)variable->as.numbr + amount);
+    } else if (variable->type == LOL_NUMBAR) {
+        variable->as.numbar += (float)amount;
+    } else {
+        lol_fail("expected NUMBR, but found %s", lol_type_name(variable));
+    }
+}
+
+/* Evaluates a `:{var}` interpolation, which does not go through `SRS`. */
+static lol_value lol_interpolate(lol_scope *scope, const char *name) {
+    return lol_yarn_owned(lol_to_yarn(lol_copy(lol_lookup(scope, name))));
+}
+
+static void lol_set_it(lol_scope *scope, lol_value value) {
+    lol_assign(&scope->it, value);
+}
+
+/* Returns the value of `IT`, leaving NOOB in its place. */
+static lol_value lol_take_it(lol_scope *scope) {
+    lol_value value = scope->it;
+    scope->it = lol_noob();
+    return value;
+}
+
+/* Whether the value is exactly equal to `IT`, as done by `WTF?` cases. */
+static int lol_matches(lol_scope *scope, lol_value value) {
+    int matches = lol_equal(&value, &scope->it);
+    lol_drop(value);
+    return matches;
+}
+
+/* ---------------------------------------------------------------------------------------------
+ * Functions
+ * ------------------------------------------------------------------------------------------ */
+
+static void lol_define(lol_scope *scope, const char *name, lol_body body, size_t arity,
+                       const char *const *parameters) {
+    size_t index;
+    lol_function *function = NULL;
+    for (index = 0; index < scope->function_count; index++) {
+        if (strcmp(scope->functions[index].name, name) == 0) {
+            function = &scope->functions[index];
+        }
+    }
+    if (function == NULL) {
+        if (scope->function_count == scope->function_capacity) {
+            scope->functions =
+                lol_grow(scope->functions, &scope->function_capacity, sizeof(lol_function));
+        }
+        function = &scope->functions[scope->function_count++];
+        function->name = lol_strdup(name);
+    }
+    function->body = body;
+    function->arity = arity;
+    function->parameters = parameters;
+    function->scope = scope;
+}
+
+/* Starts a call to a function: checks the call depth, finds the function in the scope chain and
+ * checks the number of arguments. Must be paired with `lol_leave_function`. */
+static lol_function lol_enter_function(lol_scope *scope, const char *name, size_t arguments) {
+    size_t index;
+    if (lol_call_depth + 1 > LOL_MAX_CALL_DEPTH) {
+        lol_fail("program exceeded the limit of %d nested function calls", LOL_MAX_CALL_DEPTH);
+    }
+    lol_call_depth++;
+    for (; scope != NULL; scope = scope->parent) {
+        for (index = 0; index < scope->function_count; index++) {
+            lol_function *function = &scope->functions[index];
+            if (strcmp(function->name, name) != 0) {
+                continue;
+            }
+            if (function->arity != arguments) {
+                lol_fail("function \"%s\" expects %lu argument%s, but %lu %s given", name,
+                         (unsigned long)function->arity, function->arity == 1 ? "" : "s",
+                         (unsigned long)arguments, arguments == 1 ? "was" : "were");
+            }
+            return *function;
+        }
+    }
+    lol_fail("function \"%s\" was not declared", name);
+    exit(1);
+}
+
+static void lol_leave_function(void) {
+    lol_call_depth--;
+}
+
+/* ---------------------------------------------------------------------------------------------
+ * Built-in functions, defined in the root scope so that programs can declare their own instead
+ * ------------------------------------------------------------------------------------------ */
+
+static lol_bukkit *lol_expect_bukkit(lol_value *value) {
+    if (value->type != LOL_BUKKIT) {
+        lol_fail("expected BUKKIT, but found %s", lol_type_name(value));
+    }
+    return value->as.bukkit;
+}
+
+/* Returns the index a slot name stands for, if it is written the way NUMBRs are. */
+static int lol_list_index(const char *name, size_t *index) {
+    size_t digits = strspn(name, "0123456789");
+    if (digits == 0 || digits > 10 || name[digits] != '\0' || (name[0] == '0' && digits > 1)) {
+        return 0;
+    }
+    *index = strtoul(name, NULL, 10);
+    return 1;
+}
+
+/* Returns the number of items of a list: the slots named `0`, `1` and so on, up to the first
+ * missing index. Other slots, such as `parent`, are not items. */
+static int32_t lol_list_length(lol_bukkit *bukkit) {
+    size_t length = bukkit->slots.length, entry, index;
+    char *present = lol_alloc(length);
+    memset(present, 0, length);
+    for (entry = 0; entry < length; entry++) {
+        if (lol_list_index(bukkit->slots.entries[entry].name, &index) && index < length) {
+            present[index] = 1;
+        }
+    }
+    for (index = 0; index < length && present[index]; index++) {
+    }
+    free(present);
+    return (int32_t)index;
+}
+
+static lol_value lol_builtin_len(lol_scope *scope) {
+    lol_bukkit *bukkit = lol_expect_bukkit(lol_lookup(scope, "bukkit"));
+    return lol_numbr(lol_list_length(bukkit));
+}
+
+static lol_value lol_builtin_append(lol_scope *scope) {
+    lol_bukkit *bukkit = lol_expect_bukkit(lol_lookup(scope, "bukkit"));
+    int32_t index = lol_list_length(bukkit);
+    char name[16];
+    snprintf(name, sizeof(name), "%ld", (long)index);
+    lol_map_insert(&bukkit->slots, name, lol_copy(lol_lookup(scope, "value")));
+    return lol_numbr(index);
+}
+
+/* Removes a slot and returns its value. The items after a removed item move down in place, and
+ * the last one is removed, so the list has no holes and the other slots keep their order. */
+static lol_value lol_builtin_remove(lol_scope *scope) {
+    lol_bukkit *bukkit = lol_expect_bukkit(lol_lookup(scope, "bukkit"));
+    lol_value *slot = lol_lookup(scope, "slot"), *next, removed;
+    char name[16], *end;
+    long index, length;
+    int indexed = 1;
+    if (slot->type == LOL_NUMBR) {
+        index = slot->as.numbr;
+        snprintf(name, sizeof(name), "%ld", index);
+    } else if (slot->type == LOL_YARN) {
+        index = strtol(slot->as.yarn, &end, 10);
+        snprintf(name, sizeof(name), "%ld", index);
+        indexed = index >= INT32_MIN && index <= INT32_MAX && strcmp(name, slot->as.yarn) == 0;
+    } else {
+        lol_fail("slot names must be YARNs or NUMBRs, but found %s", lol_type_name(slot));
+        exit(1);
+    }
+    if (!indexed) {
+        if (lol_map_find(&bukkit->slots, slot->as.yarn) == NULL) {
+            lol_fail("BUKKIT has no slot named \"%s\"", slot->as.yarn);
+        }
+        removed = lol_copy(lol_map_find(&bukkit->slots, slot->as.yarn));
+        lol_map_remove(&bukkit->slots, slot->as.yarn);
+        return removed;
+    }
+    if (lol_map_find(&bukkit->slots, name) == NULL) {
+        lol_fail("BUKKIT has no slot named \"%s\"", name);
+    }
+    removed = lol_copy(lol_map_find(&bukkit->slots, name));
+    length = lol_list_length(bukkit);
+    for (; index >= 0 && index + 1 < length; index++) {
+        char next_name[16];
+        snprintf(next_name, sizeof(next_name), "%ld", index + 1);
+        next = lol_map_find(&bukkit->slots, next_name);
+        lol_map_insert(&bukkit->slots, name, lol_copy(next));
+        memcpy(name, next_name, sizeof(name));
+    }
+    lol_map_remove(&bukkit->slots, name);
+    return removed;
+}
+
+static const char *lol_expect_yarn(lol_value *value) {
+    if (value->type != LOL_YARN) {
+        lol_fail("expected YARN, but found %s", lol_type_name(value));
+    }
+    return value->as.yarn;
+}
+
+/* Returns the number of UTF-8 characters of the YARN, which are the bytes that do not continue
+ * a character. */
+static int32_t lol_character_count(const char *yarn) {
+    int32_t count = 0;
+    for (; *yarn != '\0'; yarn++) {
+        count += ((unsigned char)*yarn & 0xC0) != 0x80;
+    }
+    return count;
+}
+
+/* `STRING IZ LEN`, called as the function `STRING'Z LEN`. */
+static lol_value lol_string_len(lol_scope *scope) {
+    return lol_numbr(lol_character_count(lol_expect_yarn(lol_lookup(scope, "yarn"))));
+}
+
+/* `STRING IZ AT`, called as the function `STRING'Z AT`. Returns the character at the index of
+ * the YARN, counting from 0. */
+static lol_value lol_string_at(lol_scope *scope) {
+    const char *yarn = lol_expect_yarn(lol_lookup(scope, "yarn")), *start;
+    lol_value *index = lol_lookup(scope, "index");
+    int32_t remaining;
+    size_t length;
+    char *character;
+    if (index->type != LOL_NUMBR) {
+        lol_fail("expected NUMBR, but found %s", lol_type_name(index));
+    }
+    if (index->as.numbr < 0 || index->as.numbr >= lol_character_count(yarn)) {
+        lol_fail("index %ld is out of range for a YARN of %ld characters", (long)index->as.numbr,
+                 (long)lol_character_count(yarn));
+    }
+    start = yarn;
+    for (remaining = index->as.numbr; remaining > 0; remaining--) {
+        start++;
+        while (((unsigned char)*start & 0xC0) == 0x80) {
+            start++;
+        }
+    }
+    for (length = 1; ((unsigned char)start[length] & 0xC0) == 0x80; length++) {
+    }
+    character = lol_alloc(length + 1);
+    memcpy(character, start, length);
+    character[length] = '\0';
+    return lol_yarn_owned(character);
+}
+
+static const char *const lol_builtin_parameters[][2] = {
+    {"bukkit", NULL}, {"bukkit", "value"}, {"bukkit", "slot"}, {"yarn", NULL}, {"yarn", "index"}};
+
+/* Defines the built-in functions, and the methods of the native libraries as functions named
+ * `<library>'Z <method>`, which no LOLCODE function can be named. */
+static void lol_define_builtins(lol_scope *scope) {
+    lol_define(scope, "LEN", lol_builtin_len, 1, lol_builtin_parameters[0]);
+    lol_define(scope, "APPEND", lol_builtin_append, 2, lol_builtin_parameters[1]);
+    lol_define(scope, "REMOVE", lol_builtin_remove, 2, lol_builtin_parameters[2]);
+    lol_define(scope, "STRING'Z LEN", lol_string_len, 1, lol_builtin_parameters[3]);
+    lol_define(scope, "STRING'Z AT", lol_string_at, 2, lol_builtin_parameters[4]);
+}
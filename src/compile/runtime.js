@@ -0,0 +1,616 @@
+// Runtime bundled with the LOLCODE programs compiled to JavaScript.
+//
+// It mirrors the value model of the tree-walking interpreter: casts, operators and scoping follow
+// `cast.rs`, `operations.rs` and `variable_access.rs`. Values are represented as:
+//
+//   NOOB   -> null
+//   TROOF  -> boolean
+//   NUMBR  -> number, always a 32-bit integer
+//   NUMBAR -> Numbar, wrapping a number rounded to 32-bit float precision
+//   YARN   -> string
+//   BUKKIT -> Map from slot names to values
+//
+// Runtime methods taking a position `p` ("line:column", or null if unknown) report it in the
+// errors they raise.
+
+const MAX_CALL_DEPTH = 256;
+
+class Numbar {
+    constructor(value) {
+        this.value = Math.fround(value);
+    }
+}
+
+/** Raised when the program fails, with the message the interpreter would print. */
+export class LolcodeError extends Error {
+    constructor(message, file, position) {
+        super(message);
+        this.name = "LolcodeError";
+        this.file = file;
+        this.position = position;
+    }
+
+    /** Renders the error like the interpreter does. */
+    render() {
+        const location = this.position ? `${this.file}:${this.position}` : this.file;
+        return `error: ${this.message}\n --> ${location}`;
+    }
+}
+
+function typeName(value) {
+    if (value === null) return "NOOB";
+    if (typeof value === "boolean") return "TROOF";
+    if (typeof value === "number") return "NUMBR";
+    if (value instanceof Numbar) return "NUMBAR";
+    if (typeof value === "string") return "YARN";
+    return "BUKKIT";
+}
+
+function defaultValue(type) {
+    switch (type) {
+        case "TROOF":
+            return false;
+        case "NUMBR":
+            return 0;
+        case "NUMBAR":
+            return new Numbar(0);
+        case "YARN":
+            return "";
+        case "BUKKIT":
+            return new Map();
+        default:
+            return null;
+    }
+}
+
+/** Returns the BUKKIT of the parent chain that has the slot, or null if none has it. */
+function slotOwner(bukkit, slot) {
+    const visited = new Set();
+    while (bukkit instanceof Map && !visited.has(bukkit)) {
+        if (bukkit.has(slot)) return bukkit;
+        visited.add(bukkit);
+        bukkit = bukkit.get("parent");
+    }
+    return null;
+}
+
+function toTroof(value) {
+    if (value === null) return false;
+    if (typeof value === "boolean") return value;
+    if (typeof value === "number") return value !== 0;
+    if (value instanceof Numbar) return value.value !== 0;
+    if (typeof value === "string") return value !== "";
+    return true;
+}
+
+/**
+ * Equality as done by `WTF?` cases: same type and same value, without any casting. BUKKITs are
+ * shared by reference, so they are only equal to themselves.
+ */
+function equal(left, right) {
+    if (typeName(left) !== typeName(right)) return false;
+    if (left instanceof Numbar) return left.value === right.value;
+    return left === right;
+}
+
+/** Equality as done by `BOTH SAEM`: NUMBRs and NUMBARs are compared as NUMBARs. */
+function isSaem(left, right) {
+    if (typeof left === "number" && right instanceof Numbar) {
+        return Math.fround(left) === right.value;
+    }
+    if (left instanceof Numbar && typeof right === "number") {
+        return left.value === Math.fround(right);
+    }
+    return equal(left, right);
+}
+
+/** Converts like Rust's `as i32`: saturating, with NaN becoming 0. */
+function numbarToNumbr(numbar) {
+    if (Number.isNaN(numbar)) return 0;
+    return Math.max(-2147483648, Math.min(2147483647, Math.trunc(numbar)));
+}
+
+/** NUMBARs are cast to YARNs truncated to two decimal places. */
+function numbarToYarn(numbar) {
+    // From 2^23 on, NUMBARs have no fractional part, and scaling them could overflow.
+    const truncated = Math.abs(numbar) < 8388608
+        ? Math.fround(Math.trunc(Math.fround(numbar * 100)) / 100)
+        : numbar;
+    if (Number.isNaN(truncated)) return "NaN";
+    if (truncated === Infinity) return "inf";
+    if (truncated === -Infinity) return "-inf";
+    // `toFixed` prints the negative zero that -0.001 truncates to as "0.00", like the interpreter.
+    // Large floats are integers, which `toFixed` would print in exponent notation.
+    if (Math.abs(truncated) >= 1e21) return `${BigInt(truncated)}.00`;
+    return truncated.toFixed(2);
+}
+
+function parseNumbr(text) {
+    if (!/^[+-]?[0-9]+$/.test(text)) return null;
+    const numbr = BigInt(text);
+    if (numbr < -2147483648n || numbr > 2147483647n) return null;
+    return Number(numbr);
+}
+
+/**
+ * Accepts the same syntax as Rust's `f32::from_str`, which the interpreter uses, but only finite
+ * numbers, like the interpreter does.
+ */
+function parseNumbar(text) {
+    if (!/^[+-]?([0-9]+\.?[0-9]*|\.[0-9]+)([eE][+-]?[0-9]+)?$/.test(text)) return null;
+    const numbar = Math.fround(parseFloat(text));
+    return Number.isFinite(numbar) ? numbar : null;
+}
+
+/** Quotes a YARN inside a BUKKIT rendering, escaping it like Rust's `{:?}` does. */
+function quote(yarn) {
+    let quoted = '"';
+    for (const char of yarn) {
+        const code = char.codePointAt(0);
+        if (char === '"') quoted += '\\"';
+        else if (char === "\\") quoted += "\\\\";
+        else if (char === "\n") quoted += "\\n";
+        else if (char === "\r") quoted += "\\r";
+        else if (char === "\t") quoted += "\\t";
+        else if (char === "\0") quoted += "\\0";
+        else if (code < 0x20 || code === 0x7f) quoted += `\\u{${code.toString(16)}}`;
+        else quoted += char;
+    }
+    return quoted + '"';
+}
+
+class Scope {
+    constructor(parent) {
+        this.parent = parent;
+        this.variables = new Map();
+        this.functions = new Map();
+        this.it = null;
+    }
+
+    /** Returns the scope declaring the variable, if any. */
+    find(name) {
+        for (let scope = this; scope !== null; scope = scope.parent) {
+            if (scope.variables.has(name)) return scope;
+        }
+        return null;
+    }
+
+    /** Returns the value of `IT`, leaving NOOB in its place. */
+    takeIt() {
+        const value = this.it;
+        this.it = null;
+        return value;
+    }
+
+    /** Function scopes are children of the scope the function was declared in. */
+    define(name, body, parameters) {
+        this.functions.set(name, { body, parameters, scope: this });
+    }
+}
+
+class Runtime {
+    constructor(io, file) {
+        this.visible = io.visible ?? defaultVisible();
+        this.gimmeh = io.gimmeh ?? (() => null);
+        this.file = file;
+        this.position = null;
+        this.callDepth = 0;
+        this.maxCallDepth = io.maxCallDepth ?? MAX_CALL_DEPTH;
+    }
+
+    fail(message) {
+        throw new LolcodeError(message, this.file, this.position);
+    }
+
+    illegalCast(value, to) {
+        this.fail(`cannot cast ${typeName(value)} to ${to}`);
+    }
+
+    /** Implicit cast, used when an operator expects a given type. */
+    implicitCast(value, to) {
+        const from = typeName(value);
+        if (to === "NOOB") return null;
+        if (to === "TROOF") return toTroof(value);
+        if (from === "NOOB" || from === "BUKKIT" || to === "BUKKIT") {
+            if (from === "BUKKIT" && to === "BUKKIT") return value;
+            this.illegalCast(value, to);
+        }
+        if (from === "TROOF") {
+            if (to === "NUMBR") return value ? 1 : 0;
+            if (to === "NUMBAR") return new Numbar(value ? 1 : 0);
+            return value ? "WIN" : "FAIL";
+        }
+        if (from === "NUMBR") {
+            if (to === "NUMBR") return value;
+            if (to === "NUMBAR") return new Numbar(value);
+            return String(value);
+        }
+        if (from === "NUMBAR") {
+            if (to === "NUMBR") return numbarToNumbr(value.value);
+            if (to === "NUMBAR") return value;
+            return numbarToYarn(value.value);
+        }
+        if (to === "YARN") return value;
+        const text = value.trim();
+        if (to === "NUMBR") {
+            let numbr = parseNumbr(text);
+            if (numbr === null) {
+                const numbar = parseNumbar(text);
+                if (numbar !== null) numbr = numbarToNumbr(numbar);
+            }
+            if (numbr === null) this.illegalCast(value, to);
+            return numbr;
+        }
+        const numbar = parseNumbar(text);
+        if (numbar === null) this.illegalCast(value, to);
+        return new Numbar(numbar);
+    }
+
+    toYarn(value) {
+        return this.implicitCast(value, "YARN");
+    }
+
+    /** Casts to a NUMBR, or to a NUMBAR for YARNs with a decimal point. Returns null on error. */
+    toNumber(value) {
+        if (typeof value === "number" || value instanceof Numbar) return value;
+        try {
+            if (typeof value === "string" && value.includes(".")) {
+                return this.implicitCast(value, "NUMBAR");
+            }
+            return this.implicitCast(value, "NUMBR");
+        } catch (error) {
+            if (error instanceof LolcodeError) return null;
+            throw error;
+        }
+    }
+
+    /** Explicit cast, as done by `MAEK`. A NOOB can be cast to any type, becoming its default. */
+    cast(p, value, to) {
+        this.position = p;
+        if (value === null) return defaultValue(to);
+        return this.implicitCast(value, to);
+    }
+
+    binary(p, operation, left, right) {
+        this.position = p;
+        switch (operation) {
+            case "BOTH SAEM":
+                return isSaem(left, right);
+            case "DIFFRINT":
+                return !isSaem(left, right);
+            case "WON OF":
+                return toTroof(left) !== toTroof(right);
+        }
+
+        const l = this.toNumber(left);
+        const r = this.toNumber(right);
+        if (l === null || r === null) {
+            const [lt, rt] = [typeName(left), typeName(right)];
+            switch (operation) {
+                case "SUM OF":
+                    this.fail(`cannot add ${rt} to ${lt}`);
+                case "DIFF OF":
+                    this.fail(`cannot subtract ${rt} from ${lt}`);
+                case "PRODUKT OF":
+                    this.fail(`cannot multiply ${lt} by ${rt}`);
+                case "QUOSHUNT OF":
+                    this.fail(`cannot divide ${lt} by ${rt}`);
+                case "MOD OF":
+                    this.fail(`cannot take the remainder of dividing ${lt} by ${rt}`);
+                default:
+                    this.fail(`cannot compare ${lt} and ${rt}`);
+            }
+        }
+
+        if (typeof l === "number" && typeof r === "number") {
+            switch (operation) {
+                case "SUM OF":
+                    return (l + r) | 0;
+                case "DIFF OF":
+                    return (l - r) | 0;
+                case "PRODUKT OF":
+                    return Math.imul(l, r);
+                case "QUOSHUNT OF":
+                    if (r === 0) this.fail("division by zero");
+                    return Math.trunc(l / r) | 0;
+                case "MOD OF":
+                    if (r === 0) this.fail("division by zero");
+                    return (l % r) | 0;
+                case "BIGGR OF":
+                    return l > r;
+                default:
+                    return l < r;
+            }
+        }
+
+        const a = l instanceof Numbar ? l.value : Math.fround(l);
+        const b = r instanceof Numbar ? r.value : Math.fround(r);
+        switch (operation) {
+            case "SUM OF":
+                return new Numbar(a + b);
+            case "DIFF OF":
+                return new Numbar(a - b);
+            case "PRODUKT OF":
+                return new Numbar(a * b);
+            case "QUOSHUNT OF":
+                return new Numbar(a / b);
+            case "MOD OF":
+                return new Numbar(a % b);
+            case "BIGGR OF":
+                return a > b;
+            default:
+                return a < b;
+        }
+    }
+
+    test(value) {
+        return toTroof(value);
+    }
+
+    /** Appends the YARN cast of the value to the YARN, as done by `SMOOSH`. */
+    smoosh(p, yarn, value) {
+        this.position = p;
+        return yarn + this.toYarn(value);
+    }
+
+    /**
+     * Renders a value like its `Display` implementation in the interpreter. BUKKITs list their
+     * slots in the order they were added, and a BUKKIT found inside itself is rendered as `{...}`.
+     */
+    render(value, rendering = new Set()) {
+        if (value === null) return "NOOB";
+        if (!(value instanceof Map)) return this.toYarn(value);
+        if (rendering.has(value)) return "{...}";
+        rendering.add(value);
+        const rendered = [...value].map(([name, slot]) => {
+            const text = typeof slot === "string" ? quote(slot) : this.render(slot, rendering);
+            return `${name}: ${text}`;
+        });
+        rendering.delete(value);
+        return `{${rendered.join(", ")}}`;
+    }
+
+    /** Prints a value for `VISIBLE`. This is the YARN cast, except that BUKKITs are rendered. */
+    async print(p, value, separator) {
+        this.position = p;
+        const text = value instanceof Map ? this.render(value) : this.toYarn(value);
+        await this.visible(separator ? ` ${text}` : text);
+    }
+
+    async write(text) {
+        await this.visible(text);
+    }
+
+    async readLine(p) {
+        this.position = p;
+        let line = await this.gimmeh();
+        if (line === null || line === undefined) {
+            this.fail("I/O error: reached the end of the input");
+        }
+        line = String(line);
+        if (line.endsWith("\n")) {
+            line = line.slice(0, -1);
+            if (line.endsWith("\r")) line = line.slice(0, -1);
+        }
+        return line;
+    }
+
+    /** Returns the name an `SRS` identifier refers to, which is the value of the variable. */
+    srs(p, scope, name) {
+        const value = this.read(p, scope, name, []);
+        if (typeof value !== "string") this.fail(`SRS expects a YARN, but found ${typeName(value)}`);
+        return value;
+    }
+
+    /**
+     * Returns the name of the slot an `SRS` identifier accesses with `'Z`. NUMBRs index the slots
+     * of a BUKKIT used as a list.
+     */
+    srsSlot(p, scope, name) {
+        const value = this.read(p, scope, name, []);
+        if (typeof value === "number") return String(value);
+        if (typeof value !== "string") {
+            this.fail(`slot names must be YARNs or NUMBRs, but found ${typeName(value)}`);
+        }
+        return value;
+    }
+
+    /**
+     * Returns the map holding the accessed value and its key, checking that they exist. Slots
+     * missing from a BUKKIT are found in its parent chain. A slot that is written to is copied
+     * into its BUKKIT first when it is inherited, so writes never change the parent.
+     */
+    place(p, scope, name, path, write = true) {
+        this.position = p;
+        let map;
+        if (name === "IT") {
+            // `IT` is the one of the current scope, not a declared variable.
+            map = { get: () => scope.it, set: (_, value) => (scope.it = value) };
+        } else {
+            const declaring = scope.find(name);
+            if (declaring === null) this.fail(`variable "${name}" was not declared`);
+            map = declaring.variables;
+        }
+        let key = name;
+        for (const [index, slot] of path.entries()) {
+            const bukkit = map.get(key);
+            if (!(bukkit instanceof Map)) this.fail(`expected BUKKIT, but found ${typeName(bukkit)}`);
+            let owner = slotOwner(bukkit, slot);
+            if (owner === null) this.fail(`BUKKIT has no slot named "${slot}"`);
+            if (write && index === path.length - 1 && owner !== bukkit) {
+                bukkit.set(slot, owner.get(slot));
+                owner = bukkit;
+            }
+            map = owner;
+            key = slot;
+        }
+        return { map, key };
+    }
+
+    read(p, scope, name, path) {
+        const { map, key } = this.place(p, scope, name, path, false);
+        return map.get(key);
+    }
+
+    /** Evaluates a `:{var}` interpolation, which does not go through `SRS`. */
+    interpolate(p, scope, name) {
+        return this.toYarn(this.read(p, scope, name, []));
+    }
+
+    declare(scope, value, name) {
+        scope.variables.set(name, value);
+    }
+
+    assign(value, { map, key }) {
+        map.set(key, value);
+    }
+
+    setSlot(p, value, { map, key }, slot) {
+        this.position = p;
+        const bukkit = map.get(key);
+        if (!(bukkit instanceof Map)) this.fail(`expected BUKKIT, but found ${typeName(bukkit)}`);
+        bukkit.set(slot, value);
+    }
+
+    /** Adds one or subtracts one from a variable, as done by `UPPIN` and `NERFIN`. */
+    increment(p, { map, key }, amount) {
+        this.position = p;
+        const value = map.get(key);
+        if (typeof value === "number") map.set(key, (value + amount) | 0);
+        else if (value instanceof Numbar) map.set(key, new Numbar(value.value + amount));
+        else this.fail(`expected NUMBR, but found ${typeName(value)}`);
+    }
+
+    /** Whether the value is exactly equal to `IT`, as done by `WTF?` cases. */
+    matches(scope, value) {
+        return equal(value, scope.it);
+    }
+
+    /** Starts a call: checks the call depth, finds the function and checks the arguments. */
+    enterFunction(p, scope, name, count) {
+        this.position = p;
+        if (this.callDepth + 1 > this.maxCallDepth) {
+            this.fail(`program exceeded the limit of ${this.maxCallDepth} nested function calls`);
+        }
+        this.callDepth += 1;
+        for (; scope !== null; scope = scope.parent) {
+            const fn = scope.functions.get(name);
+            if (fn === undefined) continue;
+            if (fn.parameters.length !== count) {
+                const expected = fn.parameters.length;
+                this.fail(
+                    `function "${name}" expects ${expected} argument${expected === 1 ? "" : "s"}, ` +
+                        `but ${count} ${count === 1 ? "was" : "were"} given`
+                );
+            }
+            return fn;
+        }
+        this.fail(`function "${name}" was not declared`);
+    }
+
+    leaveFunction() {
+        this.callDepth -= 1;
+    }
+
+    /**
+     * Defines the built-in functions in the root scope, so that programs can declare their own
+     * instead.
+     */
+    defineBuiltins(scope) {
+        const bukkit = (scope) => {
+            const value = scope.variables.get("bukkit");
+            if (!(value instanceof Map)) this.fail(`expected BUKKIT, but found ${typeName(value)}`);
+            return value;
+        };
+        // The items of a list are the slots named `0`, `1` and so on, up to the first missing
+        // index. Other slots, such as `parent`, are not items. The length is kept on the list,
+        // and only counted again from the start when its last item was removed.
+        const length = (list) => {
+            let length = list.items ?? 0;
+            if (length > 0 && !list.has(String(length - 1))) length = 0;
+            while (list.has(String(length))) length++;
+            list.items = length;
+            return length;
+        };
+        scope.define("LEN", async (scope) => length(bukkit(scope)), ["bukkit"]);
+        scope.define(
+            "APPEND",
+            async (scope) => {
+                const list = bukkit(scope);
+                const index = length(list);
+                list.set(String(index), scope.variables.get("value"));
+                return index;
+            },
+            ["bukkit", "value"]
+        );
+        // The items after a removed item move down in place, and the last one is removed, so the
+        // list has no holes and the other slots keep their order.
+        scope.define(
+            "REMOVE",
+            async (scope) => {
+                const list = bukkit(scope);
+                const slot = scope.variables.get("slot");
+                if (typeof slot !== "number" && typeof slot !== "string") {
+                    this.fail(`slot names must be YARNs or NUMBRs, but found ${typeName(slot)}`);
+                }
+                let name = String(slot);
+                if (!list.has(name)) this.fail(`BUKKIT has no slot named "${name}"`);
+                const removed = list.get(name);
+                let index = Number(name) | 0;
+                const items = length(list);
+                if (String(index) === name && index >= 0 && index < items) {
+                    for (; index + 1 < items; index++) {
+                        list.set(String(index), list.get(String(index + 1)));
+                    }
+                    name = String(index);
+                }
+                list.delete(name);
+                return removed;
+            },
+            ["bukkit", "slot"]
+        );
+
+        // The methods of the native libraries are functions named `<library>'Z <method>`, which no
+        // LOLCODE function can be named.
+        const characters = (scope) => {
+            const yarn = scope.variables.get("yarn");
+            if (typeof yarn !== "string") this.fail(`expected YARN, but found ${typeName(yarn)}`);
+            return [...yarn];
+        };
+        scope.define("STRING'Z LEN", async (scope) => characters(scope).length, ["yarn"]);
+        scope.define(
+            "STRING'Z AT",
+            async (scope) => {
+                const yarn = characters(scope);
+                const index = scope.variables.get("index");
+                if (typeof index !== "number") {
+                    this.fail(`expected NUMBR, but found ${typeName(index)}`);
+                }
+                if (index < 0 || index >= yarn.length) {
+                    this.fail(
+                        `index ${index} is out of range for a YARN of ${yarn.length} characters`
+                    );
+                }
+                return yarn[index];
+            },
+            ["yarn", "index"]
+        );
+    }
+
+    failAt(p, message) {
+        this.position = p;
+        this.fail(message);
+    }
+}
+
+/** Writes to stdout under Node, and logs each line to the console elsewhere. */
+function defaultVisible() {
+    const stdout = globalThis.process?.stdout;
+    if (stdout) return (text) => stdout.write(text);
+    let line = "";
+    return (text) => {
+        const lines = (line + text).split("\n");
+        line = lines.pop();
+        for (const complete of lines) console.log(complete);
+    };
+}
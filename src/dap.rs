@@ -0,0 +1,506 @@
+//! Debug adapter for the tree-walking interpreter, speaking the Debug Adapter Protocol over stdin
+//! and stdout.
+//!
+//! Messages are read on a separate thread, so `pause` requests are seen while the program runs.
+//! Everything else happens on the main thread, in the hook the interpreter calls before each
+//! statement. Since stdin and stdout carry the protocol, the program's output is sent as `output`
+//! events, and `GIMMEH` always finds the input closed.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::mpsc::{channel, Receiver};
+
+use serde_json::{json, Value as Json};
+
+use crate::error::Span;
+use crate::execute_statement;
+use crate::protocol::{read_message, write_message};
+use crate::runtime::{Runtime, StatementHook};
+use crate::source::parse;
+use crate::types::{RuntimeErrorKind, RuntimeResult, Scope, Value};
+
+/// The program only has one thread.
+const THREAD_ID: u64 = 1;
+
+/// When the program should pause next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Only pause on breakpoints.
+    Running,
+    /// Pause before the next statement, reporting the given reason.
+    Pause(&'static str),
+    StepIn,
+    /// Pause before the next statement at this call depth or lower.
+    StepOver(usize),
+    /// Pause before the next statement below this call depth.
+    StepOut(usize),
+}
+
+enum Action {
+    Start,
+    Resume,
+    Disconnect,
+}
+
+struct Frame {
+    name: String,
+    line: usize,
+}
+
+struct Session {
+    messages: Receiver<Json>,
+    output: RefCell<std::io::Stdout>,
+    seq: Cell<u64>,
+
+    program: RefCell<Option<(PathBuf, String)>>,
+    breakpoints: RefCell<HashSet<usize>>,
+    mode: Cell<Mode>,
+    frames: RefCell<Vec<Frame>>,
+
+    /// Whether the program is paused, in which case the scopes can be inspected.
+    paused: Cell<bool>,
+    /// The scopes of the chain of the paused statement, from innermost to outermost.
+    scopes: RefCell<Vec<Json>>,
+    /// Variables shown at the pause. A `variablesReference` is an index in this list plus one.
+    /// Each scope has an entry, and BUKKITs get their own entry when they are expanded.
+    variables: RefCell<Vec<Vec<(String, Value)>>>,
+}
+
+/// Sends the output of the program to the editor.
+struct OutputEvents(Rc<Session>);
+
+impl Write for OutputEvents {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.send_event(
+            "output",
+            json!({ "category": "stdout", "output": String::from_utf8_lossy(buf) }),
+        )?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn sorted_slots(variables: impl Iterator<Item = (String, Value)>) -> Vec<(String, Value)> {
+    let mut variables: Vec<(String, Value)> = variables.collect();
+    variables.sort_by(|(left, _), (right, _)| left.cmp(right));
+    variables
+}
+
+/// Starts the debug adapter on stdin and stdout, returning once the editor disconnects.
+pub fn start() -> Result<(), String> {
+    let (sender, receiver) = channel();
+    std::thread::spawn(move || {
+        let stdin = std::io::stdin();
+        let mut input = stdin.lock();
+        while let Ok(Some(message)) = read_message(&mut input) {
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    let session = Rc::new(Session {
+        messages: receiver,
+        output: RefCell::new(std::io::stdout()),
+        seq: Cell::new(0),
+        program: RefCell::new(None),
+        breakpoints: RefCell::new(HashSet::new()),
+        mode: Cell::new(Mode::Running),
+        frames: RefCell::new(vec![]),
+        paused: Cell::new(false),
+        scopes: RefCell::new(vec![]),
+        variables: RefCell::new(vec![]),
+    });
+    session.run().map_err(|err| err.to_string())
+}
+
+impl Session {
+    fn run(self: &Rc<Self>) -> std::io::Result<()> {
+        // Breakpoints are set between the `launch` and `configurationDone` requests.
+        loop {
+            let message = match self.messages.recv() {
+                Ok(message) => message,
+                Err(_) => return Ok(()),
+            };
+            match self.dispatch(&message)? {
+                Some(Action::Start) => break,
+                Some(Action::Disconnect) => return Ok(()),
+                Some(Action::Resume) | None => {}
+            }
+        }
+
+        let exit_code = self.run_program()?;
+        self.send_event("exited", json!({ "exitCode": exit_code }))?;
+        self.send_event("terminated", json!({}))?;
+
+        while let Ok(message) = self.messages.recv() {
+            if let Some(Action::Disconnect) = self.dispatch(&message)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the launched program, returning its exit code.
+    fn run_program(self: &Rc<Self>) -> std::io::Result<i32> {
+        let (path, source) = match self.program.borrow().clone() {
+            Some(program) => program,
+            None => {
+                self.send_error_output("no program was launched")?;
+                return Ok(1);
+            }
+        };
+        let file_name = path.display().to_string();
+        let (ast, parsed) = match parse(&source) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                self.send_error_output(&error.render(&source, &file_name))?;
+                return Ok(1);
+            }
+        };
+
+        let runtime = Runtime::new(
+            Box::new(OutputEvents(self.clone())),
+            Box::new(std::io::empty()),
+        )
+        .with_hook(self.clone());
+        let mut scope = Scope::with_runtime(Rc::new(runtime));
+        scope.source = Rc::new(parsed);
+        self.frames.borrow_mut().push(Frame {
+            name: "main".to_string(),
+            line: 1,
+        });
+
+        for node in ast.0.iter() {
+            match execute_statement(&mut scope, node) {
+                Ok(_) => {}
+                Err(error) if error.kind == RuntimeErrorKind::Terminated => return Ok(1),
+                Err(error) => {
+                    self.send_error_output(&error.render(&source, &file_name))?;
+                    return Ok(1);
+                }
+            }
+        }
+        Ok(0)
+    }
+
+    fn send(&self, mut message: Json) -> std::io::Result<()> {
+        let seq = self.seq.get() + 1;
+        self.seq.set(seq);
+        message["seq"] = json!(seq);
+        write_message(&mut *self.output.borrow_mut(), &message)
+    }
+
+    fn send_event(&self, event: &str, body: Json) -> std::io::Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn send_error_output(&self, error: &str) -> std::io::Result<()> {
+        self.send_event(
+            "output",
+            json!({ "category": "stderr", "output": format!("{}\n", error) }),
+        )
+    }
+
+    /// Handles a request from the editor, returning what the program should do next.
+    fn dispatch(&self, message: &Json) -> std::io::Result<Option<Action>> {
+        if message["type"] != "request" {
+            return Ok(None);
+        }
+        let command = message["command"].as_str().unwrap_or_default();
+        let arguments = &message["arguments"];
+
+        let (result, action) = match self.handle_request(command, arguments) {
+            Ok((body, action)) => (Ok(body), action),
+            Err(error) => (Err(error), None),
+        };
+        let mut response = json!({
+            "type": "response",
+            "request_seq": message["seq"],
+            "command": command,
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(body) => response["body"] = body,
+            Err(error) => response["message"] = json!(error),
+        };
+        self.send(response)?;
+
+        if command == "initialize" {
+            self.send_event("initialized", json!({}))?;
+        }
+        Ok(action)
+    }
+
+    fn handle_request(
+        &self,
+        command: &str,
+        arguments: &Json,
+    ) -> Result<(Json, Option<Action>), String> {
+        let depth = self.frames.borrow().len();
+        let resume = |mode: Mode| {
+            self.mode.set(mode);
+            Ok((json!({}), Some(Action::Resume)))
+        };
+
+        match command {
+            "initialize" => Ok((json!({ "supportsConfigurationDoneRequest": true }), None)),
+            "launch" => {
+                let path = arguments["program"]
+                    .as_str()
+                    .ok_or("launch requires a program")?;
+                let source = std::fs::read_to_string(path)
+                    .map_err(|err| format!("failed to read {}: {}", path, err))?;
+                *self.program.borrow_mut() = Some((PathBuf::from(path), source));
+                if arguments["stopOnEntry"] == true {
+                    self.mode.set(Mode::Pause("entry"));
+                }
+                Ok((json!({}), None))
+            }
+            "setBreakpoints" => {
+                let lines: Vec<u64> = arguments["breakpoints"]
+                    .as_array()
+                    .map(|breakpoints| {
+                        breakpoints
+                            .iter()
+                            .filter_map(|breakpoint| breakpoint["line"].as_u64())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                *self.breakpoints.borrow_mut() = lines.iter().map(|line| *line as usize).collect();
+                let breakpoints: Vec<Json> = lines
+                    .iter()
+                    .map(|line| json!({ "verified": true, "line": line }))
+                    .collect();
+                Ok((json!({ "breakpoints": breakpoints }), None))
+            }
+            "setExceptionBreakpoints" => Ok((json!({ "breakpoints": [] }), None)),
+            "configurationDone" => Ok((json!({}), Some(Action::Start))),
+            "threads" => Ok((
+                json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] }),
+                None,
+            )),
+            "stackTrace" => {
+                let path = self
+                    .program
+                    .borrow()
+                    .as_ref()
+                    .map(|(path, _)| path.display().to_string());
+                let frames: Vec<Json> = self
+                    .frames
+                    .borrow()
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(id, frame)| {
+                        json!({
+                            "id": id,
+                            "name": frame.name,
+                            "line": frame.line,
+                            "column": 1,
+                            "source": { "path": path },
+                        })
+                    })
+                    .collect();
+                let total_frames = frames.len();
+                Ok((
+                    json!({ "stackFrames": frames, "totalFrames": total_frames }),
+                    None,
+                ))
+            }
+            "scopes" => {
+                // Only the scopes of the paused statement are reachable.
+                let top_frame = depth.checked_sub(1).map(|id| id as u64);
+                let is_top_frame =
+                    top_frame.is_some() && arguments["frameId"].as_u64() == top_frame;
+                let scopes = match self.paused.get() && is_top_frame {
+                    true => self.scopes.borrow().clone(),
+                    false => vec![],
+                };
+                Ok((json!({ "scopes": scopes }), None))
+            }
+            "variables" => {
+                let reference = arguments["variablesReference"].as_u64().unwrap_or(0) as usize;
+                Ok((
+                    json!({ "variables": self.expand_variables(reference) }),
+                    None,
+                ))
+            }
+            "continue" => resume(Mode::Running),
+            "next" => resume(Mode::StepOver(depth)),
+            "stepIn" => resume(Mode::StepIn),
+            "stepOut" => resume(Mode::StepOut(depth)),
+            "pause" => {
+                self.mode.set(Mode::Pause("pause"));
+                Ok((json!({}), None))
+            }
+            "disconnect" | "terminate" => Ok((json!({}), Some(Action::Disconnect))),
+            _ => Err(format!("unsupported request {}", command)),
+        }
+    }
+
+    fn expand_variables(&self, reference: usize) -> Vec<Json> {
+        let entries = match reference.checked_sub(1) {
+            Some(index) => self
+                .variables
+                .borrow()
+                .get(index)
+                .cloned()
+                .unwrap_or_default(),
+            None => vec![],
+        };
+
+        let mut variables = vec![];
+        for (name, value) in entries {
+            let (text, reference) = match &value {
+                Value::Bukkit(bukkit) => {
+                    let slots = bukkit
+                        .borrow()
+                        .slots
+                        .iter()
+                        .map(|(name, value)| (name.clone(), value.clone()))
+                        .collect();
+                    let mut containers = self.variables.borrow_mut();
+                    containers.push(slots);
+                    (bukkit.to_string(), containers.len())
+                }
+                Value::Yarn(yarn) => (format!("{:?}", yarn), 0),
+                value => (value.to_string(), 0),
+            };
+            variables.push(json!({
+                "name": name,
+                "value": text,
+                "type": value.type_name(),
+                "variablesReference": reference,
+            }));
+        }
+        variables
+    }
+
+    /// Keeps the chain of scopes of the paused statement, so the editor can inspect it.
+    fn snapshot_scopes(&self, scope: &Scope) {
+        let mut containers = self.variables.borrow_mut();
+        let mut scopes = vec![];
+        containers.clear();
+
+        let mut current = Some(scope);
+        while let Some(scope) = current {
+            let mut variables = sorted_slots(
+                scope
+                    .variables
+                    .borrow()
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.clone())),
+            );
+            variables.insert(0, ("IT".to_string(), scope.it.borrow().clone()));
+            containers.push(variables);
+
+            let name = match (scope.parent_scope, scopes.len()) {
+                (None, _) => "Global".to_string(),
+                (Some(_), 0) => "Local".to_string(),
+                (Some(_), depth) => format!("Parent {}", depth),
+            };
+            scopes.push(json!({
+                "name": name,
+                "variablesReference": containers.len(),
+                "expensive": false,
+            }));
+            current = scope.parent_scope;
+        }
+        *self.scopes.borrow_mut() = scopes;
+    }
+
+    /// Waits for the editor to resume the program.
+    fn pause(&self, scope: &Scope, reason: &str) -> RuntimeResult<()> {
+        let io_error = |error: std::io::Error| RuntimeErrorKind::Io(error.to_string());
+
+        self.snapshot_scopes(scope);
+        self.paused.set(true);
+        self.send_event(
+            "stopped",
+            json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }),
+        )
+        .map_err(io_error)?;
+
+        let result = loop {
+            let message = match self.messages.recv() {
+                Ok(message) => message,
+                Err(_) => break Err(RuntimeErrorKind::Terminated.into()),
+            };
+            match self.dispatch(&message).map_err(io_error)? {
+                Some(Action::Resume) => break Ok(()),
+                Some(Action::Disconnect) => break Err(RuntimeErrorKind::Terminated.into()),
+                Some(Action::Start) | None => {}
+            }
+        };
+
+        self.paused.set(false);
+        self.variables.borrow_mut().clear();
+        self.scopes.borrow_mut().clear();
+        result
+    }
+}
+
+impl StatementHook for Session {
+    fn before_statement(&self, scope: &Scope, span: Option<Span>) -> RuntimeResult<()> {
+        // Handle the requests sent while the program was running, such as `pause`.
+        while let Ok(message) = self.messages.try_recv() {
+            let action = self
+                .dispatch(&message)
+                .map_err(|error| RuntimeErrorKind::Io(error.to_string()))?;
+            if let Some(Action::Disconnect) = action {
+                return Err(RuntimeErrorKind::Terminated.into());
+            }
+        }
+
+        // Statements without a position in the source cannot be paused on.
+        let line = match (span, self.program.borrow().as_ref()) {
+            (Some(span), Some((_, source))) => span.line_and_column(source).0,
+            _ => return Ok(()),
+        };
+        if let Some(frame) = self.frames.borrow_mut().last_mut() {
+            frame.line = line;
+        }
+
+        let depth = self.frames.borrow().len();
+        let reason = match self.mode.get() {
+            Mode::Pause(reason) => Some(reason),
+            Mode::StepIn => Some("step"),
+            Mode::StepOver(step_depth) if depth <= step_depth => Some("step"),
+            Mode::StepOut(step_depth) if depth < step_depth => Some("step"),
+            _ => None,
+        }
+        .or_else(|| {
+            self.breakpoints
+                .borrow()
+                .contains(&line)
+                .then_some("breakpoint")
+        });
+
+        match reason {
+            Some(reason) => {
+                self.mode.set(Mode::Running);
+                self.pause(scope, reason)
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn enter_function(&self, name: &str) {
+        let line = self.frames.borrow().last().map_or(1, |frame| frame.line);
+        self.frames.borrow_mut().push(Frame {
+            name: name.to_string(),
+            line,
+        });
+    }
+
+    fn exit_function(&self) {
+        self.frames.borrow_mut().pop();
+    }
+}
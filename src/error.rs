@@ -0,0 +1,379 @@
+use lolcode_ast::lexer::Token;
+use lolcode_ast::parser::expression::{ASTExpression, ASTType, Identifier, VariableAccess};
+use lolcode_ast::parser::statements::assignment::VariableAssignment;
+use lolcode_ast::parser::statements::bukkit_set_slot::BukkitSetSlot;
+use lolcode_ast::parser::statements::how_iz_i::HowIzI;
+use lolcode_ast::parser::statements::i_has_a::IHasA;
+use lolcode_ast::parser::statements::i_is::IIz;
+use lolcode_ast::parser::statements::im_in_yr::{ImInYr, LoopCondition};
+use lolcode_ast::parser::statements::visible::Visible;
+use lolcode_ast::parser::statements::ASTNode;
+
+/// A range of bytes in the source code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl From<&Token> for Span {
+    fn from(token: &Token) -> Self {
+        Span {
+            start: token.range.start,
+            end: token.range.end,
+        }
+    }
+}
+
+impl From<&Identifier> for Span {
+    fn from(identifier: &Identifier) -> Self {
+        (&identifier.token).into()
+    }
+}
+
+impl From<&VariableAccess> for Span {
+    fn from(variable_access: &VariableAccess) -> Self {
+        (&variable_access.name).into()
+    }
+}
+
+impl Span {
+    /// Returns the 1-indexed line and column of the start of the span.
+    pub fn line_and_column(&self, source: &str) -> (usize, usize) {
+        let before = &source[..self.start.min(source.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+        (line, column)
+    }
+}
+
+/// Returns the span most representative of the given expression. For operators, since the
+/// operator keyword is not kept in the AST, this is the span of their first operand.
+pub fn expression_span(expression: &ASTExpression) -> Option<Span> {
+    match expression {
+        ASTExpression::LiteralValue(token) => Some(token.into()),
+        ASTExpression::VariableAccess(variable_access) => Some(variable_access.into()),
+        ASTExpression::Not(expression) | ASTExpression::Maek(expression, _) => {
+            expression_span(expression)
+        }
+        ASTExpression::BothOf(left, _)
+        | ASTExpression::EitherOf(left, _)
+        | ASTExpression::WonOf(left, _)
+        | ASTExpression::SumOf(left, _)
+        | ASTExpression::DiffOf(left, _)
+        | ASTExpression::ProduktOf(left, _)
+        | ASTExpression::QuoshuntOf(left, _)
+        | ASTExpression::ModOf(left, _)
+        | ASTExpression::BiggrOf(left, _)
+        | ASTExpression::SmallrOf(left, _)
+        | ASTExpression::BothSaem(left, _)
+        | ASTExpression::Diffrint(left, _) => expression_span(left),
+        ASTExpression::AllOf(values)
+        | ASTExpression::AnyOf(values)
+        | ASTExpression::Smoosh(values) => values.first().and_then(expression_span),
+    }
+}
+
+/// Returns the span most representative of the given statement.
+pub fn node_span(node: &ASTNode) -> Option<Span> {
+    match node {
+        ASTNode::HAI(token) | ASTNode::KTHXBYE(token) | ASTNode::Gtfo(token) => Some(token.into()),
+        ASTNode::IHasA(IHasA { identifier, .. }) => Some(identifier.into()),
+        ASTNode::ImInYr(ImInYr { condition, .. }) => match condition {
+            Some(LoopCondition::TIL(expression)) | Some(LoopCondition::WILE(expression)) => {
+                expression_span(expression)
+            }
+            None => None,
+        },
+        ASTNode::BukkitSetSlot(BukkitSetSlot { bukkit, .. }) => Some(bukkit.into()),
+        ASTNode::VariableAssignment(VariableAssignment {
+            variable_access, ..
+        }) => Some(variable_access.into()),
+        ASTNode::Visible(Visible(expressions, _)) => expressions.first().and_then(expression_span),
+        ASTNode::FoundYr(expression) | ASTNode::Expression(expression) => {
+            expression_span(expression)
+        }
+        ASTNode::IIz(IIz { name, .. }) | ASTNode::HowIzI(HowIzI { name, .. }) => Some(name.into()),
+        ASTNode::Gimmeh(variable_access) => Some(variable_access.into()),
+        ASTNode::Wtf(_) | ASTNode::ORly(_) | ASTNode::ASTError(_) => None,
+    }
+}
+
+/// Returns the singular or plural form of a word, depending on the count.
+fn plural(count: usize, singular: &'static str, plural: &'static str) -> &'static str {
+    if count == 1 {
+        singular
+    } else {
+        plural
+    }
+}
+
+pub fn type_name(ast_type: &ASTType) -> &'static str {
+    match ast_type {
+        ASTType::Yarn => "YARN",
+        ASTType::Bukkit => "BUKKIT",
+        ASTType::Numbr => "NUMBR",
+        ASTType::Numbar => "NUMBAR",
+        ASTType::Troof => "TROOF",
+        ASTType::Noob => "NOOB",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeErrorKind {
+    IdentifierNotFound(String),
+    CannotSRSNonYarn(&'static str),
+
+    FunctionNotFound(String),
+    WrongNumberOfArguments {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+
+    SlotNotFound(String),
+    /// `SRS` slot names must be YARNs, or NUMBRs indexing a list.
+    InvalidSlotName(&'static str),
+
+    GenericError(String),
+    /// The parser reported an error for this statement.
+    ParseError(String),
+
+    InvalidType {
+        expected: &'static str,
+        found: &'static str,
+    },
+    InvalidOperands {
+        operation: &'static str,
+        left: &'static str,
+        right: &'static str,
+    },
+    IllegalCast {
+        from: &'static str,
+        to: &'static str,
+    },
+    DivisionByZero,
+    Io(String),
+
+    InvalidEscape(String),
+    InvalidCodePoint(String),
+    UnknownCharacterName(String),
+
+    StepLimitExceeded(u64),
+    CallDepthExceeded(usize),
+    TimeLimitExceeded(std::time::Duration),
+    MemoryLimitExceeded(usize),
+
+    /// `CAN HAS` names neither a native library nor a file in the searched directories.
+    LibraryNotFound {
+        library: String,
+        searched: Vec<std::path::PathBuf>,
+    },
+    /// A library imports itself. Holds the libraries of the cycle, in import order, starting
+    /// and ending with the same one.
+    ImportCycle(Vec<String>),
+    /// Running a library raised an error, already rendered against the library's source code.
+    ImportFailed {
+        library: String,
+        error: String,
+    },
+    /// The engine can only import the native libraries, not library files.
+    ImportNotSupported(String),
+    /// `CAN HAS` is nested in a block.
+    ImportNotAtTopLevel(String),
+    /// The engine cannot declare objects, or call their methods.
+    ObjectNotSupported,
+
+    /// The program was stopped by a debugger.
+    Terminated,
+}
+
+impl std::fmt::Display for RuntimeErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeErrorKind::IdentifierNotFound(name) => {
+                write!(f, "variable \"{}\" was not declared", name)
+            }
+            RuntimeErrorKind::CannotSRSNonYarn(found) => {
+                write!(f, "SRS expects a YARN, but found {}", found)
+            }
+            RuntimeErrorKind::FunctionNotFound(name) => {
+                write!(f, "function \"{}\" was not declared", name)
+            }
+            RuntimeErrorKind::WrongNumberOfArguments {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "function \"{}\" expects {} {}, but {} {} given",
+                function,
+                expected,
+                plural(*expected, "argument", "arguments"),
+                found,
+                plural(*found, "was", "were")
+            ),
+            RuntimeErrorKind::SlotNotFound(name) => {
+                write!(f, "BUKKIT has no slot named \"{}\"", name)
+            }
+            RuntimeErrorKind::InvalidSlotName(found) => {
+                write!(f, "slot names must be YARNs or NUMBRs, but found {}", found)
+            }
+            RuntimeErrorKind::GenericError(message) => write!(f, "{}", message),
+            RuntimeErrorKind::ParseError(error) => write!(f, "{}", error),
+            RuntimeErrorKind::InvalidType { expected, found } => {
+                write!(f, "expected {}, but found {}", expected, found)
+            }
+            RuntimeErrorKind::InvalidOperands {
+                operation,
+                left,
+                right,
+            } => match *operation {
+                "add" => write!(f, "cannot add {} to {}", right, left),
+                "subtract" => write!(f, "cannot subtract {} from {}", right, left),
+                "compare" => write!(f, "cannot compare {} and {}", left, right),
+                "modulo" => write!(
+                    f,
+                    "cannot take the remainder of dividing {} by {}",
+                    left, right
+                ),
+                _ => write!(f, "cannot {} {} by {}", operation, left, right),
+            },
+            RuntimeErrorKind::IllegalCast { from, to } => {
+                write!(f, "cannot cast {} to {}", from, to)
+            }
+            RuntimeErrorKind::DivisionByZero => write!(f, "division by zero"),
+            RuntimeErrorKind::Io(error) => write!(f, "I/O error: {}", error),
+            RuntimeErrorKind::InvalidEscape(escape) => {
+                write!(f, "invalid escape sequence \"{}\" in YARN", escape)
+            }
+            RuntimeErrorKind::InvalidCodePoint(code_point) => {
+                write!(f, "\"{}\" is not a valid unicode code point", code_point)
+            }
+            RuntimeErrorKind::UnknownCharacterName(name) => {
+                write!(f, "no unicode character is named \"{}\"", name)
+            }
+            RuntimeErrorKind::StepLimitExceeded(max_steps) => {
+                write!(f, "program exceeded the limit of {} steps", max_steps)
+            }
+            RuntimeErrorKind::CallDepthExceeded(max_call_depth) => write!(
+                f,
+                "program exceeded the limit of {} nested function calls",
+                max_call_depth
+            ),
+            RuntimeErrorKind::TimeLimitExceeded(max_duration) => {
+                write!(f, "program exceeded the time limit of {:?}", max_duration)
+            }
+            RuntimeErrorKind::MemoryLimitExceeded(max_memory) => {
+                write!(
+                    f,
+                    "program exceeded the memory limit of {} bytes",
+                    max_memory
+                )
+            }
+            RuntimeErrorKind::LibraryNotFound { library, searched } => {
+                write!(
+                    f,
+                    "library \"{}\" is not a native library, and {}.lol was not found",
+                    library, library
+                )?;
+                match searched.is_empty() {
+                    true => write!(f, " since the search path is empty"),
+                    false => {
+                        let searched: Vec<String> = searched
+                            .iter()
+                            .map(|directory| directory.display().to_string())
+                            .collect();
+                        write!(f, " in {}", searched.join(", "))
+                    }
+                }
+            }
+            RuntimeErrorKind::ImportCycle(cycle) => match cycle.first() {
+                Some(library) => write!(
+                    f,
+                    "library \"{}\" imports itself: {}",
+                    library,
+                    cycle.join(" -> ")
+                ),
+                None => write!(f, "a library imports itself"),
+            },
+            RuntimeErrorKind::ImportFailed { library, error } => {
+                write!(f, "library \"{}\" failed to load: {}", library, error)
+            }
+            RuntimeErrorKind::ImportNotSupported(library) => write!(
+                f,
+                "library \"{}\" can only be imported by the tree-walking engine",
+                library
+            ),
+            RuntimeErrorKind::ImportNotAtTopLevel(library) => write!(
+                f,
+                "library \"{}\" must be imported at the top level of the program, outside of any block",
+                library
+            ),
+            RuntimeErrorKind::ObjectNotSupported => {
+                write!(f, "objects can only be used by the tree-walking engine")
+            }
+            RuntimeErrorKind::Terminated => write!(f, "program was terminated by the debugger"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    pub span: Option<Span>,
+}
+
+pub type RuntimeResult<T> = Result<T, RuntimeError>;
+
+impl From<RuntimeErrorKind> for RuntimeError {
+    fn from(kind: RuntimeErrorKind) -> Self {
+        RuntimeError { kind, span: None }
+    }
+}
+
+impl std::fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl RuntimeError {
+    /// Attaches a span to the error, unless it already has a more specific one.
+    pub fn at(mut self, span: Option<Span>) -> Self {
+        if self.span.is_none() {
+            self.span = span;
+        }
+        self
+    }
+
+    /// Renders the error as a diagnostic, showing the offending line of the source code with the
+    /// error's span underlined.
+    pub fn render(&self, source: &str, file_name: &str) -> String {
+        let span = match self.span {
+            None => return format!("error: {}\n --> {}", self.kind, file_name),
+            Some(span) => span,
+        };
+
+        let (line, column) = span.line_and_column(source);
+        let line_text = source.lines().nth(line - 1).unwrap_or("");
+        let underline_length = source
+            .get(span.start..span.end)
+            .map(|text| text.lines().next().unwrap_or("").chars().count())
+            .unwrap_or(1)
+            .max(1);
+        let gutter = " ".repeat(line.to_string().len());
+        // Tabs are kept, so that the underline starts below the span however wide they are.
+        let padding: String = line_text
+            .chars()
+            .take(column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        format!(
+            "error: {message}\n{gutter}--> {file_name}:{line}:{column}\n{gutter} |\n{line} | {line_text}\n{gutter} | {padding}{underline}",
+            message = self.kind,
+            underline = "^".repeat(underline_length),
+        )
+    }
+}
@@ -0,0 +1,446 @@
+//! Formatter for LOLCODE source.
+//!
+//! The file is parsed, and its syntax tree printed back in the canonical layout: one statement
+//! per line, blocks indented, a single space between words, and lines continued with `...` joined.
+//!
+//! The syntax tree does not keep comments, so they are put back by position. Every printed line
+//! comes from one statement of the source code, in the same order, so the comments found before
+//! that statement are printed before its line, and a `BTW` comment ending its line is printed at
+//! the end of it. Single blank lines between statements are kept.
+
+use lolcode_ast::parser::expression::{ASTExpression, Identifier, VariableAccess};
+use lolcode_ast::parser::statements::assignment::VariableAssignment;
+use lolcode_ast::parser::statements::bukkit_set_slot::BukkitSetSlot;
+use lolcode_ast::parser::statements::how_iz_i::HowIzI;
+use lolcode_ast::parser::statements::i_has_a::{IHasA, IHasAInitialValue};
+use lolcode_ast::parser::statements::i_is::IIz;
+use lolcode_ast::parser::statements::im_in_yr::{
+    ImInYr, LoopCondition, LoopIterationOperation, LoopOperation,
+};
+use lolcode_ast::parser::statements::o_rly::ORly;
+use lolcode_ast::parser::statements::visible::Visible;
+use lolcode_ast::parser::statements::wtf::Wtf;
+use lolcode_ast::parser::statements::ASTNode;
+use lolcode_ast::parser::ASTBlock;
+
+use crate::error::{expression_span, type_name, RuntimeError, Span};
+use crate::source::{layout, parse, Extension, Layout, ObjectPath, Source};
+
+const INDENT: &str = "    ";
+
+/// Returns the canonical layout of the source, or an error if it does not parse.
+pub fn format_source(source: &str) -> Result<String, RuntimeError> {
+    let (ast, parsed) = parse(source)?;
+    let mut printer = Printer {
+        source,
+        parsed: &parsed,
+        layout: layout(source),
+        statement: 0,
+        comment: 0,
+        printed_until: 0,
+        depth: 0,
+        output: String::new(),
+    };
+    printer.block(&ast);
+    printer.comments_before(source.len());
+    Ok(printer.output)
+}
+
+struct Printer<'a> {
+    source: &'a str,
+    /// The statements of LOLCODE 1.3 that were rewritten before parsing.
+    parsed: &'a Source,
+    layout: Layout,
+    /// Index of the statement of the layout the next line comes from.
+    statement: usize,
+    /// Index of the next comment of the layout to print.
+    comment: usize,
+    /// End of the last statement or comment printed, in the source code.
+    printed_until: usize,
+    depth: usize,
+    output: String,
+}
+
+fn identifier(identifier: &Identifier) -> String {
+    match identifier.is_srs {
+        true => format!("SRS {}", identifier.to_string_slice()),
+        false => identifier.to_string_slice().to_string(),
+    }
+}
+
+fn variable_access(variable_access: &VariableAccess) -> String {
+    let mut text = identifier(&variable_access.name);
+    for access in variable_access.accesses.iter() {
+        text.push_str("'Z ");
+        text.push_str(&identifier(access));
+    }
+    text
+}
+
+fn object_path(object: &ObjectPath) -> String {
+    let mut text = object.variable.clone();
+    for slot in object.slots.iter() {
+        text.push_str("'Z ");
+        text.push_str(slot);
+    }
+    text
+}
+
+/// Returns ` YR <first> AN YR <second> ...`, the arguments of a function call or declaration.
+fn arguments(arguments: impl Iterator<Item = String>) -> String {
+    arguments
+        .enumerate()
+        .map(|(index, argument)| match index {
+            0 => format!(" YR {}", argument),
+            _ => format!(" AN YR {}", argument),
+        })
+        .collect()
+}
+
+impl<'a> Printer<'a> {
+    fn text(&self, span: Span) -> &'a str {
+        &self.source[span.start..span.end]
+    }
+
+    /// Prints the comments that start before the position, each on its own line.
+    fn comments_before(&mut self, position: usize) {
+        while let Some(&comment) = self
+            .layout
+            .comments
+            .get(self.comment)
+            .filter(|comment| comment.start < position)
+        {
+            self.comment += 1;
+            self.separate(comment.start);
+            // The lines inside `OBTW` comments are kept as they are.
+            let mut lines = self.text(comment).lines();
+            let first = lines.next().unwrap_or_default();
+            self.output.push_str(&INDENT.repeat(self.depth));
+            self.output.push_str(first);
+            self.output.push('\n');
+            for line in lines {
+                self.output.push_str(line.trim_end());
+                self.output.push('\n');
+            }
+            self.printed_until = comment.end;
+        }
+    }
+
+    /// Prints a blank line if there was at least one between what was printed last and the
+    /// position.
+    fn separate(&mut self, position: usize) {
+        let between = &self.source[self.printed_until.min(position)..position];
+        if !self.output.is_empty() && between.matches('\n').count() > 1 {
+            self.output.push('\n');
+        }
+    }
+
+    /// Prints the line for the next statement of the source code, after the comments before it.
+    fn line(&mut self, code: String) {
+        let span = self.layout.statements.get(self.statement).copied();
+        self.statement += 1;
+        let Some(span) = span else {
+            self.output.push_str(&INDENT.repeat(self.depth));
+            self.output.push_str(&code);
+            self.output.push('\n');
+            return;
+        };
+
+        self.comments_before(span.start);
+        self.separate(span.start);
+        self.output.push_str(&INDENT.repeat(self.depth));
+        self.output.push_str(&code);
+        self.printed_until = span.end;
+
+        // A comment is kept at the end of the line if it was at the end of this statement's line,
+        // and not after another statement on the same line.
+        let next_statement = self.layout.statements.get(self.statement);
+        let trailing = self
+            .layout
+            .comments
+            .get(self.comment)
+            .copied()
+            .filter(|comment| {
+                comment.start >= span.end
+                    && !self.source[span.end..comment.start].contains('\n')
+                    && next_statement.is_none_or(|next| next.start > comment.start)
+            });
+        if let Some(comment) = trailing {
+            self.comment += 1;
+            self.output.push(' ');
+            self.output
+                .push_str(self.text(comment).lines().next().unwrap_or_default());
+            for line in self.text(comment).lines().skip(1) {
+                self.output.push('\n');
+                self.output.push_str(line.trim_end());
+            }
+            self.printed_until = comment.end;
+        }
+        self.output.push('\n');
+    }
+
+    /// The words of the next statement of the source code, separated by single spaces.
+    fn statement_words(&self) -> String {
+        self.layout
+            .statements
+            .get(self.statement)
+            .map(|span| {
+                self.text(*span)
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default()
+    }
+
+    /// Prints the block one level deeper, with the comments between its last statement and the
+    /// line ending it.
+    fn indented_block(&mut self, block: &ASTBlock) {
+        self.depth += 1;
+        self.block(block);
+        if let Some(end) = self.layout.statements.get(self.statement) {
+            self.comments_before(end.start);
+        }
+        self.depth -= 1;
+    }
+
+    fn block(&mut self, block: &ASTBlock) {
+        for node in block.0.iter() {
+            self.statement(node);
+        }
+    }
+
+    fn statement(&mut self, node: &ASTNode) {
+        match node {
+            ASTNode::HAI(_) | ASTNode::ASTError(_) => self.line(self.statement_words()),
+            ASTNode::KTHXBYE(_) => self.line("KTHXBYE".to_string()),
+            ASTNode::IHasA(IHasA {
+                identifier: name,
+                initial_value,
+            }) => {
+                let value = match initial_value {
+                    None => String::new(),
+                    Some(IHasAInitialValue::Expression(expression)) => {
+                        format!(" ITZ {}", self.expression(expression))
+                    }
+                    Some(IHasAInitialValue::Type(ast_type)) => {
+                        format!(" ITZ A {}", type_name(ast_type))
+                    }
+                };
+                self.line(format!("I HAS A {}{}", identifier(name), value));
+            }
+            ASTNode::ImInYr(ImInYr {
+                label,
+                on_iteration,
+                condition,
+                code_block,
+            }) => {
+                let mut header = format!("IM IN YR {}", identifier(label));
+                if let Some(LoopIterationOperation { operation, operand }) = on_iteration {
+                    let operation = match operation {
+                        LoopOperation::UPPIN(_) => "UPPIN",
+                        LoopOperation::NERFIN(_) => "NERFIN",
+                    };
+                    header.push_str(&format!(" {} YR {}", operation, variable_access(operand)));
+                }
+                match condition {
+                    Some(LoopCondition::TIL(expression)) => {
+                        header.push_str(&format!(" TIL {}", self.expression(expression)))
+                    }
+                    Some(LoopCondition::WILE(expression)) => {
+                        header.push_str(&format!(" WILE {}", self.expression(expression)))
+                    }
+                    None => {}
+                }
+                self.line(header);
+                self.indented_block(code_block);
+                self.line(format!("IM OUTTA YR {}", identifier(label)));
+            }
+            ASTNode::BukkitSetSlot(BukkitSetSlot {
+                bukkit,
+                slot_name,
+                value,
+            }) => {
+                let value = self.expression(value);
+                self.line(format!(
+                    "{} HAS A {} ITZ {}",
+                    variable_access(bukkit),
+                    identifier(slot_name),
+                    value
+                ));
+            }
+            ASTNode::VariableAssignment(VariableAssignment {
+                variable_access: target,
+                expression,
+            }) => {
+                let recast = match self.parsed.extension(Some(target.into())) {
+                    Some(Extension::Recast(to)) => Some(type_name(to)),
+                    _ => None,
+                };
+                let line = match (recast, expression) {
+                    (Some(to), ASTExpression::Maek(..)) => {
+                        format!("{} IS NOW A {}", variable_access(target), to)
+                    }
+                    _ => format!(
+                        "{} R {}",
+                        variable_access(target),
+                        self.expression(expression)
+                    ),
+                };
+                self.line(line);
+            }
+            ASTNode::Visible(Visible(expressions, exclamation)) => {
+                let mut line = "VISIBLE".to_string();
+                for expression in expressions.iter() {
+                    line.push(' ');
+                    line.push_str(&self.expression(expression));
+                }
+                if exclamation.is_some() {
+                    line.push('!');
+                }
+                self.line(line);
+            }
+            ASTNode::FoundYr(expression) => {
+                self.line(format!("FOUND YR {}", self.expression(expression)))
+            }
+            ASTNode::Wtf(Wtf { omg, omg_wtf }) => {
+                self.line("WTF?".to_string());
+                self.depth += 1;
+                for (expression, block) in omg.iter() {
+                    self.line(format!("OMG {}", self.expression(expression)));
+                    self.indented_block(block);
+                }
+                if let Some(block) = omg_wtf {
+                    self.line("OMGWTF".to_string());
+                    self.indented_block(block);
+                }
+                self.depth -= 1;
+                self.line("OIC".to_string());
+            }
+            ASTNode::ORly(ORly {
+                if_true,
+                if_false,
+                mebbes,
+            }) => {
+                self.line("O RLY?".to_string());
+                self.depth += 1;
+                if let Some(block) = if_true {
+                    self.line("YA RLY".to_string());
+                    self.indented_block(block);
+                }
+                for (expression, block) in mebbes.iter() {
+                    self.line(format!("MEBBE {}", self.expression(expression)));
+                    self.indented_block(block);
+                }
+                if let Some(block) = if_false {
+                    self.line("NO WAI".to_string());
+                    self.indented_block(block);
+                }
+                self.depth -= 1;
+                self.line("OIC".to_string());
+            }
+            ASTNode::IIz(IIz {
+                name,
+                arguments: values,
+            }) => {
+                let receiver = match self.parsed.extension(Some(name.into())) {
+                    Some(Extension::Call(object)) => object_path(object),
+                    _ => "I".to_string(),
+                };
+                let values = values.iter().map(|value| self.expression(value));
+                let line = format!(
+                    "{} IZ {}{} MKAY",
+                    receiver,
+                    identifier(name),
+                    arguments(values)
+                );
+                self.line(line);
+            }
+            ASTNode::HowIzI(HowIzI {
+                name,
+                arguments: parameters,
+                body,
+            }) => {
+                let receiver = match self.parsed.extension(Some(name.into())) {
+                    Some(Extension::Method(object)) => object_path(object),
+                    _ => "I".to_string(),
+                };
+                let parameters = parameters.iter().map(identifier);
+                self.line(format!(
+                    "HOW IZ {} {}{}",
+                    receiver,
+                    identifier(name),
+                    arguments(parameters)
+                ));
+                self.indented_block(body);
+                self.line("IF U SAY SO".to_string());
+            }
+            ASTNode::Gtfo(_) => self.line("GTFO".to_string()),
+            ASTNode::Gimmeh(target) => self.line(format!("GIMMEH {}", variable_access(target))),
+            ASTNode::Expression(expression) => {
+                let parsed = self.parsed;
+                match (parsed.extension(expression_span(expression)), expression) {
+                    (Some(Extension::Import(library)), _) => {
+                        self.line(format!("CAN HAS {}?", library))
+                    }
+                    (
+                        Some(Extension::Object { parent, body }),
+                        ASTExpression::VariableAccess(name),
+                    ) => {
+                        let mut header = format!("O HAI IM {}", variable_access(name));
+                        if let Some(parent) = parent {
+                            header.push_str(&format!(" IM LIEK {}", object_path(parent)));
+                        }
+                        self.line(header);
+                        self.indented_block(body);
+                        self.line("KTHX".to_string());
+                    }
+                    _ => self.line(self.expression(expression)),
+                }
+            }
+        }
+    }
+
+    fn binary(&self, operator: &str, left: &ASTExpression, right: &ASTExpression) -> String {
+        format!(
+            "{} {} AN {}",
+            operator,
+            self.expression(left),
+            self.expression(right)
+        )
+    }
+
+    fn variadic(&self, operator: &str, values: &[ASTExpression]) -> String {
+        let values: Vec<String> = values.iter().map(|value| self.expression(value)).collect();
+        format!("{} {} MKAY", operator, values.join(" AN "))
+    }
+
+    fn expression(&self, expression: &ASTExpression) -> String {
+        match expression {
+            // Literals are printed as they were written, so YARNs keep their escapes.
+            ASTExpression::LiteralValue(token) => self.text(token.into()).to_string(),
+            ASTExpression::VariableAccess(access) => variable_access(access),
+            ASTExpression::Not(expression) => format!("NOT {}", self.expression(expression)),
+            ASTExpression::BothOf(left, right) => self.binary("BOTH OF", left, right),
+            ASTExpression::EitherOf(left, right) => self.binary("EITHER OF", left, right),
+            ASTExpression::WonOf(left, right) => self.binary("WON OF", left, right),
+            ASTExpression::SumOf(left, right) => self.binary("SUM OF", left, right),
+            ASTExpression::DiffOf(left, right) => self.binary("DIFF OF", left, right),
+            ASTExpression::ProduktOf(left, right) => self.binary("PRODUKT OF", left, right),
+            ASTExpression::QuoshuntOf(left, right) => self.binary("QUOSHUNT OF", left, right),
+            ASTExpression::ModOf(left, right) => self.binary("MOD OF", left, right),
+            ASTExpression::BiggrOf(left, right) => self.binary("BIGGR OF", left, right),
+            ASTExpression::SmallrOf(left, right) => self.binary("SMALLR OF", left, right),
+            ASTExpression::BothSaem(left, right) => self.binary("BOTH SAEM", left, right),
+            ASTExpression::Diffrint(left, right) => self.binary("DIFFRINT", left, right),
+            ASTExpression::AllOf(values) => self.variadic("ALL OF", values),
+            ASTExpression::AnyOf(values) => self.variadic("ANY OF", values),
+            ASTExpression::Smoosh(values) => self.variadic("SMOOSH", values),
+            ASTExpression::Maek(expression, ast_type) => format!(
+                "MAEK {} A {}",
+                self.expression(expression),
+                type_name(ast_type)
+            ),
+        }
+    }
+}
@@ -0,0 +1,185 @@
+//! Collection of the BUKKITs that reference counting cannot free.
+//!
+//! BUKKITs are freed as soon as their last reference is dropped, except when they reference each
+//! other in a cycle, such as an object whose slot holds itself or a parent that holds its child.
+//! The collector finds those cycles by trial deletion: it counts, for every tracked BUKKIT, the
+//! references held by the slots of other tracked BUKKITs. A BUKKIT with more references than
+//! that is also referenced from outside the heap, by the variables and `IT` of a scope on the
+//! call stack or by a value being evaluated, and is a root. Everything not reachable from a root
+//! is garbage, so the collector empties the slots of those BUKKITs, which breaks their cycles and
+//! lets reference counting free them.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use crate::types::{BukkitRef, Value, WeakBukkitRef};
+
+/// Number of BUKKITs given slots since the last collection that triggers the next one, by
+/// default.
+pub const DEFAULT_GC_THRESHOLD: usize = 1000;
+
+/// When the collector runs. Each threshold, when reached, triggers a collection at the next
+/// step of the program. `None` disables a threshold, and with both disabled the collector only
+/// runs when [`Runtime::collect_garbage`](crate::Runtime::collect_garbage) is called.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GcThresholds {
+    /// Number of BUKKITs given slots since the last collection.
+    pub allocations: Option<usize>,
+    /// Number of bytes the memory in use grew by since the last collection.
+    pub memory_growth: Option<usize>,
+}
+
+impl Default for GcThresholds {
+    fn default() -> Self {
+        GcThresholds {
+            allocations: Some(DEFAULT_GC_THRESHOLD),
+            memory_growth: None,
+        }
+    }
+}
+
+/// What the collector did so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GcStats {
+    pub collections: u64,
+    /// Number of BUKKITs freed by the collector, not counting those freed by reference counting.
+    pub freed: u64,
+    /// Bytes held by YARNs and BUKKITs stored in variables, as counted by the memory limit.
+    pub live_bytes: usize,
+}
+
+impl std::fmt::Display for GcStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} collections, {} BUKKITs freed, {} live bytes",
+            self.collections, self.freed, self.live_bytes
+        )
+    }
+}
+
+pub(crate) struct Collector {
+    thresholds: GcThresholds,
+    /// Every BUKKIT that has slots, and so can be part of a cycle. Freed BUKKITs are removed at
+    /// the next collection.
+    heap: RefCell<Vec<WeakBukkitRef>>,
+    tracked_since_collection: Cell<usize>,
+    memory_after_collection: Cell<usize>,
+    collections: Cell<u64>,
+    freed: Cell<u64>,
+}
+
+impl Collector {
+    pub fn new(thresholds: GcThresholds) -> Self {
+        Collector {
+            thresholds,
+            heap: RefCell::new(vec![]),
+            tracked_since_collection: Cell::new(0),
+            memory_after_collection: Cell::new(0),
+            collections: Cell::new(0),
+            freed: Cell::new(0),
+        }
+    }
+
+    pub fn thresholds(&self) -> &GcThresholds {
+        &self.thresholds
+    }
+
+    pub fn track(&self, bukkit: &BukkitRef) {
+        self.heap.borrow_mut().push(bukkit.downgrade());
+        self.tracked_since_collection
+            .set(self.tracked_since_collection.get() + 1);
+    }
+
+    pub fn is_due(&self, memory: usize) -> bool {
+        let allocations = self.tracked_since_collection.get();
+        let memory_growth = memory.saturating_sub(self.memory_after_collection.get());
+        self.thresholds
+            .allocations
+            .is_some_and(|threshold| allocations >= threshold)
+            || self
+                .thresholds
+                .memory_growth
+                .is_some_and(|threshold| memory_growth >= threshold)
+    }
+
+    /// Frees the BUKKITs that are only reachable from each other. `memory` is read once they are
+    /// freed, to measure the growth until the next collection.
+    pub fn collect(&self, memory: &Cell<usize>) {
+        let heap: Vec<BukkitRef> = self
+            .heap
+            .take()
+            .iter()
+            .filter_map(WeakBukkitRef::upgrade)
+            .collect();
+        let indices: HashMap<*const (), usize> = heap
+            .iter()
+            .enumerate()
+            .map(|(index, bukkit)| (bukkit.address(), index))
+            .collect();
+
+        let mut internal_references = vec![0; heap.len()];
+        let mut edges = Vec::with_capacity(heap.len());
+        for bukkit in heap.iter() {
+            // A BUKKIT borrowed by the interpreter is being changed, so its slots cannot be
+            // trusted: try again at the next collection.
+            let Some(bukkit) = bukkit.try_borrow() else {
+                *self.heap.borrow_mut() = heap.iter().map(BukkitRef::downgrade).collect();
+                return;
+            };
+            let targets: Vec<usize> = bukkit
+                .slots
+                .values()
+                .filter_map(|value| match value {
+                    Value::Bukkit(target) => indices.get(&target.address()).copied(),
+                    _ => None,
+                })
+                .collect();
+            for target in targets.iter() {
+                internal_references[*target] += 1;
+            }
+            edges.push(targets);
+        }
+
+        // `heap` holds one of the references of every BUKKIT itself.
+        let mut pending: Vec<usize> = (0..heap.len())
+            .filter(|index| heap[*index].references() - 1 > internal_references[*index])
+            .collect();
+        let mut reachable = vec![false; heap.len()];
+        while let Some(index) = pending.pop() {
+            if !std::mem::replace(&mut reachable[index], true) {
+                pending.extend(edges[index].iter().copied());
+            }
+        }
+
+        let (live, garbage): (Vec<_>, Vec<_>) = heap
+            .into_iter()
+            .zip(reachable)
+            .partition(|(_, reachable)| *reachable);
+        self.heap
+            .borrow_mut()
+            .extend(live.iter().map(|(bukkit, _)| bukkit.downgrade()));
+        drop(live);
+
+        // Emptying the slots breaks the cycles, so the BUKKITs are freed along with `garbage`.
+        let slots: Vec<_> = garbage
+            .iter()
+            .map(|(bukkit, _)| std::mem::take(&mut bukkit.borrow_mut().slots))
+            .collect();
+        self.freed.set(self.freed.get() + garbage.len() as u64);
+        drop(slots);
+        drop(garbage);
+
+        self.collections.set(self.collections.get() + 1);
+        self.tracked_since_collection.set(0);
+        self.memory_after_collection.set(memory.get());
+    }
+
+    pub fn stats(&self, live_bytes: usize) -> GcStats {
+        GcStats {
+            collections: self.collections.get(),
+            freed: self.freed.get(),
+            live_bytes,
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! `CAN HAS <library>?` imports.
+//!
+//! A library is either one of the native libraries built into the interpreter, `STDIO` and
+//! `STRING`, or a `<library>.lol` file. Files are looked up in the directory of the importing file,
+//! and then in each directory of the search path: the directories given with `-I`, followed by
+//! those listed in the `LOLCODE_PATH` environment variable.
+//!
+//! A library runs once, on its own global scope, the first time the program imports it. Like an
+//! object declared with `O HAI IM`, its functions then become the methods of a BUKKIT, which every
+//! import declares as a global variable named after the library, and the slots of the BUKKIT are
+//! the variables of the library's global scope. So after `CAN HAS MATHS?`, the program calls the
+//! `SQUARE` function of `MATHS.lol` with `MATHS IZ SQUARE YR 3 MKAY`, and reads and writes its
+//! `PI` variable as `MATHS'Z PI`. The functions keep running on the global scope of the library,
+//! so they see its variables and call each other with `I IZ`, and the scope lives as long as the
+//! program that imported the library first. Files are told apart by their canonical path, and a library that
+//! imports itself, directly or through other libraries, is rejected.
+//!
+//! lolcode_ast does not parse `CAN HAS` yet, so `source::parse` rewrites the imports before
+//! parsing, and they run when the program reaches them, in the order they are written. They must
+//! be at the top level of the program.
+//!
+//! Only the tree-walking interpreter imports library files. The other engines, and WebAssembly
+//! builds, which have no file system to load them from, only import the native libraries, whose
+//! methods are [built in](crate::builtins).
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::read_to_string;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::builtins;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::execute_statement;
+use crate::runtime::Runtime;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::source::parse;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::types::RuntimeError;
+use crate::types::{Bukkit, BukkitRef, Function, RuntimeErrorKind, RuntimeResult, Scope, Value};
+
+/// Environment variable listing directories to search for libraries, separated like `PATH`.
+pub const SEARCH_PATH_VARIABLE: &str = "LOLCODE_PATH";
+
+/// The native library every LOLCODE program traditionally starts by importing. `VISIBLE` and
+/// `GIMMEH` are always available, so it adds nothing, and every engine accepts it.
+pub(crate) const STDIO: &str = "STDIO";
+
+/// The libraries built into the interpreter, which every engine can import.
+pub(crate) const NATIVE_LIBRARIES: [&str; 2] = [STDIO, "STRING"];
+
+/// Returns the search path made of the given directories, followed by the directories listed in
+/// the `LOLCODE_PATH` environment variable.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn search_path(mut directories: Vec<PathBuf>) -> Vec<PathBuf> {
+    if let Some(paths) = std::env::var_os(SEARCH_PATH_VARIABLE) {
+        directories
+            .extend(std::env::split_paths(&paths).filter(|path| !path.as_os_str().is_empty()));
+    }
+    directories
+}
+
+/// The libraries a program imported, shared by the libraries it imports in turn. Only the native
+/// libraries can be imported in WebAssembly builds, which leave it empty.
+#[derive(Debug, Default)]
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub(crate) struct Modules {
+    search_path: Vec<PathBuf>,
+    /// The BUKKIT of each library file that finished running, by its canonical path, so it runs
+    /// only once.
+    loaded: RefCell<HashMap<PathBuf, BukkitRef>>,
+    /// The canonical path and the name of the libraries being run, in the order they were
+    /// imported, to detect cycles.
+    loading: RefCell<Vec<(PathBuf, String)>>,
+}
+
+impl Modules {
+    pub fn new(search_path: Vec<PathBuf>) -> Self {
+        Modules {
+            search_path,
+            ..Default::default()
+        }
+    }
+
+    pub fn search_path(&self) -> &[PathBuf] {
+        &self.search_path
+    }
+}
+
+/// Executes `CAN HAS <library>?`, declaring the library as a variable named after it.
+pub(crate) fn import(scope: &Scope, library: &str) -> RuntimeResult<()> {
+    let value = Value::Bukkit(load(scope, library)?);
+    let allocated = value.memory_size();
+    let previous = scope
+        .variables
+        .borrow_mut()
+        .insert(library.to_string(), value);
+    let released = previous.map_or(0, |previous| previous.memory_size());
+    scope.runtime.charge_memory(released, allocated)
+}
+
+fn load(scope: &Scope, name: &str) -> RuntimeResult<BukkitRef> {
+    match native_library(&scope.runtime, name) {
+        Some(library) => Ok(library),
+        None => load_file(scope, name),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn load_file(_scope: &Scope, name: &str) -> RuntimeResult<BukkitRef> {
+    Err(RuntimeErrorKind::LibraryNotFound {
+        library: name.to_string(),
+        searched: vec![],
+    }
+    .into())
+}
+
+/// Runs the library file the first time it is imported.
+#[cfg(not(target_arch = "wasm32"))]
+fn load_file(scope: &Scope, name: &str) -> RuntimeResult<BukkitRef> {
+    let modules = scope.runtime.modules();
+    let file = find_file(modules.search_path(), name, scope.source.directory())?;
+    let file = file.canonicalize().unwrap_or(file);
+    if let Some(library) = modules.loaded.borrow().get(&file) {
+        return Ok(library.clone());
+    }
+    let loading = modules.loading.borrow().clone();
+    if let Some(start) = loading.iter().position(|(path, _)| *path == file) {
+        let mut cycle: Vec<String> = loading[start..]
+            .iter()
+            .map(|(_, name)| name.clone())
+            .collect();
+        cycle.push(name.to_string());
+        return Err(RuntimeErrorKind::ImportCycle(cycle).into());
+    }
+
+    modules
+        .loading
+        .borrow_mut()
+        .push((file.clone(), name.to_string()));
+    let library = run_file(scope, &file);
+    modules.loading.borrow_mut().pop();
+    let library = library.map_err(|error| RuntimeErrorKind::ImportFailed {
+        library: name.to_string(),
+        error,
+    })?;
+
+    modules.loaded.borrow_mut().insert(file, library.clone());
+    Ok(library)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn find_file(
+    search_path: &[PathBuf],
+    name: &str,
+    directory: Option<&Path>,
+) -> RuntimeResult<PathBuf> {
+    let file_name = format!("{}.lol", name);
+    // The directory of a file given by a bare name is empty, which is the current directory.
+    let directory = directory.map(|directory| match directory.as_os_str().is_empty() {
+        true => PathBuf::from("."),
+        false => directory.to_path_buf(),
+    });
+    let searched: Vec<PathBuf> = directory
+        .into_iter()
+        .chain(search_path.iter().cloned())
+        .collect();
+    searched
+        .iter()
+        .map(|directory| directory.join(&file_name))
+        .find(|file| file.is_file())
+        .ok_or_else(|| {
+            RuntimeErrorKind::LibraryNotFound {
+                library: name.to_string(),
+                searched,
+            }
+            .into()
+        })
+}
+
+/// Runs the library file on a global scope of its own, and returns a BUKKIT whose slots are the
+/// variables of that scope, and whose methods are its functions. The root scope of the importer
+/// keeps the library's scope alive. Errors are rendered against the library's own source code.
+#[cfg(not(target_arch = "wasm32"))]
+fn run_file(importer: &Scope, file: &Path) -> Result<BukkitRef, String> {
+    let runtime = &importer.runtime;
+    let file_name = file.display().to_string();
+    let source =
+        read_to_string(file).map_err(|error| format!("cannot read {}: {}", file_name, error))?;
+
+    let render = |error: RuntimeError| match error.span {
+        Some(span) => {
+            let (line, column) = span.line_and_column(&source);
+            format!("{} at {}:{}:{}", error.kind, file_name, line, column)
+        }
+        None => format!("{} in {}", error.kind, file_name),
+    };
+
+    let (ast, parsed) = parse(&source).map_err(render)?;
+    let parsed = Rc::new(parsed.with_directory(file.parent()));
+    let mut scope = Scope::with_runtime(runtime.clone());
+    scope.source = parsed.clone();
+    for node in ast.into_iter() {
+        execute_statement(&mut scope, &node).map_err(render)?;
+    }
+
+    let scope = Rc::new(scope);
+    parsed.set_home(&scope);
+    let library = BukkitRef::new(Bukkit::default());
+    library.borrow_mut().namespace = Some(Rc::downgrade(&scope));
+    library
+        .borrow_mut()
+        .methods
+        .extend(scope.functions.borrow().clone());
+    importer.root().libraries.borrow_mut().push(scope);
+    Ok(library)
+}
+
+/// Returns the BUKKIT of a native library, whose methods are built in.
+fn native_library(runtime: &Rc<Runtime>, name: &str) -> Option<BukkitRef> {
+    if !NATIVE_LIBRARIES.contains(&name) {
+        return None;
+    }
+    let library = BukkitRef::new(Bukkit::default());
+    library
+        .borrow_mut()
+        .methods
+        .extend(builtins::library_methods(name).map(|(method, builtin)| {
+            let runtime = runtime.clone();
+            let function = Function::Native {
+                arity: builtin.arity,
+                function: Rc::new(move |arguments: &[Value]| {
+                    builtin.call(&runtime, arguments.to_vec())
+                }),
+            };
+            (method.to_string(), function)
+        }));
+    Some(library)
+}
@@ -0,0 +1,107 @@
+//! Type inference of expressions, shared by the static tools: the language server and the linter
+//! through [`crate::lsp::analyze`], and the type checker.
+//!
+//! Each tool decides what the type of a variable is, and what to do with the operands of the
+//! operators, through [`Inference`]. The types of the operators' results are inferred here.
+
+use lolcode_ast::parser::expression::{ASTExpression, ASTType, VariableAccess};
+
+use crate::error::type_name;
+use crate::operations::literal_value;
+
+/// The name of a type, or None if it cannot be inferred.
+pub(crate) type Type = Option<&'static str>;
+
+/// How an operator uses the value of one of its operands.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Operand<'a> {
+    /// Compared without being cast, by `BOTH SAEM` and `DIFFRINT`.
+    Compared,
+    /// Cast to a TROOF, which any value can be.
+    Troof,
+    /// Cast to a NUMBR or a NUMBAR.
+    Number,
+    /// Cast to a YARN.
+    Yarn,
+    /// Cast by `MAEK` to the type.
+    Maek(&'a ASTType),
+}
+
+pub(crate) trait Inference {
+    /// Returns the type of the value the variable access reads.
+    fn variable_access(&mut self, variable_access: &VariableAccess) -> Type;
+
+    /// Called with each operand of an operator, once its type is inferred.
+    fn operand(&mut self, _operand: &ASTExpression, _value_type: Type, _usage: Operand) {}
+}
+
+/// Type of the result of a math operator, which is a NUMBAR if any of the operands is one.
+fn math_type(left: Type, right: Type) -> Type {
+    match (left?, right?) {
+        ("NUMBAR", "NUMBR" | "NUMBAR" | "TROOF") | ("NUMBR" | "TROOF", "NUMBAR") => Some("NUMBAR"),
+        ("NUMBR" | "TROOF", "NUMBR" | "TROOF") => Some("NUMBR"),
+        _ => None,
+    }
+}
+
+fn operand(inference: &mut impl Inference, expression: &ASTExpression, usage: Operand) -> Type {
+    let value_type = expression_type(inference, expression);
+    inference.operand(expression, value_type, usage);
+    value_type
+}
+
+/// Returns the type of the value of the expression, if it can be inferred.
+pub(crate) fn expression_type(inference: &mut impl Inference, expression: &ASTExpression) -> Type {
+    match expression {
+        ASTExpression::LiteralValue(token) => Some(literal_value(token).type_name()),
+        ASTExpression::VariableAccess(variable_access) => {
+            inference.variable_access(variable_access)
+        }
+        ASTExpression::Not(expression) => {
+            operand(inference, expression, Operand::Troof);
+            Some("TROOF")
+        }
+        ASTExpression::BothOf(left, right)
+        | ASTExpression::EitherOf(left, right)
+        | ASTExpression::WonOf(left, right) => {
+            operand(inference, left, Operand::Troof);
+            operand(inference, right, Operand::Troof);
+            Some("TROOF")
+        }
+        ASTExpression::BothSaem(left, right) | ASTExpression::Diffrint(left, right) => {
+            operand(inference, left, Operand::Compared);
+            operand(inference, right, Operand::Compared);
+            Some("TROOF")
+        }
+        ASTExpression::BiggrOf(left, right) | ASTExpression::SmallrOf(left, right) => {
+            operand(inference, left, Operand::Number);
+            operand(inference, right, Operand::Number);
+            Some("TROOF")
+        }
+        ASTExpression::AllOf(values) | ASTExpression::AnyOf(values) => {
+            for value in values.iter() {
+                operand(inference, value, Operand::Troof);
+            }
+            Some("TROOF")
+        }
+        ASTExpression::SumOf(left, right)
+        | ASTExpression::DiffOf(left, right)
+        | ASTExpression::ProduktOf(left, right)
+        | ASTExpression::QuoshuntOf(left, right)
+        | ASTExpression::ModOf(left, right) => {
+            let left = operand(inference, left, Operand::Number);
+            let right = operand(inference, right, Operand::Number);
+            math_type(left, right)
+        }
+        ASTExpression::Smoosh(values) => {
+            for value in values.iter() {
+                operand(inference, value, Operand::Yarn);
+            }
+            Some("YARN")
+        }
+        ASTExpression::Maek(value, ast_type) => {
+            operand(inference, value, Operand::Maek(ast_type));
+            Some(type_name(ast_type))
+        }
+    }
+}
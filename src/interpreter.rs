@@ -0,0 +1,178 @@
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+use crate::error::RuntimeError;
+use crate::execute_statement;
+use crate::object::call_method_on;
+use crate::runtime::Runtime;
+use crate::source::parse;
+use crate::types::{Function, RuntimeErrorKind, RuntimeResult, Scope, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InterpreterError {
+    /// The source code could not be parsed.
+    Parse(RuntimeError),
+    Runtime(RuntimeError),
+}
+
+impl InterpreterError {
+    /// Renders the error as a diagnostic against the source code, like
+    /// [`RuntimeError::render`].
+    pub fn render(&self, source: &str, file_name: &str) -> String {
+        match self {
+            InterpreterError::Parse(error) | InterpreterError::Runtime(error) => {
+                error.render(source, file_name)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for InterpreterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InterpreterError::Parse(error) => write!(f, "{}", error),
+            InterpreterError::Runtime(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<RuntimeError> for InterpreterError {
+    fn from(error: RuntimeError) -> Self {
+        InterpreterError::Runtime(error)
+    }
+}
+
+/// A LOLCODE interpreter that can be embedded in a Rust program.
+///
+/// The global scope persists across calls to [`Interpreter::evaluate`], so variables and
+/// functions declared by one piece of source code can be used by the following ones, and the host
+/// can read and write globals or expose its own functions to LOLCODE.
+///
+/// ```
+/// use lolcode_interpreter::{Interpreter, SharedBuffer, Value};
+///
+/// let output = SharedBuffer::new();
+/// let mut interpreter = Interpreter::with_io(output.clone(), std::io::empty());
+/// interpreter.register_function("TWICE", 1, |arguments| match arguments[0] {
+///     Value::Numbr(numbr) => Ok(Value::Numbr(numbr * 2)),
+///     _ => Ok(Value::Noob),
+/// });
+/// interpreter.set_global("x", 21);
+/// interpreter.evaluate("I IZ TWICE YR x MKAY\nVISIBLE IT")?;
+/// assert_eq!(output.contents(), "42\n");
+/// assert_eq!(interpreter.get_global("x"), Some(Value::Numbr(21)));
+/// # Ok::<(), lolcode_interpreter::InterpreterError>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct Interpreter {
+    scope: Scope<'static>,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an interpreter whose `VISIBLE` statements write to `output` and whose `GIMMEH`
+    /// statements read from `input`, instead of the standard output and input.
+    pub fn with_io(output: impl Write + 'static, input: impl BufRead + 'static) -> Self {
+        Interpreter::with_runtime(Runtime::new(Box::new(output), Box::new(input)))
+    }
+
+    /// Creates an interpreter with a custom runtime, for example one with resource limits.
+    pub fn with_runtime(runtime: Runtime) -> Self {
+        Interpreter {
+            scope: Scope::with_runtime(Rc::new(runtime)),
+        }
+    }
+
+    /// Parses and executes the source code on the global scope. Returns the value of `IT`
+    /// afterwards. Libraries imported with `CAN HAS` are looked up in the search path of the
+    /// runtime. If the parser reports an error, nothing is executed.
+    pub fn evaluate(&mut self, source: &str) -> Result<Value, InterpreterError> {
+        let (ast, parsed) = parse(source).map_err(InterpreterError::Parse)?;
+
+        self.scope.runtime.restart();
+        self.scope.source = Rc::new(parsed);
+        for node in ast.into_iter() {
+            execute_statement(&mut self.scope, &node)?;
+        }
+
+        Ok(self.scope.it.borrow().clone())
+    }
+
+    /// Returns the runtime the code runs on, for example to read what its garbage collector did.
+    pub fn runtime(&self) -> &Runtime {
+        &self.scope.runtime
+    }
+
+    /// Returns the value of a global variable, if it was declared.
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.scope.variables.borrow().get(name).cloned()
+    }
+
+    /// Sets the value of a global variable, declaring it if needed. Values set by the host count
+    /// towards the memory limit, but are never rejected by it.
+    pub fn set_global(&mut self, name: &str, value: impl Into<Value>) {
+        let value = value.into();
+        let allocated = value.memory_size();
+        let previous = self
+            .scope
+            .variables
+            .borrow_mut()
+            .insert(name.to_string(), value);
+        let released = previous.map_or(0, |previous| previous.memory_size());
+        let _ = self.scope.runtime.charge_memory(released, allocated);
+    }
+
+    /// Calls a method of the object held by a global variable, as `<object> IZ <method>` does. The
+    /// call counts towards the call depth limit, and is seen by the runtime hook.
+    pub fn call_method(
+        &mut self,
+        object: &str,
+        method: &str,
+        arguments: &[Value],
+    ) -> Result<Value, InterpreterError> {
+        let receiver = match self.get_global(object) {
+            Some(Value::Bukkit(bukkit)) => bukkit,
+            Some(value) => {
+                return Err(RuntimeError::from(RuntimeErrorKind::InvalidType {
+                    expected: "BUKKIT",
+                    found: value.type_name(),
+                })
+                .into())
+            }
+            None => {
+                return Err(RuntimeError::from(RuntimeErrorKind::IdentifierNotFound(
+                    object.to_string(),
+                ))
+                .into())
+            }
+        };
+
+        self.scope.runtime.restart();
+        Ok(call_method_on(
+            &self.scope,
+            receiver,
+            method,
+            arguments.to_vec(),
+        )?)
+    }
+
+    /// Registers a Rust closure as a global function, which LOLCODE can call with
+    /// `I IZ <name> YR <argument> ... MKAY`. The closure receives the evaluated arguments.
+    pub fn register_function(
+        &mut self,
+        name: &str,
+        arity: usize,
+        function: impl Fn(&[Value]) -> RuntimeResult<Value> + 'static,
+    ) {
+        self.scope.functions.borrow_mut().insert(
+            name.to_string(),
+            Function::Native {
+                arity,
+                function: Rc::new(function),
+            },
+        );
+    }
+}
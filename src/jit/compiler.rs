@@ -0,0 +1,1031 @@
+use std::collections::{HashMap, HashSet};
+
+use cranelift::codegen::ir::condcodes::{FloatCC, IntCC};
+use cranelift::codegen::ir::{
+    types, AbiParam, Block, FuncRef, InstBuilder, MemFlags, Type as IrType, Value as IrValue,
+};
+use cranelift::codegen::Context;
+use cranelift::frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift::jit::JITModule;
+use cranelift::module::{FuncId, Module};
+use cranelift::prelude::EntityRef;
+
+use lolcode_ast::parser::expression::{ASTExpression, ASTType, Identifier, VariableAccess};
+use lolcode_ast::parser::statements::assignment::VariableAssignment;
+use lolcode_ast::parser::statements::i_has_a::{IHasA, IHasAInitialValue};
+use lolcode_ast::parser::statements::im_in_yr::{
+    ImInYr, LoopCondition, LoopIterationOperation, LoopOperation,
+};
+use lolcode_ast::parser::statements::o_rly::ORly;
+use lolcode_ast::parser::statements::visible::Visible;
+use lolcode_ast::parser::statements::ASTNode;
+use lolcode_ast::parser::ASTBlock;
+
+use super::{
+    Backend, Compiled, FreeVariable, Guard, Helpers, RegionKind, Site, SiteKind, Type, COMPLETED,
+    FIRST_ERROR, FIRST_VARIABLE_SLOT, FOUND_YR, GTFO, IT_BITS_SLOT, IT_TAG_SLOT, RESULT_BITS_SLOT,
+    RESULT_TAG_SLOT,
+};
+use crate::error::{expression_span, node_span, Span};
+use crate::operations::{literal_value, BinaryOperation};
+use crate::source::Source;
+use crate::types::Value;
+use crate::yarn::{parse_yarn, yarn_literal, YarnPart};
+
+/// Why a region is left to the interpreter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum NotCompilable {
+    /// The region uses something compiled code cannot do, whatever the types of its variables.
+    Construct,
+    /// A variable does not hold a TROOF, NUMBR or NUMBAR, or would change type while running.
+    /// Another run, with variables of other types, may still be compiled.
+    Types,
+}
+
+type CompileResult<T> = Result<T, NotCompilable>;
+
+/// A value computed by the compiled code, whose LOLCODE type is known at compile time.
+#[derive(Clone, Copy)]
+struct Typed {
+    value: IrValue,
+    ty: Type,
+}
+
+struct CompileScope {
+    variables: HashMap<String, (Variable, Type)>,
+    /// Names declared directly in a loop body. The body runs in the same scope on every
+    /// iteration, so reading one of them before its declaration may see the previous
+    /// iteration's value, which cannot be known at compile time.
+    pending: HashSet<String>,
+    /// The tag and the bits of the scope's `IT`, which may hold any type.
+    it: (Variable, Variable),
+}
+
+struct Compiler<'a, 'f> {
+    builder: FunctionBuilder<'f>,
+    module: &'a mut JITModule,
+    helpers: &'a Helpers,
+    helper_refs: HashMap<FuncId, FuncRef>,
+    source: &'a Source,
+    observe: &'a dyn Fn(&str) -> Option<Type>,
+
+    entry: Block,
+    exit: Block,
+    slots: IrValue,
+    context: IrValue,
+
+    variables: usize,
+    free: Vec<(FreeVariable, Variable)>,
+    guards: Vec<Guard>,
+    scopes: Vec<CompileScope>,
+    /// Where `GTFO` jumps to in each enclosing loop.
+    loops: Vec<Block>,
+    span: Option<Span>,
+    sites: Vec<Site>,
+    texts: Vec<String>,
+}
+
+fn variable_name(identifier: &Identifier) -> CompileResult<String> {
+    if identifier.is_srs {
+        return Err(NotCompilable::Construct);
+    }
+    Ok(identifier.to_string_slice().to_string())
+}
+
+fn ast_type(ast_type: &ASTType) -> CompileResult<Type> {
+    match ast_type {
+        ASTType::Troof => Ok(Type::Troof),
+        ASTType::Numbr => Ok(Type::Numbr),
+        ASTType::Numbar => Ok(Type::Numbar),
+        ASTType::Yarn | ASTType::Bukkit | ASTType::Noob => Err(NotCompilable::Construct),
+    }
+}
+
+/// Returns the names declared with `I HAS A` directly in the block.
+fn declared_names(block: &ASTBlock) -> HashSet<String> {
+    block
+        .0
+        .iter()
+        .filter_map(|node| match node {
+            ASTNode::IHasA(IHasA { identifier, .. }) => {
+                Some(identifier.to_string_slice().to_string())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Compiles a region to a native function, returning the types of the variables it read from
+/// outside along with the result. Those types guard every later use of the compiled code.
+pub(super) fn compile(
+    backend: &mut Backend,
+    kind: RegionKind,
+    statements: &[ASTNode],
+    source: &Source,
+    observe: &dyn Fn(&str) -> Option<Type>,
+) -> (CompileResult<Compiled>, Vec<Guard>) {
+    let Backend { module, helpers } = backend;
+    let pointer = module.target_config().pointer_type();
+    let mut context = module.make_context();
+    context.func.signature.params.push(AbiParam::new(pointer));
+    context.func.signature.params.push(AbiParam::new(pointer));
+    context
+        .func
+        .signature
+        .returns
+        .push(AbiParam::new(types::I32));
+    let mut function_context = FunctionBuilderContext::new();
+
+    let mut builder = FunctionBuilder::new(&mut context.func, &mut function_context);
+    let entry = builder.create_block();
+    builder.append_block_params_for_function_params(entry);
+    // The variables read from outside are only known once the whole region is compiled, so the
+    // entry block, which loads them, is filled last. It must still come first in the layout.
+    builder.func.layout.append_block(entry);
+    let exit = builder.create_block();
+    builder.append_block_param(exit, types::I32);
+    let (slots, context_pointer) = {
+        let parameters = builder.block_params(entry);
+        (parameters[0], parameters[1])
+    };
+    let body = builder.create_block();
+    builder.switch_to_block(body);
+
+    let mut compiler = Compiler {
+        builder,
+        module: &mut *module,
+        helpers: &*helpers,
+        helper_refs: HashMap::new(),
+        source,
+        observe,
+        entry,
+        exit,
+        slots,
+        context: context_pointer,
+        variables: 0,
+        free: vec![],
+        guards: vec![],
+        scopes: vec![],
+        loops: vec![],
+        span: None,
+        sites: vec![],
+        texts: vec![],
+    };
+    let result = compiler.compile_region(kind, statements, body);
+    let Compiler {
+        mut builder,
+        free,
+        guards,
+        sites,
+        texts,
+        ..
+    } = compiler;
+    if let Err(error) = result {
+        return (Err(error), guards);
+    }
+    builder.seal_all_blocks();
+    builder.finalize();
+
+    let code = match define(module, &mut context) {
+        Some(code) => code,
+        // The code generator rejected the function, which no change of types will fix.
+        None => return (Err(NotCompilable::Construct), guards),
+    };
+    let compiled = Compiled {
+        code,
+        variables: free.into_iter().map(|(variable, _)| variable).collect(),
+        sites,
+        texts,
+    };
+    (Ok(compiled), guards)
+}
+
+fn define(module: &mut JITModule, context: &mut Context) -> Option<super::Code> {
+    let id = module
+        .declare_anonymous_function(&context.func.signature)
+        .ok()?;
+    let defined = module.define_function(id, context);
+    module.clear_context(context);
+    defined.ok()?;
+    module.finalize_definitions().ok()?;
+    let pointer = module.get_finalized_function(id);
+    // SAFETY: the function was declared with this signature above.
+    Some(unsafe { std::mem::transmute::<*const u8, super::Code>(pointer) })
+}
+
+impl<'a, 'f> Compiler<'a, 'f> {
+    fn compile_region(
+        &mut self,
+        kind: RegionKind,
+        statements: &[ASTNode],
+        body: Block,
+    ) -> CompileResult<()> {
+        self.push_scope(HashSet::new());
+        for node in statements.iter() {
+            self.compile_statement(node)?;
+        }
+        let completed = self.builder.ins().iconst(types::I32, COMPLETED as i64);
+        self.builder.ins().jump(self.exit, &[completed]);
+
+        // Write back the variables changed by the region, and the top-level `IT` of a function.
+        self.builder.switch_to_block(self.exit);
+        let status = self.builder.block_params(self.exit)[0];
+        for index in 0..self.free.len() {
+            let (free, variable) = &self.free[index];
+            if !free.written {
+                continue;
+            }
+            let (variable, ty) = (*variable, free.ty);
+            let value = self.builder.use_var(variable);
+            let bits = self.encode(Typed { value, ty });
+            self.store_slot(FIRST_VARIABLE_SLOT + index, bits);
+        }
+        if kind == RegionKind::Function {
+            let (tag, bits) = self.scopes[0].it;
+            let tag = self.builder.use_var(tag);
+            let tag = self.builder.ins().uextend(types::I64, tag);
+            let bits = self.builder.use_var(bits);
+            self.store_slot(IT_TAG_SLOT, tag);
+            self.store_slot(IT_BITS_SLOT, bits);
+        }
+        self.builder.ins().return_(&[status]);
+
+        self.builder.switch_to_block(self.entry);
+        for index in 0..self.free.len() {
+            let (free, variable) = &self.free[index];
+            let (variable, ty) = (*variable, free.ty);
+            let offset = (8 * (FIRST_VARIABLE_SLOT + index)) as i32;
+            let bits = self
+                .builder
+                .ins()
+                .load(types::I64, MemFlags::trusted(), self.slots, offset);
+            let value = self.decode(bits, ty);
+            self.builder.def_var(variable, value);
+        }
+        self.builder.ins().jump(body, &[]);
+        Ok(())
+    }
+
+    fn store_slot(&mut self, slot: usize, bits: IrValue) {
+        let offset = (8 * slot) as i32;
+        self.builder
+            .ins()
+            .store(MemFlags::trusted(), bits, self.slots, offset);
+    }
+
+    fn new_variable(&mut self, ty: IrType) -> Variable {
+        let variable = Variable::new(self.variables);
+        self.variables += 1;
+        self.builder.declare_var(variable, ty);
+        variable
+    }
+
+    /// Opens a new scope, with its own `IT` initialized to NOOB.
+    fn push_scope(&mut self, pending: HashSet<String>) {
+        let tag = self.new_variable(types::I8);
+        let bits = self.new_variable(types::I64);
+        let noob_tag = self.builder.ins().iconst(types::I8, Type::NOOB_TAG as i64);
+        let zero = self.builder.ins().iconst(types::I64, 0);
+        self.builder.def_var(tag, noob_tag);
+        self.builder.def_var(bits, zero);
+        self.scopes.push(CompileScope {
+            variables: HashMap::new(),
+            pending,
+            it: (tag, bits),
+        });
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// Resolves a variable to the scope that declared it in the region, or else to the variable
+    /// of the same name outside of the region, which must hold a value of a compilable type.
+    fn resolve(&mut self, name: &str) -> CompileResult<(Variable, Type)> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(found) = scope.variables.get(name) {
+                return Ok(*found);
+            }
+            if scope.pending.contains(name) {
+                return Err(NotCompilable::Construct);
+            }
+        }
+        if let Some((free, variable)) = self.free.iter().find(|(free, _)| free.name == name) {
+            return Ok((*variable, free.ty));
+        }
+
+        let ty = (self.observe)(name);
+        self.guards.push((name.to_string(), ty));
+        let ty = ty.ok_or(NotCompilable::Types)?;
+        let variable = self.new_variable(ty.ir());
+        self.free.push((
+            FreeVariable {
+                name: name.to_string(),
+                ty,
+                written: false,
+            },
+            variable,
+        ));
+        Ok((variable, ty))
+    }
+
+    fn resolve_access(
+        &mut self,
+        variable_access: &VariableAccess,
+        write: bool,
+    ) -> CompileResult<(Variable, Type)> {
+        let VariableAccess { name, accesses } = variable_access;
+        if !accesses.is_empty() {
+            return Err(NotCompilable::Construct);
+        }
+        let name = variable_name(name)?;
+        let (variable, ty) = self.resolve(&name)?;
+        if write {
+            if let Some((free, _)) = self.free.iter_mut().find(|(free, _)| free.name == name) {
+                if self
+                    .scopes
+                    .iter()
+                    .all(|scope| !scope.variables.contains_key(&name))
+                {
+                    free.written = true;
+                }
+            }
+        }
+        Ok((variable, ty))
+    }
+
+    fn helper(&mut self, id: FuncId) -> FuncRef {
+        if let Some(func_ref) = self.helper_refs.get(&id) {
+            return *func_ref;
+        }
+        let func_ref = self.module.declare_func_in_func(id, self.builder.func);
+        self.helper_refs.insert(id, func_ref);
+        func_ref
+    }
+
+    /// Returns the status the compiled code exits with when the error happens at the site.
+    fn site(&mut self, span: Option<Span>, kind: SiteKind) -> i64 {
+        self.sites.push(Site { span, kind });
+        (FIRST_ERROR as usize + self.sites.len() - 1) as i64
+    }
+
+    /// Leaves the compiled code with the given status when `condition` is true.
+    fn exit_if(&mut self, condition: IrValue, status: i64) {
+        let status = self.builder.ins().iconst(types::I32, status);
+        let next = self.builder.create_block();
+        self.builder
+            .ins()
+            .brif(condition, self.exit, &[status], next, &[]);
+        self.builder.switch_to_block(next);
+    }
+
+    /// Leaves the compiled code unconditionally. Whatever follows in the same block is
+    /// unreachable, and is compiled into a block of its own.
+    fn exit_with(&mut self, status: u32) {
+        let status = self.builder.ins().iconst(types::I32, status as i64);
+        self.builder.ins().jump(self.exit, &[status]);
+        let unreachable = self.builder.create_block();
+        self.builder.switch_to_block(unreachable);
+    }
+
+    fn compile_scoped_block(&mut self, block: &ASTBlock) -> CompileResult<()> {
+        self.push_scope(HashSet::new());
+        for node in block.0.iter() {
+            self.compile_statement(node)?;
+        }
+        self.pop_scope();
+        Ok(())
+    }
+
+    fn compile_statement(&mut self, node: &ASTNode) -> CompileResult<()> {
+        let previous_span = self.span;
+        self.span = node_span(node).or(previous_span);
+        self.compile_node(node)?;
+        self.span = previous_span;
+        Ok(())
+    }
+
+    fn compile_node(&mut self, node: &ASTNode) -> CompileResult<()> {
+        match node {
+            ASTNode::HAI(_) | ASTNode::KTHXBYE(_) => {}
+            ASTNode::IHasA(IHasA {
+                identifier,
+                initial_value,
+            }) => {
+                let value = match initial_value {
+                    Some(IHasAInitialValue::Expression(e)) => self.compile_expression(e)?,
+                    Some(IHasAInitialValue::Type(t)) => self.zero(ast_type(t)?),
+                    // NOOB variables cannot be compiled.
+                    None => return Err(NotCompilable::Construct),
+                };
+                let name = variable_name(identifier)?;
+                let variable = self.new_variable(value.ty.ir());
+                self.builder.def_var(variable, value.value);
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .variables
+                    .insert(name, (variable, value.ty));
+            }
+            ASTNode::ImInYr(ImInYr {
+                on_iteration,
+                condition,
+                code_block,
+                ..
+            }) => {
+                // The whole loop runs in a single child scope, like the tree-walker does.
+                self.push_scope(declared_names(code_block));
+                let header = self.builder.create_block();
+                let body = self.builder.create_block();
+                let end = self.builder.create_block();
+                self.builder.ins().jump(header, &[]);
+
+                self.builder.switch_to_block(header);
+                match condition {
+                    None => {
+                        self.builder.ins().jump(body, &[]);
+                    }
+                    Some(LoopCondition::TIL(expression)) => {
+                        let done = self.compile_truth(expression)?;
+                        self.builder.ins().brif(done, end, &[], body, &[]);
+                    }
+                    Some(LoopCondition::WILE(expression)) => {
+                        let running = self.compile_truth(expression)?;
+                        self.builder.ins().brif(running, body, &[], end, &[]);
+                    }
+                };
+
+                self.builder.switch_to_block(body);
+                self.loops.push(end);
+                for node in code_block.0.iter() {
+                    self.compile_statement(node)?;
+                }
+                self.loops.pop();
+
+                if let Some(LoopIterationOperation { operation, operand }) = on_iteration {
+                    let amount = match operation {
+                        LoopOperation::UPPIN(_) => 1,
+                        LoopOperation::NERFIN(_) => -1,
+                    };
+                    let (variable, ty) = self.resolve_access(operand, true)?;
+                    let value = self.builder.use_var(variable);
+                    let value = match ty {
+                        Type::Numbr => self.builder.ins().iadd_imm(value, amount),
+                        Type::Numbar => {
+                            let amount = self.builder.ins().f32const(amount as f32);
+                            self.builder.ins().fadd(value, amount)
+                        }
+                        // The interpreter reports the type error.
+                        Type::Troof => return Err(NotCompilable::Types),
+                    };
+                    self.builder.def_var(variable, value);
+                }
+                self.builder.ins().jump(header, &[]);
+
+                self.builder.switch_to_block(end);
+                self.pop_scope();
+            }
+            ASTNode::VariableAssignment(VariableAssignment {
+                variable_access,
+                expression,
+            }) => {
+                let value = self.compile_expression(expression)?;
+                let (variable, ty) = self.resolve_access(variable_access, true)?;
+                if value.ty != ty {
+                    return Err(NotCompilable::Types);
+                }
+                self.builder.def_var(variable, value.value);
+            }
+            ASTNode::Visible(Visible(expressions, has_exclamation)) => {
+                let write_site = self.site(self.span, SiteKind::Helper);
+                for (index, expression) in expressions.iter().enumerate() {
+                    let text = match expression {
+                        ASTExpression::LiteralValue(token) => match yarn_literal(token) {
+                            Some(raw) => Some(self.constant_yarn(raw)?),
+                            None => None,
+                        },
+                        _ => None,
+                    };
+                    let value = match text {
+                        Some(_) => None,
+                        None => Some(self.compile_expression(expression)?),
+                    };
+                    if index > 0 {
+                        self.write_text(" ".to_string(), write_site);
+                    }
+                    match (text, value) {
+                        (Some(text), _) => self.write_text(text, write_site),
+                        (None, Some(value)) => self.write_value(value, write_site),
+                        (None, None) => unreachable!(),
+                    }
+                }
+                if has_exclamation.is_none() {
+                    self.write_text("\n".to_string(), write_site);
+                }
+                let flush = self.helper(self.helpers.flush);
+                let call = self.builder.ins().call(flush, &[self.context]);
+                let failed = self.builder.inst_results(call)[0];
+                self.exit_if(failed, write_site);
+            }
+            ASTNode::FoundYr(expression) => {
+                let value = self.compile_expression(expression)?;
+                let tag = self.builder.ins().iconst(types::I64, value.ty.tag() as i64);
+                let bits = self.encode(value);
+                self.store_slot(RESULT_TAG_SLOT, tag);
+                self.store_slot(RESULT_BITS_SLOT, bits);
+                self.exit_with(FOUND_YR);
+            }
+            ASTNode::Gtfo(_) => match self.loops.last() {
+                Some(end) => {
+                    let end = *end;
+                    self.builder.ins().jump(end, &[]);
+                    let unreachable = self.builder.create_block();
+                    self.builder.switch_to_block(unreachable);
+                }
+                None => self.exit_with(GTFO),
+            },
+            ASTNode::ORly(ORly {
+                if_true,
+                if_false,
+                mebbes,
+            }) => {
+                let end = self.builder.create_block();
+                if let Some(block) = if_true {
+                    let it = self.it_truth();
+                    self.compile_branch(it, block, end)?;
+                }
+                for (expression, block) in mebbes.iter() {
+                    let condition = self.compile_truth(expression)?;
+                    self.compile_branch(condition, block, end)?;
+                }
+                if let Some(block) = if_false {
+                    self.compile_scoped_block(block)?;
+                }
+                self.builder.ins().jump(end, &[]);
+                self.builder.switch_to_block(end);
+            }
+            ASTNode::Expression(expression) => {
+                // `O HAI IM` declarations are parsed as expressions.
+                if self.source.extension(expression_span(expression)).is_some() {
+                    return Err(NotCompilable::Construct);
+                }
+                let value = self.compile_expression(expression)?;
+                let tag = self.builder.ins().iconst(types::I8, value.ty.tag() as i64);
+                let bits = self.encode(value);
+                let (tag_variable, bits_variable) = self.scopes.last().unwrap().it;
+                self.builder.def_var(tag_variable, tag);
+                self.builder.def_var(bits_variable, bits);
+            }
+            // Function calls and definitions, I/O other than `VISIBLE`, BUKKITs and `WTF?` are
+            // left to the interpreter.
+            ASTNode::IIz(_)
+            | ASTNode::HowIzI(_)
+            | ASTNode::Gimmeh(_)
+            | ASTNode::BukkitSetSlot(_)
+            | ASTNode::Wtf(_)
+            | ASTNode::ASTError(_) => return Err(NotCompilable::Construct),
+        };
+        Ok(())
+    }
+
+    /// Runs the block when `condition` holds, then jumps to `end`. Otherwise continues in a new
+    /// block.
+    fn compile_branch(
+        &mut self,
+        condition: IrValue,
+        block: &ASTBlock,
+        end: Block,
+    ) -> CompileResult<()> {
+        let then = self.builder.create_block();
+        let next = self.builder.create_block();
+        self.builder.ins().brif(condition, then, &[], next, &[]);
+        self.builder.switch_to_block(then);
+        self.compile_scoped_block(block)?;
+        self.builder.ins().jump(end, &[]);
+        self.builder.switch_to_block(next);
+        Ok(())
+    }
+
+    /// Resolves the escape sequences of a YARN literal at compile time. Interpolations are left
+    /// to the interpreter.
+    fn constant_yarn(&mut self, raw: &str) -> CompileResult<String> {
+        match parse_yarn(raw) {
+            Ok(parts) => parts
+                .into_iter()
+                .map(|part| match part {
+                    YarnPart::Text(text) => Ok(text),
+                    YarnPart::Variable(_) => Err(NotCompilable::Construct),
+                })
+                .collect(),
+            Err(_) => Err(NotCompilable::Construct),
+        }
+    }
+
+    fn write_text(&mut self, text: String, site: i64) {
+        let index = match self.texts.iter().position(|existing| *existing == text) {
+            Some(index) => index,
+            None => {
+                self.texts.push(text);
+                self.texts.len() - 1
+            }
+        };
+        let write_text = self.helper(self.helpers.write_text);
+        let index = self.builder.ins().iconst(types::I32, index as i64);
+        let call = self.builder.ins().call(write_text, &[self.context, index]);
+        let failed = self.builder.inst_results(call)[0];
+        self.exit_if(failed, site);
+    }
+
+    fn write_value(&mut self, value: Typed, site: i64) {
+        let write_value = self.helper(self.helpers.write_value);
+        let tag = self.builder.ins().iconst(types::I64, value.ty.tag() as i64);
+        let bits = self.encode(value);
+        let call = self
+            .builder
+            .ins()
+            .call(write_value, &[self.context, tag, bits]);
+        let failed = self.builder.inst_results(call)[0];
+        self.exit_if(failed, site);
+    }
+
+    fn zero(&mut self, ty: Type) -> Typed {
+        let value = match ty {
+            Type::Troof => self.builder.ins().iconst(types::I8, 0),
+            Type::Numbr => self.builder.ins().iconst(types::I32, 0),
+            Type::Numbar => self.builder.ins().f32const(0.0),
+        };
+        Typed { value, ty }
+    }
+
+    /// Widens a value to the 64 bits a slot holds.
+    fn encode(&mut self, value: Typed) -> IrValue {
+        match value.ty {
+            Type::Troof => self.builder.ins().uextend(types::I64, value.value),
+            Type::Numbr => self.builder.ins().sextend(types::I64, value.value),
+            Type::Numbar => {
+                let bits = self
+                    .builder
+                    .ins()
+                    .bitcast(types::I32, MemFlags::new(), value.value);
+                self.builder.ins().uextend(types::I64, bits)
+            }
+        }
+    }
+
+    fn decode(&mut self, bits: IrValue, ty: Type) -> IrValue {
+        match ty {
+            Type::Troof => self.builder.ins().ireduce(types::I8, bits),
+            Type::Numbr => self.builder.ins().ireduce(types::I32, bits),
+            Type::Numbar => {
+                let bits = self.builder.ins().ireduce(types::I32, bits);
+                self.builder
+                    .ins()
+                    .bitcast(types::F32, MemFlags::new(), bits)
+            }
+        }
+    }
+
+    /// Whether the `IT` of the current scope is truthy.
+    fn it_truth(&mut self) -> IrValue {
+        let (tag, bits) = self.scopes.last().unwrap().it;
+        let tag = self.builder.use_var(tag);
+        let bits = self.builder.use_var(bits);
+        // NOOB is stored as zero bits, so it is falsy like a zero NUMBR or a FAIL.
+        let low = self.builder.ins().ireduce(types::I32, bits);
+        let integer = self.builder.ins().icmp_imm(IntCC::NotEqual, low, 0);
+        let numbar = self.builder.ins().bitcast(types::F32, MemFlags::new(), low);
+        let numbar = self.truth(Typed {
+            value: numbar,
+            ty: Type::Numbar,
+        });
+        let is_numbar = self
+            .builder
+            .ins()
+            .icmp_imm(IntCC::Equal, tag, Type::Numbar.tag() as i64);
+        self.builder.ins().select(is_numbar, numbar, integer)
+    }
+
+    fn truth(&mut self, value: Typed) -> IrValue {
+        match value.ty {
+            Type::Troof => value.value,
+            Type::Numbr => self.builder.ins().icmp_imm(IntCC::NotEqual, value.value, 0),
+            Type::Numbar => {
+                let zero = self.builder.ins().f32const(0.0);
+                self.builder
+                    .ins()
+                    .fcmp(FloatCC::NotEqual, value.value, zero)
+            }
+        }
+    }
+
+    fn compile_truth(&mut self, expression: &ASTExpression) -> CompileResult<IrValue> {
+        let value = self.compile_expression(expression)?;
+        Ok(self.truth(value))
+    }
+
+    /// Casts the value as done by the math operators: a TROOF becomes a NUMBR.
+    fn number(&mut self, value: Typed) -> Typed {
+        match value.ty {
+            Type::Troof => Typed {
+                value: self.builder.ins().uextend(types::I32, value.value),
+                ty: Type::Numbr,
+            },
+            _ => value,
+        }
+    }
+
+    fn numbar(&mut self, value: Typed) -> IrValue {
+        match self.number(value) {
+            Typed {
+                value,
+                ty: Type::Numbr,
+            } => self.builder.ins().fcvt_from_sint(types::F32, value),
+            Typed { value, .. } => value,
+        }
+    }
+
+    fn cast(&mut self, value: Typed, to: Type) -> Typed {
+        let converted = match (value.ty, to) {
+            (_, Type::Troof) => self.truth(value),
+            (Type::Numbar, Type::Numbr) => {
+                self.builder.ins().fcvt_to_sint_sat(types::I32, value.value)
+            }
+            (_, Type::Numbr) => self.number(value).value,
+            (_, Type::Numbar) => self.numbar(value),
+        };
+        Typed {
+            value: converted,
+            ty: to,
+        }
+    }
+
+    fn compile_expression(&mut self, expression: &ASTExpression) -> CompileResult<Typed> {
+        let previous_span = self.span;
+        self.span = expression_span(expression).or(previous_span);
+        let value = self.compile_expression_inner(expression);
+        self.span = previous_span;
+        value
+    }
+
+    fn compile_expression_inner(&mut self, expression: &ASTExpression) -> CompileResult<Typed> {
+        Ok(match expression {
+            ASTExpression::LiteralValue(token) => match literal_value(token) {
+                Value::Troof(troof) => Typed {
+                    value: self.builder.ins().iconst(types::I8, troof as i64),
+                    ty: Type::Troof,
+                },
+                Value::Numbr(numbr) => Typed {
+                    value: self.builder.ins().iconst(types::I32, numbr as i64),
+                    ty: Type::Numbr,
+                },
+                Value::Numbar(numbar) => Typed {
+                    value: self.builder.ins().f32const(numbar),
+                    ty: Type::Numbar,
+                },
+                _ => return Err(NotCompilable::Construct),
+            },
+            ASTExpression::VariableAccess(variable_access) => {
+                let (variable, ty) = self.resolve_access(variable_access, false)?;
+                Typed {
+                    value: self.builder.use_var(variable),
+                    ty,
+                }
+            }
+            ASTExpression::BothOf(left, right) => {
+                self.compile_short_circuit(&[left.as_ref(), right.as_ref()], false)?
+            }
+            ASTExpression::EitherOf(left, right) => {
+                self.compile_short_circuit(&[left.as_ref(), right.as_ref()], true)?
+            }
+            ASTExpression::AllOf(values) => {
+                let values: Vec<_> = values.iter().collect();
+                self.compile_short_circuit(&values, false)?
+            }
+            ASTExpression::AnyOf(values) => {
+                let values: Vec<_> = values.iter().collect();
+                self.compile_short_circuit(&values, true)?
+            }
+            ASTExpression::WonOf(left, right) => {
+                let left = self.compile_truth(left)?;
+                let right = self.compile_truth(right)?;
+                Typed {
+                    value: self.builder.ins().bxor(left, right),
+                    ty: Type::Troof,
+                }
+            }
+            ASTExpression::Not(expression) => {
+                let value = self.compile_truth(expression)?;
+                Typed {
+                    value: self.builder.ins().bxor_imm(value, 1),
+                    ty: Type::Troof,
+                }
+            }
+            ASTExpression::SumOf(left, right) => {
+                self.compile_binary(BinaryOperation::SumOf, left, right)?
+            }
+            ASTExpression::DiffOf(left, right) => {
+                self.compile_binary(BinaryOperation::DiffOf, left, right)?
+            }
+            ASTExpression::ProduktOf(left, right) => {
+                self.compile_binary(BinaryOperation::ProduktOf, left, right)?
+            }
+            ASTExpression::QuoshuntOf(left, right) => {
+                self.compile_binary(BinaryOperation::QuoshuntOf, left, right)?
+            }
+            ASTExpression::ModOf(left, right) => {
+                self.compile_binary(BinaryOperation::ModOf, left, right)?
+            }
+            ASTExpression::BiggrOf(left, right) => {
+                self.compile_binary(BinaryOperation::BiggrOf, left, right)?
+            }
+            ASTExpression::SmallrOf(left, right) => {
+                self.compile_binary(BinaryOperation::SmallrOf, left, right)?
+            }
+            ASTExpression::BothSaem(left, right) => {
+                self.compile_binary(BinaryOperation::BothSaem, left, right)?
+            }
+            ASTExpression::Diffrint(left, right) => {
+                self.compile_binary(BinaryOperation::Diffrint, left, right)?
+            }
+            ASTExpression::Maek(expression, ast_type_to) => {
+                let to = ast_type(ast_type_to)?;
+                let value = self.compile_expression(expression)?;
+                self.cast(value, to)
+            }
+            ASTExpression::Smoosh(_) => return Err(NotCompilable::Construct),
+        })
+    }
+
+    /// Compiles a short-circuiting chain of operands. With `stop_on` false this is an `ALL OF`,
+    /// and with `stop_on` true an `ANY OF`.
+    fn compile_short_circuit(
+        &mut self,
+        operands: &[&ASTExpression],
+        stop_on: bool,
+    ) -> CompileResult<Typed> {
+        let end = self.builder.create_block();
+        let result = self.builder.append_block_param(end, types::I8);
+        for operand in operands.iter() {
+            let value = self.compile_truth(operand)?;
+            let next = self.builder.create_block();
+            if stop_on {
+                self.builder.ins().brif(value, end, &[value], next, &[]);
+            } else {
+                self.builder.ins().brif(value, next, &[], end, &[value]);
+            }
+            self.builder.switch_to_block(next);
+        }
+        // Every operand was evaluated without stopping.
+        let all = self.builder.ins().iconst(types::I8, !stop_on as i64);
+        self.builder.ins().jump(end, &[all]);
+        self.builder.switch_to_block(end);
+        Ok(Typed {
+            value: result,
+            ty: Type::Troof,
+        })
+    }
+
+    fn compile_binary(
+        &mut self,
+        operation: BinaryOperation,
+        left: &ASTExpression,
+        right: &ASTExpression,
+    ) -> CompileResult<Typed> {
+        let left = self.compile_expression(left)?;
+        let right = self.compile_expression(right)?;
+        Ok(match operation {
+            BinaryOperation::BothSaem => Typed {
+                value: self.equal(left, right),
+                ty: Type::Troof,
+            },
+            BinaryOperation::Diffrint => {
+                let equal = self.equal(left, right);
+                Typed {
+                    value: self.builder.ins().bxor_imm(equal, 1),
+                    ty: Type::Troof,
+                }
+            }
+            BinaryOperation::WonOf => unreachable!(),
+            operation => {
+                let (left, right) = (self.number(left), self.number(right));
+                if left.ty == Type::Numbr && right.ty == Type::Numbr {
+                    self.numbr_operation(operation, left.value, right.value)
+                } else {
+                    let left = self.numbar(left);
+                    let right = self.numbar(right);
+                    self.numbar_operation(operation, left, right)
+                }
+            }
+        })
+    }
+
+    /// Equality as done by `BOTH SAEM`: values of different types are never equal, except a
+    /// NUMBR and a NUMBAR, which are compared as NUMBARs.
+    fn equal(&mut self, left: Typed, right: Typed) -> IrValue {
+        match (left.ty, right.ty) {
+            (Type::Troof, Type::Troof) | (Type::Numbr, Type::Numbr) => {
+                self.builder
+                    .ins()
+                    .icmp(IntCC::Equal, left.value, right.value)
+            }
+            (Type::Troof, _) | (_, Type::Troof) => self.builder.ins().iconst(types::I8, 0),
+            _ => {
+                let left = self.numbar(left);
+                let right = self.numbar(right);
+                self.builder.ins().fcmp(FloatCC::Equal, left, right)
+            }
+        }
+    }
+
+    /// NUMBR arithmetic wraps around on overflow.
+    fn numbr_operation(
+        &mut self,
+        operation: BinaryOperation,
+        left: IrValue,
+        right: IrValue,
+    ) -> Typed {
+        let value = match operation {
+            BinaryOperation::SumOf => self.builder.ins().iadd(left, right),
+            BinaryOperation::DiffOf => self.builder.ins().isub(left, right),
+            BinaryOperation::ProduktOf => self.builder.ins().imul(left, right),
+            BinaryOperation::QuoshuntOf | BinaryOperation::ModOf => {
+                let is_zero = self.builder.ins().icmp_imm(IntCC::Equal, right, 0);
+                let site = self.site(self.span, SiteKind::DivisionByZero);
+                self.exit_if(is_zero, site);
+                // Dividing the smallest NUMBR by -1 traps, so -1 is handled separately, as a
+                // negation for QUOSHUNT OF and a zero remainder for MOD OF.
+                let is_minus_one = self.builder.ins().icmp_imm(IntCC::Equal, right, -1);
+                let one = self.builder.ins().iconst(types::I32, 1);
+                let divisor = self.builder.ins().select(is_minus_one, one, right);
+                if operation == BinaryOperation::QuoshuntOf {
+                    let quotient = self.builder.ins().sdiv(left, divisor);
+                    let negated = self.builder.ins().ineg(left);
+                    self.builder.ins().select(is_minus_one, negated, quotient)
+                } else {
+                    self.builder.ins().srem(left, divisor)
+                }
+            }
+            BinaryOperation::BiggrOf => {
+                return Typed {
+                    value: self
+                        .builder
+                        .ins()
+                        .icmp(IntCC::SignedGreaterThan, left, right),
+                    ty: Type::Troof,
+                }
+            }
+            BinaryOperation::SmallrOf => {
+                return Typed {
+                    value: self.builder.ins().icmp(IntCC::SignedLessThan, left, right),
+                    ty: Type::Troof,
+                }
+            }
+            BinaryOperation::BothSaem | BinaryOperation::Diffrint | BinaryOperation::WonOf => {
+                unreachable!()
+            }
+        };
+        Typed {
+            value,
+            ty: Type::Numbr,
+        }
+    }
+
+    fn numbar_operation(
+        &mut self,
+        operation: BinaryOperation,
+        left: IrValue,
+        right: IrValue,
+    ) -> Typed {
+        let value = match operation {
+            BinaryOperation::SumOf => self.builder.ins().fadd(left, right),
+            BinaryOperation::DiffOf => self.builder.ins().fsub(left, right),
+            BinaryOperation::ProduktOf => self.builder.ins().fmul(left, right),
+            BinaryOperation::QuoshuntOf => self.builder.ins().fdiv(left, right),
+            BinaryOperation::ModOf => {
+                // Cranelift has no floating point remainder.
+                let numbar_mod = self.helper(self.helpers.numbar_mod);
+                let call = self.builder.ins().call(numbar_mod, &[left, right]);
+                self.builder.inst_results(call)[0]
+            }
+            BinaryOperation::BiggrOf => {
+                return Typed {
+                    value: self.builder.ins().fcmp(FloatCC::GreaterThan, left, right),
+                    ty: Type::Troof,
+                }
+            }
+            BinaryOperation::SmallrOf => {
+                return Typed {
+                    value: self.builder.ins().fcmp(FloatCC::LessThan, left, right),
+                    ty: Type::Troof,
+                }
+            }
+            BinaryOperation::BothSaem | BinaryOperation::Diffrint | BinaryOperation::WonOf => {
+                unreachable!()
+            }
+        };
+        Typed {
+            value,
+            ty: Type::Numbar,
+        }
+    }
+}
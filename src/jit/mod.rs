@@ -0,0 +1,435 @@
+//! Native code generation for numeric code, enabled by the `jit` feature.
+//!
+//! Loops, and the bodies of called functions, are compiled with Cranelift when every variable
+//! they use holds a TROOF, a NUMBR or a NUMBAR and keeps its type. The types of the variables
+//! read from outside of the compiled code are checked before each run, and the interpreter runs
+//! the code instead whenever they differ from the ones it was compiled for, or when the code
+//! uses anything else, such as YARNs, BUKKITs or function calls.
+//!
+//! Compiled code does not count steps or call the debugger hooks, so it is only used when there
+//! are no step or time limits and no tracer or debugger attached.
+
+use std::cell::{OnceCell, RefCell};
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::rc::Rc;
+
+use cranelift::codegen::ir::{types, AbiParam};
+use cranelift::codegen::settings::{self, Configurable};
+use cranelift::jit::{JITBuilder, JITModule};
+use cranelift::module::{default_libcall_names, FuncId, Linkage, Module};
+
+use lolcode_ast::parser::statements::how_iz_i::HowIzI;
+use lolcode_ast::parser::statements::im_in_yr::ImInYr;
+use lolcode_ast::parser::statements::ASTNode;
+
+use crate::error::{RuntimeError, Span};
+use crate::runtime::Runtime;
+use crate::types::{Interrupt, RuntimeErrorKind, RuntimeResult, Scope, Value};
+use crate::variable_access::get_scope_that_has_value;
+
+mod compiler;
+
+use compiler::NotCompilable;
+
+/// The compiled code returns one of these statuses, or [`FIRST_ERROR`] plus the index of the
+/// [`Site`] where it failed.
+const COMPLETED: u32 = 0;
+const GTFO: u32 = 1;
+const FOUND_YR: u32 = 2;
+const FIRST_ERROR: u32 = 3;
+
+/// Layout of the slots shared with the compiled code. Each slot holds a value widened to 64
+/// bits, and its type is either known at compile time or stored in a tag slot.
+const RESULT_TAG_SLOT: usize = 0;
+const RESULT_BITS_SLOT: usize = 1;
+const IT_TAG_SLOT: usize = 2;
+const IT_BITS_SLOT: usize = 3;
+const FIRST_VARIABLE_SLOT: usize = 4;
+
+/// The signature of compiled code: it takes the slots and the [`Context`], and returns a status.
+type Code = unsafe extern "C" fn(*mut u64, *mut Context) -> u32;
+
+/// The types compiled code can hold in variables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Troof,
+    Numbr,
+    Numbar,
+}
+
+impl Type {
+    const NOOB_TAG: u64 = 0;
+
+    fn of(value: &Value) -> Option<Type> {
+        match value {
+            Value::Troof(_) => Some(Type::Troof),
+            Value::Numbr(_) => Some(Type::Numbr),
+            Value::Numbar(_) => Some(Type::Numbar),
+            _ => None,
+        }
+    }
+
+    fn ir(self) -> cranelift::codegen::ir::Type {
+        match self {
+            Type::Troof => types::I8,
+            Type::Numbr => types::I32,
+            Type::Numbar => types::F32,
+        }
+    }
+
+    fn tag(self) -> u64 {
+        match self {
+            Type::Troof => 1,
+            Type::Numbr => 2,
+            Type::Numbar => 3,
+        }
+    }
+}
+
+fn encode(value: &Value) -> u64 {
+    match value {
+        Value::Troof(troof) => *troof as u64,
+        Value::Numbr(numbr) => *numbr as i64 as u64,
+        Value::Numbar(numbar) => numbar.to_bits() as u64,
+        _ => 0,
+    }
+}
+
+fn decode(tag: u64, bits: u64) -> Value {
+    match tag {
+        1 => Value::Troof(bits != 0),
+        2 => Value::Numbr(bits as i32),
+        3 => Value::Numbar(f32::from_bits(bits as u32)),
+        _ => Value::Noob,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RegionKind {
+    /// A single `IM IN YR` statement.
+    Loop,
+    /// The body of a function, whose `IT` is returned when it ends without `FOUND YR` or `GTFO`.
+    Function,
+}
+
+/// A variable declared outside of the compiled code. Its value is loaded when the compiled code
+/// starts, and stored back when it ends if it was assigned.
+struct FreeVariable {
+    name: String,
+    ty: Type,
+    written: bool,
+}
+
+/// The type a variable outside of the compiled code must have for the code to be used, or None
+/// if it did not hold a compilable type.
+type Guard = (String, Option<Type>);
+
+#[derive(Debug, Clone, Copy)]
+enum SiteKind {
+    DivisionByZero,
+    /// A call to one of the helpers, which left its error in the [`Context`].
+    Helper,
+}
+
+/// Somewhere the compiled code can fail.
+struct Site {
+    span: Option<Span>,
+    kind: SiteKind,
+}
+
+struct Compiled {
+    code: Code,
+    variables: Vec<FreeVariable>,
+    sites: Vec<Site>,
+    /// The YARNs printed by `VISIBLE`, by index.
+    texts: Vec<String>,
+}
+
+/// The result of compiling a region for given types of its variables. `compiled` is None when
+/// the interpreter has to run it.
+struct Region {
+    guards: Vec<Guard>,
+    compiled: Option<Rc<Compiled>>,
+}
+
+/// How many times a region is compiled for different types of its variables. Once it has been
+/// compiled this many times, the interpreter runs it for any other types, which bounds the
+/// regions kept for a source to this many per loop and function.
+const MAX_VARIANTS: usize = 4;
+
+/// The regions compiled from the loops and functions of a [`Source`](crate::source::Source), by
+/// the start of the span of the loop's label or of the function's name.
+#[derive(Default)]
+pub(crate) struct Regions(RefCell<HashMap<usize, Vec<Region>>>);
+
+impl Debug for Regions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Regions")
+            .field("count", &self.0.borrow().len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Lets compiled code call back into the runtime.
+struct Context<'a> {
+    runtime: &'a Runtime,
+    texts: &'a [String],
+    error: Option<RuntimeError>,
+}
+
+/// Functions called by the compiled code. Those returning a `u32` return 1 after storing an error
+/// in the context, and 0 otherwise.
+struct Helpers {
+    write_text: FuncId,
+    write_value: FuncId,
+    flush: FuncId,
+    numbar_mod: FuncId,
+}
+
+extern "C" fn write_text(context: *mut Context, index: u32) -> u32 {
+    // SAFETY: compiled code is only called by `execute`, which passes a valid context.
+    let context = unsafe { &mut *context };
+    let text = &context.texts[index as usize];
+    report(context, context.runtime.write(text))
+}
+
+extern "C" fn write_value(context: *mut Context, tag: u64, bits: u64) -> u32 {
+    // SAFETY: compiled code is only called by `execute`, which passes a valid context.
+    let context = unsafe { &mut *context };
+    let result = decode(tag, bits)
+        .to_visible_yarn()
+        .and_then(|text| context.runtime.write(&text));
+    report(context, result)
+}
+
+extern "C" fn flush(context: *mut Context) -> u32 {
+    // SAFETY: compiled code is only called by `execute`, which passes a valid context.
+    let context = unsafe { &mut *context };
+    report(context, context.runtime.flush())
+}
+
+extern "C" fn numbar_mod(left: f32, right: f32) -> f32 {
+    left % right
+}
+
+fn report(context: &mut Context, result: RuntimeResult<()>) -> u32 {
+    match result {
+        Ok(()) => 0,
+        Err(error) => {
+            context.error = Some(error);
+            1
+        }
+    }
+}
+
+struct Backend {
+    module: JITModule,
+    helpers: Helpers,
+}
+
+impl Backend {
+    /// Returns None if Cranelift does not support the host.
+    fn new() -> Option<Backend> {
+        let mut flags = settings::builder();
+        flags.set("opt_level", "speed").ok()?;
+        // Same as `JITBuilder::new`, which panics instead on hosts Cranelift does not support.
+        flags.set("use_colocated_libcalls", "false").ok()?;
+        flags.set("is_pic", "true").ok()?;
+        let isa = cranelift::native::builder()
+            .ok()?
+            .finish(settings::Flags::new(flags))
+            .ok()?;
+        let mut builder = JITBuilder::with_isa(isa, default_libcall_names());
+        builder.symbol("lol_write_text", write_text as *const u8);
+        builder.symbol("lol_write_value", write_value as *const u8);
+        builder.symbol("lol_flush", flush as *const u8);
+        builder.symbol("lol_numbar_mod", numbar_mod as *const u8);
+        let mut module = JITModule::new(builder);
+
+        let pointer = module.target_config().pointer_type();
+        let mut helper = |name: &str, params: &[cranelift::codegen::ir::Type], returns| {
+            let mut signature = module.make_signature();
+            signature
+                .params
+                .extend(params.iter().map(|param| AbiParam::new(*param)));
+            signature.returns.push(AbiParam::new(returns));
+            module
+                .declare_function(name, Linkage::Import, &signature)
+                .ok()
+        };
+        let helpers = Helpers {
+            write_text: helper("lol_write_text", &[pointer, types::I32], types::I32)?,
+            write_value: helper(
+                "lol_write_value",
+                &[pointer, types::I64, types::I64],
+                types::I32,
+            )?,
+            flush: helper("lol_flush", &[pointer], types::I32)?,
+            numbar_mod: helper("lol_numbar_mod", &[types::F32, types::F32], types::F32)?,
+        };
+        Some(Backend { module, helpers })
+    }
+}
+
+/// The code generator of a running program, kept by its [`Runtime`]. The compiled regions are
+/// kept by the source code they come from, in [`Regions`].
+#[derive(Default)]
+pub(crate) struct Jit {
+    /// Created on first use, and None if the host is not supported.
+    backend: OnceCell<Option<RefCell<Backend>>>,
+}
+
+/// Returns the type of the variable, as seen from the scope, if it is compilable.
+fn observe(scope: &Scope, name: &str) -> Option<Type> {
+    let scope = get_scope_that_has_value(scope, name)?;
+    let variables = scope.variables.borrow();
+    Type::of(variables.get(name)?)
+}
+
+impl Jit {
+    fn compiled(
+        &self,
+        scope: &Scope,
+        kind: RegionKind,
+        key: usize,
+        statements: &[ASTNode],
+    ) -> Option<Rc<Compiled>> {
+        let observe = |name: &str| observe(scope, name);
+
+        let mut regions = scope.source.regions.0.borrow_mut();
+        let variants = regions.entry(key).or_default();
+        let found = variants
+            .iter()
+            .find(|region| region.guards.iter().all(|(name, ty)| observe(name) == *ty));
+        if let Some(region) = found {
+            return region.compiled.clone();
+        }
+        if variants.len() >= MAX_VARIANTS {
+            return None;
+        }
+
+        let backend = self
+            .backend
+            .get_or_init(|| Backend::new().map(RefCell::new));
+        let (compiled, guards) = compiler::compile(
+            &mut backend.as_ref()?.borrow_mut(),
+            kind,
+            statements,
+            &scope.source,
+            &observe,
+        );
+        let (compiled, guards) = match compiled {
+            Ok(compiled) => (Some(Rc::new(compiled)), guards),
+            Err(NotCompilable::Types) => (None, guards),
+            // No types would make it compilable, so the region is never tried again.
+            Err(NotCompilable::Construct) => (None, vec![]),
+        };
+        variants.push(Region {
+            guards,
+            compiled: compiled.clone(),
+        });
+        compiled
+    }
+}
+
+/// How compiled code ended.
+enum Exit {
+    /// The end of the region was reached, leaving this value in its `IT`.
+    Completed(Value),
+    Gtfo,
+    FoundYr(Value),
+}
+
+fn execute(scope: &Scope, compiled: &Compiled) -> RuntimeResult<Exit> {
+    let mut slots = vec![0; FIRST_VARIABLE_SLOT + compiled.variables.len()];
+    for (index, variable) in compiled.variables.iter().enumerate() {
+        // The guards checked that the variable exists.
+        if let Some(scope) = get_scope_that_has_value(scope, &variable.name) {
+            slots[FIRST_VARIABLE_SLOT + index] =
+                encode(&scope.variables.borrow()[variable.name.as_str()]);
+        }
+    }
+
+    let mut context = Context {
+        runtime: &scope.runtime,
+        texts: &compiled.texts,
+        error: None,
+    };
+    // SAFETY: the slots hold a value of the compiled type for every variable.
+    let status = unsafe { (compiled.code)(slots.as_mut_ptr(), &mut context) };
+
+    for (index, variable) in compiled.variables.iter().enumerate() {
+        if !variable.written {
+            continue;
+        }
+        let value = decode(variable.ty.tag(), slots[FIRST_VARIABLE_SLOT + index]);
+        if let Some(scope) = get_scope_that_has_value(scope, &variable.name) {
+            scope
+                .variables
+                .borrow_mut()
+                .insert(variable.name.clone(), value);
+        }
+    }
+
+    match status {
+        COMPLETED => Ok(Exit::Completed(decode(
+            slots[IT_TAG_SLOT],
+            slots[IT_BITS_SLOT],
+        ))),
+        GTFO => Ok(Exit::Gtfo),
+        FOUND_YR => Ok(Exit::FoundYr(decode(
+            slots[RESULT_TAG_SLOT],
+            slots[RESULT_BITS_SLOT],
+        ))),
+        status => {
+            let site = &compiled.sites[(status - FIRST_ERROR) as usize];
+            let error = match site.kind {
+                SiteKind::DivisionByZero => RuntimeErrorKind::DivisionByZero.into(),
+                SiteKind::Helper => context
+                    .error
+                    .take()
+                    .expect("helpers store their error before failing"),
+            };
+            Err(error.at(site.span))
+        }
+    }
+}
+
+fn run(
+    scope: &Scope,
+    kind: RegionKind,
+    key: usize,
+    statements: &[ASTNode],
+) -> Option<RuntimeResult<Exit>> {
+    let compiled = scope
+        .runtime
+        .jit()?
+        .compiled(scope, kind, key, statements)?;
+    Some(execute(scope, &compiled))
+}
+
+/// Runs an `IM IN YR` statement as native code. Returns None if the interpreter has to run it.
+pub(crate) fn run_loop(scope: &Scope, node: &ASTNode) -> Option<RuntimeResult<Option<Interrupt>>> {
+    let ASTNode::ImInYr(ImInYr { label, .. }) = node else {
+        return None;
+    };
+    let key = Span::from(label).start;
+    let exit = run(scope, RegionKind::Loop, key, std::slice::from_ref(node))?;
+    Some(exit.map(|exit| match exit {
+        Exit::Completed(_) => None,
+        Exit::Gtfo => Some(Interrupt::Gtfo),
+        Exit::FoundYr(value) => Some(Interrupt::FoundYr(value)),
+    }))
+}
+
+/// Runs the body of a function as native code, on the scope holding its arguments. Returns None
+/// if the interpreter has to run it.
+pub(crate) fn run_function(scope: &Scope, how_iz_i: &HowIzI) -> Option<RuntimeResult<Value>> {
+    let key = Span::from(&how_iz_i.name).start;
+    let exit = run(scope, RegionKind::Function, key, &how_iz_i.body.0)?;
+    Some(exit.map(|exit| match exit {
+        Exit::Completed(value) | Exit::FoundYr(value) => value,
+        Exit::Gtfo => Value::Noob,
+    }))
+}
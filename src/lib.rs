@@ -1,10 +1,11 @@
 use std::fs::read_to_string;
 use std::path::PathBuf;
+use std::rc::Rc;
 
-use lolcode_ast::lexer::{NumberToken, TokenType, TokenValue};
 use lolcode_ast::parser::expression::{ASTExpression, Identifier};
 use lolcode_ast::parser::statements::assignment::VariableAssignment;
 use lolcode_ast::parser::statements::bukkit_set_slot::BukkitSetSlot;
+use lolcode_ast::parser::statements::how_iz_i::HowIzI;
 use lolcode_ast::parser::statements::i_has_a::{IHasA, IHasAInitialValue};
 use lolcode_ast::parser::statements::i_is::IIz;
 use lolcode_ast::parser::statements::im_in_yr::{
@@ -15,30 +16,93 @@ use lolcode_ast::parser::statements::visible::Visible;
 use lolcode_ast::parser::statements::ASTNode;
 use lolcode_ast::parser::ASTBlock;
 
+use error::{expression_span, node_span};
 use lolcode_ast::parser::statements::wtf::Wtf;
-use types::{RuntimeError, RuntimeResult, Scope, Value};
+use operations::{binary_operation, literal_value, BinaryOperation};
+use source::Extension;
+use trace::Tracer;
+use types::{Interrupt, RuntimeErrorKind, RuntimeResult, Scope};
+
+pub use gc::{GcStats, GcThresholds, DEFAULT_GC_THRESHOLD};
+#[cfg(not(target_arch = "wasm32"))]
+pub use import::search_path;
+pub use import::SEARCH_PATH_VARIABLE;
+pub use interpreter::{Interpreter, InterpreterError};
+pub use runtime::{Limits, Runtime, SharedBuffer, DEFAULT_MAX_CALL_DEPTH};
+pub use trace::TraceFormat;
+pub use types::{Bukkit, BukkitRef, Function, NativeFunction, Value};
 use variable_access::{
-    get_variable_access_from_scope, mutate_variable_access, write_identifier_to_scope,
+    expect_bukkit, get_scope_that_has_function, mutate_variable_access, resolve_slot_name,
+    write_identifier_to_scope,
 };
+use yarn::{interpolate_yarn, yarn_literal};
 
+mod builtins;
+mod cast;
+pub mod check;
+pub mod compile;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod dap;
+pub mod error;
+pub mod fmt;
+mod gc;
+mod import;
+mod infer;
+mod interpreter;
+#[cfg(feature = "jit")]
+mod jit;
+pub mod lint;
+pub mod lsp;
+mod object;
+mod operations;
+#[cfg(not(target_arch = "wasm32"))]
+mod protocol;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod repl;
+mod runtime;
+mod source;
+mod trace;
 mod types;
 mod variable_access;
+pub mod vm;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+mod yarn;
 
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let string = match self {
-            Value::Noob => "NOOB".to_string(),
-            Value::Yarn(yarn) => yarn.clone(),
-            Value::Troof(troof) => troof.to_string(),
-            Value::Numbr(numbr) => numbr.to_string(),
-            Value::Numbar(numbr) => numbr.to_string(),
-            Value::Bukkit(bukkit) => bukkit.to_string(),
-        };
-        write!(f, "{}", string)
+        match self {
+            Value::Noob => write!(f, "NOOB"),
+            Value::Yarn(yarn) => write!(f, "{}", yarn),
+            Value::Troof(troof) => write!(f, "{}", if *troof { "WIN" } else { "FAIL" }),
+            Value::Numbr(numbr) => write!(f, "{}", numbr),
+            Value::Numbar(numbar) => write!(f, "{}", cast::numbar_to_yarn(*numbar)),
+            Value::Bukkit(bukkit) => write!(f, "{}", bukkit),
+        }
     }
 }
 
-pub fn execute_statement(scope: &mut Scope, node: &ASTNode) -> RuntimeResult<Option<Value>> {
+pub fn execute_statement(scope: &mut Scope, node: &ASTNode) -> RuntimeResult<Option<Interrupt>> {
+    scope
+        .runtime
+        .step()
+        .and_then(|_| scope.runtime.before_statement(scope, node_span(node)))
+        .and_then(|_| execute_node(scope, node))
+        .and_then(|interrupt| {
+            scope.runtime.after_statement(scope, node)?;
+            Ok(interrupt)
+        })
+        .map_err(|error| error.at(node_span(node)))
+}
+
+fn execute_node(scope: &mut Scope, node: &ASTNode) -> RuntimeResult<Option<Interrupt>> {
+    #[cfg(feature = "jit")]
+    if let ASTNode::ImInYr(_) = node {
+        if let Some(result) = jit::run_loop(scope, node) {
+            return result;
+        }
+    }
+
     Ok(match node {
         ASTNode::HAI(_) => None,
         ASTNode::IHasA(IHasA {
@@ -69,25 +133,39 @@ pub fn execute_statement(scope: &mut Scope, node: &ASTNode) -> RuntimeResult<Opt
                     parse_expression(&while_scope, expression)?.to_boolean()
                 }
             } {
-                execute_block(&mut while_scope, &code_block)?;
+                // Count iterations too, so even a loop with an empty body uses up steps.
+                while_scope.runtime.step()?;
+                match execute_block(&mut while_scope, &code_block)? {
+                    Some(Interrupt::Gtfo) => break,
+                    Some(interrupt) => return Ok(Some(interrupt)),
+                    None => {}
+                };
                 if let Some(LoopIterationOperation { operation, operand }) = on_iteration {
                     match operation {
                         LoopOperation::UPPIN(_) => mutate_variable_access(
                             &while_scope,
                             operand,
                             Box::new(|operand| match operand {
-                                Value::Numbr(num) => Ok((num + 1).into()),
+                                Value::Numbr(num) => Ok(num.wrapping_add(1).into()),
                                 Value::Numbar(num) => Ok((num + 1f32).into()),
-                                _ => Err(RuntimeError::IdentifierNotFound),
+                                value => Err(RuntimeErrorKind::InvalidType {
+                                    expected: "NUMBR",
+                                    found: value.type_name(),
+                                }
+                                .into()),
                             }),
                         )?,
                         LoopOperation::NERFIN(_) => mutate_variable_access(
                             &while_scope,
                             operand,
                             Box::new(|operand| match operand {
-                                Value::Numbr(num) => Ok((num - 1).into()),
+                                Value::Numbr(num) => Ok(num.wrapping_sub(1).into()),
                                 Value::Numbar(num) => Ok((num - 1f32).into()),
-                                _ => Err(RuntimeError::IdentifierNotFound),
+                                value => Err(RuntimeErrorKind::InvalidType {
+                                    expected: "NUMBR",
+                                    found: value.type_name(),
+                                }
+                                .into()),
                             }),
                         )?,
                     };
@@ -100,14 +178,10 @@ pub fn execute_statement(scope: &mut Scope, node: &ASTNode) -> RuntimeResult<Opt
             slot_name,
             value,
         }) => {
-            let mut bukkit = get_variable_access_from_scope(scope, bukkit)?;
             let expression_value = parse_expression(scope, value)?;
-            match *bukkit {
-                Value::Bukkit(ref mut bukkit) => bukkit
-                    .0
-                    .insert(slot_name.to_string_slice().into(), expression_value),
-                _ => return Err(RuntimeError::InvalidType),
-            };
+            let bukkit = expect_bukkit(object::read_variable_access(scope, bukkit)?)?;
+            let slot_name = resolve_slot_name(scope, slot_name)?;
+            bukkit.set_slot(&scope.runtime, &slot_name, expression_value)?;
             None
         }
         ASTNode::VariableAssignment(VariableAssignment {
@@ -120,33 +194,44 @@ pub fn execute_statement(scope: &mut Scope, node: &ASTNode) -> RuntimeResult<Opt
         }
         ASTNode::Visible(Visible(expressions, has_exclamation)) => {
             for (index, expression) in expressions.into_iter().enumerate() {
-                let value = parse_expression(scope, expression)?;
+                let text = parse_expression(scope, expression)?
+                    .to_visible_yarn()
+                    .map_err(|error| error.at(expression_span(expression)))?;
                 if index == 0 {
-                    print!("{}", value);
+                    scope.runtime.write(&text)?;
                 } else {
-                    print!(" {}", value);
+                    scope.runtime.write(&format!(" {}", text))?;
                 }
             }
             if has_exclamation.is_none() {
-                println!("");
+                scope.runtime.write("\n")?;
             }
+            scope.runtime.flush()?;
             None
         }
-        ASTNode::FoundYr(expr) => Some(parse_expression(scope, expr)?),
-        ASTNode::Wtf(Wtf { omg, omg_wtf }) => {
+        ASTNode::FoundYr(expr) => Some(Interrupt::FoundYr(parse_expression(scope, expr)?)),
+        ASTNode::Wtf(Wtf { omg, omg_wtf }) => 'block: {
+            // Once a case matches, every following case is executed as well, until a GTFO is
+            // found.
             let mut found = false;
             for (expression, block) in omg.into_iter() {
-                let value = parse_expression(scope, expression)?;
-                if value == *scope.it.borrow() {
-                    execute_block(&mut scope.child(), block)?;
-                    found = true;
-                    break;
+                if !found {
+                    let value = parse_expression(scope, expression)?;
+                    found = value == *scope.it.borrow();
+                }
+                if found {
+                    match execute_block(&mut scope.child(), block)? {
+                        Some(Interrupt::Gtfo) => break 'block None,
+                        Some(interrupt) => break 'block Some(interrupt),
+                        None => {}
+                    };
                 }
             }
             if let Some(block) = omg_wtf {
-                if !found {
-                    execute_block(&mut scope.child(), block)?;
-                }
+                match execute_block(&mut scope.child(), block)? {
+                    Some(Interrupt::Gtfo) => break 'block None,
+                    interrupt => break 'block interrupt,
+                };
             }
             None
         }
@@ -157,87 +242,238 @@ pub fn execute_statement(scope: &mut Scope, node: &ASTNode) -> RuntimeResult<Opt
         }) => 'block: {
             if let Some(block) = if_true {
                 if scope.it.borrow().to_boolean() {
-                    execute_block(&mut scope.child(), block)?;
-                    break 'block None;
+                    break 'block execute_block(&mut scope.child(), block)?;
                 }
             }
             for (expression, block) in mebbes.into_iter() {
                 let expression_value = parse_expression(scope, expression)?;
                 if expression_value.to_boolean() {
-                    execute_block(&mut scope.child(), block)?;
-                    break 'block None;
+                    break 'block execute_block(&mut scope.child(), block)?;
                 }
             }
             if let Some(block) = if_false {
-                execute_block(scope, block)?;
+                break 'block execute_block(&mut scope.child(), block)?;
             }
             None
         }
-        ASTNode::IIz(IIz {
-            name: _,
-            arguments: _,
-        }) => todo!(),
-        ASTNode::HowIzI(_) => todo!(),
-        ASTNode::Gtfo(_) => Some(Value::Noob),
+        ASTNode::IIz(IIz { name, arguments }) => {
+            let source = scope.source.clone();
+            let value = match source.extension(Some(name.into())) {
+                Some(Extension::Call(object)) => {
+                    object::call_method(scope, object, name, arguments)?
+                }
+                _ => call_function(scope, name, arguments)?,
+            };
+            scope.set_it(value)?;
+            None
+        }
+        ASTNode::HowIzI(how_iz_i) => {
+            let source = scope.source.clone();
+            match source.extension(Some((&how_iz_i.name).into())) {
+                Some(Extension::Method(object)) => object::define_method(scope, object, how_iz_i)?,
+                _ => {
+                    let function = Function::Lolcode {
+                        how_iz_i: Rc::new(how_iz_i.clone()),
+                        source,
+                    };
+                    scope
+                        .functions
+                        .borrow_mut()
+                        .insert(how_iz_i.name.to_string_slice().to_string(), function);
+                }
+            }
+            None
+        }
+        ASTNode::Gtfo(_) => Some(Interrupt::Gtfo),
         ASTNode::Gimmeh(variable) => {
-            let mut input = String::new();
-            std::io::stdin()
-                .read_line(&mut input)
-                .expect("Could not read user input");
+            let input = scope.runtime.read_line()?;
             mutate_variable_access(scope, variable, Box::new(|_| Ok(input.into())))?;
             None
         }
         ASTNode::Expression(expression) => {
-            let value = parse_expression(scope, expression)?;
-            scope.it = value.into();
+            let source = scope.source.clone();
+            match (source.extension(expression_span(expression)), expression) {
+                (Some(Extension::Import(library)), _) => import::import(scope, library)?,
+                (
+                    Some(Extension::Object { parent, body }),
+                    ASTExpression::VariableAccess(variable_access),
+                ) => object::declare_object(scope, &variable_access.name, parent.as_ref(), body)?,
+                _ => {
+                    let value = parse_expression(scope, expression)?;
+                    scope.set_it(value)?;
+                }
+            }
             None
         }
-        ASTNode::ASTError(_) => todo!(),
+        ASTNode::ASTError(error) => {
+            return Err(RuntimeErrorKind::ParseError(format!("{:?}", error)).into())
+        }
         ASTNode::KTHXBYE(_) => None,
     })
 }
 
-pub fn execute_block(scope: &mut Scope, block: &ASTBlock) -> RuntimeResult<Value> {
+pub fn execute_block(scope: &mut Scope, block: &ASTBlock) -> RuntimeResult<Option<Interrupt>> {
     for statement in block.0.iter() {
         match execute_statement(scope, statement)? {
-            Some(value) => return Ok(value),
+            Some(interrupt) => return Ok(Some(interrupt)),
             None => {}
         };
     }
 
-    Ok(Value::Noob)
+    Ok(None)
 }
 
-pub fn execute_file(file: PathBuf) -> Result<(), String> {
-    let file = read_to_string(file).map_err(|_| "Failed to read source code".to_string())?;
-    let ast = lolcode_ast::tokenize_and_parse(file).map_err(|err| format!("{:?}", err))?;
-    let mut scope = Scope::default();
+/// Calls a function declared with `HOW IZ I`. The arguments are evaluated on the caller's scope,
+/// and bound to the function's parameters on a fresh child of the global scope the function was
+/// declared in. So the body sees its parameters, its own variables, the global variables and
+/// functions, and the functions declared next to it, but not the variables of the blocks around
+/// its declaration or its call. If the function body ends without a `FOUND YR` or `GTFO`, the
+/// value of `IT` is returned.
+fn call_function(
+    scope: &Scope,
+    name: &Identifier,
+    arguments: &[ASTExpression],
+) -> RuntimeResult<Value> {
+    scope.runtime.enter_call()?;
+    scope.runtime.enter_function(name.to_string_slice());
+    let result = invoke_function(scope, name, arguments);
+    scope.runtime.exit_function();
+    scope.runtime.exit_call();
+    result
+}
 
-    for node in ast.into_iter() {
-        execute_statement(&mut scope, &node).map_err(|_err| "runtime error".to_string())?;
+fn invoke_function(
+    scope: &Scope,
+    name: &Identifier,
+    arguments: &[ASTExpression],
+) -> RuntimeResult<Value> {
+    let function_name = name.to_string_slice();
+    let Some(declaring_scope) = get_scope_that_has_function(scope, function_name) else {
+        let builtin = builtins::find(function_name)
+            .ok_or_else(|| RuntimeErrorKind::FunctionNotFound(function_name.to_string()))?;
+        let arguments = arguments
+            .iter()
+            .map(|argument| parse_expression(scope, argument))
+            .collect::<RuntimeResult<Vec<Value>>>()?;
+        return builtin.call(&scope.runtime, arguments);
+    };
+    let function = declaring_scope
+        .functions
+        .borrow()
+        .get(function_name)
+        .unwrap()
+        .clone();
+
+    check_arity(function_name, &function, arguments.len())?;
+
+    let (how_iz_i, source) = match function {
+        Function::Lolcode { how_iz_i, source } => (how_iz_i, source),
+        Function::Native { function, .. } => {
+            let arguments = arguments
+                .iter()
+                .map(|argument| parse_expression(scope, argument))
+                .collect::<RuntimeResult<Vec<Value>>>()?;
+            return (*function)(&arguments);
+        }
+    };
+
+    let mut function_scope = declaring_scope.root().child();
+    function_scope.source = source;
+    // A function declared inside a block can still call itself and the other functions of that
+    // block, even though its body does not run inside the block.
+    if declaring_scope.parent_scope.is_some() {
+        let siblings = declaring_scope.functions.borrow().clone();
+        function_scope.functions.replace(siblings);
+    }
+    for (parameter, argument) in how_iz_i.arguments.iter().zip(arguments) {
+        let value = parse_expression(scope, argument)?;
+        write_identifier_to_scope(&mut function_scope, parameter, value)?;
     }
 
-    let _root_scope = Scope::default();
+    run_function_body(&mut function_scope, &how_iz_i)
+}
 
+fn check_arity(name: &str, function: &Function, found: usize) -> RuntimeResult<()> {
+    let expected = function.arity();
+    if expected != found {
+        return Err(RuntimeErrorKind::WrongNumberOfArguments {
+            function: name.to_string(),
+            expected,
+            found,
+        }
+        .into());
+    }
     Ok(())
 }
 
+/// Runs the body of a function or method on a scope that already has its parameters bound.
+fn run_function_body(function_scope: &mut Scope, how_iz_i: &HowIzI) -> RuntimeResult<Value> {
+    #[cfg(feature = "jit")]
+    if let Some(result) = jit::run_function(function_scope, how_iz_i) {
+        return result;
+    }
+
+    Ok(match execute_block(function_scope, &how_iz_i.body)? {
+        Some(Interrupt::FoundYr(value)) => value,
+        Some(Interrupt::Gtfo) => Value::Noob,
+        None => function_scope.take_it(),
+    })
+}
+
+/// Runs the file using the tree-walking engine, returning what the garbage collector did. When
+/// tracing, the events are written to stderr. Libraries imported with `CAN HAS` are looked up next
+/// to the file, and then in the search path.
+pub fn execute_file(
+    file: PathBuf,
+    limits: Limits,
+    gc: GcThresholds,
+    trace: Option<TraceFormat>,
+    search_path: Vec<PathBuf>,
+) -> Result<GcStats, String> {
+    let file_name = file.display().to_string();
+    let source = read_to_string(&file).map_err(|_| "Failed to read source code".to_string())?;
+    let (ast, parsed) = source::parse(&source).map_err(|err| err.render(&source, &file_name))?;
+    let mut runtime = Runtime::default()
+        .with_limits(limits)
+        .with_gc(gc)
+        .with_search_path(search_path);
+    if let Some(format) = trace {
+        let tracer = Tracer::new(format, source.clone(), Box::new(std::io::stderr()));
+        runtime = runtime.with_hook(Rc::new(tracer));
+    }
+    let mut scope = Scope::with_runtime(Rc::new(runtime));
+    scope.source = Rc::new(parsed.with_directory(file.parent()));
+
+    for node in ast.into_iter() {
+        execute_statement(&mut scope, &node).map_err(|err| err.render(&source, &file_name))?;
+    }
+
+    Ok(scope.runtime.gc_stats())
+}
+
+fn evaluate_binary_operation(
+    scope: &Scope,
+    operation: BinaryOperation,
+    left: &ASTExpression,
+    right: &ASTExpression,
+) -> RuntimeResult<Value> {
+    let left = parse_expression(scope, left)?;
+    let right = parse_expression(scope, right)?;
+    binary_operation(operation, left, right)
+}
+
 fn parse_expression(scope: &Scope, expression: &ASTExpression) -> RuntimeResult<Value> {
+    evaluate_expression(scope, expression).map_err(|error| error.at(expression_span(expression)))
+}
+
+fn evaluate_expression(scope: &Scope, expression: &ASTExpression) -> RuntimeResult<Value> {
     Ok(match expression {
-        ASTExpression::LiteralValue(val) => match &val.token_type {
-            TokenType::Value(val) => match val {
-                TokenValue::NOOB => Value::Noob,
-                TokenValue::Number(num) => match num {
-                    NumberToken::Int(int) => Value::Numbr(*int),
-                    NumberToken::Float(float) => Value::Numbar(*float),
-                },
-                TokenValue::String(string) => Value::Yarn(string.clone()),
-                TokenValue::Boolean(bool) => Value::Troof(*bool),
-            },
-            _ => unreachable!(),
+        ASTExpression::LiteralValue(token) => match yarn_literal(token) {
+            Some(raw) => interpolate_yarn(scope, raw)?,
+            None => literal_value(token),
         },
         ASTExpression::VariableAccess(variable_access) => {
-            get_variable_access_from_scope(scope, variable_access)?.clone()
+            object::read_variable_access(scope, variable_access)?
         }
         ASTExpression::BothOf(left, right) => (parse_expression(scope, left)?.to_boolean()
             && parse_expression(scope, right)?.to_boolean())
@@ -245,9 +481,9 @@ fn parse_expression(scope: &Scope, expression: &ASTExpression) -> RuntimeResult<
         ASTExpression::EitherOf(left, right) => (parse_expression(scope, left)?.to_boolean()
             || parse_expression(scope, right)?.to_boolean())
         .into(),
-        ASTExpression::WonOf(left, right) => (parse_expression(scope, left)?.to_boolean()
-            != parse_expression(scope, right)?.to_boolean())
-        .into(),
+        ASTExpression::WonOf(left, right) => {
+            evaluate_binary_operation(scope, BinaryOperation::WonOf, left, right)?
+        }
         ASTExpression::Not(expression) => parse_expression(scope, expression)?.not().into(),
         ASTExpression::AllOf(values) => {
             for elem in values.into_iter() {
@@ -266,99 +502,41 @@ fn parse_expression(scope: &Scope, expression: &ASTExpression) -> RuntimeResult<
             return Ok(false.into());
         }
         ASTExpression::SumOf(left, right) => {
-            let left = parse_expression(scope, left)?;
-            let right = parse_expression(scope, right)?;
-            match (left, right) {
-                (Value::Numbar(l), Value::Numbar(r)) => Value::Numbar(l + r),
-                (Value::Numbr(l), Value::Numbar(r)) => Value::Numbar(l as f32 + r),
-                (Value::Numbar(l), Value::Numbr(r)) => Value::Numbar(l + r as f32),
-                (Value::Numbr(l), Value::Numbr(r)) => Value::Numbr(l + r),
-                _ => return Err(RuntimeError::InvalidType),
-            }
+            evaluate_binary_operation(scope, BinaryOperation::SumOf, left, right)?
         }
         ASTExpression::DiffOf(left, right) => {
-            let left = parse_expression(scope, left)?;
-            let right = parse_expression(scope, right)?;
-            match (left, right) {
-                (Value::Numbar(l), Value::Numbar(r)) => Value::Numbar(l - r),
-                (Value::Numbr(l), Value::Numbar(r)) => Value::Numbar(l as f32 - r),
-                (Value::Numbar(l), Value::Numbr(r)) => Value::Numbar(l - r as f32),
-                (Value::Numbr(l), Value::Numbr(r)) => Value::Numbr(l - r),
-                _ => return Err(RuntimeError::InvalidType),
-            }
+            evaluate_binary_operation(scope, BinaryOperation::DiffOf, left, right)?
         }
         ASTExpression::ProduktOf(left, right) => {
-            let left = parse_expression(scope, left)?;
-            let right = parse_expression(scope, right)?;
-            match (left, right) {
-                (Value::Numbar(l), Value::Numbar(r)) => Value::Numbar(l * r),
-                (Value::Numbr(l), Value::Numbar(r)) => Value::Numbar(l as f32 * r),
-                (Value::Numbar(l), Value::Numbr(r)) => Value::Numbar(l * r as f32),
-                (Value::Numbr(l), Value::Numbr(r)) => Value::Numbr(l * r),
-                _ => return Err(RuntimeError::InvalidType),
-            }
+            evaluate_binary_operation(scope, BinaryOperation::ProduktOf, left, right)?
         }
         ASTExpression::QuoshuntOf(left, right) => {
-            let left = parse_expression(scope, left)?;
-            let right = parse_expression(scope, right)?;
-            match (left, right) {
-                (Value::Numbar(l), Value::Numbar(r)) => Value::Numbar(l / r),
-                (Value::Numbr(l), Value::Numbar(r)) => Value::Numbar(l as f32 / r),
-                (Value::Numbar(l), Value::Numbr(r)) => Value::Numbar(l / r as f32),
-                (Value::Numbr(l), Value::Numbr(r)) => Value::Numbr(l / r),
-                _ => return Err(RuntimeError::InvalidType),
-            }
+            evaluate_binary_operation(scope, BinaryOperation::QuoshuntOf, left, right)?
         }
         ASTExpression::ModOf(left, right) => {
-            let left = parse_expression(scope, left)?;
-            let right = parse_expression(scope, right)?;
-            match (left, right) {
-                (Value::Numbar(l), Value::Numbar(r)) => Value::Numbar(l % r),
-                (Value::Numbr(l), Value::Numbar(r)) => Value::Numbar(l as f32 % r),
-                (Value::Numbar(l), Value::Numbr(r)) => Value::Numbar(l % r as f32),
-                (Value::Numbr(l), Value::Numbr(r)) => Value::Numbr(l % r),
-                _ => return Err(RuntimeError::InvalidType),
-            }
+            evaluate_binary_operation(scope, BinaryOperation::ModOf, left, right)?
         }
         ASTExpression::BiggrOf(left, right) => {
-            let left = parse_expression(scope, left)?;
-            let right = parse_expression(scope, right)?;
-            match (left, right) {
-                (Value::Numbar(l), Value::Numbar(r)) => Value::Troof(l > r),
-                (Value::Numbr(l), Value::Numbar(r)) => Value::Troof(l as f32 > r),
-                (Value::Numbar(l), Value::Numbr(r)) => Value::Troof(l > r as f32),
-                (Value::Numbr(l), Value::Numbr(r)) => Value::Troof(l > r),
-                _ => return Err(RuntimeError::InvalidType),
-            }
+            evaluate_binary_operation(scope, BinaryOperation::BiggrOf, left, right)?
         }
         ASTExpression::SmallrOf(left, right) => {
-            let left = parse_expression(scope, left)?;
-            let right = parse_expression(scope, right)?;
-            match (left, right) {
-                (Value::Numbar(l), Value::Numbar(r)) => Value::Troof(l < r),
-                (Value::Numbr(l), Value::Numbar(r)) => Value::Troof((l as f32) < r),
-                (Value::Numbar(l), Value::Numbr(r)) => Value::Troof(l < r as f32),
-                (Value::Numbr(l), Value::Numbr(r)) => Value::Troof(l < r),
-                _ => return Err(RuntimeError::InvalidType),
-            }
+            evaluate_binary_operation(scope, BinaryOperation::SmallrOf, left, right)?
         }
         ASTExpression::BothSaem(left, right) => {
-            (parse_expression(scope, left)? == parse_expression(scope, right)?).into()
+            evaluate_binary_operation(scope, BinaryOperation::BothSaem, left, right)?
         }
         ASTExpression::Diffrint(left, right) => {
-            (parse_expression(scope, left)? == parse_expression(scope, right)?).into()
+            evaluate_binary_operation(scope, BinaryOperation::Diffrint, left, right)?
         }
         ASTExpression::Smoosh(values) => {
             let mut result = String::new();
             for value in values.into_iter() {
-                let value = parse_expression(scope, value)?;
-                match value {
-                    Value::Yarn(string) => result.push_str(&string),
-                    _ => return Err(RuntimeError::InvalidType),
-                }
+                result.push_str(&parse_expression(scope, value)?.to_yarn()?);
             }
             Value::Yarn(result)
         }
-        ASTExpression::Maek(_, _) => todo!(),
+        ASTExpression::Maek(expression, ast_type) => {
+            parse_expression(scope, expression)?.cast(ast_type)?
+        }
     })
 }
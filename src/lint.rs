@@ -0,0 +1,320 @@
+//! Static checks that find likely mistakes without running the program.
+
+use std::collections::HashSet;
+
+use lolcode_ast::parser::expression::ASTExpression;
+use lolcode_ast::parser::statements::how_iz_i::HowIzI;
+use lolcode_ast::parser::statements::im_in_yr::{ImInYr, LoopCondition, LoopIterationOperation};
+use lolcode_ast::parser::statements::o_rly::ORly;
+use lolcode_ast::parser::statements::wtf::Wtf;
+use lolcode_ast::parser::statements::ASTNode;
+use lolcode_ast::parser::ASTBlock;
+
+use crate::error::{expression_span, node_span, RuntimeError, Span};
+use crate::lsp::{analyze_ast, SymbolKind};
+use crate::source::{parse, Extension, Source};
+
+/// Name of the configuration file looked up in the current directory.
+pub const DEFAULT_CONFIG_FILE: &str = "lolcode-lint.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    UseBeforeDeclaration,
+    UnusedVariable,
+    SrsNonYarn,
+    ORlyWithoutIt,
+    UnreachableCode,
+    LoopVariableMismatch,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 6] = [
+        Rule::UseBeforeDeclaration,
+        Rule::UnusedVariable,
+        Rule::SrsNonYarn,
+        Rule::ORlyWithoutIt,
+        Rule::UnreachableCode,
+        Rule::LoopVariableMismatch,
+    ];
+
+    /// The name of the rule in the configuration file and in warnings.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::UseBeforeDeclaration => "use-before-declaration",
+            Rule::UnusedVariable => "unused-variable",
+            Rule::SrsNonYarn => "srs-non-yarn",
+            Rule::ORlyWithoutIt => "o-rly-without-it",
+            Rule::UnreachableCode => "unreachable-code",
+            Rule::LoopVariableMismatch => "loop-variable-mismatch",
+        }
+    }
+}
+
+/// Which rules are enabled. All of them are by default.
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    disabled: HashSet<Rule>,
+}
+
+impl LintConfig {
+    /// Parses a JSON object mapping rule names to whether they are enabled, such as
+    /// `{ "unused-variable": false }`. Rules that are not mentioned stay enabled.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let rules: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(json).map_err(|err| err.to_string())?;
+
+        let mut config = LintConfig::default();
+        for (name, enabled) in rules.iter() {
+            let rule = Rule::ALL
+                .into_iter()
+                .find(|rule| rule.name() == name)
+                .ok_or_else(|| format!("unknown lint rule \"{}\"", name))?;
+            let enabled = enabled
+                .as_bool()
+                .ok_or_else(|| format!("lint rule \"{}\" must be true or false", name))?;
+            if !enabled {
+                config.disabled.insert(rule);
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn is_enabled(&self, rule: Rule) -> bool {
+        !self.disabled.contains(&rule)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Warning {
+    pub rule: Rule,
+    pub span: Option<Span>,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn render(&self, source: &str, file_name: &str) -> String {
+        let position = match self.span {
+            Some(span) => {
+                let (line, column) = span.line_and_column(source);
+                format!("{}:{}:{}", file_name, line, column)
+            }
+            None => file_name.to_string(),
+        };
+        format!(
+            "{}: warning[{}]: {}",
+            position,
+            self.rule.name(),
+            self.message
+        )
+    }
+}
+
+/// Checks the source, returning the warnings sorted by position. Fails if the source does not
+/// parse.
+pub fn lint(source: &str, config: &LintConfig) -> Result<Vec<Warning>, RuntimeError> {
+    let (ast, parsed) = parse(source)?;
+    let analysis = analyze_ast(&ast, &parsed);
+
+    let mut linter = Linter {
+        parsed: &parsed,
+        warnings: vec![],
+    };
+
+    for (span, name) in analysis.unresolved.iter() {
+        linter.warn(
+            Rule::UseBeforeDeclaration,
+            Some(*span),
+            format!(
+                "variable \"{}\" is used before being declared with I HAS A",
+                name
+            ),
+        );
+    }
+    let used: HashSet<usize> = analysis
+        .references
+        .iter()
+        .map(|reference| reference.symbol)
+        .collect();
+    for (index, symbol) in analysis.symbols.iter().enumerate() {
+        if symbol.kind == SymbolKind::Variable && !used.contains(&index) {
+            linter.warn(
+                Rule::UnusedVariable,
+                Some(symbol.span),
+                format!("variable \"{}\" is declared but never used", symbol.name),
+            );
+        }
+    }
+    for lookup in analysis.srs_lookups.iter() {
+        if let Some(name_type) = lookup.name_type.filter(|name_type| *name_type != "YARN") {
+            linter.warn(
+                Rule::SrsNonYarn,
+                Some(lookup.span),
+                format!("SRS expects a YARN, but the variable holds a {}", name_type),
+            );
+        }
+    }
+    linter.block(&ast);
+
+    let mut warnings: Vec<Warning> = linter
+        .warnings
+        .into_iter()
+        .filter(|warning| config.is_enabled(warning.rule))
+        .collect();
+    warnings.sort_by_key(|warning| warning.span.map(|span| span.start));
+    Ok(warnings)
+}
+
+struct Linter<'a> {
+    /// The statements of LOLCODE 1.3 that were rewritten before parsing.
+    parsed: &'a Source,
+    warnings: Vec<Warning>,
+}
+
+/// Collects the names of the variables the expression reads.
+fn expression_variables(expression: &ASTExpression, names: &mut Vec<String>) {
+    match expression {
+        ASTExpression::LiteralValue(_) => {}
+        ASTExpression::VariableAccess(variable_access) => {
+            names.push(variable_access.name.to_string_slice().to_string())
+        }
+        ASTExpression::Not(expression) | ASTExpression::Maek(expression, _) => {
+            expression_variables(expression, names)
+        }
+        ASTExpression::BothOf(left, right)
+        | ASTExpression::EitherOf(left, right)
+        | ASTExpression::WonOf(left, right)
+        | ASTExpression::SumOf(left, right)
+        | ASTExpression::DiffOf(left, right)
+        | ASTExpression::ProduktOf(left, right)
+        | ASTExpression::QuoshuntOf(left, right)
+        | ASTExpression::ModOf(left, right)
+        | ASTExpression::BiggrOf(left, right)
+        | ASTExpression::SmallrOf(left, right)
+        | ASTExpression::BothSaem(left, right)
+        | ASTExpression::Diffrint(left, right) => {
+            expression_variables(left, names);
+            expression_variables(right, names);
+        }
+        ASTExpression::AllOf(values)
+        | ASTExpression::AnyOf(values)
+        | ASTExpression::Smoosh(values) => {
+            for value in values.iter() {
+                expression_variables(value, names);
+            }
+        }
+    }
+}
+
+impl Linter<'_> {
+    fn warn(&mut self, rule: Rule, span: Option<Span>, message: String) {
+        self.warnings.push(Warning {
+            rule,
+            span,
+            message,
+        });
+    }
+
+    fn block(&mut self, block: &ASTBlock) {
+        let mut previous: Option<&ASTNode> = None;
+        let mut exited = false;
+
+        for node in block.0.iter() {
+            if exited {
+                self.warn(
+                    Rule::UnreachableCode,
+                    node_span(node),
+                    "statement is never executed".to_string(),
+                );
+                // Only the first unreachable statement of the block is reported.
+                exited = false;
+                previous = Some(node);
+                continue;
+            }
+            self.statement(node, previous);
+            exited = matches!(node, ASTNode::FoundYr(_) | ASTNode::Gtfo(_));
+            previous = Some(node);
+        }
+    }
+
+    fn statement(&mut self, node: &ASTNode, previous: Option<&ASTNode>) {
+        match node {
+            ASTNode::ORly(ORly {
+                if_true,
+                if_false,
+                mebbes,
+            }) => {
+                // Only bare expressions and function calls set IT.
+                if !previous.is_some_and(|previous| self.sets_it(previous)) {
+                    self.warn(
+                        Rule::ORlyWithoutIt,
+                        previous.and_then(node_span),
+                        "O RLY? is not preceded by an expression, so it tests an unrelated IT"
+                            .to_string(),
+                    );
+                }
+                for block in if_true.iter().chain(mebbes.iter().map(|(_, block)| block)) {
+                    self.block(block);
+                }
+                if let Some(block) = if_false {
+                    self.block(block);
+                }
+            }
+            ASTNode::Wtf(Wtf { omg, omg_wtf }) => {
+                for (_, block) in omg.iter() {
+                    self.block(block);
+                }
+                if let Some(block) = omg_wtf {
+                    self.block(block);
+                }
+            }
+            ASTNode::ImInYr(ImInYr {
+                on_iteration,
+                condition,
+                code_block,
+                ..
+            }) => {
+                if let (
+                    Some(LoopIterationOperation { operand, .. }),
+                    Some(LoopCondition::TIL(expression) | LoopCondition::WILE(expression)),
+                ) = (on_iteration, condition)
+                {
+                    let mut names = vec![];
+                    expression_variables(expression, &mut names);
+                    let operand_name = operand.name.to_string_slice();
+                    if !names.iter().any(|name| name == operand_name) {
+                        self.warn(
+                            Rule::LoopVariableMismatch,
+                            Some(operand.into()),
+                            format!(
+                                "the loop changes \"{}\", which its condition does not use",
+                                operand_name
+                            ),
+                        );
+                    }
+                }
+                self.block(code_block);
+            }
+            ASTNode::HowIzI(HowIzI { body, .. }) => self.block(body),
+            ASTNode::Expression(expression) => {
+                if let Some(Extension::Object { body, .. }) =
+                    self.parsed.extension(expression_span(expression))
+                {
+                    self.block(body);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns whether the statement sets IT. Imports and object declarations are expression
+    /// statements that do not.
+    fn sets_it(&self, node: &ASTNode) -> bool {
+        match node {
+            ASTNode::IIz(_) => true,
+            ASTNode::Expression(expression) => {
+                self.parsed.extension(expression_span(expression)).is_none()
+            }
+            _ => false,
+        }
+    }
+}
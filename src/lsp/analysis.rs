@@ -0,0 +1,438 @@
+use std::collections::HashMap;
+
+use lolcode_ast::parser::expression::{ASTExpression, Identifier, VariableAccess};
+use lolcode_ast::parser::statements::assignment::VariableAssignment;
+use lolcode_ast::parser::statements::bukkit_set_slot::BukkitSetSlot;
+use lolcode_ast::parser::statements::how_iz_i::HowIzI;
+use lolcode_ast::parser::statements::i_has_a::{IHasA, IHasAInitialValue};
+use lolcode_ast::parser::statements::i_is::IIz;
+use lolcode_ast::parser::statements::im_in_yr::{ImInYr, LoopCondition, LoopIterationOperation};
+use lolcode_ast::parser::statements::o_rly::ORly;
+use lolcode_ast::parser::statements::visible::Visible;
+use lolcode_ast::parser::statements::wtf::Wtf;
+use lolcode_ast::parser::statements::ASTNode;
+use lolcode_ast::parser::ASTBlock;
+
+use crate::error::{expression_span, type_name, RuntimeError, Span};
+use crate::infer::{expression_type, Inference, Type};
+use crate::object::ME;
+use crate::source::{parse_with_errors, Extension, ObjectPath, Source};
+use crate::variable_access::IT;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Variable,
+    Parameter,
+    Function,
+    /// A variable declared in an `O HAI IM` block, which becomes a slot of the object.
+    Slot,
+}
+
+/// Something declared by `I HAS A`, `HOW IZ I` or one of its parameters.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub span: Span,
+    /// Name of the function the symbol was declared in, if any.
+    pub container: Option<String>,
+    /// Types the variable was seen holding, in order, so the last one is its current type. Types
+    /// that could not be inferred are left out.
+    pub types: Vec<&'static str>,
+    /// For functions, the names of their parameters.
+    pub parameters: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Reference {
+    pub span: Span,
+    pub symbol: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub message: String,
+}
+
+/// An `SRS` identifier, whose name is the value of a variable.
+#[derive(Debug, Clone, Copy)]
+pub struct SrsLookup {
+    pub span: Span,
+    /// Type of the variable holding the name, if it can be inferred.
+    pub name_type: Option<&'static str>,
+}
+
+/// What the language server knows about a document. References are resolved with the same scope
+/// rules as the tree-walking interpreter.
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    pub symbols: Vec<Symbol>,
+    pub references: Vec<Reference>,
+    /// Variables used where no declaration of them is in scope, with their names.
+    pub unresolved: Vec<(Span, String)>,
+    pub srs_lookups: Vec<SrsLookup>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+fn contains(span: Span, offset: usize) -> bool {
+    span.start <= offset && offset <= span.end
+}
+
+impl Analysis {
+    /// Returns the symbol declared or referenced at the offset.
+    pub fn symbol_at(&self, offset: usize) -> Option<usize> {
+        self.references
+            .iter()
+            .find(|reference| contains(reference.span, offset))
+            .map(|reference| reference.symbol)
+            .or_else(|| {
+                self.symbols
+                    .iter()
+                    .position(|symbol| contains(symbol.span, offset))
+            })
+    }
+}
+
+#[derive(Default)]
+struct AnalysisScope {
+    variables: HashMap<String, usize>,
+    functions: HashMap<String, usize>,
+}
+
+struct Analyzer<'a> {
+    /// The statements of LOLCODE 1.3 that were rewritten before parsing.
+    parsed: &'a Source,
+    analysis: Analysis,
+    scopes: Vec<AnalysisScope>,
+    container: Option<String>,
+    /// Whether the statements analyzed are in an `O HAI IM` block, outside of its methods.
+    in_object: bool,
+}
+
+impl Diagnostic {
+    /// Reports the error on its span, or on the first line of the source code if it has none.
+    fn from_error(source: &str, error: RuntimeError) -> Self {
+        let span = error.span.unwrap_or(Span {
+            start: 0,
+            end: source.find('\n').unwrap_or(source.len()),
+        });
+        Diagnostic {
+            span,
+            message: error.kind.to_string(),
+        }
+    }
+}
+
+pub fn analyze(source: &str) -> Analysis {
+    match parse_with_errors(source) {
+        Ok((ast, parsed, errors)) => {
+            let mut analysis = analyze_ast(&ast, &parsed);
+            analysis.diagnostics = errors
+                .into_iter()
+                .map(|error| Diagnostic::from_error(source, error))
+                .collect();
+            analysis
+        }
+        Err(error) => Analysis {
+            diagnostics: vec![Diagnostic::from_error(source, error)],
+            ..Default::default()
+        },
+    }
+}
+
+/// Analyzes source code that was already parsed.
+pub(crate) fn analyze_ast(ast: &ASTBlock, parsed: &Source) -> Analysis {
+    let mut analyzer = Analyzer {
+        parsed,
+        analysis: Analysis::default(),
+        scopes: vec![AnalysisScope::default()],
+        container: None,
+        in_object: false,
+    };
+    analyzer.block(ast);
+    analyzer.analysis
+}
+
+impl<'a> Analyzer<'a> {
+    fn declare(
+        &mut self,
+        identifier: &Identifier,
+        kind: SymbolKind,
+        types: Vec<&'static str>,
+    ) -> Option<usize> {
+        // The name of SRS identifiers is only known at runtime.
+        if identifier.is_srs {
+            return None;
+        }
+        let name = identifier.to_string_slice().to_string();
+        let symbol = self.analysis.symbols.len();
+        self.analysis.symbols.push(Symbol {
+            name: name.clone(),
+            kind,
+            span: identifier.into(),
+            container: self.container.clone(),
+            types,
+            parameters: vec![],
+        });
+
+        let scope = self.scopes.last_mut().unwrap();
+        match kind {
+            SymbolKind::Function => scope.functions.insert(name, symbol),
+            SymbolKind::Variable | SymbolKind::Parameter | SymbolKind::Slot => {
+                scope.variables.insert(name, symbol)
+            }
+        };
+        Some(symbol)
+    }
+
+    /// Records a reference to the symbol the identifier names, and returns it. For `SRS`
+    /// identifiers, the variable holding the name is referenced instead, and None is returned since
+    /// the symbol is only known at runtime.
+    fn reference(&mut self, identifier: &Identifier, is_function: bool) -> Option<usize> {
+        let name = identifier.to_string_slice();
+        let is_variable = !is_function || identifier.is_srs;
+        let symbol = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| match is_variable {
+                true => scope.variables.get(name).copied(),
+                false => scope.functions.get(name).copied(),
+            });
+
+        match symbol {
+            Some(symbol) => self.analysis.references.push(Reference {
+                span: identifier.into(),
+                symbol,
+            }),
+            None if is_variable && name != IT && name != ME => self
+                .analysis
+                .unresolved
+                .push((identifier.into(), name.to_string())),
+            None => {}
+        };
+
+        if identifier.is_srs {
+            let name_type =
+                symbol.and_then(|symbol| self.analysis.symbols[symbol].types.last().copied());
+            self.analysis.srs_lookups.push(SrsLookup {
+                span: identifier.into(),
+                name_type,
+            });
+            return None;
+        }
+        symbol
+    }
+
+    /// Records a reference to the variable holding the object a method is declared on or called on.
+    fn object(&mut self, object: &ObjectPath) {
+        let symbol = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.variables.get(&object.variable).copied());
+        let span = Span {
+            start: object.span.start,
+            end: object.span.start + object.variable.len(),
+        };
+        match symbol {
+            Some(symbol) => self.analysis.references.push(Reference { span, symbol }),
+            None if object.variable != ME => self
+                .analysis
+                .unresolved
+                .push((span, object.variable.clone())),
+            None => {}
+        }
+    }
+
+    /// Analyzes the body of a function or method, with its parameters in a scope of their own.
+    fn function_body(&mut self, name: &Identifier, arguments: &[Identifier], body: &ASTBlock) {
+        let container = self.container.replace(name.to_string_slice().to_string());
+        let in_object = std::mem::replace(&mut self.in_object, false);
+        self.scopes.push(AnalysisScope::default());
+        for argument in arguments.iter() {
+            self.declare(argument, SymbolKind::Parameter, vec![]);
+        }
+        self.block(body);
+        self.scopes.pop();
+        self.in_object = in_object;
+        self.container = container;
+    }
+
+    /// Records a reference to the variable, and that it now holds a value of the given type.
+    fn assign(&mut self, variable_access: &VariableAccess, value_type: Option<&'static str>) {
+        let symbol = self.reference(&variable_access.name, false);
+        if let (Some(symbol), Some(value_type), true) =
+            (symbol, value_type, variable_access.accesses.is_empty())
+        {
+            let types = &mut self.analysis.symbols[symbol].types;
+            if types.last() != Some(&value_type) {
+                types.push(value_type);
+            }
+        }
+    }
+
+    fn block(&mut self, block: &ASTBlock) {
+        for node in block.0.iter() {
+            self.statement(node);
+        }
+    }
+
+    fn scoped_block(&mut self, block: &ASTBlock) {
+        self.scopes.push(AnalysisScope::default());
+        self.block(block);
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, node: &ASTNode) {
+        match node {
+            ASTNode::HAI(_) | ASTNode::KTHXBYE(_) | ASTNode::Gtfo(_) => {}
+            ASTNode::IHasA(IHasA {
+                identifier,
+                initial_value,
+            }) => {
+                let value_type = match initial_value {
+                    Some(IHasAInitialValue::Expression(expression)) => self.expression(expression),
+                    Some(IHasAInitialValue::Type(ast_type)) => Some(type_name(ast_type)),
+                    None => Some("NOOB"),
+                };
+                let kind = match self.in_object {
+                    true => SymbolKind::Slot,
+                    false => SymbolKind::Variable,
+                };
+                self.declare(identifier, kind, value_type.into_iter().collect());
+            }
+            ASTNode::ImInYr(ImInYr {
+                on_iteration,
+                condition,
+                code_block,
+                ..
+            }) => {
+                self.scopes.push(AnalysisScope::default());
+                if let Some(LoopCondition::TIL(expression) | LoopCondition::WILE(expression)) =
+                    condition
+                {
+                    self.expression(expression);
+                }
+                self.block(code_block);
+                if let Some(LoopIterationOperation { operand, .. }) = on_iteration {
+                    self.reference(&operand.name, false);
+                }
+                self.scopes.pop();
+            }
+            ASTNode::BukkitSetSlot(BukkitSetSlot { bukkit, value, .. }) => {
+                self.expression(value);
+                self.reference(&bukkit.name, false);
+            }
+            ASTNode::VariableAssignment(VariableAssignment {
+                variable_access,
+                expression,
+            }) => {
+                let value_type = self.expression(expression);
+                self.assign(variable_access, value_type);
+            }
+            ASTNode::Visible(Visible(expressions, _)) => {
+                for expression in expressions.iter() {
+                    self.expression(expression);
+                }
+            }
+            ASTNode::Expression(expression) => {
+                let parsed = self.parsed;
+                match (parsed.extension(expression_span(expression)), expression) {
+                    (Some(Extension::Import(_)), _) => {}
+                    (
+                        Some(Extension::Object { parent, body }),
+                        ASTExpression::VariableAccess(variable_access),
+                    ) => {
+                        if let Some(parent) = parent {
+                            self.object(parent);
+                        }
+                        self.declare(&variable_access.name, SymbolKind::Variable, vec!["BUKKIT"]);
+                        self.scoped_block(body);
+                    }
+                    _ => {
+                        self.expression(expression);
+                    }
+                }
+            }
+            ASTNode::FoundYr(expression) => {
+                self.expression(expression);
+            }
+            ASTNode::Wtf(Wtf { omg, omg_wtf }) => {
+                for (expression, block) in omg.iter() {
+                    self.expression(expression);
+                    self.scoped_block(block);
+                }
+                if let Some(block) = omg_wtf {
+                    self.scoped_block(block);
+                }
+            }
+            ASTNode::ORly(ORly {
+                if_true,
+                if_false,
+                mebbes,
+            }) => {
+                if let Some(block) = if_true {
+                    self.scoped_block(block);
+                }
+                for (expression, block) in mebbes.iter() {
+                    self.expression(expression);
+                    self.scoped_block(block);
+                }
+                if let Some(block) = if_false {
+                    self.scoped_block(block);
+                }
+            }
+            ASTNode::IIz(IIz { name, arguments }) => {
+                for argument in arguments.iter() {
+                    self.expression(argument);
+                }
+                match self.parsed.extension(Some(name.into())) {
+                    Some(Extension::Call(object)) => self.object(object),
+                    _ => {
+                        self.reference(name, true);
+                    }
+                }
+            }
+            ASTNode::HowIzI(HowIzI {
+                name,
+                arguments,
+                body,
+            }) => {
+                if let Some(Extension::Method(object)) = self.parsed.extension(Some(name.into())) {
+                    self.object(object);
+                    self.function_body(name, arguments, body);
+                    return;
+                }
+                // The function is declared before its body runs, so it can call itself.
+                if let Some(symbol) = self.declare(name, SymbolKind::Function, vec![]) {
+                    self.analysis.symbols[symbol].parameters = arguments
+                        .iter()
+                        .map(|argument| argument.to_string_slice().to_string())
+                        .collect();
+                }
+                self.function_body(name, arguments, body);
+            }
+            ASTNode::Gimmeh(variable_access) => self.assign(variable_access, Some("YARN")),
+            // Reported from the errors of the parse.
+            ASTNode::ASTError(_) => {}
+        };
+    }
+
+    /// Records the references in the expression, and returns the type of its value if it can be
+    /// inferred.
+    fn expression(&mut self, expression: &ASTExpression) -> Type {
+        expression_type(self, expression)
+    }
+}
+
+impl Inference for Analyzer<'_> {
+    fn variable_access(&mut self, variable_access: &VariableAccess) -> Type {
+        let symbol = self.reference(&variable_access.name, false)?;
+        if !variable_access.accesses.is_empty() {
+            return None;
+        }
+        self.analysis.symbols[symbol].types.last().copied()
+    }
+}
@@ -0,0 +1,16 @@
+//! Language server for LOLCODE, speaking the Language Server Protocol over stdin and stdout.
+//!
+//! Documents are fully re-analyzed on every change. The server provides parse diagnostics, hover
+//! with the inferred types of variables, go-to-definition, document symbols and completion.
+//!
+//! The analysis of documents is also what the linter works from, so it is available on every
+//! target, while the server is not built for WebAssembly.
+
+mod analysis;
+#[cfg(not(target_arch = "wasm32"))]
+mod server;
+
+pub(crate) use analysis::analyze_ast;
+pub use analysis::{analyze, Analysis, Diagnostic, Reference, SrsLookup, Symbol, SymbolKind};
+#[cfg(not(target_arch = "wasm32"))]
+pub use server::start;
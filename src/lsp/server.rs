@@ -0,0 +1,360 @@
+//! The server itself, reading requests from stdin and writing responses to stdout.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use serde_json::{json, Value as Json};
+
+use crate::error::Span;
+use crate::lsp::{analyze, Analysis, Symbol, SymbolKind};
+use crate::protocol::{read_message, write_message};
+
+const KEYWORDS: &[&str] = &[
+    "HAI",
+    "KTHXBYE",
+    "BTW",
+    "OBTW",
+    "TLDR",
+    "I HAS A",
+    "ITZ",
+    "ITZ A",
+    "R",
+    "VISIBLE",
+    "GIMMEH",
+    "SUM OF",
+    "DIFF OF",
+    "PRODUKT OF",
+    "QUOSHUNT OF",
+    "MOD OF",
+    "BIGGR OF",
+    "SMALLR OF",
+    "BOTH OF",
+    "EITHER OF",
+    "WON OF",
+    "NOT",
+    "ALL OF",
+    "ANY OF",
+    "MKAY",
+    "BOTH SAEM",
+    "DIFFRINT",
+    "SMOOSH",
+    "MAEK",
+    "AN",
+    "A",
+    "O RLY?",
+    "YA RLY",
+    "MEBBE",
+    "NO WAI",
+    "OIC",
+    "WTF?",
+    "OMG",
+    "OMGWTF",
+    "GTFO",
+    "IM IN YR",
+    "UPPIN",
+    "NERFIN",
+    "YR",
+    "TIL",
+    "WILE",
+    "IM OUTTA YR",
+    "HOW IZ I",
+    "IF U SAY SO",
+    "FOUND YR",
+    "I IZ",
+    "SRS",
+    "HAS A",
+    "IT",
+    "WIN",
+    "FAIL",
+    "NOOB",
+    "NUMBR",
+    "NUMBAR",
+    "YARN",
+    "TROOF",
+    "BUKKIT",
+];
+
+// Values defined by the protocol.
+const TEXT_DOCUMENT_SYNC_FULL: u32 = 1;
+const SEVERITY_ERROR: u32 = 1;
+const COMPLETION_FUNCTION: u32 = 3;
+const COMPLETION_FIELD: u32 = 5;
+const COMPLETION_VARIABLE: u32 = 6;
+const COMPLETION_KEYWORD: u32 = 14;
+const SYMBOL_FIELD: u32 = 8;
+const SYMBOL_FUNCTION: u32 = 12;
+const SYMBOL_VARIABLE: u32 = 13;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+struct Document {
+    source: String,
+    analysis: Analysis,
+}
+
+/// Converts a byte offset to a protocol position, whose character is counted in UTF-16 units.
+fn position(source: &str, offset: usize) -> Json {
+    let before = source.get(..offset).unwrap_or(source);
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+    json!({ "line": line, "character": character })
+}
+
+fn offset(source: &str, position: &Json) -> Option<usize> {
+    let line = position["line"].as_u64()? as usize;
+    let character = position["character"].as_u64()? as usize;
+
+    let line_start: usize = source.split_inclusive('\n').take(line).map(str::len).sum();
+    let mut units = 0;
+    for (index, char) in source[line_start..].char_indices() {
+        if units >= character || char == '\n' {
+            return Some(line_start + index);
+        }
+        units += char.len_utf16();
+    }
+    Some(source.len())
+}
+
+fn range(source: &str, span: Span) -> Json {
+    json!({ "start": position(source, span.start), "end": position(source, span.end) })
+}
+
+fn hover_text(symbol: &Symbol) -> String {
+    match symbol.kind {
+        SymbolKind::Function => {
+            let mut signature = format!("HOW IZ I {}", symbol.name);
+            for (index, parameter) in symbol.parameters.iter().enumerate() {
+                let separator = if index == 0 { "" } else { " AN" };
+                signature.push_str(&format!("{} YR {}", separator, parameter));
+            }
+            format!("```lolcode\n{}\n```", signature)
+        }
+        SymbolKind::Variable | SymbolKind::Parameter | SymbolKind::Slot => {
+            let mut types: Vec<&str> = vec![];
+            for value_type in symbol.types.iter() {
+                if !types.contains(value_type) {
+                    types.push(value_type);
+                }
+            }
+            let types = match types.is_empty() {
+                true => "unknown type".to_string(),
+                false => types.join(" or "),
+            };
+            let kind = match symbol.kind {
+                SymbolKind::Parameter => "parameter",
+                SymbolKind::Slot => "slot",
+                _ => "variable",
+            };
+            format!("{} **{}**: {}", kind, symbol.name, types)
+        }
+    }
+}
+
+#[derive(Default)]
+struct Server {
+    documents: HashMap<String, Document>,
+}
+
+/// Starts the language server on stdin and stdout, returning once the client asks it to exit.
+pub fn start() -> Result<(), String> {
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    Server::default()
+        .run(&mut stdin.lock(), &mut stdout.lock())
+        .map_err(|err| err.to_string())
+}
+
+impl Server {
+    fn run(&mut self, input: &mut impl BufRead, output: &mut impl Write) -> std::io::Result<()> {
+        while let Some(message) = read_message(input)? {
+            // Messages without a method are responses to requests, which the server never sends.
+            let method = match message["method"].as_str() {
+                Some(method) => method,
+                None => continue,
+            };
+            let params = &message["params"];
+
+            match message.get("id") {
+                Some(id) => {
+                    let response = match self.handle_request(method, params) {
+                        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                        Err((code, error)) => json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "error": { "code": code, "message": error },
+                        }),
+                    };
+                    write_message(output, &response)?;
+                }
+                None if method == "exit" => return Ok(()),
+                None => self.handle_notification(method, params, output)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&mut self, method: &str, params: &Json) -> Result<Json, (i64, String)> {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": TEXT_DOCUMENT_SYNC_FULL,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "documentSymbolProvider": true,
+                    "completionProvider": {},
+                },
+                "serverInfo": { "name": "lolcode", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => Ok(Json::Null),
+            "textDocument/hover" => {
+                let (document, symbol) = self.symbol_at(params)?;
+                Ok(match symbol {
+                    None => Json::Null,
+                    Some(symbol) => json!({
+                        "contents": {
+                            "kind": "markdown",
+                            "value": hover_text(&document.analysis.symbols[symbol]),
+                        },
+                    }),
+                })
+            }
+            "textDocument/definition" => {
+                let (document, symbol) = self.symbol_at(params)?;
+                Ok(match symbol {
+                    None => Json::Null,
+                    Some(symbol) => json!({
+                        "uri": params["textDocument"]["uri"],
+                        "range": range(&document.source, document.analysis.symbols[symbol].span),
+                    }),
+                })
+            }
+            "textDocument/documentSymbol" => {
+                let (uri, document) = self.document(params)?;
+                let symbols: Vec<Json> = document
+                    .analysis
+                    .symbols
+                    .iter()
+                    .map(|symbol| {
+                        let kind = match symbol.kind {
+                            SymbolKind::Function => SYMBOL_FUNCTION,
+                            SymbolKind::Variable | SymbolKind::Parameter => SYMBOL_VARIABLE,
+                            SymbolKind::Slot => SYMBOL_FIELD,
+                        };
+                        json!({
+                            "name": symbol.name,
+                            "kind": kind,
+                            "location": { "uri": uri, "range": range(&document.source, symbol.span) },
+                            "containerName": symbol.container,
+                        })
+                    })
+                    .collect();
+                Ok(json!(symbols))
+            }
+            "textDocument/completion" => {
+                let mut items: Vec<Json> = KEYWORDS
+                    .iter()
+                    .map(|keyword| json!({ "label": keyword, "kind": COMPLETION_KEYWORD }))
+                    .collect();
+                if let Ok((_, document)) = self.document(params) {
+                    let mut names = vec![];
+                    for symbol in document.analysis.symbols.iter() {
+                        if names.contains(&&symbol.name) {
+                            continue;
+                        }
+                        names.push(&symbol.name);
+                        let kind = match symbol.kind {
+                            SymbolKind::Function => COMPLETION_FUNCTION,
+                            SymbolKind::Variable | SymbolKind::Parameter => COMPLETION_VARIABLE,
+                            SymbolKind::Slot => COMPLETION_FIELD,
+                        };
+                        items.push(json!({
+                            "label": symbol.name,
+                            "kind": kind,
+                            "detail": hover_text(symbol),
+                        }));
+                    }
+                }
+                Ok(json!(items))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unsupported method {}", method))),
+        }
+    }
+
+    fn handle_notification(
+        &mut self,
+        method: &str,
+        params: &Json,
+        output: &mut impl Write,
+    ) -> std::io::Result<()> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let source = match method {
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            // Documents are synchronized in full, so the last change has the whole text.
+            "textDocument/didChange" => params["contentChanges"]
+                .as_array()
+                .and_then(|changes| changes.last())
+                .and_then(|change| change["text"].as_str()),
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return self.publish_diagnostics(uri, output);
+            }
+            _ => None,
+        };
+
+        if let Some(source) = source {
+            let document = Document {
+                source: source.to_string(),
+                analysis: analyze(source),
+            };
+            self.documents.insert(uri.to_string(), document);
+            self.publish_diagnostics(uri, output)?;
+        }
+        Ok(())
+    }
+
+    fn publish_diagnostics(&self, uri: &str, output: &mut impl Write) -> std::io::Result<()> {
+        let diagnostics: Vec<Json> = match self.documents.get(uri) {
+            None => vec![],
+            Some(document) => document
+                .analysis
+                .diagnostics
+                .iter()
+                .map(|diagnostic| {
+                    json!({
+                        "range": range(&document.source, diagnostic.span),
+                        "severity": SEVERITY_ERROR,
+                        "source": "lolcode",
+                        "message": diagnostic.message,
+                    })
+                })
+                .collect(),
+        };
+        write_message(
+            output,
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": { "uri": uri, "diagnostics": diagnostics },
+            }),
+        )
+    }
+
+    fn document<'a>(&self, params: &'a Json) -> Result<(&'a str, &Document), (i64, String)> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or((INVALID_PARAMS, "missing textDocument.uri".to_string()))?;
+        let document = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("unknown document {}", uri)))?;
+        Ok((uri, document))
+    }
+
+    fn symbol_at(&self, params: &Json) -> Result<(&Document, Option<usize>), (i64, String)> {
+        let (_, document) = self.document(params)?;
+        let offset = offset(&document.source, &params["position"])
+            .ok_or((INVALID_PARAMS, "invalid position".to_string()))?;
+        Ok((document, document.analysis.symbol_at(offset)))
+    }
+}
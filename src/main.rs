@@ -1,5 +1,9 @@
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use lolcode_interpreter::lint::{lint, LintConfig, DEFAULT_CONFIG_FILE};
+use lolcode_interpreter::{GcThresholds, Limits, TraceFormat};
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -7,19 +11,220 @@ struct Args {
     command: Commands,
 }
 
+#[derive(Debug, Clone, ValueEnum)]
+enum Engine {
+    /// Walks the AST directly. This is the reference implementation.
+    Tree,
+    /// Compiles the AST to bytecode and runs it on a virtual machine.
+    Vm,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum Trace {
+    /// One line of text per statement.
+    Human,
+    /// One JSON object per line.
+    Json,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum Target {
+    /// A standalone C file.
+    C,
+    /// A self-contained ES module, for browsers and other JavaScript engines.
+    Js,
+}
+
 #[derive(Debug, Subcommand)]
 enum Commands {
-    Run { filename: PathBuf },
-    Ast { filename: PathBuf },
+    Run {
+        filename: PathBuf,
+        #[arg(long, value_enum, default_value_t = Engine::Tree)]
+        engine: Engine,
+        /// Maximum number of steps the program can execute.
+        #[arg(long)]
+        max_steps: Option<u64>,
+        /// Maximum depth of nested function calls.
+        #[arg(long, default_value_t = lolcode_interpreter::DEFAULT_MAX_CALL_DEPTH)]
+        max_call_depth: usize,
+        /// Maximum time the program can run for, in milliseconds.
+        #[arg(long)]
+        timeout_ms: Option<u64>,
+        /// Maximum number of bytes of YARNs and BUKKITs the program can keep in variables.
+        #[arg(long)]
+        max_memory: Option<usize>,
+        /// Number of new BUKKITs after which the garbage collector runs. 0 disables it.
+        #[arg(long, default_value_t = lolcode_interpreter::DEFAULT_GC_THRESHOLD)]
+        gc_threshold: usize,
+        /// Also runs the garbage collector whenever the memory in use grew by this many bytes.
+        #[arg(long)]
+        gc_memory_threshold: Option<usize>,
+        /// Reports the garbage collections and the live bytes to stderr once the program ends.
+        #[arg(long)]
+        gc_stats: bool,
+        /// Writes an event to stderr after each executed statement. Only supported by the tree
+        /// engine.
+        #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "human")]
+        trace: Option<Trace>,
+        /// Directory to search for the libraries imported with `CAN HAS`, after the one of the
+        /// file. Can be given several times, and is searched before the directories listed in
+        /// LOLCODE_PATH.
+        #[arg(short = 'I', long = "include", value_name = "DIR")]
+        include: Vec<PathBuf>,
+    },
+    Ast {
+        filename: PathBuf,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
+    Repl,
+    /// Rewrites the files in the canonical layout.
+    Fmt {
+        filenames: Vec<PathBuf>,
+        /// Instead of rewriting them, exits with an error if any file is not formatted.
+        #[arg(long)]
+        check: bool,
+    },
+    /// Reports the type errors the files would definitely raise, without running them.
+    Check {
+        filenames: Vec<PathBuf>,
+    },
+    /// Reports likely mistakes in the files without running them.
+    Lint {
+        filenames: Vec<PathBuf>,
+        /// JSON file enabling or disabling rules. Defaults to lolcode-lint.json, if it exists.
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+    /// Translates the file to another language.
+    Compile {
+        filename: PathBuf,
+        #[arg(long, value_enum)]
+        target: Target,
+        /// Where to write the result. Defaults to the file name with the target's extension.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Starts a language server on stdin and stdout.
+    #[cfg(not(target_arch = "wasm32"))]
+    Lsp,
+    /// Starts a debug adapter on stdin and stdout.
+    #[cfg(not(target_arch = "wasm32"))]
+    Dap,
+}
+
+fn format_file(filename: &PathBuf, check: bool) -> Result<(), String> {
+    let source = std::fs::read_to_string(filename).map_err(|err| err.to_string())?;
+    let formatted = lolcode_interpreter::fmt::format_source(&source)
+        .map_err(|error| error.render(&source, &filename.display().to_string()))?;
+    if formatted == source {
+        return Ok(());
+    }
+    if check {
+        return Err("file is not formatted".to_string());
+    }
+    std::fs::write(filename, formatted).map_err(|err| err.to_string())
+}
+
+fn load_lint_config(config: Option<PathBuf>) -> Result<LintConfig, String> {
+    let path = match config {
+        Some(path) => path,
+        None if Path::new(DEFAULT_CONFIG_FILE).exists() => PathBuf::from(DEFAULT_CONFIG_FILE),
+        None => return Ok(LintConfig::default()),
+    };
+    let json =
+        std::fs::read_to_string(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    LintConfig::from_json(&json).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// Prints the warnings for the file, returning whether there were none.
+fn lint_file(filename: &PathBuf, config: &LintConfig) -> Result<bool, String> {
+    let source = std::fs::read_to_string(filename).map_err(|err| err.to_string())?;
+    let warnings = lint(&source, config)
+        .map_err(|error| error.render(&source, &filename.display().to_string()))?;
+    for warning in warnings.iter() {
+        println!(
+            "{}",
+            warning.render(&source, &filename.display().to_string())
+        );
+    }
+    Ok(warnings.is_empty())
+}
+
+/// Prints the type errors of the file, returning whether there were none.
+fn check_file(filename: &PathBuf) -> Result<bool, String> {
+    let source = std::fs::read_to_string(filename).map_err(|err| err.to_string())?;
+    let errors = lolcode_interpreter::check::check(&source)
+        .map_err(|error| error.render(&source, &filename.display().to_string()))?;
+    for error in errors.iter() {
+        eprintln!(
+            "{}\n",
+            error.render(&source, &filename.display().to_string())
+        );
+    }
+    Ok(errors.is_empty())
+}
+
+fn compile_file(filename: &PathBuf, target: Target, output: Option<PathBuf>) -> Result<(), String> {
+    let source = std::fs::read_to_string(filename).map_err(|err| err.to_string())?;
+    let (target, extension) = match target {
+        Target::C => (lolcode_interpreter::compile::Target::C, "c"),
+        Target::Js => (lolcode_interpreter::compile::Target::Js, "mjs"),
+    };
+    let compiled =
+        lolcode_interpreter::compile::compile(&source, &filename.display().to_string(), target)?;
+    let output = output.unwrap_or_else(|| filename.with_extension(extension));
+    std::fs::write(&output, compiled).map_err(|err| format!("{}: {}", output.display(), err))
 }
 
 fn main() {
     let args = Args::parse();
 
     match args.command {
-        Commands::Run { filename } => {
-            lolcode_interpreter::execute_file(filename.into())
-                .unwrap_or_else(|err| println!("Error: {:?}", err));
+        Commands::Run {
+            filename,
+            engine,
+            max_steps,
+            max_call_depth,
+            timeout_ms,
+            max_memory,
+            gc_threshold,
+            gc_memory_threshold,
+            gc_stats,
+            trace,
+            include,
+        } => {
+            let limits = Limits {
+                max_steps,
+                max_call_depth: Some(max_call_depth),
+                max_duration: timeout_ms.map(Duration::from_millis),
+                max_memory,
+            };
+            let gc = GcThresholds {
+                allocations: Some(gc_threshold).filter(|threshold| *threshold > 0),
+                memory_growth: gc_memory_threshold,
+            };
+            let trace = trace.map(|trace| match trace {
+                Trace::Human => TraceFormat::Human,
+                Trace::Json => TraceFormat::Json,
+            });
+            let result = match engine {
+                Engine::Tree => {
+                    let search_path = lolcode_interpreter::search_path(include);
+                    lolcode_interpreter::execute_file(filename, limits, gc, trace, search_path)
+                }
+                Engine::Vm if trace.is_some() => {
+                    Err("--trace is only supported by the tree engine".to_string())
+                }
+                Engine::Vm => lolcode_interpreter::vm::execute_file(filename, limits, gc),
+            };
+            match result {
+                Ok(stats) if gc_stats => eprintln!("gc: {}", stats),
+                Ok(_) => {}
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+            }
         }
         Commands::Ast { filename } => {
             let code = match std::fs::read_to_string(filename) {
@@ -32,5 +237,76 @@ fn main() {
             let ast = lolcode_ast::tokenize_and_parse(code);
             println!("{:#?}", ast);
         }
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::Repl => {
+            lolcode_interpreter::repl::start().unwrap_or_else(|err| eprintln!("{}", err));
+        }
+        Commands::Fmt { filenames, check } => {
+            let mut success = true;
+            for filename in filenames {
+                if let Err(err) = format_file(&filename, check) {
+                    eprintln!("{}: {}", filename.display(), err);
+                    success = false;
+                }
+            }
+            if !success {
+                std::process::exit(1);
+            }
+        }
+        Commands::Check { filenames } => {
+            let mut success = true;
+            for filename in filenames {
+                match check_file(&filename) {
+                    Ok(clean) => success &= clean,
+                    Err(err) => {
+                        eprintln!("{}: {}", filename.display(), err);
+                        success = false;
+                    }
+                }
+            }
+            if !success {
+                std::process::exit(1);
+            }
+        }
+        Commands::Lint { filenames, config } => {
+            let config = match load_lint_config(config) {
+                Ok(config) => config,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+            };
+            let mut success = true;
+            for filename in filenames {
+                match lint_file(&filename, &config) {
+                    Ok(clean) => success &= clean,
+                    Err(err) => {
+                        eprintln!("{}: {}", filename.display(), err);
+                        success = false;
+                    }
+                }
+            }
+            if !success {
+                std::process::exit(1);
+            }
+        }
+        Commands::Compile {
+            filename,
+            target,
+            output,
+        } => {
+            if let Err(err) = compile_file(&filename, target, output) {
+                eprintln!("{}: {}", filename.display(), err);
+                std::process::exit(1);
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::Lsp => {
+            lolcode_interpreter::lsp::start().unwrap_or_else(|err| eprintln!("{}", err));
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::Dap => {
+            lolcode_interpreter::dap::start().unwrap_or_else(|err| eprintln!("{}", err));
+        }
     }
 }
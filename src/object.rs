@@ -0,0 +1,201 @@
+//! The LOLCODE 1.3 object model, built on BUKKITs.
+//!
+//! An object is declared with an `O HAI IM <name> [IM LIEK <parent>] ... KTHX` block: the
+//! variables and functions declared in the block become the object's slots and methods, and `ME`
+//! refers to the object being declared. Methods can also be added later with
+//! `HOW IZ <object> <method>`, and are called with `<object> IZ <method> [YR <argument> ...] MKAY`.
+//! Inside a method, `ME` is the object the method was called on.
+//!
+//! Slots and methods missing from an object are looked up in its `parent` slot. Reading a slot
+//! that is not found anywhere calls the object's `omgwtf` method instead, and stores its result.
+//!
+//! lolcode_ast has no nodes for the declaration blocks and methods, so `source::parse` rewrites
+//! them before parsing, and the interpreter calls [`declare_object`], [`define_method`] and
+//! [`call_method`] when it reaches the rewritten statements. A method call is a statement, whose
+//! value is stored in `IT`. Slot lookup through parents and `omgwtf` applies to every variable
+//! access.
+
+use std::rc::Rc;
+
+use lolcode_ast::parser::expression::{ASTExpression, Identifier, VariableAccess};
+use lolcode_ast::parser::statements::how_iz_i::HowIzI;
+use lolcode_ast::parser::ASTBlock;
+
+use crate::source::ObjectPath;
+use crate::types::{Bukkit, BukkitRef, Function, OMGWTF_METHOD, PARENT_SLOT};
+use crate::variable_access::{
+    expect_bukkit, get_identifier_from_scope, get_variable_from_scope, resolve_identifier_name,
+    resolve_slot_name, write_identifier_to_scope,
+};
+use crate::{
+    check_arity, execute_block, parse_expression, run_function_body, RuntimeErrorKind,
+    RuntimeResult, Scope, Value,
+};
+
+/// The variable holding the object inside its declaration block and its methods.
+pub const ME: &str = "ME";
+
+/// Returns the value at the variable access. Slots are looked up through the parent chain, and a
+/// missing slot is filled in by calling the `omgwtf` method of its BUKKIT.
+pub fn read_variable_access(
+    scope: &Scope,
+    variable_access: &VariableAccess,
+) -> RuntimeResult<Value> {
+    let VariableAccess {
+        name: identifier,
+        accesses,
+    } = variable_access;
+
+    let mut value = get_identifier_from_scope(scope, identifier)?.clone();
+    for access in accesses.iter() {
+        let slot_name = resolve_slot_name(scope, access)?;
+        value = read_slot(scope, expect_bukkit(value)?, &slot_name)?;
+    }
+    Ok(value)
+}
+
+/// Returns the object at the path, looking its slots up like [`read_variable_access`].
+fn read_object_path(scope: &Scope, path: &ObjectPath) -> RuntimeResult<BukkitRef> {
+    let read = || {
+        let mut value = get_variable_from_scope(scope, &path.variable)?.clone();
+        for slot_name in path.slots.iter() {
+            value = read_slot(scope, expect_bukkit(value)?, slot_name)?;
+        }
+        expect_bukkit(value)
+    };
+    read().map_err(|error| error.at(Some(path.span)))
+}
+
+fn read_slot(scope: &Scope, bukkit: BukkitRef, slot_name: &str) -> RuntimeResult<Value> {
+    let slot = bukkit.borrow().get(slot_name);
+    match slot {
+        Some(value) => Ok(value),
+        None => {
+            if bukkit.borrow().method(OMGWTF_METHOD).is_none() {
+                return Err(RuntimeErrorKind::SlotNotFound(slot_name.to_string()).into());
+            }
+            let value = call_method_on(scope, bukkit.clone(), OMGWTF_METHOD, vec![])?;
+            bukkit.set_slot(&scope.runtime, slot_name, value.clone())?;
+            Ok(value)
+        }
+    }
+}
+
+/// Executes an `O HAI IM` block, declaring `name` as the resulting object. The block runs on a
+/// child scope whose variables and functions become the object's slots and methods.
+pub fn declare_object(
+    scope: &mut Scope,
+    name: &Identifier,
+    parent: Option<&ObjectPath>,
+    body: &ASTBlock,
+) -> RuntimeResult<()> {
+    let object = BukkitRef::new(Bukkit::default());
+    if let Some(parent) = parent {
+        let parent = read_object_path(scope, parent)?;
+        object.set_slot(&scope.runtime, PARENT_SLOT, Value::Bukkit(parent))?;
+    }
+
+    {
+        let mut body_scope = scope.child();
+        body_scope
+            .variables
+            .borrow_mut()
+            .insert(ME.to_string(), Value::Bukkit(object.clone()));
+
+        execute_block(&mut body_scope, body)?;
+
+        let mut slots = body_scope.variables.take();
+        let released = slots.values().map(Value::memory_size).sum();
+        scope.runtime.release_memory(released);
+        slots.remove(ME);
+        for (name, value) in slots {
+            object.set_slot(&scope.runtime, &name, value)?;
+        }
+        object
+            .borrow_mut()
+            .methods
+            .extend(body_scope.functions.take());
+    }
+
+    write_identifier_to_scope(scope, name, Value::Bukkit(object))
+}
+
+/// Executes `HOW IZ <object> <method>`, adding the method to an existing object.
+pub fn define_method(scope: &Scope, object: &ObjectPath, method: &HowIzI) -> RuntimeResult<()> {
+    let name = resolve_identifier_name(scope, &method.name)?;
+    let object = read_object_path(scope, object)?;
+    let function = Function::Lolcode {
+        how_iz_i: Rc::new(method.clone()),
+        source: scope.source.clone(),
+    };
+    object.borrow_mut().methods.insert(name, function);
+    Ok(())
+}
+
+/// Executes `<object> IZ <method> ... MKAY`. The arguments are evaluated on the caller's scope.
+pub fn call_method(
+    scope: &Scope,
+    object: &ObjectPath,
+    method: &Identifier,
+    arguments: &[ASTExpression],
+) -> RuntimeResult<Value> {
+    let receiver = read_object_path(scope, object)?;
+    let method_name = resolve_identifier_name(scope, method)?;
+    let arguments = arguments
+        .iter()
+        .map(|argument| parse_expression(scope, argument))
+        .collect::<RuntimeResult<Vec<Value>>>()?;
+    call_method_on(scope, receiver, &method_name, arguments)
+}
+
+/// Calls the method on the receiver, counting it towards the call depth like any other call.
+pub fn call_method_on(
+    scope: &Scope,
+    receiver: BukkitRef,
+    method_name: &str,
+    arguments: Vec<Value>,
+) -> RuntimeResult<Value> {
+    scope.runtime.enter_call()?;
+    scope.runtime.enter_function(method_name);
+    let result = invoke_method(scope, receiver, method_name, arguments);
+    scope.runtime.exit_function();
+    scope.runtime.exit_call();
+    result
+}
+
+/// Runs the method on a fresh child of the global scope, with `ME` bound to the receiver. Methods
+/// declared in an imported library run on the library's global scope.
+pub fn invoke_method(
+    scope: &Scope,
+    receiver: BukkitRef,
+    method_name: &str,
+    arguments: Vec<Value>,
+) -> RuntimeResult<Value> {
+    let function = receiver
+        .borrow()
+        .method(method_name)
+        .ok_or_else(|| RuntimeErrorKind::FunctionNotFound(method_name.to_string()))?;
+    check_arity(method_name, &function, arguments.len())?;
+
+    let (how_iz_i, source) = match function {
+        Function::Lolcode { how_iz_i, source } => (how_iz_i, source),
+        Function::Native { function, .. } => return (*function)(&arguments),
+    };
+
+    let home = source.home();
+    let root: &Scope = match &home {
+        Some(home) => home,
+        None => scope.root(),
+    };
+    let mut method_scope = root.child();
+    method_scope.source = source;
+    method_scope
+        .variables
+        .borrow_mut()
+        .insert(ME.to_string(), Value::Bukkit(receiver));
+    for (parameter, argument) in how_iz_i.arguments.iter().zip(arguments) {
+        write_identifier_to_scope(&mut method_scope, parameter, argument)?;
+    }
+
+    run_function_body(&mut method_scope, &how_iz_i)
+}
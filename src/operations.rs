@@ -0,0 +1,120 @@
+use lolcode_ast::lexer::{NumberToken, Token, TokenType, TokenValue};
+
+use crate::types::{RuntimeErrorKind, RuntimeResult, Value};
+
+/// Operators that take two already evaluated operands. Shared by the tree-walking interpreter and
+/// the bytecode VM, so both have the exact same semantics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperation {
+    SumOf,
+    DiffOf,
+    ProduktOf,
+    QuoshuntOf,
+    ModOf,
+    BiggrOf,
+    SmallrOf,
+    BothSaem,
+    Diffrint,
+    WonOf,
+}
+
+pub fn literal_value(token: &Token) -> Value {
+    match &token.token_type {
+        TokenType::Value(val) => match val {
+            TokenValue::NOOB => Value::Noob,
+            TokenValue::Number(num) => match num {
+                NumberToken::Int(int) => Value::Numbr(*int),
+                NumberToken::Float(float) => Value::Numbar(*float),
+            },
+            TokenValue::String(string) => Value::Yarn(string.clone()),
+            TokenValue::Boolean(bool) => Value::Troof(*bool),
+        },
+        _ => unreachable!(),
+    }
+}
+
+/// Casts both operands of a math operator to numbers.
+fn numeric_operands(
+    left: Value,
+    right: Value,
+    operation: &'static str,
+) -> RuntimeResult<(Value, Value)> {
+    match (left.to_number(), right.to_number()) {
+        (Ok(left), Ok(right)) => Ok((left, right)),
+        _ => Err(RuntimeErrorKind::InvalidOperands {
+            operation,
+            left: left.type_name(),
+            right: right.type_name(),
+        }
+        .into()),
+    }
+}
+
+/// NUMBR arithmetic wraps around on overflow, like the JIT and the compile targets do. So dividing
+/// the smallest NUMBR by -1 gives the smallest NUMBR back, with a remainder of 0.
+pub fn binary_operation(
+    operation: BinaryOperation,
+    left: Value,
+    right: Value,
+) -> RuntimeResult<Value> {
+    Ok(match operation {
+        BinaryOperation::SumOf => match numeric_operands(left, right, "add")? {
+            (Value::Numbar(l), Value::Numbar(r)) => Value::Numbar(l + r),
+            (Value::Numbr(l), Value::Numbar(r)) => Value::Numbar(l as f32 + r),
+            (Value::Numbar(l), Value::Numbr(r)) => Value::Numbar(l + r as f32),
+            (Value::Numbr(l), Value::Numbr(r)) => Value::Numbr(l.wrapping_add(r)),
+            _ => unreachable!(),
+        },
+        BinaryOperation::DiffOf => match numeric_operands(left, right, "subtract")? {
+            (Value::Numbar(l), Value::Numbar(r)) => Value::Numbar(l - r),
+            (Value::Numbr(l), Value::Numbar(r)) => Value::Numbar(l as f32 - r),
+            (Value::Numbar(l), Value::Numbr(r)) => Value::Numbar(l - r as f32),
+            (Value::Numbr(l), Value::Numbr(r)) => Value::Numbr(l.wrapping_sub(r)),
+            _ => unreachable!(),
+        },
+        BinaryOperation::ProduktOf => match numeric_operands(left, right, "multiply")? {
+            (Value::Numbar(l), Value::Numbar(r)) => Value::Numbar(l * r),
+            (Value::Numbr(l), Value::Numbar(r)) => Value::Numbar(l as f32 * r),
+            (Value::Numbar(l), Value::Numbr(r)) => Value::Numbar(l * r as f32),
+            (Value::Numbr(l), Value::Numbr(r)) => Value::Numbr(l.wrapping_mul(r)),
+            _ => unreachable!(),
+        },
+        BinaryOperation::QuoshuntOf => match numeric_operands(left, right, "divide")? {
+            (Value::Numbar(l), Value::Numbar(r)) => Value::Numbar(l / r),
+            (Value::Numbr(l), Value::Numbar(r)) => Value::Numbar(l as f32 / r),
+            (Value::Numbar(l), Value::Numbr(r)) => Value::Numbar(l / r as f32),
+            (Value::Numbr(_), Value::Numbr(0)) => {
+                return Err(RuntimeErrorKind::DivisionByZero.into())
+            }
+            (Value::Numbr(l), Value::Numbr(r)) => Value::Numbr(l.wrapping_div(r)),
+            _ => unreachable!(),
+        },
+        BinaryOperation::ModOf => match numeric_operands(left, right, "modulo")? {
+            (Value::Numbar(l), Value::Numbar(r)) => Value::Numbar(l % r),
+            (Value::Numbr(l), Value::Numbar(r)) => Value::Numbar(l as f32 % r),
+            (Value::Numbar(l), Value::Numbr(r)) => Value::Numbar(l % r as f32),
+            (Value::Numbr(_), Value::Numbr(0)) => {
+                return Err(RuntimeErrorKind::DivisionByZero.into())
+            }
+            (Value::Numbr(l), Value::Numbr(r)) => Value::Numbr(l.wrapping_rem(r)),
+            _ => unreachable!(),
+        },
+        BinaryOperation::BiggrOf => match numeric_operands(left, right, "compare")? {
+            (Value::Numbar(l), Value::Numbar(r)) => Value::Troof(l > r),
+            (Value::Numbr(l), Value::Numbar(r)) => Value::Troof(l as f32 > r),
+            (Value::Numbar(l), Value::Numbr(r)) => Value::Troof(l > r as f32),
+            (Value::Numbr(l), Value::Numbr(r)) => Value::Troof(l > r),
+            _ => unreachable!(),
+        },
+        BinaryOperation::SmallrOf => match numeric_operands(left, right, "compare")? {
+            (Value::Numbar(l), Value::Numbar(r)) => Value::Troof(l < r),
+            (Value::Numbr(l), Value::Numbar(r)) => Value::Troof((l as f32) < r),
+            (Value::Numbar(l), Value::Numbr(r)) => Value::Troof(l < r as f32),
+            (Value::Numbr(l), Value::Numbr(r)) => Value::Troof(l < r),
+            _ => unreachable!(),
+        },
+        BinaryOperation::BothSaem => left.is_saem(&right).into(),
+        BinaryOperation::Diffrint => (!left.is_saem(&right)).into(),
+        BinaryOperation::WonOf => (left.to_boolean() != right.to_boolean()).into(),
+    })
+}
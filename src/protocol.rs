@@ -0,0 +1,56 @@
+//! The base protocol shared by the language server and the debug adapter: JSON messages preceded
+//! by a `Content-Length` header.
+
+use std::io::{BufRead, Write};
+
+use serde_json::Value as Json;
+
+fn invalid_data(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// Reads the next message. Returns None once the input is closed.
+pub fn read_message(input: &mut impl BufRead) -> std::io::Result<Option<Json>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                let length = value.trim().parse::<usize>().map_err(|err| {
+                    invalid_data(format!(
+                        "invalid Content-Length \"{}\": {}",
+                        value.trim(),
+                        err
+                    ))
+                })?;
+                content_length = Some(length);
+            }
+        }
+    }
+
+    let content_length =
+        content_length.ok_or_else(|| invalid_data("missing Content-Length header".to_string()))?;
+    let mut content = vec![0; content_length];
+    input.read_exact(&mut content)?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|err| invalid_data(err.to_string()))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Json) -> std::io::Result<()> {
+    let content = message.to_string();
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    output.flush()
+}
@@ -0,0 +1,135 @@
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use lolcode_ast::parser::statements::ASTNode;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+use crate::execute_statement;
+use crate::import::search_path;
+use crate::runtime::Runtime;
+use crate::source::{is_incomplete, parse};
+use crate::types::Scope;
+
+const PROMPT: &str = "lol> ";
+const CONTINUATION_PROMPT: &str = "...> ";
+
+fn history_file() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".lolcode_history"))
+}
+
+/// What a [`Session`] did with a line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    /// The input goes on, since it stopped in the middle of a block, a comment or a line
+    /// continued with `...`.
+    Incomplete,
+    /// The input ran. If its last statement was an expression, holds the value of `IT`, which is
+    /// echoed back.
+    Ran(Option<String>),
+    /// The input could not be parsed, or raised an error. Holds the rendered error.
+    Failed(String),
+}
+
+/// An interactive session. All inputs share the same root scope, so variables and functions
+/// declared in one input are available in the next ones. An input spans several lines when it
+/// opens a block, and runs once the block is closed.
+pub struct Session {
+    scope: Scope<'static>,
+    input: String,
+}
+
+impl Session {
+    pub fn new(runtime: Runtime) -> Self {
+        Session {
+            scope: Scope::with_runtime(Rc::new(runtime)),
+            input: String::new(),
+        }
+    }
+
+    /// Drops the lines of the input given so far.
+    pub fn clear(&mut self) {
+        self.input.clear();
+    }
+
+    /// Adds the line to the input, and runs the input if it is complete.
+    pub fn push_line(&mut self, line: &str) -> Reply {
+        self.input.push_str(line);
+        self.input.push('\n');
+        if is_incomplete(&self.input) {
+            return Reply::Incomplete;
+        }
+        let input = std::mem::take(&mut self.input);
+
+        let ast = match parse(&input) {
+            Ok((ast, parsed)) => {
+                self.scope.source = Rc::new(parsed.with_directory(Some(Path::new("."))));
+                ast
+            }
+            Err(err) => return Reply::Failed(err.render(&input, "<repl>")),
+        };
+
+        let mut last_was_expression = false;
+        for node in ast.into_iter() {
+            if let Err(err) = execute_statement(&mut self.scope, &node) {
+                return Reply::Failed(err.render(&input, "<repl>"));
+            }
+            last_was_expression = matches!(node, ASTNode::Expression(_));
+        }
+        Reply::Ran(last_was_expression.then(|| self.scope.it.borrow().to_string()))
+    }
+
+    /// The lines of the input given so far, if it is continued.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+}
+
+/// Starts an interactive session on the terminal. Libraries imported with `CAN HAS` are looked
+/// up in the current directory, and then in `LOLCODE_PATH`.
+pub fn start() -> Result<(), String> {
+    let mut editor = DefaultEditor::new().map_err(|err| err.to_string())?;
+    let history_file = history_file();
+    if let Some(history_file) = &history_file {
+        let _ = editor.load_history(history_file);
+    }
+
+    let runtime = Runtime::default().with_search_path(search_path(vec![]));
+    let mut session = Session::new(runtime);
+
+    loop {
+        let prompt = if session.input().is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                session.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err.to_string()),
+        };
+
+        // The whole input is a single history entry, even when it spans several lines.
+        let entry = format!("{}{}", session.input(), line);
+        let reply = session.push_line(&line);
+        if reply == Reply::Incomplete {
+            continue;
+        }
+        let _ = editor.add_history_entry(entry.as_str());
+        match reply {
+            Reply::Ran(Some(echo)) => println!("{}", echo),
+            Reply::Failed(error) => eprintln!("{}", error),
+            Reply::Ran(None) | Reply::Incomplete => {}
+        }
+    }
+
+    if let Some(history_file) = &history_file {
+        let _ = editor.save_history(history_file);
+    }
+
+    Ok(())
+}
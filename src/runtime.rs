@@ -0,0 +1,356 @@
+use std::cell::{Cell, RefCell};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use lolcode_ast::parser::statements::ASTNode;
+
+use crate::error::Span;
+use crate::gc::{Collector, GcStats, GcThresholds};
+use crate::import::Modules;
+#[cfg(feature = "jit")]
+use crate::jit::Jit;
+use crate::types::{BukkitRef, RuntimeErrorKind, RuntimeResult, Scope};
+
+/// Deep recursion overflows the native stack, since every LOLCODE call recurses in the
+/// interpreter, so calls are limited by default.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 256;
+
+/// Limits on the resources a program can use. Each one, when exceeded, raises its own
+/// [`RuntimeErrorKind`]. `None` means unlimited.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits {
+    /// Maximum number of executed steps. The tree-walker counts statements and loop iterations,
+    /// while the VM counts instructions.
+    pub max_steps: Option<u64>,
+    pub max_call_depth: Option<usize>,
+    pub max_duration: Option<Duration>,
+    /// Maximum number of bytes held by YARNs and BUKKITs stored in variables.
+    pub max_memory: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_steps: None,
+            max_call_depth: Some(DEFAULT_MAX_CALL_DEPTH),
+            max_duration: None,
+            max_memory: None,
+        }
+    }
+}
+
+/// Lets a debugger or a tracer observe and pause the tree-walking interpreter.
+pub(crate) trait StatementHook {
+    /// Called before each statement is executed. Returning an error stops the program.
+    fn before_statement(&self, scope: &Scope, span: Option<Span>) -> RuntimeResult<()>;
+
+    /// Called after a statement executed successfully.
+    fn after_statement(&self, _scope: &Scope, _node: &ASTNode) -> RuntimeResult<()> {
+        Ok(())
+    }
+
+    fn enter_function(&self, _name: &str) {}
+
+    fn exit_function(&self) {}
+}
+
+/// State shared by every scope of a running program, such as where `VISIBLE` writes to and where
+/// `GIMMEH` reads from.
+pub struct Runtime {
+    output: RefCell<Box<dyn Write>>,
+    input: RefCell<Box<dyn BufRead>>,
+
+    limits: Limits,
+    steps: Cell<u64>,
+    call_depth: Cell<usize>,
+    memory: Cell<usize>,
+    /// Only read when there is a time limit, since `Instant::now` panics on
+    /// wasm32-unknown-unknown.
+    started_at: Cell<Option<Instant>>,
+
+    gc: Collector,
+    modules: Modules,
+    hook: Option<Rc<dyn StatementHook>>,
+
+    /// None when the JIT is turned off.
+    #[cfg(feature = "jit")]
+    jit: Option<Jit>,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Runtime::new(
+            Box::new(std::io::stdout()),
+            Box::new(BufReader::new(std::io::stdin())),
+        )
+    }
+}
+
+impl std::fmt::Debug for Runtime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Runtime").finish_non_exhaustive()
+    }
+}
+
+fn io_error(error: std::io::Error) -> RuntimeErrorKind {
+    RuntimeErrorKind::Io(error.to_string())
+}
+
+impl Runtime {
+    pub fn new(output: Box<dyn Write>, input: Box<dyn BufRead>) -> Self {
+        Runtime {
+            output: RefCell::new(output),
+            input: RefCell::new(input),
+            limits: Limits::default(),
+            steps: Cell::new(0),
+            call_depth: Cell::new(0),
+            memory: Cell::new(0),
+            started_at: Cell::new(None),
+            gc: Collector::new(GcThresholds::default()),
+            modules: Modules::default(),
+            hook: None,
+            #[cfg(feature = "jit")]
+            jit: Some(Jit::default()),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self.restart();
+        self
+    }
+
+    pub fn with_gc(mut self, thresholds: GcThresholds) -> Self {
+        self.gc = Collector::new(thresholds);
+        self
+    }
+
+    /// Sets the directories searched for the `.lol` files imported with `CAN HAS`, after the
+    /// directory of the importing file.
+    pub fn with_search_path(mut self, search_path: Vec<PathBuf>) -> Self {
+        self.modules = Modules::new(search_path);
+        self
+    }
+
+    /// Turns the native code compiler on or off. It is on by default in builds with the `jit`
+    /// feature, and this does nothing in the others.
+    #[cfg_attr(not(feature = "jit"), allow(unused_mut, unused_variables))]
+    pub fn with_jit(mut self, enabled: bool) -> Self {
+        #[cfg(feature = "jit")]
+        {
+            self.jit = enabled.then(Jit::default);
+        }
+        self
+    }
+
+    pub(crate) fn with_hook(mut self, hook: Rc<dyn StatementHook>) -> Self {
+        self.hook = Some(hook);
+        self
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    pub fn search_path(&self) -> &[PathBuf] {
+        self.modules.search_path()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn modules(&self) -> &Modules {
+        &self.modules
+    }
+
+    pub fn gc_thresholds(&self) -> &GcThresholds {
+        self.gc.thresholds()
+    }
+
+    pub fn gc_stats(&self) -> GcStats {
+        self.gc.stats(self.memory.get())
+    }
+
+    /// Returns the native code compiler, unless it is turned off, or steps are counted or
+    /// statements are observed, which compiled code does not do.
+    #[cfg(feature = "jit")]
+    pub(crate) fn jit(&self) -> Option<&Jit> {
+        let counts_steps = self.limits.max_steps.is_some() || self.limits.max_duration.is_some();
+        if counts_steps || self.hook.is_some() {
+            return None;
+        }
+        self.jit.as_ref()
+    }
+
+    /// Resets the step count and the clock used by the time limit. Called before running each
+    /// new piece of code.
+    pub fn restart(&self) {
+        self.steps.set(0);
+        self.started_at
+            .set(self.limits.max_duration.map(|_| Instant::now()));
+    }
+
+    /// Accounts for one executed step, checking the step and time limits. Steps are also where
+    /// the garbage collector runs, once one of its thresholds is reached.
+    pub fn step(&self) -> RuntimeResult<()> {
+        let steps = self.steps.get() + 1;
+        self.steps.set(steps);
+
+        if self.gc.is_due(self.memory.get()) {
+            self.collect_garbage();
+        }
+
+        if let Some(max_steps) = self.limits.max_steps {
+            if steps > max_steps {
+                return Err(RuntimeErrorKind::StepLimitExceeded(max_steps).into());
+            }
+        }
+        if let (Some(max_duration), Some(started_at)) =
+            (self.limits.max_duration, self.started_at.get())
+        {
+            if started_at.elapsed() > max_duration {
+                return Err(RuntimeErrorKind::TimeLimitExceeded(max_duration).into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn enter_call(&self) -> RuntimeResult<()> {
+        let depth = self.call_depth.get() + 1;
+        if let Some(max_call_depth) = self.limits.max_call_depth {
+            if depth > max_call_depth {
+                return Err(RuntimeErrorKind::CallDepthExceeded(max_call_depth).into());
+            }
+        }
+        self.call_depth.set(depth);
+        Ok(())
+    }
+
+    pub fn exit_call(&self) {
+        self.call_depth.set(self.call_depth.get().saturating_sub(1));
+    }
+
+    pub(crate) fn before_statement(&self, scope: &Scope, span: Option<Span>) -> RuntimeResult<()> {
+        match &self.hook {
+            Some(hook) => hook.before_statement(scope, span),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn after_statement(&self, scope: &Scope, node: &ASTNode) -> RuntimeResult<()> {
+        match &self.hook {
+            Some(hook) => hook.after_statement(scope, node),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn enter_function(&self, name: &str) {
+        if let Some(hook) = &self.hook {
+            hook.enter_function(name);
+        }
+    }
+
+    pub(crate) fn exit_function(&self) {
+        if let Some(hook) = &self.hook {
+            hook.exit_function();
+        }
+    }
+
+    /// Accounts for a stored value being replaced by another, checking the memory limit. Before
+    /// giving up, the garbage collector gets a chance to free BUKKITs only held by cycles.
+    pub fn charge_memory(&self, released: usize, allocated: usize) -> RuntimeResult<()> {
+        let memory = self.memory.get().saturating_sub(released) + allocated;
+        self.memory.set(memory);
+
+        if let Some(max_memory) = self.limits.max_memory {
+            if memory > max_memory {
+                self.collect_garbage();
+            }
+            if self.memory.get() > max_memory {
+                return Err(RuntimeErrorKind::MemoryLimitExceeded(max_memory).into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn release_memory(&self, released: usize) {
+        self.memory.set(self.memory.get().saturating_sub(released));
+    }
+
+    pub(crate) fn track_bukkit(&self, bukkit: &BukkitRef) {
+        self.gc.track(bukkit);
+    }
+
+    /// Frees the BUKKITs that reference each other in cycles but cannot be reached from the
+    /// program anymore, releasing their memory.
+    pub fn collect_garbage(&self) {
+        self.gc.collect(&self.memory);
+    }
+
+    pub fn write(&self, text: &str) -> RuntimeResult<()> {
+        self.output
+            .borrow_mut()
+            .write_all(text.as_bytes())
+            .map_err(io_error)?;
+        Ok(())
+    }
+
+    pub fn flush(&self) -> RuntimeResult<()> {
+        self.output.borrow_mut().flush().map_err(io_error)?;
+        Ok(())
+    }
+
+    /// Reads a line from the input, without its line terminator.
+    pub fn read_line(&self) -> RuntimeResult<String> {
+        // Make sure prompts written with `VISIBLE "..."!` show up before waiting for input.
+        self.flush()?;
+
+        let mut line = String::new();
+        let bytes_read = self
+            .input
+            .borrow_mut()
+            .read_line(&mut line)
+            .map_err(io_error)?;
+        if bytes_read == 0 {
+            return Err(RuntimeErrorKind::Io("reached the end of the input".to_string()).into());
+        }
+
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(line)
+    }
+}
+
+/// An in-memory output that can be read back after being given to a [`Runtime`], for example to
+/// capture what a program printed.
+#[derive(Debug, Clone, Default)]
+pub struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear();
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,864 @@
+//! Parsing, including the statements lolcode_ast does not parse yet: `CAN HAS <library>?`
+//! imports, `<variable> IS NOW A <type>` casts, and the LOLCODE 1.3 `O HAI IM` object
+//! declarations, `HOW IZ <object> <method>` and `<object> IZ <method>` methods.
+//!
+//! Before the source code is handed to lolcode_ast, [`parse`] finds those statements and rewrites
+//! them into statements it parses, without moving anything else, so the spans of every node are
+//! the same as in the original:
+//!
+//! - `CAN HAS <library>?` keeps only the name of the library, which parses as an expression
+//!   statement. Imports must be at the top level of the program, outside of any block.
+//! - `O HAI IM <name> [IM LIEK <parent>]` keeps only the name too. The block, up to its `KTHX`,
+//!   is blanked out. The blocks are parsed apart from the rest of the program, all those nested
+//!   at the same depth at once.
+//! - `<variable> IS NOW A <type>` keeps only the variable, which parses as an expression
+//!   statement. Once parsed, the statement is replaced with the assignment
+//!   `<variable> R MAEK <variable> A <type>`, which every engine runs.
+//! - The object of `HOW IZ <object> <method>` and `<object> IZ <method>` is replaced with `I`, so
+//!   they parse as a function declaration and a function call.
+//!
+//! What was rewritten is kept in the [`Source`], by the position of the node the statement was
+//! parsed into, and the interpreter looks it up when it executes that node. Strings and comments
+//! are skipped while looking for the statements, and commas separating statements are turned
+//! into line breaks, which lolcode_ast reads the same way.
+
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+
+use lolcode_ast::parser::expression::{ASTExpression, ASTType, Identifier};
+use lolcode_ast::parser::statements::assignment::VariableAssignment;
+use lolcode_ast::parser::statements::ASTNode;
+use lolcode_ast::parser::ASTBlock;
+
+use crate::error::{expression_span, node_span, Span};
+use crate::import::NATIVE_LIBRARIES;
+use crate::types::{RuntimeError, RuntimeErrorKind, RuntimeResult, Scope};
+
+/// A statement of LOLCODE 1.3 that was rewritten before parsing.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Extension {
+    /// `CAN HAS <library>?`, parsed as the expression `<library>`.
+    Import(String),
+    /// `O HAI IM <name> [IM LIEK <parent>] ... KTHX`, parsed as the expression `<name>`.
+    Object {
+        parent: Option<ObjectPath>,
+        body: ASTBlock,
+    },
+    /// `HOW IZ <object> <method>`, parsed as `HOW IZ I <method>`.
+    Method(ObjectPath),
+    /// `<object> IZ <method>`, parsed as `I IZ <method>`.
+    Call(ObjectPath),
+    /// `<variable> IS NOW A <type>`, parsed as `<variable> R MAEK <variable> A <type>`.
+    Recast(ASTType),
+}
+
+/// The variable or slot holding an object, like `ME` or `shapes'Z square`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ObjectPath {
+    pub variable: String,
+    pub slots: Vec<String>,
+    pub span: Span,
+}
+
+/// What the interpreter needs to know about a piece of source code, besides its syntax tree.
+#[derive(Debug, Default)]
+pub struct Source {
+    /// The rewritten statements, by the start of the span of the node they were parsed into.
+    extensions: HashMap<usize, Extension>,
+    /// The directory of the file, where the libraries it imports are looked up first.
+    directory: Option<PathBuf>,
+    /// The global scope of the library, once it has run, if the source code is a library.
+    home: OnceCell<Weak<Scope<'static>>>,
+    /// The code compiled from the loops and functions of the source code.
+    #[cfg(feature = "jit")]
+    pub(crate) regions: crate::jit::Regions,
+}
+
+impl Source {
+    pub(crate) fn with_directory(mut self, directory: Option<&Path>) -> Self {
+        self.directory = directory.map(Path::to_path_buf);
+        self
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn directory(&self) -> Option<&Path> {
+        self.directory.as_deref()
+    }
+
+    /// Returns the global scope of the library, on which its functions and methods run, instead
+    /// of the global scope of their caller.
+    pub(crate) fn home(&self) -> Option<Rc<Scope<'static>>> {
+        self.home.get().and_then(Weak::upgrade)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn set_home(&self, home: &Rc<Scope<'static>>) {
+        let _ = self.home.set(Rc::downgrade(home));
+    }
+
+    /// Returns the statement the node spanning `span` was rewritten from, if it was.
+    pub(crate) fn extension(&self, span: Option<Span>) -> Option<&Extension> {
+        if self.extensions.is_empty() {
+            return None;
+        }
+        self.extensions.get(&span?.start)
+    }
+}
+
+/// Parses the source code, with the statements of LOLCODE 1.3 that lolcode_ast does not parse.
+/// Fails if lolcode_ast reported an error for any statement.
+pub(crate) fn parse(source: &str) -> RuntimeResult<(ASTBlock, Source)> {
+    let (ast, parsed, mut errors) = parse_with_errors(source)?;
+    match errors.is_empty() {
+        true => Ok((ast, parsed)),
+        false => Err(errors.remove(0)),
+    }
+}
+
+/// Parses the source code like [`parse`], but keeps the statements lolcode_ast reported an error
+/// for as [`ASTNode::ASTError`] nodes, and returns their errors in order. Used by the tools that
+/// still have something to say about the rest of the code.
+pub(crate) fn parse_with_errors(
+    source: &str,
+) -> RuntimeResult<(ASTBlock, Source, Vec<RuntimeError>)> {
+    let mut rewriter = Rewriter::new(source);
+    let mut text = rewriter.separated.clone();
+    let count = rewriter.statements.len();
+    rewriter.rewrite(0..count, &mut text, 0)?;
+    rewriter.parse_objects()?;
+    let mut ast = parse_text(text)?;
+    desugar(&mut ast, &rewriter.extensions, source, &mut rewriter.errors);
+    rewriter
+        .errors
+        .sort_by_key(|error| error.span.map(|span| span.start));
+    Ok((
+        ast,
+        Source {
+            extensions: rewriter.extensions,
+            ..Default::default()
+        },
+        rewriter.errors,
+    ))
+}
+
+/// Where the statements and comments of the source code are.
+pub(crate) struct Layout {
+    /// The span of each statement, from its first word to its last, in order. Statements
+    /// continued with `...` span several lines, and statements separated by commas share one.
+    pub statements: Vec<Span>,
+    /// The span of each `BTW` and `OBTW` comment, in order.
+    pub comments: Vec<Span>,
+}
+
+/// Returns where the statements and comments of the source code are, for the formatter, which
+/// prints the syntax tree back with the comments where they were.
+pub(crate) fn layout(source: &str) -> Layout {
+    let Statements {
+        statements,
+        comments,
+        ..
+    } = split_statements(source);
+    let statements = statements
+        .iter()
+        .map(|statement| Span {
+            start: statement.start(),
+            end: statement.end(),
+        })
+        .collect();
+    Layout {
+        statements,
+        comments,
+    }
+}
+
+/// Returns whether the source code stops in the middle of a statement: in a block that is not
+/// closed yet, in an `OBTW` comment without its `TLDR`, or after a line continued with `...`.
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub(crate) fn is_incomplete(source: &str) -> bool {
+    let rewriter = Rewriter::new(source);
+    let depth: isize = rewriter
+        .statements
+        .iter()
+        .map(|statement| rewriter.depth_change(statement))
+        .sum();
+    depth > 0 || split_statements(source).unfinished
+}
+
+/// The calls of the methods of native libraries, `<library> IZ <method>`, in a program run
+/// without its [`Source`]. The engines that run such programs have no objects, and call the
+/// method as the built-in function named `<library>'Z <method>`.
+#[derive(Debug, Default)]
+pub(crate) struct LibraryCalls(HashMap<usize, String>);
+
+impl LibraryCalls {
+    /// Returns the name of the function `I IZ <name>` calls, which is a method of a native
+    /// library if the statement was rewritten from `<library> IZ <name>`.
+    pub fn function_name(&self, name: &Identifier) -> String {
+        match self.0.get(&Span::from(name).start) {
+            Some(function) => function.clone(),
+            None => name.to_string_slice().to_string(),
+        }
+    }
+}
+
+/// Parses the source code for the engines that only run the syntax tree, without its [`Source`].
+/// Only the native libraries can be imported, and their methods called. Objects cannot be
+/// declared.
+pub(crate) fn parse_without_extensions(source: &str) -> RuntimeResult<(ASTBlock, LibraryCalls)> {
+    let (mut ast, parsed) = parse(source)?;
+    let mut extensions: Vec<_> = parsed.extensions.iter().collect();
+    extensions.sort_by_key(|(start, _)| **start);
+    let imported: Vec<&str> = extensions
+        .iter()
+        .filter_map(|(_, extension)| match extension {
+            Extension::Import(library) => Some(library.as_str()),
+            _ => None,
+        })
+        .collect();
+    let mut calls = LibraryCalls::default();
+    for &(&start, extension) in extensions.iter() {
+        let error = match extension {
+            Extension::Import(library) if NATIVE_LIBRARIES.contains(&library.as_str()) => continue,
+            Extension::Recast(_) => continue,
+            Extension::Call(object)
+                if object.slots.is_empty()
+                    && NATIVE_LIBRARIES.contains(&object.variable.as_str())
+                    && imported.contains(&object.variable.as_str()) =>
+            {
+                let method = word_at(source, start);
+                let function = format!(
+                    "{}'Z {}",
+                    object.variable,
+                    &source[method.start..method.end]
+                );
+                calls.0.insert(start, function);
+                continue;
+            }
+            Extension::Import(library) => RuntimeError::from(RuntimeErrorKind::ImportNotSupported(
+                library.clone(),
+            ))
+            .at(Some(Span {
+                start,
+                end: start + library.len(),
+            })),
+            Extension::Object { .. } => RuntimeError::from(RuntimeErrorKind::ObjectNotSupported)
+                .at(Some(word_at(source, start))),
+            Extension::Method(object) | Extension::Call(object) => {
+                RuntimeError::from(RuntimeErrorKind::ObjectNotSupported).at(Some(object.span))
+            }
+        };
+        return Err(error);
+    }
+    // The imports are at the top level, parsed as expression statements naming the library.
+    ast.0.retain(|node| match node {
+        ASTNode::Expression(expression) => !matches!(
+            parsed.extension(expression_span(expression)),
+            Some(Extension::Import(_))
+        ),
+        _ => true,
+    });
+    Ok((ast, calls))
+}
+
+/// Returns the span of the word starting at `start`.
+fn word_at(source: &str, start: usize) -> Span {
+    let length = source[start..]
+        .find(|c: char| c.is_whitespace() || c == ',')
+        .unwrap_or(source.len() - start);
+    Span {
+        start,
+        end: start + length,
+    }
+}
+
+/// Replaces the `<variable> IS NOW A <type>` statements, parsed as the expression `<variable>`,
+/// with the assignment they are equivalent to, and collects the errors of the statements that
+/// lolcode_ast could not parse.
+fn desugar(
+    block: &mut ASTBlock,
+    extensions: &HashMap<usize, Extension>,
+    source: &str,
+    errors: &mut Vec<RuntimeError>,
+) {
+    for node in block.0.iter_mut() {
+        match node {
+            ASTNode::Expression(ASTExpression::VariableAccess(variable_access)) => {
+                let start = Span::from(&*variable_access).start;
+                if let Some(Extension::Recast(to)) = extensions.get(&start) {
+                    let variable_access = variable_access.clone();
+                    let value = ASTExpression::VariableAccess(variable_access.clone());
+                    *node = ASTNode::VariableAssignment(VariableAssignment {
+                        variable_access,
+                        expression: ASTExpression::Maek(Box::new(value), to.clone()),
+                    });
+                }
+            }
+            ASTNode::ImInYr(im_in_yr) => {
+                desugar(&mut im_in_yr.code_block, extensions, source, errors)
+            }
+            ASTNode::HowIzI(how_iz_i) => desugar(&mut how_iz_i.body, extensions, source, errors),
+            ASTNode::ORly(o_rly) => {
+                let mebbes = o_rly.mebbes.iter_mut().map(|(_, block)| block);
+                for block in o_rly
+                    .if_true
+                    .iter_mut()
+                    .chain(mebbes)
+                    .chain(&mut o_rly.if_false)
+                {
+                    desugar(block, extensions, source, errors);
+                }
+            }
+            ASTNode::Wtf(wtf) => {
+                let omgs = wtf.omg.iter_mut().map(|(_, block)| block);
+                for block in omgs.chain(&mut wtf.omg_wtf) {
+                    desugar(block, extensions, source, errors);
+                }
+            }
+            ASTNode::ASTError(token) => {
+                let span = Span::from(&*token);
+                let message = match source.get(span.start..span.end) {
+                    Some(text) if !text.trim().is_empty() => format!("unexpected \"{}\"", text),
+                    _ => "unexpected end of the statement".to_string(),
+                };
+                errors
+                    .push(RuntimeError::from(RuntimeErrorKind::ParseError(message)).at(Some(span)));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parses the name of a type, as written after `IS NOW A`.
+fn ast_type(word: &str) -> Option<ASTType> {
+    Some(match word {
+        "YARN" => ASTType::Yarn,
+        "NUMBR" => ASTType::Numbr,
+        "NUMBAR" => ASTType::Numbar,
+        "TROOF" => ASTType::Troof,
+        "BUKKIT" => ASTType::Bukkit,
+        "NOOB" => ASTType::Noob,
+        _ => return None,
+    })
+}
+
+fn parse_text(text: Vec<u8>) -> RuntimeResult<ASTBlock> {
+    // Only whole characters are ever blanked out, so the text is still valid UTF-8.
+    let text = String::from_utf8(text).expect("rewritten source code is not UTF-8");
+    lolcode_ast::tokenize_and_parse(text)
+        .map_err(|error| RuntimeErrorKind::ParseError(format!("{:?}", error)).into())
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Word {
+    start: usize,
+    end: usize,
+}
+
+#[derive(Debug, Default)]
+struct Statement {
+    words: Vec<Word>,
+    /// The comma ending the statement, if it does not end with the line.
+    comma: Option<usize>,
+}
+
+impl Statement {
+    fn start(&self) -> usize {
+        self.words[0].start
+    }
+
+    fn end(&self) -> usize {
+        self.words[self.words.len() - 1].end
+    }
+}
+
+/// The statements of the source code, split by [`split_statements`].
+#[derive(Debug, Default)]
+struct Statements {
+    statements: Vec<Statement>,
+    /// Whether the source code ends in an `OBTW` comment without its `TLDR`, or in a line
+    /// continued with `...`.
+    unfinished: bool,
+    /// The `BTW` and `OBTW` comments, without the line break ending them.
+    comments: Vec<Span>,
+}
+
+/// Splits the source code into the words of its statements, skipping comments. A string is a
+/// single word, and so is the `?` ending `O RLY?`, `WTF?` or `CAN HAS <library>?`.
+fn split_statements(source: &str) -> Statements {
+    let bytes = source.as_bytes();
+    let mut statements = vec![];
+    let mut statement = Statement::default();
+    let mut unfinished = false;
+    let mut comments = vec![];
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\n' | b',' => {
+                let continued = bytes[index] == b'\n'
+                    && statement
+                        .words
+                        .last()
+                        .is_some_and(|word| matches!(&source[word.start..word.end], "..." | "…"));
+                unfinished = continued;
+                if continued {
+                    statement.words.pop();
+                } else if !statement.words.is_empty() {
+                    if bytes[index] == b',' {
+                        statement.comma = Some(index);
+                    }
+                    statements.push(std::mem::take(&mut statement));
+                }
+                index += 1;
+            }
+            b'"' => {
+                unfinished = false;
+                let start = index;
+                index += 1;
+                while index < bytes.len() && bytes[index] != b'"' {
+                    // `:` escapes the next character, which can be a `"`.
+                    index += if bytes[index] == b':' { 2 } else { 1 };
+                }
+                index = (index + 1).min(bytes.len());
+                statement.words.push(Word { start, end: index });
+            }
+            byte if byte.is_ascii_whitespace() => index += 1,
+            _ => {
+                unfinished = false;
+                let start = index;
+                while index < bytes.len()
+                    && !bytes[index].is_ascii_whitespace()
+                    && !matches!(bytes[index], b',' | b'"')
+                {
+                    index += 1;
+                }
+                match &source[start..index] {
+                    "BTW" => {
+                        while index < bytes.len() && bytes[index] != b'\n' {
+                            index += 1;
+                        }
+                        let end = start + source[start..index].trim_end().len();
+                        comments.push(Span { start, end });
+                    }
+                    "OBTW" => {
+                        match end_of_comment(source, index) {
+                            Some(end) => index = end,
+                            None => {
+                                index = bytes.len();
+                                unfinished = true;
+                            }
+                        }
+                        comments.push(Span { start, end: index });
+                    }
+                    word if word.len() > 1 && word.ends_with('?') => {
+                        statement.words.push(Word {
+                            start,
+                            end: index - 1,
+                        });
+                        statement.words.push(Word {
+                            start: index - 1,
+                            end: index,
+                        });
+                    }
+                    _ => statement.words.push(Word { start, end: index }),
+                }
+            }
+        }
+    }
+    if !statement.words.is_empty() {
+        statements.push(statement);
+    }
+    Statements {
+        statements,
+        unfinished,
+        comments,
+    }
+}
+
+/// Returns where the `OBTW` comment that starts before `index` ends, after its `TLDR`, or None if
+/// it does not end.
+fn end_of_comment(source: &str, mut index: usize) -> Option<usize> {
+    let bytes = source.as_bytes();
+    while index < bytes.len() {
+        let start = index;
+        while index < bytes.len() && !bytes[index].is_ascii_whitespace() && bytes[index] != b',' {
+            index += 1;
+        }
+        if &source[start..index] == "TLDR" {
+            return Some(index);
+        }
+        if index == start {
+            index += 1;
+        }
+    }
+    None
+}
+
+fn is_identifier(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_alphabetic())
+        && word.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Replaces the bytes in the range with spaces, keeping the line breaks.
+fn blank(text: &mut [u8], range: Range<usize>) {
+    for byte in &mut text[range] {
+        if *byte != b'\n' {
+            *byte = b' ';
+        }
+    }
+}
+
+/// An `O HAI IM` block whose body is not parsed yet.
+struct ObjectBlock {
+    /// Where the name of the object starts.
+    name: usize,
+    parent: Option<ObjectPath>,
+    /// The statements of the body, up to the `KTHX` ending it.
+    body: Range<usize>,
+    depth: isize,
+}
+
+struct Rewriter<'a> {
+    source: &'a str,
+    /// The source code with the commas separating statements turned into line breaks.
+    separated: Vec<u8>,
+    statements: Vec<Statement>,
+    extensions: HashMap<usize, Extension>,
+    /// The `O HAI IM` blocks found by [`Rewriter::rewrite`], parsed by
+    /// [`Rewriter::parse_objects`].
+    objects: Vec<ObjectBlock>,
+    /// The errors lolcode_ast reported for statements.
+    errors: Vec<RuntimeError>,
+}
+
+impl<'a> Rewriter<'a> {
+    fn new(source: &'a str) -> Self {
+        let Statements { statements, .. } = split_statements(source);
+        let mut separated = source.as_bytes().to_vec();
+        for comma in statements.iter().filter_map(|statement| statement.comma) {
+            separated[comma] = b'\n';
+        }
+        Rewriter {
+            source,
+            separated,
+            statements,
+            extensions: HashMap::new(),
+            objects: vec![],
+            errors: vec![],
+        }
+    }
+
+    fn word(&self, word: Word) -> &str {
+        &self.source[word.start..word.end]
+    }
+
+    fn starts_with(&self, statement: &Statement, keywords: &[&str]) -> bool {
+        statement.words.len() >= keywords.len()
+            && statement
+                .words
+                .iter()
+                .zip(keywords)
+                .all(|(word, keyword)| self.word(*word) == *keyword)
+    }
+
+    fn is_object_end(&self, statement: &Statement) -> bool {
+        statement.words.len() == 1 && self.starts_with(statement, &["KTHX"])
+    }
+
+    /// Returns by how much the statement changes the nesting depth of blocks.
+    fn depth_change(&self, statement: &Statement) -> isize {
+        const OPENERS: [&[&str]; 5] = [
+            &["IM", "IN", "YR"],
+            &["HOW", "IZ"],
+            &["O", "RLY", "?"],
+            &["WTF", "?"],
+            &["O", "HAI", "IM"],
+        ];
+        const CLOSERS: [&[&str]; 3] = [&["IM", "OUTTA", "YR"], &["IF", "U", "SAY", "SO"], &["OIC"]];
+        if OPENERS
+            .iter()
+            .any(|opener| self.starts_with(statement, opener))
+        {
+            1
+        } else if self.is_object_end(statement)
+            || CLOSERS
+                .iter()
+                .any(|closer| self.starts_with(statement, closer))
+        {
+            -1
+        } else {
+            0
+        }
+    }
+
+    /// Parses a `CAN HAS <library>?` statement, returning the library and the span of its name.
+    /// Returns None for any other statement, and an error for an import nested in a block.
+    fn import(&self, statement: &Statement, depth: isize) -> RuntimeResult<Option<(String, Span)>> {
+        if !self.starts_with(statement, &["CAN", "HAS"]) {
+            return Ok(None);
+        }
+        let span = Span {
+            start: statement.start(),
+            end: statement.end(),
+        };
+        let name = match statement.words[2..] {
+            [name, question_mark]
+                if is_identifier(self.word(name)) && self.word(question_mark) == "?" =>
+            {
+                name
+            }
+            _ => {
+                return Err(RuntimeError::from(RuntimeErrorKind::ParseError(
+                    "expected CAN HAS <library>?".to_string(),
+                ))
+                .at(Some(span)))
+            }
+        };
+        let library = self.word(name).to_string();
+        let name = Span {
+            start: name.start,
+            end: name.end,
+        };
+        if depth > 0 {
+            return Err(
+                RuntimeError::from(RuntimeErrorKind::ImportNotAtTopLevel(library)).at(Some(name)),
+            );
+        }
+        Ok(Some((library, name)))
+    }
+
+    /// Parses the object path at the start of the words, returning it with the number of words it
+    /// spans. `shapes'Z square` spans two words.
+    fn object_path(&self, words: &[Word]) -> Option<(ObjectPath, usize)> {
+        let mut names = vec![];
+        for (index, word) in words.iter().enumerate() {
+            let text = self.word(*word);
+            let (name, continued) = match text.strip_suffix("'Z") {
+                Some(name) => (name, true),
+                None => (text, false),
+            };
+            if !is_identifier(name) {
+                return None;
+            }
+            names.push(name.to_string());
+            if !continued {
+                let variable = names.remove(0);
+                let span = Span {
+                    start: words[0].start,
+                    end: word.end,
+                };
+                return Some((
+                    ObjectPath {
+                        variable,
+                        slots: names,
+                        span,
+                    },
+                    index + 1,
+                ));
+            }
+        }
+        None
+    }
+
+    /// Rewrites the statements in the range into `text`, which they have not been rewritten in
+    /// yet.
+    fn rewrite(
+        &mut self,
+        statements: Range<usize>,
+        text: &mut [u8],
+        mut depth: isize,
+    ) -> RuntimeResult<()> {
+        let mut index = statements.start;
+        while index < statements.end {
+            let statement = &self.statements[index];
+            let words = &statement.words;
+
+            if self.starts_with(statement, &["O", "HAI", "IM"]) {
+                index = self.rewrite_object(index, statements.end, text, depth)?;
+                continue;
+            }
+            if let Some((library, name)) = self.import(statement, depth)? {
+                blank(text, statement.start()..name.start);
+                blank(text, name.end..statement.end());
+                self.extensions
+                    .insert(name.start, Extension::Import(library));
+                index += 1;
+                continue;
+            }
+            depth += self.depth_change(statement);
+
+            if self.starts_with(statement, &["HOW", "IZ"]) {
+                if words.len() > 3 && self.word(words[2]) != "I" {
+                    if let Some((object, length)) = self.object_path(&words[2..]) {
+                        if let Some(method) = words.get(2 + length) {
+                            text[object.span.start] = b'I';
+                            blank(text, object.span.start + 1..object.span.end);
+                            self.extensions
+                                .insert(method.start, Extension::Method(object));
+                        }
+                    }
+                }
+            } else if self.word(words[0]) != "I" {
+                if let Some((object, length)) = self.object_path(words) {
+                    let is_call = words
+                        .get(length)
+                        .is_some_and(|word| self.word(*word) == "IZ");
+                    let rest: Vec<&str> = words[length..].iter().map(|w| self.word(*w)).collect();
+                    if let (true, Some(method)) = (is_call, words.get(length + 1)) {
+                        text[object.span.start] = b'I';
+                        blank(text, object.span.start + 1..object.span.end);
+                        self.extensions
+                            .insert(method.start, Extension::Call(object));
+                    } else if let ["IS", "NOW", "A", to] = rest[..] {
+                        let to = ast_type(to).ok_or_else(|| {
+                            RuntimeError::from(RuntimeErrorKind::ParseError(format!(
+                                "expected a type after IS NOW A, but found {}",
+                                to
+                            )))
+                            .at(Some(Span {
+                                start: words[length + 3].start,
+                                end: words[length + 3].end,
+                            }))
+                        })?;
+                        blank(text, object.span.end..statement.end());
+                        self.extensions
+                            .insert(object.span.start, Extension::Recast(to));
+                    }
+                }
+            }
+            index += 1;
+        }
+        Ok(())
+    }
+
+    /// Rewrites the `O HAI IM` block starting at the statement, leaving its body to
+    /// [`Rewriter::parse_objects`]. Returns the index of the statement following its `KTHX`.
+    fn rewrite_object(
+        &mut self,
+        header: usize,
+        limit: usize,
+        text: &mut [u8],
+        depth: isize,
+    ) -> RuntimeResult<usize> {
+        let words = &self.statements[header].words;
+        let start = words[0].start;
+        let header_span = Span {
+            start,
+            end: self.statements[header].end(),
+        };
+        let error = |message: &str| -> RuntimeResult<usize> {
+            Err(
+                RuntimeError::from(RuntimeErrorKind::ParseError(message.to_string()))
+                    .at(Some(header_span)),
+            )
+        };
+
+        let name = match words.get(3) {
+            Some(name) if is_identifier(self.word(*name)) => *name,
+            _ => return error("expected the name of the object after O HAI IM"),
+        };
+        let parent = match &words[4..] {
+            [] => None,
+            [im, liek, parent @ ..] if self.word(*im) == "IM" && self.word(*liek) == "LIEK" => {
+                match self.object_path(parent) {
+                    Some((path, length)) if length == parent.len() => Some(path),
+                    _ => return error("expected the parent of the object after IM LIEK"),
+                }
+            }
+            _ => return error("expected IM LIEK or the end of the statement after the name"),
+        };
+
+        let mut nested = 0;
+        let end = (header + 1..limit).find(|&index| {
+            let statement = &self.statements[index];
+            if self.starts_with(statement, &["O", "HAI", "IM"]) {
+                nested += 1;
+            } else if self.is_object_end(statement) {
+                if nested == 0 {
+                    return true;
+                }
+                nested -= 1;
+            }
+            false
+        });
+        let Some(end) = end else {
+            return error("O HAI IM is missing its KTHX");
+        };
+
+        blank(text, start..name.start);
+        blank(text, name.end..self.statements[end].end());
+        self.objects.push(ObjectBlock {
+            name: name.start,
+            parent,
+            body: header + 1..end,
+            depth,
+        });
+        Ok(end + 1)
+    }
+
+    /// Parses the bodies of the `O HAI IM` blocks found so far, then those of the blocks nested
+    /// in them, and so on. The bodies nested at the same depth are parsed together, from the
+    /// source code with everything else blanked out, except for `HAI` and `KTHXBYE`, which
+    /// lolcode_ast may expect around them.
+    fn parse_objects(&mut self) -> RuntimeResult<()> {
+        let around: Vec<Range<usize>> = self
+            .statements
+            .iter()
+            .filter(|statement| {
+                self.starts_with(statement, &["HAI"]) || self.starts_with(statement, &["KTHXBYE"])
+            })
+            .map(|statement| statement.start()..statement.end())
+            .collect();
+        while !self.objects.is_empty() {
+            let objects = std::mem::take(&mut self.objects);
+            let mut kept = around.clone();
+            kept.extend(objects.iter().map(|object| {
+                self.statements[object.body.start].start()..self.statements[object.body.end].end()
+            }));
+            kept.sort_by_key(|range| range.start);
+            let mut text = self.separated.clone();
+            let mut position = 0;
+            for range in kept {
+                blank(&mut text, position..range.start.max(position));
+                position = position.max(range.end);
+            }
+            let length = text.len();
+            blank(&mut text, position..length);
+
+            // The `KTHX` ending each body is turned into a `GTFO`, which marks where the body
+            // ends among the parsed statements.
+            for object in &objects {
+                let kthx = self.statements[object.body.end].start();
+                text[kthx..kthx + 4].copy_from_slice(b"GTFO");
+                self.rewrite(object.body.clone(), &mut text, object.depth + 1)?;
+            }
+            let mut block = parse_text(text)?;
+            desugar(&mut block, &self.extensions, self.source, &mut self.errors);
+
+            let mut nodes = block
+                .0
+                .into_iter()
+                .filter(|node| !matches!(node, ASTNode::HAI(_) | ASTNode::KTHXBYE(_)));
+            for object in objects {
+                let kthx = self.statements[object.body.end].start();
+                let body = nodes
+                    .by_ref()
+                    .take_while(|node| {
+                        !matches!(node, ASTNode::Gtfo(_))
+                            || node_span(node).map(|span| span.start) != Some(kthx)
+                    })
+                    .collect();
+                self.extensions.insert(
+                    object.name,
+                    Extension::Object {
+                        parent: object.parent,
+                        body: ASTBlock(body),
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+}
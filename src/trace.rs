@@ -0,0 +1,185 @@
+//! Tracing of the tree-walking interpreter: an event is written after each executed statement,
+//! with its position, the value of `IT` and the variables it wrote.
+
+use std::cell::{Cell, RefCell};
+use std::io::Write;
+
+use lolcode_ast::parser::expression::VariableAccess;
+use lolcode_ast::parser::statements::assignment::VariableAssignment;
+use lolcode_ast::parser::statements::bukkit_set_slot::BukkitSetSlot;
+use lolcode_ast::parser::statements::i_has_a::IHasA;
+use lolcode_ast::parser::statements::im_in_yr::{ImInYr, LoopIterationOperation};
+use lolcode_ast::parser::statements::ASTNode;
+use serde_json::{json, Value as Json};
+
+use crate::error::{node_span, Span};
+use crate::runtime::StatementHook;
+use crate::types::{BukkitRef, RuntimeErrorKind, RuntimeResult, Scope, Value};
+use crate::variable_access::{get_identifier_from_scope, get_variable_access_from_scope};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// One line of text per event.
+    Human,
+    /// One JSON object per line.
+    Json,
+}
+
+pub(crate) struct Tracer {
+    format: TraceFormat,
+    source: String,
+    output: RefCell<Box<dyn Write>>,
+    /// How many statements are currently being executed, so nested statements can be told apart.
+    depth: Cell<usize>,
+}
+
+fn statement_kind(node: &ASTNode) -> &'static str {
+    match node {
+        ASTNode::HAI(_) => "HAI",
+        ASTNode::IHasA(_) => "I HAS A",
+        ASTNode::ImInYr(_) => "IM IN YR",
+        ASTNode::BukkitSetSlot(_) => "HAS A",
+        ASTNode::VariableAssignment(_) => "R",
+        ASTNode::Visible(_) => "VISIBLE",
+        ASTNode::FoundYr(_) => "FOUND YR",
+        ASTNode::Wtf(_) => "WTF?",
+        ASTNode::ORly(_) => "O RLY?",
+        ASTNode::IIz(_) => "I IZ",
+        ASTNode::HowIzI(_) => "HOW IZ I",
+        ASTNode::Gtfo(_) => "GTFO",
+        ASTNode::Gimmeh(_) => "GIMMEH",
+        ASTNode::Expression(_) => "EXPRESSION",
+        ASTNode::ASTError(_) => "ERROR",
+        ASTNode::KTHXBYE(_) => "KTHXBYE",
+    }
+}
+
+fn access_name(variable_access: &VariableAccess) -> String {
+    let mut name = variable_access.name.to_string_slice().to_string();
+    for access in variable_access.accesses.iter() {
+        name.push_str("'Z ");
+        name.push_str(access.to_string_slice());
+    }
+    name
+}
+
+fn read_access(scope: &Scope, variable_access: &VariableAccess) -> Option<(String, Value)> {
+    let value = get_variable_access_from_scope(scope, variable_access).ok()?;
+    Some((access_name(variable_access), value))
+}
+
+/// Returns the variables the statement itself writes to, with their new values. Statements in
+/// nested blocks have their own events.
+fn written_variables(scope: &Scope, node: &ASTNode) -> Vec<(String, Value)> {
+    let written = match node {
+        ASTNode::IHasA(IHasA { identifier, .. }) => get_identifier_from_scope(scope, identifier)
+            .ok()
+            .map(|value| (identifier.to_string_slice().to_string(), value.clone())),
+        ASTNode::VariableAssignment(VariableAssignment {
+            variable_access, ..
+        })
+        | ASTNode::Gimmeh(variable_access)
+        | ASTNode::BukkitSetSlot(BukkitSetSlot {
+            bukkit: variable_access,
+            ..
+        }) => read_access(scope, variable_access),
+        ASTNode::ImInYr(ImInYr {
+            on_iteration: Some(LoopIterationOperation { operand, .. }),
+            ..
+        }) => read_access(scope, operand),
+        _ => None,
+    };
+    written.into_iter().collect()
+}
+
+/// Converts the value to JSON. A BUKKIT nested in itself becomes the string `"{...}"`.
+fn value_to_json(value: &Value, rendering: &mut Vec<BukkitRef>) -> Json {
+    match value {
+        Value::Yarn(yarn) => json!(yarn),
+        Value::Numbr(numbr) => json!(numbr),
+        Value::Numbar(numbar) => json!(numbar),
+        Value::Troof(troof) => json!(troof),
+        Value::Noob => Json::Null,
+        Value::Bukkit(bukkit) if rendering.iter().any(|outer| outer.ptr_eq(bukkit)) => {
+            json!("{...}")
+        }
+        Value::Bukkit(bukkit) => {
+            rendering.push(bukkit.clone());
+            let object = bukkit
+                .borrow()
+                .slots
+                .iter()
+                .map(|(name, value)| (name.clone(), value_to_json(value, rendering)))
+                .collect();
+            rendering.pop();
+            Json::Object(object)
+        }
+    }
+}
+
+impl Tracer {
+    pub fn new(format: TraceFormat, source: String, output: Box<dyn Write>) -> Self {
+        Tracer {
+            format,
+            source,
+            output: RefCell::new(output),
+            depth: Cell::new(0),
+        }
+    }
+
+    fn event(&self, scope: &Scope, node: &ASTNode, span: Option<Span>) -> String {
+        let position = span.map(|span| span.line_and_column(&self.source));
+        let it = scope.it.borrow();
+        let written = written_variables(scope, node);
+        let depth = self.depth.get();
+
+        match self.format {
+            TraceFormat::Human => {
+                let mut event = format!(
+                    "{}{}",
+                    "  ".repeat(depth),
+                    match position {
+                        Some((line, column)) => format!("{}:{}", line, column),
+                        None => "?".to_string(),
+                    }
+                );
+                event.push_str(&format!(" {} IT={}", statement_kind(node), it));
+                for (name, value) in written.iter() {
+                    event.push_str(&format!(" {}={}", name, value));
+                }
+                event
+            }
+            TraceFormat::Json => {
+                let written: serde_json::Map<String, Json> = written
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value_to_json(value, &mut vec![])))
+                    .collect();
+                json!({
+                    "kind": statement_kind(node),
+                    "line": position.map(|(line, _)| line),
+                    "column": position.map(|(_, column)| column),
+                    "depth": depth,
+                    "it": value_to_json(&it, &mut vec![]),
+                    "it_type": it.type_name(),
+                    "written": written,
+                })
+                .to_string()
+            }
+        }
+    }
+}
+
+impl StatementHook for Tracer {
+    fn before_statement(&self, _scope: &Scope, _span: Option<Span>) -> RuntimeResult<()> {
+        self.depth.set(self.depth.get() + 1);
+        Ok(())
+    }
+
+    fn after_statement(&self, scope: &Scope, node: &ASTNode) -> RuntimeResult<()> {
+        self.depth.set(self.depth.get().saturating_sub(1));
+        let event = self.event(scope, node, node_span(node));
+        writeln!(self.output.borrow_mut(), "{}", event)
+            .map_err(|error| RuntimeErrorKind::Io(error.to_string()))?;
+        Ok(())
+    }
+}
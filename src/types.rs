@@ -1,25 +1,366 @@
-use std::cell::RefCell;
+use std::cell::{Cell, Ref, RefCell, RefMut};
 use std::collections::HashMap;
+use std::rc::{Rc, Weak};
 
+use indexmap::IndexMap;
 use lolcode_ast::parser::expression::ASTType;
+use lolcode_ast::parser::statements::how_iz_i::HowIzI;
 
-pub enum RuntimeError {
-    IdentifierNotFound,
-    CannotSRSNonYarn,
+pub use crate::error::{RuntimeError, RuntimeErrorKind, RuntimeResult};
+use crate::runtime::Runtime;
+use crate::source::Source;
 
-    GenericError,
+/// Statements that stop the execution of the enclosing block early.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Interrupt {
+    FoundYr(Value),
+    Gtfo,
+}
 
-    InvalidType,
+pub type NativeFunction = Rc<dyn Fn(&[Value]) -> RuntimeResult<Value>>;
+
+/// A function that can be called with `I IZ`.
+#[derive(Clone)]
+pub enum Function {
+    /// Declared in LOLCODE with `HOW IZ I`. The declaration is shared by every copy of the
+    /// function, so calling it does not copy its body, and runs with the source code it was
+    /// declared in.
+    Lolcode {
+        how_iz_i: Rc<HowIzI>,
+        source: Rc<Source>,
+    },
+    /// Registered by the host program. Calls with a different number of arguments than `arity`
+    /// are rejected before the function is called.
+    Native {
+        arity: usize,
+        function: NativeFunction,
+    },
 }
 
-pub type RuntimeResult<T> = Result<T, RuntimeError>;
+impl Function {
+    pub fn arity(&self) -> usize {
+        match self {
+            Function::Lolcode { how_iz_i, .. } => how_iz_i.arguments.len(),
+            Function::Native { arity, .. } => *arity,
+        }
+    }
+}
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Bukkit(pub HashMap<String, Value>);
+impl std::fmt::Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Function::Lolcode { how_iz_i, .. } => f
+                .debug_struct("Lolcode")
+                .field("how_iz_i", how_iz_i)
+                .finish_non_exhaustive(),
+            Function::Native { arity, .. } => f
+                .debug_struct("Native")
+                .field("arity", arity)
+                .finish_non_exhaustive(),
+        }
+    }
+}
 
-impl Default for Bukkit {
-    fn default() -> Self {
-        Bukkit(HashMap::new())
+/// The slot holding the object a BUKKIT inherits from, set with `IM LIEK <parent>`. Slots and
+/// methods not found in a BUKKIT are looked up in its parent, and so on up the chain.
+pub const PARENT_SLOT: &str = "parent";
+/// The method called when reading a slot that is not found anywhere in the parent chain. Its
+/// return value is stored in the slot, and read instead.
+pub const OMGWTF_METHOD: &str = "omgwtf";
+
+/// An object: a set of named slots, and the methods declared in it with `HOW IZ`. Slots keep the
+/// order they were added in, and a BUKKIT used as a list keeps its items in the slots named `0`,
+/// `1` and so on.
+#[derive(Debug, Clone, Default)]
+pub struct Bukkit {
+    pub slots: IndexMap<String, Value>,
+    pub methods: HashMap<String, Function>,
+    /// The number of items, as of the last slot written or removed through [`BukkitRef`].
+    length: usize,
+    /// The global scope of the library the BUKKIT was imported from, if it was. Its variables
+    /// are the slots of the BUKKIT, so reading and writing them goes to the library.
+    pub(crate) namespace: Option<Weak<Scope<'static>>>,
+}
+
+/// Returns the index a slot name stands for, if it is written the way NUMBRs are.
+fn list_index(name: &str) -> Option<usize> {
+    let canonical =
+        name.bytes().all(|byte| byte.is_ascii_digit()) && (name == "0" || !name.starts_with('0'));
+    canonical.then(|| name.parse().ok()).flatten()
+}
+
+impl Bukkit {
+    /// Returns the number of items of the BUKKIT used as a list: the slots named `0`, `1` and so
+    /// on, up to the first missing index. Other slots, such as `parent`, are not items.
+    ///
+    /// The length is kept up to date as slots are written through [`BukkitRef`], so this takes
+    /// constant time. After [`Bukkit::slots`] was changed directly, it is only counted again if
+    /// the last item is missing or the slot after it is present.
+    pub fn length(&self) -> usize {
+        let last_present = self.length == 0 || self.has_item(self.length - 1);
+        if last_present && !self.has_item(self.length) {
+            return self.length;
+        }
+        let mut present = vec![false; self.slots.len()];
+        for index in self.slots.keys().filter_map(|name| list_index(name)) {
+            if let Some(present) = present.get_mut(index) {
+                *present = true;
+            }
+        }
+        present
+            .iter()
+            .position(|present| !present)
+            .unwrap_or(present.len())
+    }
+
+    fn has_item(&self, index: usize) -> bool {
+        self.slots.contains_key(&index.to_string())
+    }
+
+    /// Sets the slot, keeping the length of the list up to date.
+    fn insert(&mut self, name: &str, value: Value) -> Option<Value> {
+        let mut length = self.length();
+        let previous = self.slots.insert(name.to_string(), value);
+        if list_index(name) == Some(length) {
+            while self.has_item(length) {
+                length += 1;
+            }
+        }
+        self.length = length;
+        previous
+    }
+
+    /// Removes the slot, keeping the other slots in order and the length of the list up to date.
+    fn remove(&mut self, name: &str) -> Option<Value> {
+        let length = self.length();
+        let removed = self.slots.shift_remove(name)?;
+        self.length = list_index(name).map_or(length, |index| index.min(length));
+        Some(removed)
+    }
+
+    pub fn parent(&self) -> Option<BukkitRef> {
+        match self.slots.get(PARENT_SLOT) {
+            Some(Value::Bukkit(parent)) => Some(parent.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the slot, looking it up in the parent chain if this BUKKIT does not have it.
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.lookup(|bukkit| match bukkit.namespace() {
+            Some(namespace) => namespace.variables.borrow().get(name).cloned(),
+            None => bukkit.slots.get(name).cloned(),
+        })
+    }
+
+    fn namespace(&self) -> Option<Rc<Scope<'static>>> {
+        self.namespace.as_ref().and_then(Weak::upgrade)
+    }
+
+    /// Returns the method, looking it up in the parent chain if this BUKKIT does not declare it.
+    pub fn method(&self, name: &str) -> Option<Function> {
+        self.lookup(|bukkit| bukkit.methods.get(name).cloned())
+    }
+
+    /// Walks the parent chain until `find` returns something. A chain that loops back on itself
+    /// ends the lookup instead of walking forever.
+    fn lookup<T>(&self, find: impl Fn(&Bukkit) -> Option<T>) -> Option<T> {
+        if let Some(found) = find(self) {
+            return Some(found);
+        }
+        let mut visited: Vec<BukkitRef> = vec![];
+        let mut parent = self.parent();
+        while let Some(current) = parent {
+            if visited.iter().any(|bukkit| bukkit.ptr_eq(&current)) {
+                return None;
+            }
+            let bukkit = current.borrow();
+            if let Some(found) = find(&bukkit) {
+                return Some(found);
+            }
+            parent = bukkit.parent();
+            drop(bukkit);
+            visited.push(current);
+        }
+        None
+    }
+}
+
+/// A BUKKIT on the heap. Values only hold references to it, so assigning a BUKKIT to another
+/// variable or passing it to a function shares it, and changes made through one reference show
+/// through all the others.
+///
+/// The bytes of the slots written through [`BukkitRef::set_slot`] count towards the memory limit
+/// of the runtime, until they are overwritten or the BUKKIT is freed.
+#[derive(Clone, Default)]
+pub struct BukkitRef(Rc<HeapBukkit>);
+
+#[derive(Default)]
+struct HeapBukkit {
+    bukkit: RefCell<Bukkit>,
+    memory: Cell<usize>,
+    runtime: RefCell<Weak<Runtime>>,
+}
+
+impl Drop for HeapBukkit {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.runtime.get_mut().upgrade() {
+            runtime.release_memory(self.memory.get());
+        }
+    }
+}
+
+impl BukkitRef {
+    pub fn new(bukkit: Bukkit) -> Self {
+        BukkitRef(Rc::new(HeapBukkit {
+            bukkit: RefCell::new(bukkit),
+            memory: Cell::new(0),
+            runtime: RefCell::new(Weak::new()),
+        }))
+    }
+
+    pub fn borrow(&self) -> Ref<'_, Bukkit> {
+        self.0.bukkit.borrow()
+    }
+
+    pub fn borrow_mut(&self) -> RefMut<'_, Bukkit> {
+        self.0.bukkit.borrow_mut()
+    }
+
+    pub(crate) fn try_borrow(&self) -> Option<Ref<'_, Bukkit>> {
+        self.0.bukkit.try_borrow().ok()
+    }
+
+    /// Whether both references point to the same BUKKIT.
+    pub fn ptr_eq(&self, other: &BukkitRef) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+
+    pub(crate) fn address(&self) -> *const () {
+        Rc::as_ptr(&self.0).cast()
+    }
+
+    /// Returns the number of references to the BUKKIT, including this one.
+    pub(crate) fn references(&self) -> usize {
+        Rc::strong_count(&self.0)
+    }
+
+    pub(crate) fn downgrade(&self) -> WeakBukkitRef {
+        WeakBukkitRef(Rc::downgrade(&self.0))
+    }
+
+    /// Sets the slot on this BUKKIT, never on a parent, and charges its bytes to the runtime. The
+    /// first slot hands the BUKKIT to the garbage collector of the runtime, since it can now be
+    /// part of a cycle. The slots of a library are the variables of its global scope, and are set
+    /// there.
+    pub fn set_slot(&self, runtime: &Rc<Runtime>, name: &str, value: Value) -> RuntimeResult<()> {
+        let namespace = self.borrow().namespace();
+        if let Some(namespace) = namespace {
+            let allocated = value.memory_size();
+            let previous = namespace
+                .variables
+                .borrow_mut()
+                .insert(name.to_string(), value);
+            let released = previous.map_or(0, |previous| previous.memory_size());
+            return runtime.charge_memory(released, allocated);
+        }
+
+        let allocated = name.len() + value.memory_size();
+        let previous = self.borrow_mut().insert(name, value);
+        let released = previous.map_or(0, |previous| name.len() + previous.memory_size());
+
+        self.0
+            .memory
+            .set(self.0.memory.get().saturating_sub(released) + allocated);
+        let runtime_changed = !std::ptr::eq(self.0.runtime.borrow().as_ptr(), Rc::as_ptr(runtime));
+        if runtime_changed {
+            *self.0.runtime.borrow_mut() = Rc::downgrade(runtime);
+            runtime.track_bukkit(self);
+        }
+        runtime.charge_memory(released, allocated)
+    }
+
+    /// Removes the slot from this BUKKIT, never from a parent, releasing its bytes. The slots
+    /// after it keep their order.
+    pub fn remove_slot(&self, runtime: &Runtime, name: &str) -> Option<Value> {
+        let removed = self.borrow_mut().remove(name)?;
+        let released = name.len() + removed.memory_size();
+        self.0
+            .memory
+            .set(self.0.memory.get().saturating_sub(released));
+        runtime.release_memory(released);
+        Some(removed)
+    }
+
+    /// Renders the slots in the order they were added, after the variables of a library sorted by
+    /// name. YARN slots are quoted, so they can be told apart from other types, and a BUKKIT
+    /// nested in itself is rendered as `{...}`.
+    fn render(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        rendering: &mut Vec<BukkitRef>,
+    ) -> std::fmt::Result {
+        if rendering.iter().any(|bukkit| bukkit.ptr_eq(self)) {
+            return write!(f, "{{...}}");
+        }
+        rendering.push(self.clone());
+
+        let bukkit = self.borrow();
+        let mut variables: Vec<(String, Value)> = match bukkit.namespace() {
+            Some(namespace) => namespace.variables.borrow().clone().into_iter().collect(),
+            None => vec![],
+        };
+        variables.sort_by(|(left, _), (right, _)| left.cmp(right));
+        let variables = variables.iter().map(|(name, value)| (name, value));
+        write!(f, "{{")?;
+        for (index, (name, value)) in variables.chain(bukkit.slots.iter()).enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            match value {
+                Value::Yarn(yarn) => write!(f, "{}: {:?}", name, yarn)?,
+                Value::Bukkit(nested) => {
+                    write!(f, "{}: ", name)?;
+                    nested.render(f, rendering)?;
+                }
+                value => write!(f, "{}: {}", name, value)?,
+            }
+        }
+        write!(f, "}}")?;
+
+        rendering.pop();
+        Ok(())
+    }
+}
+
+/// A reference that does not keep its BUKKIT alive, held by the garbage collector.
+pub(crate) struct WeakBukkitRef(Weak<HeapBukkit>);
+
+impl WeakBukkitRef {
+    pub fn upgrade(&self) -> Option<BukkitRef> {
+        self.0.upgrade().map(BukkitRef)
+    }
+}
+
+impl std::fmt::Display for BukkitRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.render(f, &mut vec![])
+    }
+}
+
+/// Shows the address rather than the slots, since BUKKITs can contain themselves.
+impl std::fmt::Debug for BukkitRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("BukkitRef")
+            .field(&Rc::as_ptr(&self.0))
+            .finish()
+    }
+}
+
+/// BUKKITs are compared by identity: two references are the same only when they share the BUKKIT.
+impl PartialEq for BukkitRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other)
     }
 }
 
@@ -27,29 +368,83 @@ impl Default for Bukkit {
 pub struct Scope<'a> {
     pub parent_scope: Option<&'a Scope<'a>>,
     pub variables: RefCell<HashMap<String, Value>>,
+    pub functions: RefCell<HashMap<String, Function>>,
     pub it: RefCell<Value>,
+    pub runtime: Rc<Runtime>,
+    /// The source code being executed, which child scopes inherit.
+    pub(crate) source: Rc<Source>,
+    /// The global scopes of the libraries first imported by this global scope, which their
+    /// functions keep running on.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) libraries: RefCell<Vec<Rc<Scope<'static>>>>,
 }
 
 impl Default for Scope<'_> {
     fn default() -> Self {
+        Scope::with_runtime(Rc::new(Runtime::default()))
+    }
+}
+
+impl<'a> Scope<'a> {
+    pub fn with_runtime(runtime: Rc<Runtime>) -> Scope<'a> {
         Self {
             parent_scope: None,
             variables: RefCell::new(HashMap::new()),
+            functions: RefCell::new(HashMap::new()),
             it: RefCell::new(Value::Noob),
+            runtime,
+            source: Rc::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            libraries: RefCell::new(vec![]),
         }
     }
-}
 
-impl<'a> Scope<'a> {
     pub fn from_parent(parent: &'a Scope) -> Scope<'a> {
-        let mut scope = Scope::default();
+        let mut scope = Scope::with_runtime(parent.runtime.clone());
         scope.parent_scope = Some(parent);
+        scope.source = parent.source.clone();
         scope
     }
 
     pub fn child(&'a self) -> Scope<'a> {
         Scope::from_parent(self)
     }
+
+    /// Returns the outermost scope, which holds the global variables and functions.
+    pub fn root(&self) -> &Scope<'a> {
+        let mut scope = self;
+        while let Some(parent) = scope.parent_scope {
+            scope = parent;
+        }
+        scope
+    }
+
+    pub fn set_it(&self, value: Value) -> RuntimeResult<()> {
+        let allocated = value.memory_size();
+        let previous = self.it.replace(value);
+        self.runtime
+            .charge_memory(previous.memory_size(), allocated)
+    }
+
+    /// Moves the value out of `IT`, leaving NOOB in its place.
+    pub fn take_it(&self) -> Value {
+        let value = self.it.replace(Value::Noob);
+        self.runtime.release_memory(value.memory_size());
+        value
+    }
+}
+
+impl Drop for Scope<'_> {
+    fn drop(&mut self) {
+        let released: usize = self
+            .variables
+            .borrow()
+            .values()
+            .map(Value::memory_size)
+            .sum();
+        self.runtime
+            .release_memory(released + self.it.borrow().memory_size());
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -59,16 +454,7 @@ pub enum Value {
     Numbar(f32),
     Troof(bool),
     Noob,
-    Bukkit(Bukkit),
-}
-
-impl std::fmt::Display for Bukkit {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for value in self.0.values().into_iter() {
-            write!(f, "{}", value)?;
-        }
-        Ok(())
-    }
+    Bukkit(BukkitRef),
 }
 
 impl From<ASTType> for Value {
@@ -81,7 +467,7 @@ impl From<&ASTType> for Value {
     fn from(value: &ASTType) -> Self {
         match value {
             ASTType::Yarn => Value::Yarn(String::default()),
-            ASTType::Bukkit => Value::Bukkit(Bukkit::default()),
+            ASTType::Bukkit => Value::Bukkit(BukkitRef::default()),
             ASTType::Numbr => Value::Numbr(0),
             ASTType::Numbar => Value::Numbar(0.0),
             ASTType::Troof => Value::Troof(false),
@@ -115,6 +501,26 @@ impl From<bool> for Value {
 }
 
 impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Noob => "NOOB",
+            Value::Yarn(_) => "YARN",
+            Value::Numbr(_) => "NUMBR",
+            Value::Numbar(_) => "NUMBAR",
+            Value::Troof(_) => "TROOF",
+            Value::Bukkit(_) => "BUKKIT",
+        }
+    }
+
+    /// Number of bytes held by YARNs, as counted by the memory limit. A BUKKIT is only a
+    /// reference here: its slots are counted by the BUKKIT itself.
+    pub fn memory_size(&self) -> usize {
+        match self {
+            Value::Yarn(yarn) => yarn.len(),
+            _ => 0,
+        }
+    }
+
     pub fn to_boolean(&self) -> bool {
         match self {
             Value::Noob => false,
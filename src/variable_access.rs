@@ -1,7 +1,10 @@
 use lolcode_ast::parser::expression::VariableAccess;
 
-use crate::{Identifier, RuntimeError, RuntimeResult, Scope, Value};
-use std::cell::{Ref, RefMut};
+use crate::{BukkitRef, Identifier, RuntimeErrorKind, RuntimeResult, Scope, Value};
+use std::cell::RefMut;
+
+/// The implicit variable holding the value of the last bare expression or function call.
+pub const IT: &str = "IT";
 
 pub fn get_scope_that_has_value<'a>(
     mut scope: &'a Scope,
@@ -19,32 +22,78 @@ pub fn get_scope_that_has_value<'a>(
     }
 }
 
-pub fn get_scope_that_has_value_srs<'a>(
-    scope: &'a Scope,
-    ident_name: &str,
-    is_srs: bool,
+pub fn get_scope_that_has_function<'a>(
+    mut scope: &'a Scope,
+    function_name: &str,
 ) -> Option<&'a Scope<'a>> {
-    let scope = get_scope_that_has_value(scope, ident_name)?;
+    loop {
+        if scope.functions.borrow().contains_key(function_name) {
+            break Some(scope);
+        }
+
+        scope = match scope.parent_scope {
+            None => break None,
+            Some(scope) => scope,
+        };
+    }
+}
+
+/// Returns the name the identifier refers to. For `SRS` identifiers, this is the value of the
+/// variable with the identifier's name, which must be a YARN.
+pub fn resolve_identifier_name(scope: &Scope, identifier: &Identifier) -> RuntimeResult<String> {
+    let ident_name = identifier.to_string_slice();
+
+    if !identifier.is_srs {
+        return Ok(ident_name.to_string());
+    };
 
-    if !is_srs {
-        return Some(scope);
+    let scope = get_scope_that_has_value(scope, ident_name)
+        .ok_or_else(|| RuntimeErrorKind::IdentifierNotFound(ident_name.to_string()))?;
+    let resolved_name = match scope.variables.borrow().get(ident_name).unwrap() {
+        Value::Yarn(yarn) => yarn.clone(),
+        value => return Err(RuntimeErrorKind::CannotSRSNonYarn(value.type_name()).into()),
     };
 
-    let value = Ref::map(scope.variables.borrow(), |v| v.get(ident_name).unwrap());
+    Ok(resolved_name)
+}
 
-    match *value {
-        Value::Yarn(ref yarn) => get_scope_that_has_value(scope, &yarn),
-        _ => return None,
+/// Returns the name of the slot accessed with `'Z`. `SRS` slot names can also be NUMBRs, which
+/// index the slots of a BUKKIT used as a list.
+pub fn resolve_slot_name(scope: &Scope, identifier: &Identifier) -> RuntimeResult<String> {
+    let ident_name = identifier.to_string_slice();
+    if !identifier.is_srs {
+        return Ok(ident_name.to_string());
     }
+
+    let scope = get_scope_that_has_value(scope, ident_name)
+        .ok_or_else(|| RuntimeErrorKind::IdentifierNotFound(ident_name.to_string()))?;
+    let slot_name = match scope.variables.borrow().get(ident_name).unwrap() {
+        Value::Yarn(yarn) => yarn.clone(),
+        Value::Numbr(index) => index.to_string(),
+        value => return Err(RuntimeErrorKind::InvalidSlotName(value.type_name()).into()),
+    };
+
+    Ok(slot_name)
 }
 
 pub fn get_identifier_from_scope<'a>(
     scope: &'a Scope,
     identifier: &Identifier,
 ) -> RuntimeResult<RefMut<'a, Value>> {
-    let ident_name = identifier.to_string_slice();
-    let scope = match get_scope_that_has_value_srs(scope, ident_name, identifier.is_srs) {
-        None => return Err(RuntimeError::IdentifierNotFound),
+    let ident_name = resolve_identifier_name(scope, identifier)?;
+    get_variable_from_scope(scope, &ident_name)
+}
+
+/// Returns the variable with the given name, or `IT`.
+pub fn get_variable_from_scope<'a>(
+    scope: &'a Scope,
+    ident_name: &str,
+) -> RuntimeResult<RefMut<'a, Value>> {
+    if ident_name == IT {
+        return Ok(scope.it.borrow_mut());
+    }
+    let scope = match get_scope_that_has_value(scope, ident_name) {
+        None => return Err(RuntimeErrorKind::IdentifierNotFound(ident_name.to_string()).into()),
         Some(s) => s,
     };
 
@@ -58,68 +107,83 @@ pub fn write_identifier_to_scope<'a>(
     ident_name: &Identifier,
     initial_value: Value,
 ) -> RuntimeResult<()> {
-    scope
+    let ident_name = resolve_identifier_name(scope, ident_name)?;
+    let allocated = initial_value.memory_size();
+    let previous = scope
         .variables
         .borrow_mut()
-        .insert(ident_name.to_string_slice().to_string(), initial_value);
-
-    Ok(())
+        .insert(ident_name, initial_value);
+    scope.runtime.charge_memory(
+        previous.map_or(0, |previous| previous.memory_size()),
+        allocated,
+    )
 }
 
+/// Replaces the value at the variable access with the mutator's result. A slot is always written
+/// on the BUKKIT itself, even when its current value is inherited from a parent.
 pub fn mutate_variable_access(
     scope: &Scope,
     variable_access: &VariableAccess,
-    mutator: Box<dyn FnOnce(&Value) -> RuntimeResult<Value>>,
+    mutator: Box<dyn FnOnce(&Value) -> RuntimeResult<Value> + '_>,
 ) -> RuntimeResult<()> {
     let VariableAccess {
         name: identifier,
         accesses,
     } = variable_access;
 
-    let mut value = &mut *get_identifier_from_scope(scope, identifier)?;
-
-    for access in accesses.into_iter() {
-        match value {
-            Value::Bukkit(bukkit) => {
-                value = bukkit
-                    .0
-                    .get_mut(access.to_string_slice())
-                    .ok_or(RuntimeError::InvalidType)?;
-            }
-            _ => return Err(RuntimeError::InvalidType),
-        };
-    }
+    let Some((slot, path)) = accesses.split_last() else {
+        let mut value = get_identifier_from_scope(scope, identifier)?;
+        let new_value = mutator(&value)?;
+        let (released, allocated) = (value.memory_size(), new_value.memory_size());
+        *value = new_value;
+        drop(value);
+        return scope.runtime.charge_memory(released, allocated);
+    };
 
-    let new_value = mutator(value)?;
-    *value = new_value;
+    let owner = get_path_from_scope(scope, identifier, path)?;
+    let bukkit = expect_bukkit(owner)?;
+    let slot_name = resolve_slot_name(scope, slot)?;
+    let current = bukkit
+        .borrow()
+        .get(&slot_name)
+        .ok_or_else(|| RuntimeErrorKind::SlotNotFound(slot_name.clone()))?;
+    let new_value = mutator(&current)?;
+    bukkit.set_slot(&scope.runtime, &slot_name, new_value)
+}
 
-    Ok(())
+pub fn expect_bukkit(value: Value) -> RuntimeResult<BukkitRef> {
+    match value {
+        Value::Bukkit(bukkit) => Ok(bukkit),
+        value => Err(RuntimeErrorKind::InvalidType {
+            expected: "BUKKIT",
+            found: value.type_name(),
+        }
+        .into()),
+    }
 }
 
-pub fn get_variable_access_from_scope<'a>(
-    scope: &'a Scope,
+/// Returns the value at the variable access. Slots are looked up through the parent chain of
+/// each BUKKIT.
+pub fn get_variable_access_from_scope(
+    scope: &Scope,
     variable_access: &VariableAccess,
-) -> RuntimeResult<RefMut<'a, Value>> {
-    let VariableAccess {
-        name: identifier,
-        accesses,
-    } = variable_access;
+) -> RuntimeResult<Value> {
+    get_path_from_scope(scope, &variable_access.name, &variable_access.accesses)
+}
 
-    let mut value = get_identifier_from_scope(scope, identifier)?;
-
-    for access in accesses.into_iter() {
-        match *value {
-            Value::Bukkit(ref bukkit) => {
-                if bukkit.0.contains_key(access.to_string_slice()) {
-                    return Err(RuntimeError::InvalidType);
-                };
-                value = RefMut::map(value, |bukit| match bukit {
-                    Value::Bukkit(bukkit) => bukkit.0.get_mut(access.to_string_slice()).unwrap(),
-                    _ => unreachable!(),
-                });
-            }
-            _ => return Err(RuntimeError::InvalidType),
-        };
+fn get_path_from_scope(
+    scope: &Scope,
+    identifier: &Identifier,
+    accesses: &[Identifier],
+) -> RuntimeResult<Value> {
+    let mut value = get_identifier_from_scope(scope, identifier)?.clone();
+
+    for access in accesses.iter() {
+        let slot_name = resolve_slot_name(scope, access)?;
+        value = expect_bukkit(value)?
+            .borrow()
+            .get(&slot_name)
+            .ok_or(RuntimeErrorKind::SlotNotFound(slot_name))?;
     }
     Ok(value)
 }
@@ -0,0 +1,723 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use lolcode_ast::parser::expression::{ASTExpression, Identifier, VariableAccess};
+use lolcode_ast::parser::statements::assignment::VariableAssignment;
+use lolcode_ast::parser::statements::bukkit_set_slot::BukkitSetSlot;
+use lolcode_ast::parser::statements::how_iz_i::HowIzI;
+use lolcode_ast::parser::statements::i_has_a::{IHasA, IHasAInitialValue};
+use lolcode_ast::parser::statements::i_is::IIz;
+use lolcode_ast::parser::statements::im_in_yr::{
+    ImInYr, LoopCondition, LoopIterationOperation, LoopOperation,
+};
+use lolcode_ast::parser::statements::o_rly::ORly;
+use lolcode_ast::parser::statements::visible::Visible;
+use lolcode_ast::parser::statements::wtf::Wtf;
+use lolcode_ast::parser::statements::ASTNode;
+use lolcode_ast::parser::ASTBlock;
+
+use super::{Function, Instruction, Program, Slot, SlotName};
+use crate::error::{expression_span, node_span, Span};
+use crate::operations::{literal_value, BinaryOperation};
+use crate::source::LibraryCalls;
+use crate::types::{RuntimeErrorKind, Value};
+use crate::variable_access::IT;
+use crate::yarn::{parse_yarn, yarn_literal, YarnPart};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompileError {
+    /// `SRS` identifiers used as variable names name them at runtime, so they cannot be resolved
+    /// to slots. `SRS` slot names of BUKKITs are supported.
+    SrsNotSupported(String),
+    /// The parser reported an error for this statement.
+    ParseError(String),
+}
+
+impl std::fmt::Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompileError::SrsNotSupported(name) => write!(
+                f,
+                "SRS {} cannot be compiled to bytecode, use the tree-walking engine instead",
+                name
+            ),
+            CompileError::ParseError(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+type CompileResult<T> = Result<T, CompileError>;
+
+/// Where `GTFO` and `FOUND YR` jump to.
+enum ExitTarget {
+    /// `GTFO` breaks out of the loop. The jumps are patched once the end of the loop is known.
+    Loop(Vec<usize>),
+    /// `GTFO` breaks out of the `WTF?`.
+    Wtf(Vec<usize>),
+    /// `GTFO` and `FOUND YR` return from the function.
+    Function,
+    /// At the top level, `GTFO` and `FOUND YR` only skip the rest of the current statement.
+    TopLevel(Vec<usize>),
+}
+
+struct CompileScope {
+    variables: HashMap<String, usize>,
+    it: usize,
+}
+
+struct FunctionCompiler<'a> {
+    function: Function,
+    scopes: Vec<CompileScope>,
+    exits: Vec<ExitTarget>,
+    span: Option<Span>,
+    is_main: bool,
+    /// Variables declared in the outermost scope of the program.
+    globals: &'a HashMap<String, usize>,
+    functions: &'a mut Vec<Function>,
+    library_calls: &'a LibraryCalls,
+}
+
+fn slot_name(identifier: &Identifier) -> CompileResult<String> {
+    let name = identifier.to_string_slice().to_string();
+    if identifier.is_srs {
+        return Err(CompileError::SrsNotSupported(name));
+    }
+    Ok(name)
+}
+
+/// Compiles a program to bytecode. The program is function 0 of the result.
+pub fn compile(block: &ASTBlock) -> CompileResult<Program> {
+    compile_with_library_calls(block, &LibraryCalls::default())
+}
+
+/// Compiles a program to bytecode, calling the methods of native libraries as built-in functions.
+pub(crate) fn compile_with_library_calls(
+    block: &ASTBlock,
+    library_calls: &LibraryCalls,
+) -> CompileResult<Program> {
+    // Variables declared at the top level are resolved as globals from inside functions, even
+    // if the function is declared before them.
+    let mut globals = HashMap::new();
+    let mut global_names = vec!["IT".to_string()];
+    for node in block.0.iter() {
+        if let ASTNode::IHasA(IHasA { identifier, .. }) = node {
+            let name = slot_name(identifier)?;
+            if !globals.contains_key(&name) {
+                globals.insert(name.clone(), global_names.len());
+                global_names.push(name);
+            }
+        }
+    }
+
+    let mut functions = vec![Function::default()];
+    let mut compiler = FunctionCompiler {
+        function: Function {
+            name: "main".to_string(),
+            locals: global_names.len(),
+            slot_names: global_names,
+            ..Function::default()
+        },
+        scopes: vec![CompileScope {
+            variables: globals.clone(),
+            it: 0,
+        }],
+        exits: vec![],
+        span: None,
+        is_main: true,
+        globals: &globals,
+        functions: &mut functions,
+        library_calls,
+    };
+
+    compiler.emit(Instruction::Push(Value::Noob));
+    compiler.emit(Instruction::Declare(Slot::Local(0)));
+    for node in block.0.iter() {
+        compiler.exits.push(ExitTarget::TopLevel(vec![]));
+        compiler.compile_statement(node)?;
+        match compiler.exits.pop() {
+            Some(ExitTarget::TopLevel(jumps)) => compiler.patch_jumps(jumps),
+            _ => unreachable!(),
+        }
+    }
+    compiler.emit(Instruction::Push(Value::Noob));
+    compiler.emit(Instruction::Return);
+
+    let main = compiler.function;
+    functions[0] = main;
+    Ok(Program { functions })
+}
+
+impl<'a> FunctionCompiler<'a> {
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.function.instructions.push(instruction);
+        self.function.spans.push(self.span);
+        self.function.instructions.len() - 1
+    }
+
+    fn next_address(&self) -> usize {
+        self.function.instructions.len()
+    }
+
+    fn patch_jump(&mut self, jump: usize) {
+        let target = self.next_address();
+        match &mut self.function.instructions[jump] {
+            Instruction::Jump(address)
+            | Instruction::JumpIfFalse(address)
+            | Instruction::JumpIfTrue(address) => *address = target,
+            _ => unreachable!(),
+        }
+    }
+
+    fn patch_jumps(&mut self, jumps: Vec<usize>) {
+        for jump in jumps {
+            self.patch_jump(jump);
+        }
+    }
+
+    fn new_slot(&mut self, name: String) -> usize {
+        self.function.slot_names.push(name);
+        self.function.locals += 1;
+        self.function.locals - 1
+    }
+
+    /// Opens a new scope, with its own `IT` initialized to NOOB.
+    fn push_scope(&mut self) {
+        let it = self.new_slot("IT".to_string());
+        self.emit(Instruction::Push(Value::Noob));
+        self.emit(Instruction::Declare(Slot::Local(it)));
+        self.scopes.push(CompileScope {
+            variables: HashMap::new(),
+            it,
+        });
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn it_slot(&self) -> Slot {
+        Slot::Local(self.scopes.last().unwrap().it)
+    }
+
+    fn resolve(&self, name: &str) -> Option<Slot> {
+        if name == IT {
+            return Some(self.it_slot());
+        }
+        for scope in self.scopes.iter().rev() {
+            if let Some(slot) = scope.variables.get(name) {
+                return Some(Slot::Local(*slot));
+            }
+        }
+        if self.is_main {
+            return None;
+        }
+        self.globals.get(name).map(|slot| Slot::Global(*slot))
+    }
+
+    fn declare(&mut self, name: String) -> Slot {
+        if let Some(slot) = self.scopes.last().unwrap().variables.get(&name) {
+            return Slot::Local(*slot);
+        }
+        let slot = self.new_slot(name.clone());
+        self.scopes.last_mut().unwrap().variables.insert(name, slot);
+        Slot::Local(slot)
+    }
+
+    /// Resolves a variable access to its slot and the path of BUKKIT slots after it. If the
+    /// variable, or a variable naming one of the slots with `SRS`, cannot be resolved, emits an
+    /// instruction that fails at runtime and returns None.
+    fn resolve_access(
+        &mut self,
+        variable_access: &VariableAccess,
+    ) -> CompileResult<Option<(Slot, Rc<[SlotName]>)>> {
+        let VariableAccess { name, accesses } = variable_access;
+        let name = slot_name(name)?;
+        let Some(slot) = self.resolve(&name) else {
+            self.emit(Instruction::Fail(RuntimeErrorKind::IdentifierNotFound(
+                name,
+            )));
+            return Ok(None);
+        };
+        let mut path = Vec::with_capacity(accesses.len());
+        for access in accesses.iter() {
+            match self.resolve_slot_name(access) {
+                Some(slot_name) => path.push(slot_name),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some((slot, path.into())))
+    }
+
+    /// Resolves the name of a BUKKIT slot. An `SRS` name is resolved to the variable holding it,
+    /// and if that variable cannot be resolved, emits an instruction that fails at runtime and
+    /// returns None.
+    fn resolve_slot_name(&mut self, identifier: &Identifier) -> Option<SlotName> {
+        let name = identifier.to_string_slice().to_string();
+        if !identifier.is_srs {
+            return Some(SlotName::Named(name));
+        }
+        match self.resolve(&name) {
+            Some(slot) => Some(SlotName::Srs(slot)),
+            None => {
+                self.emit(Instruction::Fail(RuntimeErrorKind::IdentifierNotFound(
+                    name,
+                )));
+                None
+            }
+        }
+    }
+
+    fn compile_block(&mut self, block: &ASTBlock) -> CompileResult<()> {
+        for node in block.0.iter() {
+            self.compile_statement(node)?;
+        }
+        Ok(())
+    }
+
+    fn compile_scoped_block(&mut self, block: &ASTBlock) -> CompileResult<()> {
+        self.push_scope();
+        self.compile_block(block)?;
+        self.pop_scope();
+        Ok(())
+    }
+
+    fn compile_statement(&mut self, node: &ASTNode) -> CompileResult<()> {
+        let previous_span = self.span;
+        self.span = node_span(node).or(previous_span);
+        self.compile_node(node)?;
+        self.span = previous_span;
+        Ok(())
+    }
+
+    fn compile_node(&mut self, node: &ASTNode) -> CompileResult<()> {
+        match node {
+            ASTNode::HAI(_) | ASTNode::KTHXBYE(_) => {}
+            ASTNode::IHasA(IHasA {
+                identifier,
+                initial_value,
+            }) => {
+                match initial_value {
+                    Some(IHasAInitialValue::Expression(e)) => self.compile_expression(e)?,
+                    Some(IHasAInitialValue::Type(t)) => {
+                        self.emit(Instruction::PushDefault(t.clone()));
+                    }
+                    None => {
+                        self.emit(Instruction::Push(Value::Noob));
+                    }
+                };
+                let slot = self.declare(slot_name(identifier)?);
+                self.emit(Instruction::Declare(slot));
+            }
+            ASTNode::ImInYr(ImInYr {
+                on_iteration,
+                condition,
+                code_block,
+                ..
+            }) => {
+                // The whole loop runs in a single child scope, like the tree-walker does.
+                self.push_scope();
+                let start = self.next_address();
+                let condition_jump = match condition {
+                    None => None,
+                    Some(LoopCondition::TIL(expression)) => {
+                        self.compile_expression(expression)?;
+                        Some(self.emit(Instruction::JumpIfTrue(0)))
+                    }
+                    Some(LoopCondition::WILE(expression)) => {
+                        self.compile_expression(expression)?;
+                        Some(self.emit(Instruction::JumpIfFalse(0)))
+                    }
+                };
+
+                self.exits.push(ExitTarget::Loop(vec![]));
+                self.compile_block(code_block)?;
+                let breaks = match self.exits.pop() {
+                    Some(ExitTarget::Loop(breaks)) => breaks,
+                    _ => unreachable!(),
+                };
+
+                if let Some(LoopIterationOperation { operation, operand }) = on_iteration {
+                    let amount = match operation {
+                        LoopOperation::UPPIN(_) => 1,
+                        LoopOperation::NERFIN(_) => -1,
+                    };
+                    if let Some((slot, path)) = self.resolve_access(operand)? {
+                        self.emit(Instruction::Step(slot, path, amount));
+                    }
+                }
+                self.emit(Instruction::Jump(start));
+
+                if let Some(jump) = condition_jump {
+                    self.patch_jump(jump);
+                }
+                self.patch_jumps(breaks);
+                self.pop_scope();
+            }
+            ASTNode::BukkitSetSlot(BukkitSetSlot {
+                bukkit,
+                slot_name: name,
+                value,
+            }) => {
+                self.compile_expression(value)?;
+                if let Some((slot, path)) = self.resolve_access(bukkit)? {
+                    if let Some(name) = self.resolve_slot_name(name) {
+                        self.emit(Instruction::SetBukkitSlot(slot, path, name));
+                    }
+                }
+            }
+            ASTNode::VariableAssignment(VariableAssignment {
+                variable_access,
+                expression,
+            }) => {
+                self.compile_expression(expression)?;
+                if let Some((slot, path)) = self.resolve_access(variable_access)? {
+                    if path.is_empty() {
+                        self.emit(Instruction::Store(slot));
+                    } else {
+                        self.emit(Instruction::StoreAccess(slot, path));
+                    }
+                }
+            }
+            ASTNode::Visible(Visible(expressions, has_exclamation)) => {
+                for (index, expression) in expressions.iter().enumerate() {
+                    self.compile_expression(expression)?;
+                    self.emit(Instruction::Print(index != 0));
+                }
+                if has_exclamation.is_none() {
+                    self.emit(Instruction::PrintNewline);
+                }
+                self.emit(Instruction::Flush);
+            }
+            ASTNode::FoundYr(expression) => {
+                self.compile_expression(expression)?;
+                self.compile_exit(true);
+            }
+            ASTNode::Gtfo(_) => self.compile_exit(false),
+            ASTNode::Wtf(Wtf { omg, omg_wtf }) => {
+                self.exits.push(ExitTarget::Wtf(vec![]));
+                // Jump from the end of a case body into the next one, so cases fall through.
+                let mut fallthrough: Option<usize> = None;
+                for (expression, block) in omg.iter() {
+                    self.compile_expression(expression)?;
+                    self.emit(Instruction::Load(self.it_slot()));
+                    self.emit(Instruction::Equal);
+                    let next_case = self.emit(Instruction::JumpIfFalse(0));
+                    if let Some(jump) = fallthrough.take() {
+                        self.patch_jump(jump);
+                    }
+                    self.compile_scoped_block(block)?;
+                    fallthrough = Some(self.emit(Instruction::Jump(0)));
+                    self.patch_jump(next_case);
+                }
+                if let Some(jump) = fallthrough.take() {
+                    self.patch_jump(jump);
+                }
+                if let Some(block) = omg_wtf {
+                    self.compile_scoped_block(block)?;
+                }
+                match self.exits.pop() {
+                    Some(ExitTarget::Wtf(breaks)) => self.patch_jumps(breaks),
+                    _ => unreachable!(),
+                }
+            }
+            ASTNode::ORly(ORly {
+                if_true,
+                if_false,
+                mebbes,
+            }) => {
+                let mut end_jumps = vec![];
+                if let Some(block) = if_true {
+                    self.emit(Instruction::Load(self.it_slot()));
+                    let next = self.emit(Instruction::JumpIfFalse(0));
+                    self.compile_scoped_block(block)?;
+                    end_jumps.push(self.emit(Instruction::Jump(0)));
+                    self.patch_jump(next);
+                }
+                for (expression, block) in mebbes.iter() {
+                    self.compile_expression(expression)?;
+                    let next = self.emit(Instruction::JumpIfFalse(0));
+                    self.compile_scoped_block(block)?;
+                    end_jumps.push(self.emit(Instruction::Jump(0)));
+                    self.patch_jump(next);
+                }
+                if let Some(block) = if_false {
+                    self.compile_scoped_block(block)?;
+                }
+                self.patch_jumps(end_jumps);
+            }
+            ASTNode::IIz(IIz { name, arguments }) => {
+                for argument in arguments.iter() {
+                    self.compile_expression(argument)?;
+                }
+                self.emit(Instruction::Call(
+                    self.library_calls.function_name(name),
+                    arguments.len(),
+                ));
+                self.emit(Instruction::Store(self.it_slot()));
+            }
+            ASTNode::HowIzI(how_iz_i) => {
+                let index = self.compile_function(how_iz_i)?;
+                self.emit(Instruction::DefineFunction(index));
+            }
+            ASTNode::Gimmeh(variable_access) => {
+                if let Some((slot, path)) = self.resolve_access(variable_access)? {
+                    self.emit(Instruction::Gimmeh(slot, path));
+                }
+            }
+            ASTNode::Expression(expression) => {
+                self.compile_expression(expression)?;
+                self.emit(Instruction::Store(self.it_slot()));
+            }
+            ASTNode::ASTError(error) => {
+                return Err(CompileError::ParseError(format!("{:?}", error)));
+            }
+        };
+        Ok(())
+    }
+
+    /// Compiles a `GTFO`, or a `FOUND YR` whose value is already on the stack.
+    fn compile_exit(&mut self, has_value: bool) {
+        let target = if has_value {
+            // FOUND YR skips loops and WTF?s, and returns from the function.
+            self.exits
+                .iter()
+                .rposition(|exit| matches!(exit, ExitTarget::Function | ExitTarget::TopLevel(_)))
+        } else {
+            self.exits.len().checked_sub(1)
+        };
+        let target = target.expect("statements are always compiled inside an exit target");
+
+        match self.exits[target] {
+            ExitTarget::Function => {
+                if !has_value {
+                    self.emit(Instruction::Push(Value::Noob));
+                }
+                self.emit(Instruction::Return);
+            }
+            _ => {
+                if has_value {
+                    self.emit(Instruction::Pop);
+                }
+                let jump = self.emit(Instruction::Jump(0));
+                match &mut self.exits[target] {
+                    ExitTarget::Loop(jumps)
+                    | ExitTarget::Wtf(jumps)
+                    | ExitTarget::TopLevel(jumps) => jumps.push(jump),
+                    ExitTarget::Function => unreachable!(),
+                }
+            }
+        }
+    }
+
+    fn compile_function(&mut self, how_iz_i: &HowIzI) -> CompileResult<usize> {
+        let HowIzI {
+            name,
+            arguments: parameters,
+            body,
+            ..
+        } = how_iz_i;
+
+        // Reserve the index first, so recursive functions can be compiled.
+        let index = self.functions.len();
+        self.functions.push(Function::default());
+
+        let mut compiler = FunctionCompiler {
+            function: Function {
+                name: name.to_string_slice().to_string(),
+                parameters: parameters.len(),
+                ..Function::default()
+            },
+            scopes: vec![],
+            exits: vec![ExitTarget::Function],
+            span: self.span,
+            is_main: false,
+            globals: self.globals,
+            functions: &mut *self.functions,
+            library_calls: self.library_calls,
+        };
+
+        // Parameters take the first slots, followed by the function scope's IT.
+        let mut variables = HashMap::new();
+        for parameter in parameters.iter() {
+            let name = slot_name(parameter)?;
+            let slot = compiler.new_slot(name.clone());
+            variables.insert(name, slot);
+        }
+        let it = compiler.new_slot("IT".to_string());
+        compiler.scopes.push(CompileScope { variables, it });
+        compiler.emit(Instruction::Push(Value::Noob));
+        compiler.emit(Instruction::Declare(Slot::Local(it)));
+
+        compiler.compile_block(body)?;
+        // Falling off the end of the function returns IT.
+        compiler.emit(Instruction::Load(Slot::Local(it)));
+        compiler.emit(Instruction::Return);
+
+        let function = compiler.function;
+        self.functions[index] = function;
+        Ok(index)
+    }
+
+    fn compile_expression(&mut self, expression: &ASTExpression) -> CompileResult<()> {
+        let previous_span = self.span;
+        self.span = expression_span(expression).or(previous_span);
+        self.compile_expression_inner(expression)?;
+        self.span = previous_span;
+        Ok(())
+    }
+
+    fn compile_binary(
+        &mut self,
+        operation: BinaryOperation,
+        left: &ASTExpression,
+        right: &ASTExpression,
+    ) -> CompileResult<()> {
+        self.compile_expression(left)?;
+        self.compile_expression(right)?;
+        self.emit(Instruction::Binary(operation));
+        Ok(())
+    }
+
+    /// Compiles a YARN literal. Escape sequences are resolved at compile time, while each
+    /// `:{var}` is loaded and concatenated at runtime.
+    fn compile_yarn(&mut self, raw: &str) {
+        let parts = match parse_yarn(raw) {
+            Ok(parts) => parts,
+            Err(kind) => {
+                self.emit(Instruction::Fail(kind));
+                self.emit(Instruction::Push(Value::Noob));
+                return;
+            }
+        };
+
+        if let [YarnPart::Text(text)] = parts.as_slice() {
+            self.emit(Instruction::Push(Value::Yarn(text.clone())));
+            return;
+        }
+
+        for part in parts.iter() {
+            match part {
+                YarnPart::Text(text) => {
+                    self.emit(Instruction::Push(Value::Yarn(text.clone())));
+                }
+                YarnPart::Variable(name) => match self.resolve(name) {
+                    Some(slot) => {
+                        self.emit(Instruction::Load(slot));
+                        self.emit(Instruction::ToYarn);
+                    }
+                    None => {
+                        self.emit(Instruction::Fail(RuntimeErrorKind::IdentifierNotFound(
+                            name.clone(),
+                        )));
+                        self.emit(Instruction::Push(Value::Noob));
+                    }
+                },
+            }
+        }
+        self.emit(Instruction::Concat(parts.len()));
+    }
+
+    /// Compiles a short-circuiting chain of operands. With `stop_on` false this is an `ALL OF`,
+    /// and with `stop_on` true an `ANY OF`.
+    fn compile_short_circuit<'b>(
+        &mut self,
+        operands: impl Iterator<Item = &'b ASTExpression>,
+        stop_on: bool,
+    ) -> CompileResult<()> {
+        let mut stops = vec![];
+        for operand in operands {
+            self.compile_expression(operand)?;
+            stops.push(self.emit(if stop_on {
+                Instruction::JumpIfTrue(0)
+            } else {
+                Instruction::JumpIfFalse(0)
+            }));
+        }
+        self.emit(Instruction::Push(Value::Troof(!stop_on)));
+        let end = self.emit(Instruction::Jump(0));
+        self.patch_jumps(stops);
+        self.emit(Instruction::Push(Value::Troof(stop_on)));
+        self.patch_jump(end);
+        Ok(())
+    }
+
+    fn compile_expression_inner(&mut self, expression: &ASTExpression) -> CompileResult<()> {
+        match expression {
+            ASTExpression::LiteralValue(token) => match yarn_literal(token) {
+                Some(raw) => self.compile_yarn(raw),
+                None => {
+                    self.emit(Instruction::Push(literal_value(token)));
+                }
+            },
+            ASTExpression::VariableAccess(variable_access) => {
+                match self.resolve_access(variable_access)? {
+                    Some((slot, path)) => {
+                        self.emit(Instruction::Load(slot));
+                        for slot_name in path.iter() {
+                            self.emit(Instruction::GetBukkitSlot(slot_name.clone()));
+                        }
+                    }
+                    // The instruction emitted by resolve_access always fails, but the stack
+                    // must still be balanced.
+                    None => {
+                        self.emit(Instruction::Push(Value::Noob));
+                    }
+                }
+            }
+            ASTExpression::BothOf(left, right) => {
+                self.compile_short_circuit([&**left, &**right].into_iter(), false)?
+            }
+            ASTExpression::EitherOf(left, right) => {
+                self.compile_short_circuit([&**left, &**right].into_iter(), true)?
+            }
+            ASTExpression::AllOf(values) => self.compile_short_circuit(values.iter(), false)?,
+            ASTExpression::AnyOf(values) => self.compile_short_circuit(values.iter(), true)?,
+            ASTExpression::WonOf(left, right) => {
+                self.compile_binary(BinaryOperation::WonOf, left, right)?
+            }
+            ASTExpression::Not(expression) => {
+                self.compile_expression(expression)?;
+                self.emit(Instruction::Not);
+            }
+            ASTExpression::SumOf(left, right) => {
+                self.compile_binary(BinaryOperation::SumOf, left, right)?
+            }
+            ASTExpression::DiffOf(left, right) => {
+                self.compile_binary(BinaryOperation::DiffOf, left, right)?
+            }
+            ASTExpression::ProduktOf(left, right) => {
+                self.compile_binary(BinaryOperation::ProduktOf, left, right)?
+            }
+            ASTExpression::QuoshuntOf(left, right) => {
+                self.compile_binary(BinaryOperation::QuoshuntOf, left, right)?
+            }
+            ASTExpression::ModOf(left, right) => {
+                self.compile_binary(BinaryOperation::ModOf, left, right)?
+            }
+            ASTExpression::BiggrOf(left, right) => {
+                self.compile_binary(BinaryOperation::BiggrOf, left, right)?
+            }
+            ASTExpression::SmallrOf(left, right) => {
+                self.compile_binary(BinaryOperation::SmallrOf, left, right)?
+            }
+            ASTExpression::BothSaem(left, right) => {
+                self.compile_binary(BinaryOperation::BothSaem, left, right)?
+            }
+            ASTExpression::Diffrint(left, right) => {
+                self.compile_binary(BinaryOperation::Diffrint, left, right)?
+            }
+            ASTExpression::Smoosh(values) => {
+                for value in values.iter() {
+                    self.compile_expression(value)?;
+                    self.emit(Instruction::ToYarn);
+                }
+                self.emit(Instruction::Concat(values.len()));
+            }
+            ASTExpression::Maek(expression, ast_type) => {
+                self.compile_expression(expression)?;
+                self.emit(Instruction::Cast(ast_type.clone()));
+            }
+        };
+        Ok(())
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::{Instruction, Program, Slot, SlotName};
+use crate::builtins;
+use crate::operations::binary_operation;
+use crate::runtime::Runtime;
+use crate::types::{RuntimeErrorKind, RuntimeResult, Value};
+use crate::variable_access::expect_bukkit;
+
+struct Frame {
+    function: usize,
+    ip: usize,
+    locals: Vec<Option<Value>>,
+}
+
+/// Stack-based virtual machine that runs a compiled [`Program`].
+pub struct Machine<'a> {
+    program: &'a Program,
+    /// The first frame is the program itself, whose locals are the globals.
+    frames: Vec<Frame>,
+    stack: Vec<Value>,
+    defined_functions: HashMap<String, usize>,
+    runtime: Rc<Runtime>,
+}
+
+impl<'a> Machine<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        Machine::with_runtime(program, Rc::new(Runtime::default()))
+    }
+
+    pub fn with_runtime(program: &'a Program, runtime: Rc<Runtime>) -> Self {
+        Machine {
+            program,
+            frames: vec![Frame {
+                function: 0,
+                ip: 0,
+                locals: vec![None; program.functions[0].locals],
+            }],
+            stack: vec![],
+            defined_functions: HashMap::new(),
+            runtime,
+        }
+    }
+
+    /// Runs the program until it ends, returning the value it returned.
+    pub fn run(&mut self) -> RuntimeResult<Value> {
+        let program = self.program;
+        loop {
+            let frame = self.frames.last_mut().unwrap();
+            let function = &program.functions[frame.function];
+            let ip = frame.ip;
+            frame.ip += 1;
+
+            let result = self
+                .runtime
+                .step()
+                .and_then(|_| self.execute(&function.instructions[ip]));
+            match result {
+                Ok(Some(value)) => return Ok(value),
+                Ok(None) => {}
+                Err(error) => return Err(error.at(function.spans[ip])),
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack
+            .pop()
+            .expect("the compiler keeps the stack balanced")
+    }
+
+    fn slot(&mut self, slot: Slot) -> &mut Option<Value> {
+        match slot {
+            Slot::Local(index) => &mut self.frames.last_mut().unwrap().locals[index],
+            Slot::Global(index) => &mut self.frames[0].locals[index],
+        }
+    }
+
+    fn slot_name(&self, slot: Slot) -> String {
+        let (function, index) = match slot {
+            Slot::Local(index) => (self.frames.last().unwrap().function, index),
+            Slot::Global(index) => (0, index),
+        };
+        self.program.functions[function].slot_names[index].clone()
+    }
+
+    fn declared_value(&mut self, slot: Slot) -> RuntimeResult<&mut Value> {
+        if self.slot(slot).is_none() {
+            return Err(RuntimeErrorKind::IdentifierNotFound(self.slot_name(slot)).into());
+        }
+        Ok(self.slot(slot).as_mut().unwrap())
+    }
+
+    /// Returns the name of a BUKKIT slot, reading it from its variable if it is an `SRS` name.
+    fn resolve_slot_name(&mut self, slot_name: &SlotName) -> RuntimeResult<String> {
+        match slot_name {
+            SlotName::Named(name) => Ok(name.clone()),
+            SlotName::Srs(slot) => match self.declared_value(*slot)? {
+                Value::Yarn(yarn) => Ok(yarn.clone()),
+                Value::Numbr(index) => Ok(index.to_string()),
+                value => Err(RuntimeErrorKind::InvalidSlotName(value.type_name()).into()),
+            },
+        }
+    }
+
+    /// Returns the value of a slot of a BUKKIT.
+    fn get_slot(&mut self, bukkit: Value, slot_name: &SlotName) -> RuntimeResult<Value> {
+        let bukkit = expect_bukkit(bukkit)?;
+        let slot_name = self.resolve_slot_name(slot_name)?;
+        let value = bukkit.borrow().get(&slot_name);
+        value.ok_or_else(|| RuntimeErrorKind::SlotNotFound(slot_name).into())
+    }
+
+    /// Returns the value at the given path of BUKKIT slots inside a variable.
+    fn access(&mut self, slot: Slot, path: &[SlotName]) -> RuntimeResult<Value> {
+        let mut value = self.declared_value(slot)?.clone();
+        for slot_name in path.iter() {
+            value = self.get_slot(value, slot_name)?;
+        }
+        Ok(value)
+    }
+
+    /// Replaces the value at the given path with the mutator's result. Slots are always written
+    /// on the BUKKIT itself, even when their current value is inherited from a parent.
+    fn mutate(
+        &mut self,
+        slot: Slot,
+        path: &[SlotName],
+        mutator: impl FnOnce(&Value) -> RuntimeResult<Value>,
+    ) -> RuntimeResult<()> {
+        let Some((slot_name, path)) = path.split_last() else {
+            let target = self.declared_value(slot)?;
+            let new_value = mutator(target)?;
+            let allocated = new_value.memory_size();
+            let previous = std::mem::replace(target, new_value);
+            return self
+                .runtime
+                .charge_memory(previous.memory_size(), allocated);
+        };
+
+        let bukkit = expect_bukkit(self.access(slot, path)?)?;
+        let slot_name = self.resolve_slot_name(slot_name)?;
+        let current = bukkit
+            .borrow()
+            .get(&slot_name)
+            .ok_or_else(|| RuntimeErrorKind::SlotNotFound(slot_name.clone()))?;
+        bukkit.set_slot(&self.runtime, &slot_name, mutator(&current)?)
+    }
+
+    fn jump(&mut self, address: usize) {
+        self.frames.last_mut().unwrap().ip = address;
+    }
+
+    /// Executes a single instruction. Returns the program's return value once it ends.
+    fn execute(&mut self, instruction: &Instruction) -> RuntimeResult<Option<Value>> {
+        match instruction {
+            Instruction::Push(value) => self.stack.push(value.clone()),
+            Instruction::PushDefault(ast_type) => self.stack.push(ast_type.into()),
+            Instruction::Pop => {
+                self.pop();
+            }
+            Instruction::Load(slot) => {
+                let value = self.declared_value(*slot)?.clone();
+                self.stack.push(value);
+            }
+            Instruction::Store(slot) => {
+                let value = self.pop();
+                let allocated = value.memory_size();
+                let previous = std::mem::replace(self.declared_value(*slot)?, value);
+                self.runtime
+                    .charge_memory(previous.memory_size(), allocated)?;
+            }
+            Instruction::Declare(slot) => {
+                let value = self.pop();
+                let allocated = value.memory_size();
+                let previous = self.slot(*slot).replace(value);
+                self.runtime.charge_memory(
+                    previous.map_or(0, |previous| previous.memory_size()),
+                    allocated,
+                )?;
+            }
+            Instruction::StoreAccess(slot, path) => {
+                let value = self.pop();
+                self.mutate(*slot, path, |_| Ok(value))?;
+            }
+            Instruction::SetBukkitSlot(slot, path, slot_name) => {
+                let value = self.pop();
+                let bukkit = expect_bukkit(self.access(*slot, path)?)?;
+                let slot_name = self.resolve_slot_name(slot_name)?;
+                bukkit.set_slot(&self.runtime, &slot_name, value)?;
+            }
+            Instruction::GetBukkitSlot(slot_name) => {
+                let bukkit = self.pop();
+                let value = self.get_slot(bukkit, slot_name)?;
+                self.stack.push(value);
+            }
+            Instruction::Step(slot, path, amount) => {
+                let amount = *amount;
+                self.mutate(*slot, path, |target| match target {
+                    Value::Numbr(num) => Ok(Value::Numbr(num.wrapping_add(amount))),
+                    Value::Numbar(num) => Ok(Value::Numbar(*num + amount as f32)),
+                    value => Err(RuntimeErrorKind::InvalidType {
+                        expected: "NUMBR",
+                        found: value.type_name(),
+                    }
+                    .into()),
+                })?;
+            }
+            Instruction::Binary(operation) => {
+                let right = self.pop();
+                let left = self.pop();
+                self.stack.push(binary_operation(*operation, left, right)?);
+            }
+            Instruction::Not => {
+                let value = self.pop();
+                self.stack.push(value.not().into());
+            }
+            Instruction::Equal => {
+                let right = self.pop();
+                let left = self.pop();
+                self.stack.push((left == right).into());
+            }
+            Instruction::Cast(ast_type) => {
+                let value = self.pop();
+                self.stack.push(value.cast(ast_type)?);
+            }
+            Instruction::ToYarn => {
+                let value = self.pop();
+                self.stack.push(Value::Yarn(value.to_yarn()?));
+            }
+            Instruction::Concat(count) => {
+                let mut result = String::new();
+                for value in self.stack.split_off(self.stack.len() - count) {
+                    if let Value::Yarn(yarn) = value {
+                        result.push_str(&yarn);
+                    }
+                }
+                self.stack.push(Value::Yarn(result));
+            }
+            Instruction::Jump(address) => self.jump(*address),
+            Instruction::JumpIfFalse(address) => {
+                if !self.pop().to_boolean() {
+                    self.jump(*address);
+                }
+            }
+            Instruction::JumpIfTrue(address) => {
+                if self.pop().to_boolean() {
+                    self.jump(*address);
+                }
+            }
+            Instruction::Print(with_space) => {
+                let text = self.pop().to_visible_yarn()?;
+                if *with_space {
+                    self.runtime.write(&format!(" {}", text))?;
+                } else {
+                    self.runtime.write(&text)?;
+                }
+            }
+            Instruction::PrintNewline => self.runtime.write("\n")?,
+            Instruction::Flush => self.runtime.flush()?,
+            Instruction::Gimmeh(slot, path) => {
+                let input = self.runtime.read_line()?;
+                self.mutate(*slot, path, |_| Ok(input.into()))?;
+            }
+            Instruction::DefineFunction(index) => {
+                let name = self.program.functions[*index].name.clone();
+                self.defined_functions.insert(name, *index);
+            }
+            Instruction::Call(name, argument_count) => {
+                let Some(&index) = self.defined_functions.get(name) else {
+                    let builtin = builtins::find(name)
+                        .ok_or_else(|| RuntimeErrorKind::FunctionNotFound(name.clone()))?;
+                    let arguments = self.stack.split_off(self.stack.len() - argument_count);
+                    let value = builtin.call(&self.runtime, arguments)?;
+                    self.stack.push(value);
+                    return Ok(None);
+                };
+                let program = self.program;
+                let function = &program.functions[index];
+                if function.parameters != *argument_count {
+                    return Err(RuntimeErrorKind::WrongNumberOfArguments {
+                        function: name.clone(),
+                        expected: function.parameters,
+                        found: *argument_count,
+                    }
+                    .into());
+                }
+
+                self.runtime.enter_call()?;
+                let mut locals = vec![None; function.locals];
+                let arguments = self.stack.split_off(self.stack.len() - argument_count);
+                // The arguments become locals of the callee, which are released when it returns.
+                let allocated = arguments.iter().map(Value::memory_size).sum();
+                for (local, argument) in locals.iter_mut().zip(arguments) {
+                    *local = Some(argument);
+                }
+                self.runtime.charge_memory(0, allocated)?;
+                self.frames.push(Frame {
+                    function: index,
+                    ip: 0,
+                    locals,
+                });
+            }
+            Instruction::Return => {
+                let value = self.pop();
+                let frame = self.frames.pop().unwrap();
+                if self.frames.is_empty() {
+                    return Ok(Some(value));
+                }
+                self.runtime.exit_call();
+                let released = frame.locals.iter().flatten().map(Value::memory_size).sum();
+                self.runtime.release_memory(released);
+                self.stack.push(value);
+            }
+            Instruction::Fail(kind) => return Err(kind.clone().into()),
+        };
+        Ok(None)
+    }
+}
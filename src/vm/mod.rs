@@ -0,0 +1,138 @@
+//! Alternative execution engine. The AST is lowered to a compact bytecode in which variables are
+//! resolved to numbered slots at compile time, and then run on a stack-based virtual machine.
+//!
+//! The tree-walking interpreter is kept as the reference implementation: both engines share the
+//! operator semantics in [`crate::operations`] and the casts in [`crate::cast`], and are expected
+//! to produce the same output for the same program.
+
+use std::fs::read_to_string;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use lolcode_ast::parser::expression::ASTType;
+
+use crate::error::Span;
+use crate::gc::{GcStats, GcThresholds};
+use crate::operations::BinaryOperation;
+use crate::runtime::{Limits, Runtime};
+use crate::source::parse_without_extensions;
+use crate::types::{RuntimeErrorKind, Value};
+
+mod compiler;
+mod machine;
+
+pub use compiler::{compile, CompileError};
+pub use machine::Machine;
+
+/// Where a variable lives. Locals are stored in the current call frame, while globals are the
+/// variables declared in the outermost scope of the program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    Local(usize),
+    Global(usize),
+}
+
+/// The name of a BUKKIT slot accessed with `'Z`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlotName {
+    Named(String),
+    /// `SRS <variable>`: the slot is named by the YARN in the variable, or by a NUMBR index.
+    Srs(Slot),
+}
+
+#[derive(Debug, Clone)]
+pub enum Instruction {
+    Push(Value),
+    /// Pushes the default value of a type, as given by `ITZ A`. Unlike `Push`, it creates a new
+    /// BUKKIT each time it is executed.
+    PushDefault(ASTType),
+    Pop,
+
+    /// Pushes the value of a declared variable.
+    Load(Slot),
+    /// Pops a value into a variable that must have already been declared.
+    Store(Slot),
+    /// Pops a value into a variable, declaring it if needed.
+    Declare(Slot),
+    /// Pops a value into a slot of a BUKKIT stored in a variable. The path must already exist.
+    StoreAccess(Slot, Rc<[SlotName]>),
+    /// Pops a value and inserts it as a new slot of the BUKKIT found at the path.
+    SetBukkitSlot(Slot, Rc<[SlotName]>, SlotName),
+    /// Replaces the BUKKIT on top of the stack by the value of one of its slots.
+    GetBukkitSlot(SlotName),
+    /// Adds the given amount to a NUMBR or NUMBAR variable, as done by UPPIN and NERFIN.
+    Step(Slot, Rc<[SlotName]>, i32),
+
+    Binary(BinaryOperation),
+    Not,
+    /// Pops two values and pushes whether they are exactly equal, as done by WTF? cases.
+    Equal,
+    Cast(ASTType),
+    ToYarn,
+    /// Pops the given number of YARNs and pushes their concatenation.
+    Concat(usize),
+
+    Jump(usize),
+    /// Pops a value, and jumps if its TROOF cast is FAIL.
+    JumpIfFalse(usize),
+    /// Pops a value, and jumps if its TROOF cast is WIN.
+    JumpIfTrue(usize),
+
+    /// Pops a value and prints it, preceded by a space if the flag is set.
+    Print(bool),
+    PrintNewline,
+    Flush,
+    /// Reads a line from the input into a variable.
+    Gimmeh(Slot, Rc<[SlotName]>),
+
+    /// Makes the function with the given index callable by its name.
+    DefineFunction(usize),
+    /// Calls a function by name with the given number of arguments, which are on the stack.
+    Call(String, usize),
+    /// Pops the return value and returns from the current function.
+    Return,
+
+    /// Raises an error when executed. Used for errors found at compile time, such as identifiers
+    /// that could not be resolved, since the tree-walker only errors when they are actually
+    /// reached.
+    Fail(RuntimeErrorKind),
+}
+
+/// A compiled function. The program itself is compiled to the function at index 0.
+#[derive(Debug, Clone, Default)]
+pub struct Function {
+    pub name: String,
+    pub parameters: usize,
+    pub locals: usize,
+    /// Name of each local slot, used for error messages.
+    pub slot_names: Vec<String>,
+    pub instructions: Vec<Instruction>,
+    /// Source span of each instruction, used for error messages.
+    pub spans: Vec<Option<Span>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub functions: Vec<Function>,
+}
+
+/// Compiles and runs the file using the VM engine, returning what the garbage collector did.
+pub fn execute_file(file: PathBuf, limits: Limits, gc: GcThresholds) -> Result<GcStats, String> {
+    let file_name = file.display().to_string();
+    let source = read_to_string(file).map_err(|_| "Failed to read source code".to_string())?;
+    let runtime = Rc::new(Runtime::default().with_limits(limits).with_gc(gc));
+    run(&source, &file_name, runtime.clone())?;
+    Ok(runtime.gc_stats())
+}
+
+/// Compiles and runs the source code using the VM engine on the given runtime, returning the
+/// value the program returned. Errors are rendered against the source as `file_name`.
+pub fn run(source: &str, file_name: &str, runtime: Rc<Runtime>) -> Result<Value, String> {
+    let (ast, library_calls) =
+        parse_without_extensions(source).map_err(|err| err.render(source, file_name))?;
+    let program = compiler::compile_with_library_calls(&ast, &library_calls)
+        .map_err(|err| err.to_string())?;
+    Machine::with_runtime(&program, runtime)
+        .run()
+        .map_err(|err| err.render(source, file_name))
+}
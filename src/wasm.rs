@@ -0,0 +1,122 @@
+//! JavaScript bindings, for running LOLCODE in the browser or any other WebAssembly host.
+//!
+//! Build with `cargo rustc --lib --crate-type cdylib --release --target wasm32-unknown-unknown
+//! --features wasm`, and generate the glue code with `wasm-bindgen`. The crate is only a cdylib
+//! for that build, so native builds do not link a dynamic library they never use.
+
+use std::io::{BufRead, Cursor, Read, Write};
+
+use js_sys::Function;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsValue;
+
+use crate::interpreter::Interpreter;
+use crate::runtime::SharedBuffer;
+
+/// The name used for the program when rendering its errors.
+const FILE_NAME: &str = "main.lol";
+
+/// The outcome of running a program: everything it wrote with `VISIBLE`, and the rendered error
+/// if it failed.
+#[wasm_bindgen(getter_with_clone)]
+pub struct RunResult {
+    pub stdout: String,
+    pub error: Option<String>,
+}
+
+/// Runs the program, feeding `input` to its `GIMMEH` statements one line at a time.
+#[wasm_bindgen]
+pub fn run(source: &str, input: &str) -> RunResult {
+    let stdout = SharedBuffer::new();
+    let mut interpreter = Interpreter::with_io(stdout.clone(), Cursor::new(input.to_string()));
+    let error = interpreter
+        .evaluate(source)
+        .err()
+        .map(|error| error.render(source, FILE_NAME));
+
+    RunResult {
+        stdout: stdout.contents(),
+        error,
+    }
+}
+
+/// Runs the program calling `visible(text)` with everything it writes, and `gimmeh()` for each
+/// line it reads. `gimmeh` returns the line without its line break, or `null`/`undefined` at the
+/// end of the input. Returns the rendered error if the program failed.
+#[wasm_bindgen(js_name = runWithCallbacks)]
+pub fn run_with_callbacks(source: &str, visible: Function, gimmeh: Function) -> Option<String> {
+    let output = CallbackOutput(visible);
+    let input = CallbackInput {
+        callback: gimmeh,
+        line: Vec::new(),
+        position: 0,
+    };
+    let mut interpreter = Interpreter::with_io(output, input);
+    interpreter
+        .evaluate(source)
+        .err()
+        .map(|error| error.render(source, FILE_NAME))
+}
+
+fn callback_error(error: JsValue) -> std::io::Error {
+    let message = error.as_string().unwrap_or_else(|| format!("{:?}", error));
+    std::io::Error::other(message)
+}
+
+/// Hands everything written to it to a JavaScript function.
+struct CallbackOutput(Function);
+
+impl Write for CallbackOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.0
+            .call1(&JsValue::NULL, &JsValue::from_str(&text))
+            .map_err(callback_error)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Asks a JavaScript function for a new line whenever the previous one was consumed.
+struct CallbackInput {
+    callback: Function,
+    line: Vec<u8>,
+    position: usize,
+}
+
+impl Read for CallbackInput {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let available = self.fill_buf()?;
+        let length = available.len().min(buf.len());
+        buf[..length].copy_from_slice(&available[..length]);
+        self.consume(length);
+        Ok(length)
+    }
+}
+
+impl BufRead for CallbackInput {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.position == self.line.len() {
+            let line = self
+                .callback
+                .call0(&JsValue::NULL)
+                .map_err(callback_error)?;
+            self.line.clear();
+            self.position = 0;
+            // A missing line is the end of the input, which reads as an empty buffer.
+            if !line.is_null() && !line.is_undefined() {
+                let line = line.as_string().unwrap_or_default();
+                self.line.extend_from_slice(line.as_bytes());
+                self.line.push(b'\n');
+            }
+        }
+        Ok(&self.line[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = (self.position + amount).min(self.line.len());
+    }
+}
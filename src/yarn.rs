@@ -0,0 +1,103 @@
+use std::str::Chars;
+
+use lolcode_ast::lexer::{Token, TokenType, TokenValue};
+
+use crate::types::{RuntimeErrorKind, RuntimeResult, Scope, Value};
+use crate::variable_access::get_variable_from_scope;
+
+/// A piece of a YARN literal, after its escape sequences are resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub enum YarnPart {
+    Text(String),
+    /// A `:{var}` interpolation, replaced by the value of the variable when evaluated.
+    Variable(String),
+}
+
+/// Returns the raw contents of the token, if it is a YARN literal.
+pub fn yarn_literal(token: &Token) -> Option<&str> {
+    match &token.token_type {
+        TokenType::Value(TokenValue::String(yarn)) => Some(yarn),
+        _ => None,
+    }
+}
+
+/// Reads the contents of an escape sequence such as `:(1F63A)`, up to its closing character.
+fn escape_contents(chars: &mut Chars, open: char, close: char) -> Result<String, RuntimeErrorKind> {
+    let mut contents = String::new();
+    for char in chars {
+        if char == close {
+            return Ok(contents);
+        }
+        contents.push(char);
+    }
+    Err(RuntimeErrorKind::InvalidEscape(format!(
+        ":{}{}",
+        open, contents
+    )))
+}
+
+/// Resolves the escape sequences of a YARN literal, as listed in the LOLCODE 1.2 spec.
+pub fn parse_yarn(raw: &str) -> Result<Vec<YarnPart>, RuntimeErrorKind> {
+    let mut parts = vec![];
+    let mut text = String::new();
+    let mut chars = raw.chars();
+
+    while let Some(char) = chars.next() {
+        if char != ':' {
+            text.push(char);
+            continue;
+        }
+
+        match chars.next() {
+            Some(')') => text.push('\n'),
+            Some('>') => text.push('\t'),
+            Some('o') => text.push('\u{7}'),
+            Some('"') => text.push('"'),
+            Some(':') => text.push(':'),
+            Some('(') => {
+                let hex = escape_contents(&mut chars, '(', ')')?;
+                let code_point = u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or(RuntimeErrorKind::InvalidCodePoint(hex))?;
+                text.push(code_point);
+            }
+            Some('[') => {
+                let name = escape_contents(&mut chars, '[', ']')?;
+                let character = unicode_names2::character(&name)
+                    .ok_or(RuntimeErrorKind::UnknownCharacterName(name))?;
+                text.push(character);
+            }
+            Some('{') => {
+                let name = escape_contents(&mut chars, '{', '}')?;
+                if !text.is_empty() {
+                    parts.push(YarnPart::Text(std::mem::take(&mut text)));
+                }
+                parts.push(YarnPart::Variable(name));
+            }
+            Some(other) => return Err(RuntimeErrorKind::InvalidEscape(format!(":{}", other))),
+            None => return Err(RuntimeErrorKind::InvalidEscape(":".to_string())),
+        }
+    }
+
+    if !text.is_empty() || parts.is_empty() {
+        parts.push(YarnPart::Text(text));
+    }
+    Ok(parts)
+}
+
+/// Evaluates a YARN literal, resolving its escape sequences and interpolating the variables it
+/// references from the scope, `IT` included.
+pub fn interpolate_yarn(scope: &Scope, raw: &str) -> RuntimeResult<Value> {
+    let mut yarn = String::new();
+    for part in parse_yarn(raw)? {
+        match part {
+            YarnPart::Text(text) => yarn.push_str(&text),
+            YarnPart::Variable(name) => {
+                let value = get_variable_from_scope(scope, &name)?.to_yarn()?;
+                yarn.push_str(&value);
+            }
+        }
+    }
+    Ok(Value::Yarn(yarn))
+}
@@ -0,0 +1,45 @@
+//! Compiles the corpus to C, builds it with gcc and compares what it prints with the tree-walking
+//! interpreter. Fails when gcc is not installed.
+
+mod common;
+
+use std::fs::write;
+use std::path::Path;
+use std::process::Command;
+
+use common::{assert_matches_tree, require_tool, Output};
+use lolcode_interpreter::compile::{compile, Target};
+
+#[test]
+fn c_matches_tree() {
+    require_tool("gcc", "c_matches_tree");
+
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    assert_matches_tree("c", |program| {
+        let stem = program.path.file_stem().unwrap().to_string_lossy();
+        let c_file = directory.join(format!("{}.c", stem));
+        let executable = directory.join(&*stem);
+        let code = compile(&program.source, &program.name, Target::C).unwrap();
+        write(&c_file, code).unwrap();
+
+        let build = Command::new("gcc")
+            .arg("-o")
+            .arg(&executable)
+            .arg(&c_file)
+            .arg("-lm")
+            .output()
+            .unwrap();
+        assert!(
+            build.status.success(),
+            "gcc failed for {}:\n{}",
+            program.name,
+            String::from_utf8_lossy(&build.stderr)
+        );
+
+        let run = Command::new(&executable).output().unwrap();
+        Output {
+            printed: String::from_utf8_lossy(&run.stdout).into_owned(),
+            failed: !run.status.success(),
+        }
+    });
+}
@@ -0,0 +1,109 @@
+//! Explicit casts with `MAEK` and `IS NOW A`, and the YARN forms of numbers.
+
+mod common;
+
+use common::evaluate;
+use lolcode_interpreter::Value;
+
+/// Casts the expression to the type with `MAEK`, returning what `VISIBLE` printed, or None if
+/// the cast failed.
+fn maek(expression: &str, to: &str) -> Option<String> {
+    let (printed, result) = evaluate(&format!("VISIBLE MAEK {} A {}", expression, to));
+    result
+        .ok()
+        .map(|_| printed.trim_end_matches('\n').to_string())
+}
+
+#[test]
+fn maek_follows_the_cast_table() {
+    let table: &[(&str, &str, Option<&str>)] = &[
+        ("WIN", "NUMBR", Some("1")),
+        ("FAIL", "NUMBAR", Some("0.00")),
+        ("WIN", "YARN", Some("WIN")),
+        ("7", "NUMBAR", Some("7.00")),
+        ("7", "YARN", Some("7")),
+        ("0", "TROOF", Some("FAIL")),
+        ("-3.75", "NUMBR", Some("-3")),
+        ("3.14159", "YARN", Some("3.14")),
+        ("0.0", "TROOF", Some("FAIL")),
+        ("\"\"", "TROOF", Some("FAIL")),
+        ("\"0\"", "TROOF", Some("WIN")),
+        ("\" 12 \"", "NUMBR", Some("12")),
+        ("\"2.9\"", "NUMBR", Some("2")),
+        ("\"-1.5\"", "NUMBAR", Some("-1.50")),
+        ("\"1e2\"", "NUMBAR", Some("100.00")),
+        ("\"twelve\"", "NUMBR", None),
+        ("\"\"", "NUMBAR", None),
+        ("NOOB", "NUMBR", Some("0")),
+        ("NOOB", "YARN", Some("")),
+        ("NOOB", "TROOF", Some("FAIL")),
+    ];
+    for (expression, to, expected) in table {
+        let expected = expected.map(str::to_string);
+        assert_eq!(
+            maek(expression, to),
+            expected,
+            "MAEK {} A {}",
+            expression,
+            to
+        );
+    }
+}
+
+#[test]
+fn numbars_are_truncated_to_two_decimal_places() {
+    assert_eq!(maek("2.999", "YARN").as_deref(), Some("2.99"));
+    assert_eq!(maek("-2.999", "YARN").as_deref(), Some("-2.99"));
+    // Truncating does not leave a negative zero.
+    assert_eq!(maek("-0.001", "YARN").as_deref(), Some("0.00"));
+    assert_eq!(maek("\"-0.0\"", "NUMBAR").as_deref(), Some("0.00"));
+    // Large values are printed with all their digits, and scaling them does not overflow.
+    assert_eq!(
+        maek("\"16777216\"", "NUMBAR").as_deref(),
+        Some("16777216.00")
+    );
+    let large = format!("{:.2}", 1e37f32);
+    assert_eq!(maek("\"1e37\"", "NUMBAR"), Some(large));
+}
+
+#[test]
+fn yarns_must_be_finite_numbers() {
+    for yarn in ["nan", "NaN", "inf", "-inf", "infinity", "1e39"] {
+        let expression = format!("\"{}\"", yarn);
+        assert_eq!(maek(&expression, "NUMBAR"), None, "{} as a NUMBAR", yarn);
+        assert_eq!(maek(&expression, "NUMBR"), None, "{} as a NUMBR", yarn);
+    }
+}
+
+#[test]
+fn is_now_a_casts_the_variable_in_place() {
+    let (printed, result) = evaluate(
+        "I HAS A x ITZ \"42\"
+x IS NOW A NUMBR
+VISIBLE SUM OF x AN 1
+x IS NOW A NUMBAR
+VISIBLE x
+I HAS A nothing
+nothing IS NOW A NUMBR
+VISIBLE nothing
+I HAS A box ITZ A BUKKIT
+box HAS A slot ITZ 3
+box'Z slot IS NOW A YARN
+VISIBLE SMOOSH box'Z slot AN \"!\" MKAY
+",
+    );
+    assert_eq!(printed, "43\n42.00\n0\n3!\n");
+    // The cast is a statement, so it does not set IT.
+    assert_eq!(result, Ok(Value::Noob));
+}
+
+#[test]
+fn is_now_a_reports_illegal_casts_and_unknown_types() {
+    let (_, result) = evaluate("I HAS A x ITZ \"kitteh\"\nx IS NOW A NUMBR\n");
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("cannot cast YARN to NUMBR"), "{}", error);
+
+    let (_, result) = evaluate("I HAS A x ITZ 1\nx IS NOW A KITTEH\n");
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("KITTEH"), "{}", error);
+}
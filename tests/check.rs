@@ -0,0 +1,148 @@
+//! Static type checking, which reports the errors a program would definitely raise.
+
+use lolcode_interpreter::check::check;
+use lolcode_interpreter::error::RuntimeErrorKind;
+use lolcode_interpreter::lsp::analyze;
+
+/// Returns the errors found in the source, with the text each one points at.
+fn errors(source: &str) -> Vec<(RuntimeErrorKind, String)> {
+    check(source)
+        .expect("the source parses")
+        .into_iter()
+        .map(|error| {
+            let span = error.span.expect("the error has a span");
+            (error.kind, source[span.start..span.end].to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn definite_errors_are_reported() {
+    let source = "HAI 1.2\nI HAS A nothing\nVISIBLE SUM OF nothing AN 1\n\
+                  VISIBLE SMOOSH \"a\" AN nothing MKAY\nI HAS A n ITZ 1\nVISIBLE SRS n\n\
+                  HOW IZ I twice YR x\n  FOUND YR PRODUKT OF x AN 2\nIF U SAY SO\n\
+                  I IZ twice YR 1 AN YR 2 MKAY\nVISIBLE MAEK \"kitteh\" A NUMBR\nKTHXBYE\n";
+    assert_eq!(
+        errors(source),
+        vec![
+            (
+                RuntimeErrorKind::InvalidType {
+                    expected: "NUMBR",
+                    found: "NOOB"
+                },
+                "nothing".to_string()
+            ),
+            (
+                RuntimeErrorKind::IllegalCast {
+                    from: "NOOB",
+                    to: "YARN"
+                },
+                "nothing".to_string()
+            ),
+            (RuntimeErrorKind::CannotSRSNonYarn("NUMBR"), "n".to_string()),
+            (
+                RuntimeErrorKind::WrongNumberOfArguments {
+                    function: "twice".to_string(),
+                    expected: 1,
+                    found: 2
+                },
+                "twice".to_string()
+            ),
+            (
+                RuntimeErrorKind::IllegalCast {
+                    from: "YARN",
+                    to: "NUMBR"
+                },
+                "\"kitteh\"".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn unknown_types_are_not_errors() {
+    let source = "HAI 1.2\nI HAS A x ITZ 1\nBOTH SAEM x AN 1, O RLY?\n  YA RLY, x R \"one\"\n\
+                  OIC\nVISIBLE SUM OF x AN 1\nGIMMEH y\nVISIBLE SUM OF y AN 1\nKTHXBYE\n";
+    assert_eq!(errors(source), vec![]);
+}
+
+#[test]
+fn is_now_a_is_checked_like_maek() {
+    let source = "HAI 1.2\nI HAS A b ITZ A BUKKIT\nb IS NOW A NUMBR\nI HAS A x ITZ 1\n\
+                  x IS NOW A NOOB\nVISIBLE x\nKTHXBYE\n";
+    assert_eq!(
+        errors(source),
+        vec![
+            (
+                RuntimeErrorKind::IllegalCast {
+                    from: "BUKKIT",
+                    to: "NUMBR"
+                },
+                "b".to_string()
+            ),
+            (
+                RuntimeErrorKind::IllegalCast {
+                    from: "NOOB",
+                    to: "YARN"
+                },
+                "x".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn objects_are_checked() {
+    let source = "HAI 1.3\nCAN HAS STDIO?\nO HAI IM kitteh\n  I HAS A lives ITZ 9\n  \
+                  HOW IZ I meow YR times\n    VISIBLE SUM OF NOOB AN times\n  IF U SAY SO\nKTHX\n\
+                  kitteh IZ meow MKAY\nVISIBLE SUM OF kitteh AN 1\nKTHXBYE\n";
+    // Methods are not functions of the scope, so their calls are not checked against them.
+    assert_eq!(
+        errors(source),
+        vec![
+            (
+                RuntimeErrorKind::InvalidType {
+                    expected: "NUMBR",
+                    found: "NOOB"
+                },
+                "NOOB".to_string()
+            ),
+            (
+                RuntimeErrorKind::InvalidType {
+                    expected: "NUMBR",
+                    found: "BUKKIT"
+                },
+                "kitteh".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn the_language_server_infers_the_same_types() {
+    let source = "HAI 1.2\nI HAS A x ITZ SUM OF 1 AN 2.5\nI HAS A y ITZ BIGGR OF x AN 1\n\
+                  I HAS A z ITZ \"3\"\nz IS NOW A NUMBR\nKTHXBYE\n";
+    let analysis = analyze(source);
+    let types: Vec<(&str, &[&str])> = analysis
+        .symbols
+        .iter()
+        .map(|symbol| (symbol.name.as_str(), symbol.types.as_slice()))
+        .collect();
+    assert_eq!(
+        types,
+        vec![
+            ("x", &["NUMBAR"][..]),
+            ("y", &["TROOF"][..]),
+            ("z", &["YARN", "NUMBR"][..]),
+        ]
+    );
+    assert_eq!(errors(source), vec![]);
+}
+
+#[test]
+fn parse_errors_keep_their_span() {
+    let source = "HAI 1.2\nI HAS A x ITZ 1 2\nKTHXBYE\n";
+    let error = check(source).unwrap_err();
+    let span = error.span.expect("the error has a span");
+    assert_eq!(&source[span.start..span.end], "2");
+}
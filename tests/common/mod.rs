@@ -0,0 +1,96 @@
+//! Helpers shared by the integration tests. The differential tests run every program in
+//! `tests/programs` on another engine and check that it prints the same output as the
+//! tree-walking interpreter.
+
+#![allow(dead_code)]
+
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use lolcode_interpreter::{Interpreter, InterpreterError, Runtime, SharedBuffer, Value};
+
+/// What a program printed, and whether it stopped with an error. Error messages are not compared,
+/// since each engine words them differently.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    pub printed: String,
+    pub failed: bool,
+}
+
+pub struct Program {
+    pub path: PathBuf,
+    pub name: String,
+    pub source: String,
+}
+
+/// Returns the programs of the corpus, sorted by name.
+pub fn programs() -> Vec<Program> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs");
+    let mut paths: Vec<PathBuf> = read_dir(directory)
+        .expect("the corpus directory exists")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "lol"))
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| Program {
+            name: path.file_name().unwrap().to_string_lossy().into_owned(),
+            source: read_to_string(&path).unwrap(),
+            path,
+        })
+        .collect()
+}
+
+/// Fails the test if the tool is not installed, rather than letting it pass without running
+/// anything. A machine without the tool can leave the test out with `--skip`.
+pub fn require_tool(tool: &str, test: &str) {
+    if Command::new(tool).arg("--version").output().is_err() {
+        panic!(
+            "{} was not found: install it, or leave this test out with `cargo test -- --skip {}`",
+            tool, test
+        );
+    }
+}
+
+/// Evaluates the source on a fresh interpreter, returning what it printed and its result.
+pub fn evaluate(source: &str) -> (String, Result<Value, InterpreterError>) {
+    let buffer = SharedBuffer::new();
+    let mut interpreter = Interpreter::with_io(buffer.clone(), std::io::empty());
+    let result = interpreter.evaluate(source);
+    (buffer.contents(), result)
+}
+
+/// Returns a runtime that never uses the JIT.
+pub fn interpreter_runtime(buffer: &SharedBuffer) -> Runtime {
+    Runtime::new(Box::new(buffer.clone()), Box::new(std::io::empty())).with_jit(false)
+}
+
+/// Runs the program on the tree-walking interpreter, the reference implementation.
+pub fn run_tree(program: &Program) -> Output {
+    let buffer = SharedBuffer::new();
+    let mut interpreter = Interpreter::with_runtime(interpreter_runtime(&buffer));
+    let failed = interpreter.evaluate(&program.source).is_err();
+    Output {
+        printed: buffer.contents(),
+        failed,
+    }
+}
+
+/// Checks that `run` gives the same output as the tree-walking interpreter for every program,
+/// reporting all the programs that differ at once.
+pub fn assert_matches_tree(engine: &str, mut run: impl FnMut(&Program) -> Output) {
+    let mut mismatches = vec![];
+    for program in programs() {
+        let expected = run_tree(&program);
+        let actual = run(&program);
+        if actual != expected {
+            mismatches.push(format!(
+                "{}:\n  tree: {:?}\n  {}: {:?}",
+                program.name, expected, engine, actual
+            ));
+        }
+    }
+    assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
+}
@@ -0,0 +1,84 @@
+//! Error messages, and their rendering against the source code.
+
+mod common;
+
+use common::evaluate;
+use lolcode_interpreter::error::{RuntimeError, RuntimeErrorKind, Span};
+
+fn message(source: &str) -> String {
+    let (_, result) = evaluate(source);
+    result.expect_err("the program fails").to_string()
+}
+
+#[test]
+fn argument_counts_are_pluralised() {
+    let declaration = "HOW IZ I one YR a\n  FOUND YR a\nIF U SAY SO\n";
+    assert_eq!(
+        message(&format!("{}I IZ one YR 1 AN YR 2 MKAY\n", declaration)),
+        "function \"one\" expects 1 argument, but 2 were given"
+    );
+    let declaration = "HOW IZ I two YR a AN YR b\n  FOUND YR a\nIF U SAY SO\n";
+    assert_eq!(
+        message(&format!("{}I IZ two YR 1 MKAY\n", declaration)),
+        "function \"two\" expects 2 arguments, but 1 was given"
+    );
+}
+
+#[test]
+fn operators_name_the_operation() {
+    assert_eq!(
+        message("VISIBLE MOD OF \"kitteh\" AN 2"),
+        "cannot take the remainder of dividing YARN by NUMBR"
+    );
+    assert_eq!(
+        message("VISIBLE SUM OF 1 AN \"kitteh\""),
+        "cannot add YARN to NUMBR"
+    );
+}
+
+#[test]
+fn import_cycles_without_libraries_are_displayed() {
+    let cycle = RuntimeErrorKind::ImportCycle(vec!["a".to_string(), "a".to_string()]);
+    assert_eq!(cycle.to_string(), "library \"a\" imports itself: a -> a");
+    assert_eq!(
+        RuntimeErrorKind::ImportCycle(vec![]).to_string(),
+        "a library imports itself"
+    );
+}
+
+fn error_at(start: usize, end: usize) -> RuntimeError {
+    RuntimeError::from(RuntimeErrorKind::DivisionByZero).at(Some(Span { start, end }))
+}
+
+#[test]
+fn render_underlines_the_span() {
+    let source = "HAI 1.2\nVISIBLE QUOSHUNT OF 1 AN 0\n";
+    let start = source.find("QUOSHUNT").unwrap();
+    let rendered = error_at(start, start + "QUOSHUNT".len()).render(source, "test.lol");
+    assert_eq!(
+        rendered,
+        "error: division by zero
+ --> test.lol:2:9
+  |
+2 | VISIBLE QUOSHUNT OF 1 AN 0
+  |         ^^^^^^^^"
+    );
+}
+
+#[test]
+fn render_keeps_tabs_before_the_span() {
+    let source = "HAI 1.2\n\t\tVISIBLE x\n";
+    let start = source.find('x').unwrap();
+    let rendered = error_at(start, start + 1).render(source, "test.lol");
+    assert!(
+        rendered.ends_with("2 | \t\tVISIBLE x\n  | \t\t        ^"),
+        "{}",
+        rendered
+    );
+}
+
+#[test]
+fn render_without_a_span_names_the_file() {
+    let rendered = RuntimeError::from(RuntimeErrorKind::DivisionByZero).render("", "test.lol");
+    assert_eq!(rendered, "error: division by zero\n --> test.lol");
+}
@@ -0,0 +1,121 @@
+//! The formatter, which prints the syntax tree back with the comments where they were.
+
+mod common;
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use common::evaluate;
+use lolcode_interpreter::fmt::format_source;
+
+fn format(source: &str) -> String {
+    format_source(source).expect("the source parses")
+}
+
+fn programs() -> Vec<PathBuf> {
+    let mut programs: Vec<PathBuf> = std::fs::read_dir("tests/programs")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "lol"))
+        .collect();
+    programs.sort();
+    programs
+}
+
+#[test]
+fn statements_are_printed_in_the_canonical_layout() {
+    let source = "HAI 1.2\nI HAS A x   ITZ SUM OF 1   2\nIM IN YR loop UPPIN YR x TIL \
+                  BOTH SAEM x AN 3\nVISIBLE x!\nIM OUTTA YR loop\nBOTH SAEM x AN 3, O RLY?\n\
+                  YA RLY, VISIBLE SMOOSH \"x \" AN x\nNO WAI\nVISIBLE \"no\"\nOIC\nKTHXBYE\n";
+    assert_eq!(
+        format(source),
+        "HAI 1.2\nI HAS A x ITZ SUM OF 1 AN 2\nIM IN YR loop UPPIN YR x TIL BOTH SAEM x AN 3\n\
+         \x20   VISIBLE x!\nIM OUTTA YR loop\nBOTH SAEM x AN 3\nO RLY?\n    YA RLY\n        \
+         VISIBLE SMOOSH \"x \" AN x MKAY\n    NO WAI\n        VISIBLE \"no\"\nOIC\nKTHXBYE\n"
+    );
+}
+
+#[test]
+fn lolcode_1_3_statements_are_printed_back() {
+    let source = "HAI 1.3\nCAN HAS STDIO?\nO HAI IM kitteh IM LIEK animal\nI HAS A name ITZ \
+                  \"tom\"\nHOW IZ I meow YR times\nVISIBLE ME'Z name\nIF U SAY SO\nKTHX\n\
+                  HOW IZ kitteh purr\nVISIBLE \"purr\"\nIF U SAY SO\nkitteh IZ meow YR 2 MKAY\n\
+                  I HAS A n ITZ \"3\"\nn IS NOW A   NUMBR\nVISIBLE SUM OF n ...\n  AN 1\nKTHXBYE\n";
+    assert_eq!(
+        format(source),
+        "HAI 1.3\nCAN HAS STDIO?\nO HAI IM kitteh IM LIEK animal\n    I HAS A name ITZ \"tom\"\n    \
+         HOW IZ I meow YR times\n        VISIBLE ME'Z name\n    IF U SAY SO\nKTHX\n\
+         HOW IZ kitteh purr\n    VISIBLE \"purr\"\nIF U SAY SO\nkitteh IZ meow YR 2 MKAY\n\
+         I HAS A n ITZ \"3\"\nn IS NOW A NUMBR\nVISIBLE SUM OF n AN 1\nKTHXBYE\n"
+    );
+}
+
+#[test]
+fn comments_and_blank_lines_are_kept() {
+    let source = "BTW the program\nHAI 1.2\n\n\n  I HAS A x ITZ 1   BTW one\nOBTW\n  \
+                  a block\nTLDR\nIM IN YR loop\nBTW inside\nVISIBLE x, GTFO BTW stop\n\
+                  BTW last\nIM OUTTA YR loop\nKTHXBYE\nBTW the end\n";
+    assert_eq!(
+        format(source),
+        "BTW the program\nHAI 1.2\n\nI HAS A x ITZ 1 BTW one\nOBTW\n  a block\nTLDR\n\
+         IM IN YR loop\n    BTW inside\n    VISIBLE x\n    GTFO BTW stop\n    BTW last\n\
+         IM OUTTA YR loop\nKTHXBYE\nBTW the end\n"
+    );
+}
+
+#[test]
+fn formatting_is_idempotent_and_keeps_what_programs_do() {
+    for program in programs() {
+        let source = std::fs::read_to_string(&program).unwrap();
+        let formatted = format(&source);
+        assert_eq!(format(&formatted), formatted, "{}", program.display());
+
+        let (printed, result) = evaluate(&source);
+        let (formatted_printed, formatted_result) = evaluate(&formatted);
+        assert_eq!(formatted_printed, printed, "{}", program.display());
+        assert_eq!(
+            formatted_result.map_err(|error| error.to_string()),
+            result.map_err(|error| error.to_string()),
+            "{}",
+            program.display()
+        );
+    }
+}
+
+#[test]
+fn parse_errors_keep_their_span() {
+    let source = "HAI 1.2\nI HAS A x ITZ 1 2\nKTHXBYE\n";
+    let error = format_source(source).unwrap_err();
+    let span = error.span.expect("the error has a span");
+    assert_eq!(&source[span.start..span.end], "2");
+}
+
+#[test]
+fn check_fails_on_unformatted_files_without_rewriting_them() {
+    let directory = std::env::temp_dir().join(format!("lolcode-fmt-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let file = directory.join("unformatted.lol");
+    let source = "HAI 1.2\nVISIBLE   \"hai\"\nKTHXBYE\n";
+    std::fs::write(&file, source).unwrap();
+
+    let fmt = |check: bool| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_lolcode-interpreter"));
+        command.arg("fmt").arg(&file);
+        if check {
+            command.arg("--check");
+        }
+        command.output().unwrap()
+    };
+
+    let checked = fmt(true);
+    assert!(!checked.status.success());
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), source);
+
+    assert!(fmt(false).status.success());
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "HAI 1.2\nVISIBLE \"hai\"\nKTHXBYE\n"
+    );
+    assert!(fmt(true).status.success());
+    std::fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,105 @@
+//! Declaring and calling functions with `HOW IZ I` and `I IZ`.
+
+mod common;
+
+use common::evaluate;
+use lolcode_interpreter::Value;
+
+#[test]
+fn returns_found_yr_gtfo_or_it() {
+    let (printed, result) = evaluate(
+        "HOW IZ I found
+  FOUND YR 1
+  VISIBLE \"unreachable\"
+IF U SAY SO
+HOW IZ I gtfo
+  GTFO
+IF U SAY SO
+HOW IZ I falls_off
+  SUM OF 2 AN 3
+IF U SAY SO
+I IZ found MKAY
+VISIBLE IT
+I IZ falls_off MKAY
+VISIBLE IT
+I IZ gtfo MKAY
+",
+    );
+    assert_eq!(printed, "1\n5\n");
+    assert_eq!(result, Ok(Value::Noob));
+}
+
+#[test]
+fn arguments_are_bound_on_a_fresh_scope() {
+    let (printed, result) = evaluate(
+        "I HAS A global ITZ 10
+HOW IZ I add YR a AN YR b
+  FOUND YR SUM OF SUM OF a AN b AN global
+IF U SAY SO
+I HAS A local ITZ 1
+I IZ add YR local AN YR 2 MKAY
+VISIBLE IT
+HOW IZ I peek
+  FOUND YR local
+IF U SAY SO
+I IZ peek MKAY
+VISIBLE IT
+",
+    );
+    // Top-level variables are global, so `peek` sees `local`.
+    assert_eq!(printed, "13\n1\n");
+    assert!(result.is_ok());
+
+    // Variables of the blocks around the call are not visible.
+    let (_, result) = evaluate(
+        "HOW IZ I peek
+  FOUND YR hidden
+IF U SAY SO
+IM IN YR loop
+  I HAS A hidden ITZ 5
+  I IZ peek MKAY
+  GTFO
+IM OUTTA YR loop
+",
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn functions_declared_in_a_block_can_call_themselves() {
+    let (printed, result) = evaluate(
+        "I HAS A n ITZ 0
+IM IN YR loop UPPIN YR n TIL BOTH SAEM n AN 1
+  HOW IZ I countdown YR k
+    BOTH SAEM k AN 0, O RLY?
+      YA RLY, FOUND YR \"liftoff\"
+    OIC
+    VISIBLE k
+    I IZ countdown YR DIFF OF k AN 1 MKAY
+    FOUND YR IT
+  IF U SAY SO
+  HOW IZ I start
+    I IZ countdown YR 3 MKAY
+    FOUND YR IT
+  IF U SAY SO
+  I IZ start MKAY
+  VISIBLE IT
+IM OUTTA YR loop
+",
+    );
+    assert_eq!(printed, "3\n2\n1\nliftoff\n");
+    assert!(result.is_ok());
+}
+
+#[test]
+fn calls_check_the_number_of_arguments() {
+    let (_, result) = evaluate(
+        "HOW IZ I one YR a
+  FOUND YR a
+IF U SAY SO
+I IZ one YR 1 AN YR 2 MKAY
+",
+    );
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("one"), "{}", error);
+}
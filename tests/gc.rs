@@ -0,0 +1,133 @@
+//! Checks that the garbage collector frees the BUKKIT cycles that reference counting cannot, and
+//! that the memory they held is released, on both engines.
+
+use std::rc::Rc;
+
+use lolcode_interpreter::{vm, GcStats, GcThresholds, Interpreter, Limits, Runtime, SharedBuffer};
+
+/// Makes `count` BUKKITs holding themselves, each unreachable once the next one is made, and
+/// keeps one more in `kept`.
+fn self_references(count: usize) -> String {
+    format!(
+        "HAI 1.2
+I HAS A kept ITZ A BUKKIT
+kept HAS A me ITZ kept
+I HAS A i ITZ 0
+IM IN YR making UPPIN YR i TIL BOTH SAEM i AN {}
+  I HAS A cat ITZ A BUKKIT
+  cat HAS A me ITZ cat
+  cat HAS A name ITZ \"kitteh\"
+IM OUTTA YR making
+KTHXBYE
+",
+        count
+    )
+}
+
+fn runtime(gc: GcThresholds, limits: Limits) -> Runtime {
+    Runtime::new(Box::new(SharedBuffer::new()), Box::new(std::io::empty()))
+        .with_gc(gc)
+        .with_limits(limits)
+}
+
+/// The collector only runs when [`Runtime::collect_garbage`] is called.
+fn manual() -> GcThresholds {
+    GcThresholds {
+        allocations: None,
+        memory_growth: None,
+    }
+}
+
+/// Bytes held by the slots of one BUKKIT made by [`self_references`].
+const CAT_BYTES: usize = "me".len() + "name".len() + "kitteh".len();
+
+#[test]
+fn tree_frees_self_referencing_bukkits() {
+    let mut interpreter = Interpreter::with_runtime(runtime(manual(), Limits::default()));
+    interpreter.evaluate(&self_references(10)).unwrap();
+    let runtime = interpreter.runtime();
+    assert_eq!(
+        runtime.gc_stats(),
+        GcStats {
+            collections: 0,
+            freed: 0,
+            live_bytes: "me".len() + 10 * CAT_BYTES,
+        }
+    );
+
+    // `kept` is still in a variable, so only the BUKKITs made in the loop are garbage.
+    runtime.collect_garbage();
+    assert_eq!(
+        runtime.gc_stats(),
+        GcStats {
+            collections: 1,
+            freed: 10,
+            live_bytes: "me".len(),
+        }
+    );
+}
+
+#[test]
+fn vm_frees_self_referencing_bukkits() {
+    let runtime = Rc::new(runtime(manual(), Limits::default()));
+    vm::run(&self_references(10), "cats.lol", runtime.clone()).unwrap();
+    assert_eq!(
+        runtime.gc_stats(),
+        GcStats {
+            collections: 0,
+            freed: 0,
+            live_bytes: "me".len() + 10 * CAT_BYTES,
+        }
+    );
+
+    // The variables of the VM are gone once the program ended, so `kept` is garbage too.
+    runtime.collect_garbage();
+    assert_eq!(
+        runtime.gc_stats(),
+        GcStats {
+            collections: 1,
+            freed: 11,
+            live_bytes: 0,
+        }
+    );
+}
+
+#[test]
+fn thresholds_trigger_collections() {
+    let gc = GcThresholds {
+        allocations: Some(10),
+        memory_growth: None,
+    };
+    let mut interpreter = Interpreter::with_runtime(runtime(gc, Limits::default()));
+    interpreter.evaluate(&self_references(100)).unwrap();
+    let runtime = interpreter.runtime();
+    let stats = runtime.gc_stats();
+    assert!(stats.collections >= 9, "{:?}", stats);
+    assert!(stats.freed >= 90, "{:?}", stats);
+
+    runtime.collect_garbage();
+    let stats = runtime.gc_stats();
+    assert_eq!(stats.freed, 100);
+    assert_eq!(stats.live_bytes, "me".len());
+}
+
+#[test]
+fn reaching_the_memory_limit_releases_the_memory_of_cycles() {
+    // Without a collection, the cycles would hold 1000 times as many bytes as the limit allows.
+    let limits = Limits {
+        max_memory: Some(10 * CAT_BYTES),
+        ..Limits::default()
+    };
+
+    let mut interpreter = Interpreter::with_runtime(runtime(manual(), limits.clone()));
+    interpreter.evaluate(&self_references(1000)).unwrap();
+    let stats = interpreter.runtime().gc_stats();
+    assert!(stats.collections > 0, "{:?}", stats);
+    assert!(stats.live_bytes <= 10 * CAT_BYTES, "{:?}", stats);
+
+    let runtime = Rc::new(runtime(manual(), limits));
+    vm::run(&self_references(1000), "cats.lol", runtime.clone()).unwrap();
+    let stats = runtime.gc_stats();
+    assert!(stats.collections > 0, "{:?}", stats);
+    assert!(stats.live_bytes <= 10 * CAT_BYTES, "{:?}", stats);
+}
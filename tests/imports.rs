@@ -0,0 +1,136 @@
+//! `CAN HAS <library>?` imports of the native libraries and of library files.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use common::evaluate;
+use lolcode_interpreter::error::RuntimeErrorKind;
+use lolcode_interpreter::{vm, Interpreter, InterpreterError, Runtime, SharedBuffer, Value};
+
+/// Writes the library files to a fresh directory, named after the test.
+fn libraries(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("lolcode-{}-{}", test, std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    for (name, source) in files {
+        std::fs::write(directory.join(format!("{}.lol", name)), source).unwrap();
+    }
+    directory
+}
+
+/// Evaluates the source with the directory as its search path, returning the interpreter, what
+/// it printed and its result.
+fn evaluate_with(
+    directory: &Path,
+    source: &str,
+) -> (Interpreter, String, Result<Value, InterpreterError>) {
+    let buffer = SharedBuffer::new();
+    let runtime = Runtime::new(Box::new(buffer.clone()), Box::new(std::io::empty()))
+        .with_search_path(vec![directory.to_path_buf()]);
+    let mut interpreter = Interpreter::with_runtime(runtime);
+    let result = interpreter.evaluate(source);
+    (interpreter, buffer.contents(), result)
+}
+
+#[test]
+fn string_methods_work_on_characters() {
+    let (printed, result) = evaluate(
+        "CAN HAS STRING?
+STRING IZ LEN YR \"k:(E4)t\" MKAY
+VISIBLE IT
+STRING IZ AT YR \"k:(E4)t\" AN YR 1 MKAY
+",
+    );
+    assert_eq!(printed, "3\n");
+    assert_eq!(result, Ok(Value::Yarn("ä".to_string())));
+
+    let (_, result) = evaluate("CAN HAS STRING?\nSTRING IZ AT YR \"k:(E4)t\" AN YR 3 MKAY\n");
+    assert!(matches!(
+        result,
+        Err(InterpreterError::Runtime(error)) if error.kind == RuntimeErrorKind::GenericError(
+            "index 3 is out of range for a YARN of 3 characters".to_string()
+        )
+    ));
+}
+
+#[test]
+fn library_variables_are_shared_with_the_importer() {
+    let directory = libraries(
+        "shared",
+        &[(
+            "COUNTER",
+            "HAI 1.3\nI HAS A count ITZ 0\nI HAS A name ITZ \"kitteh\"\nHOW IZ I bump\n  \
+             count R SUM OF count AN 1\n  FOUND YR count\nIF U SAY SO\nKTHXBYE\n",
+        )],
+    );
+    let (interpreter, printed, result) = evaluate_with(
+        &directory,
+        "CAN HAS COUNTER?
+COUNTER IZ bump MKAY
+VISIBLE COUNTER'Z count
+COUNTER'Z count R 10
+COUNTER IZ bump MKAY
+",
+    );
+    assert_eq!(printed, "1\n");
+    assert_eq!(result, Ok(Value::Numbr(11)));
+    // The YARN is only held by the library's variable, so its bytes are only counted once.
+    assert_eq!(interpreter.runtime().gc_stats().live_bytes, "kitteh".len());
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn missing_libraries_name_where_they_were_looked_up() {
+    let directory = libraries("missing", &[]);
+    let (_, _, result) = evaluate_with(&directory, "CAN HAS NOTHING?\n");
+    assert!(matches!(
+        result,
+        Err(InterpreterError::Runtime(error)) if error.kind == RuntimeErrorKind::LibraryNotFound {
+            library: "NOTHING".to_string(),
+            searched: vec![directory.clone()],
+        }
+    ));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn import_cycles_are_rejected() {
+    let directory = libraries(
+        "cycle",
+        &[
+            ("EGG", "HAI 1.3\nCAN HAS CHIKN?\nKTHXBYE\n"),
+            ("CHIKN", "HAI 1.3\nCAN HAS EGG?\nKTHXBYE\n"),
+        ],
+    );
+    let (_, _, result) = evaluate_with(&directory, "CAN HAS EGG?\n");
+    let Err(InterpreterError::Runtime(error)) = result else {
+        panic!("expected a runtime error, got {:?}", result);
+    };
+    let message = error.to_string();
+    assert!(
+        message.contains("library \"EGG\" imports itself: EGG -> CHIKN -> EGG"),
+        "{}",
+        message
+    );
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn other_engines_import_only_native_libraries() {
+    let buffer = SharedBuffer::new();
+    let runtime = Rc::new(Runtime::new(
+        Box::new(buffer.clone()),
+        Box::new(std::io::empty()),
+    ));
+    let source = "CAN HAS STRING?\nSTRING IZ LEN YR \"k:(E4)t\" MKAY\nVISIBLE IT\n";
+    assert!(vm::run(source, "test.lol", runtime.clone()).is_ok());
+    assert_eq!(buffer.contents(), "3\n");
+
+    let error = vm::run("CAN HAS MATHS?\n", "test.lol", runtime).unwrap_err();
+    assert!(
+        error.contains("library \"MATHS\" can only be imported by the tree-walking engine"),
+        "{}",
+        error
+    );
+}
@@ -0,0 +1,149 @@
+//! The embedding API: globals, host functions, method calls and errors.
+
+mod common;
+
+use common::interpreter_runtime;
+use lolcode_interpreter::error::{RuntimeErrorKind, Span};
+use lolcode_interpreter::{Interpreter, InterpreterError, Limits, Runtime, SharedBuffer, Value};
+
+#[test]
+fn globals_persist_across_evaluations() {
+    let buffer = SharedBuffer::new();
+    let mut interpreter = Interpreter::with_runtime(interpreter_runtime(&buffer));
+    interpreter.set_global("name", "kitteh".to_string());
+    interpreter
+        .evaluate("I HAS A greeting ITZ SMOOSH \"hai \" AN name MKAY")
+        .unwrap();
+    interpreter.evaluate("VISIBLE greeting").unwrap();
+    assert_eq!(buffer.contents(), "hai kitteh\n");
+    assert_eq!(
+        interpreter.get_global("greeting"),
+        Some(Value::Yarn("hai kitteh".to_string()))
+    );
+    assert_eq!(interpreter.get_global("missing"), None);
+}
+
+#[test]
+fn host_functions_receive_the_arguments() {
+    let mut interpreter = Interpreter::with_io(SharedBuffer::new(), std::io::empty());
+    interpreter.register_function("JOIN", 2, |arguments| {
+        Ok(Value::Yarn(format!("{}-{}", arguments[0], arguments[1])))
+    });
+    let result = interpreter.evaluate("I IZ JOIN YR 1 AN YR \"a\" MKAY");
+    assert_eq!(result, Ok(Value::Yarn("1-a".to_string())));
+
+    let error = interpreter
+        .evaluate("I IZ JOIN YR 1 MKAY")
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("expects 2 arguments"), "{}", error);
+}
+
+const COUNTER: &str = "O HAI IM counter
+  I HAS A count ITZ 0
+  HOW IZ I bump YR amount
+    ME'Z count R SUM OF ME'Z count AN amount
+    FOUND YR ME'Z count
+  IF U SAY SO
+  HOW IZ I twice
+    ME IZ bump YR 1 MKAY
+    ME IZ bump YR 1 MKAY
+    FOUND YR IT
+  IF U SAY SO
+KTHX
+";
+
+#[test]
+fn methods_can_be_called_by_the_host() {
+    let mut interpreter = Interpreter::with_io(SharedBuffer::new(), std::io::empty());
+    interpreter.evaluate(COUNTER).unwrap();
+    let result = interpreter.call_method("counter", "bump", &[Value::Numbr(5)]);
+    assert_eq!(result, Ok(Value::Numbr(5)));
+    assert_eq!(
+        interpreter.call_method("counter", "twice", &[]),
+        Ok(Value::Numbr(7))
+    );
+
+    let error = interpreter.call_method("nothing", "bump", &[]).unwrap_err();
+    assert!(matches!(
+        error,
+        InterpreterError::Runtime(error) if error.kind == RuntimeErrorKind::IdentifierNotFound("nothing".to_string())
+    ));
+}
+
+#[test]
+fn method_calls_from_the_host_count_towards_the_call_depth() {
+    let runtime = Runtime::new(Box::new(SharedBuffer::new()), Box::new(std::io::empty()))
+        .with_limits(Limits {
+            max_call_depth: Some(1),
+            ..Limits::default()
+        });
+    let mut interpreter = Interpreter::with_runtime(runtime);
+    interpreter.evaluate(COUNTER).unwrap();
+    assert_eq!(
+        interpreter.call_method("counter", "bump", &[Value::Numbr(1)]),
+        Ok(Value::Numbr(1))
+    );
+    // `twice` is one call deep, so the calls it makes are two calls deep.
+    let error = interpreter
+        .call_method("counter", "twice", &[])
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        InterpreterError::Runtime(error) if error.kind == RuntimeErrorKind::CallDepthExceeded(1)
+    ));
+}
+
+#[test]
+fn parse_errors_keep_their_span_and_nothing_runs() {
+    let buffer = SharedBuffer::new();
+    let mut interpreter = Interpreter::with_runtime(interpreter_runtime(&buffer));
+    let source = "VISIBLE \"before\"\nI HAS A x ITZ 1 2\n";
+    let error = interpreter.evaluate(source).unwrap_err();
+    let InterpreterError::Parse(parse_error) = &error else {
+        panic!("expected a parse error, got {:?}", error);
+    };
+    let start = source.find('2').unwrap();
+    assert_eq!(
+        parse_error.span,
+        Some(Span {
+            start,
+            end: start + 1
+        })
+    );
+    assert!(
+        error
+            .render(source, "test.lol")
+            .contains("--> test.lol:2:17"),
+        "{}",
+        error.render(source, "test.lol")
+    );
+    assert_eq!(buffer.contents(), "");
+}
+
+#[test]
+fn list_length_follows_slots_changed_by_the_host() {
+    let mut interpreter = Interpreter::with_io(SharedBuffer::new(), std::io::empty());
+    interpreter
+        .evaluate("I HAS A list ITZ A BUKKIT\nI IZ APPEND YR list AN YR 1 MKAY\nI IZ APPEND YR list AN YR 2 MKAY")
+        .unwrap();
+    let Some(Value::Bukkit(list)) = interpreter.get_global("list") else {
+        panic!("list is not a BUKKIT");
+    };
+    list.borrow_mut()
+        .slots
+        .insert("2".to_string(), Value::Numbr(3));
+    assert_eq!(
+        interpreter.evaluate("I IZ LEN YR list MKAY"),
+        Ok(Value::Numbr(3))
+    );
+    list.borrow_mut().slots.shift_remove("1");
+    assert_eq!(
+        interpreter.evaluate("I IZ APPEND YR list AN YR 4 MKAY"),
+        Ok(Value::Numbr(1))
+    );
+    assert_eq!(
+        interpreter.evaluate("I IZ LEN YR list MKAY"),
+        Ok(Value::Numbr(3))
+    );
+}
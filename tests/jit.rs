@@ -0,0 +1,158 @@
+//! Checks that programs give the same results with the JIT as with the interpreter alone. Only
+//! built with the `jit` feature.
+
+#![cfg(feature = "jit")]
+
+mod common;
+
+use common::{assert_matches_tree, interpreter_runtime, Output};
+use lolcode_interpreter::{Interpreter, InterpreterError, SharedBuffer, Value};
+
+/// Evaluates the source with the JIT enabled or not, returning what it printed and its result.
+fn evaluate(source: &str, jit: bool) -> (String, Result<Value, InterpreterError>) {
+    let buffer = SharedBuffer::new();
+    let mut interpreter = if jit {
+        Interpreter::with_io(buffer.clone(), std::io::empty())
+    } else {
+        Interpreter::with_runtime(interpreter_runtime(&buffer))
+    };
+    let result = interpreter.evaluate(source);
+    (buffer.contents(), result)
+}
+
+fn assert_same_as_interpreter(source: &str) {
+    assert_eq!(evaluate(source, true), evaluate(source, false));
+}
+
+#[test]
+fn jit_matches_tree() {
+    assert_matches_tree("jit", |program| {
+        let (printed, result) = evaluate(&program.source, true);
+        Output {
+            printed,
+            failed: result.is_err(),
+        }
+    });
+}
+
+#[test]
+fn failing_type_guard_falls_back_to_the_interpreter() {
+    assert_same_as_interpreter(
+        "HAI 1.2
+HOW IZ I double YR x
+  I HAS A i ITZ 0
+  IM IN YR loop UPPIN YR i TIL BOTH SAEM i AN 1
+    x R SUM OF x AN x
+  IM OUTTA YR loop
+  FOUND YR x
+IF U SAY SO
+I IZ double YR 21 MKAY
+VISIBLE IT
+I IZ double YR 1.25 MKAY
+VISIBLE IT
+I IZ double YR \"4\" MKAY
+VISIBLE IT
+I IZ double YR WIN MKAY
+VISIBLE IT
+I IZ double YR 8 MKAY
+VISIBLE IT
+I HAS A total ITZ 0
+I HAS A j ITZ 0
+IM IN YR outer UPPIN YR j TIL BOTH SAEM j AN 3
+  IM IN YR inner UPPIN YR total TIL BOTH SAEM total AN 5
+  IM OUTTA YR inner
+  VISIBLE total
+  total R \"1\"
+IM OUTTA YR outer
+KTHXBYE
+",
+    );
+}
+
+#[test]
+fn division_by_zero_in_a_loop_fails_like_the_interpreter() {
+    let source = "HAI 1.2
+I HAS A i ITZ 3
+I HAS A total ITZ 0
+IM IN YR loop NERFIN YR i
+  total R SUM OF total AN QUOSHUNT OF 6 AN i
+  VISIBLE total
+IM OUTTA YR loop
+VISIBLE \"unreachable\"
+KTHXBYE
+";
+    let (printed, result) = evaluate(source, true);
+    assert_eq!(printed, "2\n5\n11\n");
+    assert!(result.is_err());
+    assert_same_as_interpreter(source);
+
+    assert_same_as_interpreter(
+        "HAI 1.2
+HOW IZ I ratio YR a AN YR b
+  I HAS A i ITZ 0
+  IM IN YR loop UPPIN YR i TIL BOTH SAEM i AN 2
+    a R QUOSHUNT OF a AN b
+  IM OUTTA YR loop
+  FOUND YR a
+IF U SAY SO
+I IZ ratio YR 100 AN YR 5 MKAY
+VISIBLE IT
+I IZ ratio YR 1.0 AN YR 0.0 MKAY
+VISIBLE IT
+I IZ ratio YR 7 AN YR 0 MKAY
+VISIBLE IT
+KTHXBYE
+",
+    );
+}
+
+#[test]
+fn gtfo_in_a_loop_behaves_like_the_interpreter() {
+    assert_same_as_interpreter(
+        "HAI 1.2
+HOW IZ I first_multiple YR n
+  I HAS A i ITZ 1
+  IM IN YR loop UPPIN YR i
+    BOTH SAEM MOD OF i AN n AN 0, O RLY?
+      YA RLY, GTFO
+    OIC
+  IM OUTTA YR loop
+  FOUND YR i
+IF U SAY SO
+HOW IZ I stop YR n
+  IM IN YR loop NERFIN YR n
+    BOTH SAEM n AN 2, O RLY?
+      YA RLY, FOUND YR n
+    OIC
+  IM OUTTA YR loop
+IF U SAY SO
+HOW IZ I leave YR n
+  n R SUM OF n AN 1
+  GTFO
+IF U SAY SO
+I IZ first_multiple YR 7 MKAY
+VISIBLE IT
+I IZ stop YR 10 MKAY
+VISIBLE IT
+I IZ leave YR 1 MKAY
+VISIBLE MAEK IT A TROOF
+I HAS A i ITZ 0
+I HAS A hits ITZ 0
+IM IN YR outer UPPIN YR i TIL BOTH SAEM i AN 4
+  MOD OF i AN 2
+  WTF?
+    OMG 0
+      hits R SUM OF hits AN 10
+      GTFO
+    OMGWTF
+      hits R SUM OF hits AN 1
+  OIC
+  BOTH SAEM i AN 2, O RLY?
+    YA RLY, GTFO
+  OIC
+IM OUTTA YR outer
+VISIBLE i \" \" hits
+KTHXBYE
+",
+    );
+}
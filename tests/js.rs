@@ -0,0 +1,45 @@
+//! Compiles the corpus to JavaScript, runs it under Node.js and compares what it prints with the
+//! tree-walking interpreter. Fails when node is not installed.
+
+mod common;
+
+use std::fs::write;
+use std::path::Path;
+use std::process::Command;
+
+use common::{assert_matches_tree, require_tool, Output};
+use lolcode_interpreter::compile::{compile, Target};
+
+#[test]
+fn js_matches_tree() {
+    require_tool("node", "js_matches_tree");
+
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    assert_matches_tree("js", |program| {
+        let stem = program.path.file_stem().unwrap().to_string_lossy();
+        let module = format!("{}.mjs", stem);
+        let code = compile(&program.source, &program.name, Target::Js).unwrap();
+        write(directory.join(&module), code).unwrap();
+
+        // Setting the exit code instead of exiting lets node finish writing the output.
+        let runner = directory.join(format!("{}.run.mjs", stem));
+        write(
+            &runner,
+            format!(
+                "import {{ run }} from \"./{}\";\n\
+                 run().catch((error) => {{\n    \
+                     console.error(error.message);\n    \
+                     process.exitCode = 1;\n\
+                 }});\n",
+                module
+            ),
+        )
+        .unwrap();
+
+        let run = Command::new("node").arg(&runner).output().unwrap();
+        Output {
+            printed: String::from_utf8_lossy(&run.stdout).into_owned(),
+            failed: !run.status.success(),
+        }
+    });
+}
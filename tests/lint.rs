@@ -0,0 +1,74 @@
+//! Static checks that find likely mistakes without running the program.
+
+use lolcode_interpreter::lint::{lint, LintConfig, Rule};
+
+/// Returns the rules the source breaks, with the text each warning points at.
+fn warnings(source: &str) -> Vec<(Rule, String)> {
+    lint(source, &LintConfig::default())
+        .expect("the source parses")
+        .into_iter()
+        .map(|warning| {
+            let span = warning.span.expect("the warning has a span");
+            (warning.rule, source[span.start..span.end].to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn every_rule_is_reported_where_it_applies() {
+    let source = "HAI 1.2\nVISIBLE y\nI HAS A y ITZ 1\nI HAS A unused ITZ 2\nI HAS A n ITZ 5\n\
+                  VISIBLE SRS n\nI HAS A i ITZ 0\nI HAS A j ITZ 0\n\
+                  IM IN YR loop UPPIN YR i TIL BOTH SAEM j AN 3\n  GTFO\n  VISIBLE \"never\"\n\
+                  IM OUTTA YR loop\nI HAS A z ITZ 1\nO RLY?\n  YA RLY\n    VISIBLE z\nOIC\nKTHXBYE\n";
+    assert_eq!(
+        warnings(source),
+        vec![
+            (Rule::UseBeforeDeclaration, "y".to_string()),
+            (Rule::UnusedVariable, "y".to_string()),
+            (Rule::UnusedVariable, "unused".to_string()),
+            (Rule::SrsNonYarn, "n".to_string()),
+            (Rule::LoopVariableMismatch, "i".to_string()),
+            (Rule::UnreachableCode, "\"never\"".to_string()),
+            (Rule::ORlyWithoutIt, "z".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn rules_can_be_disabled() {
+    let source = "HAI 1.2\nI HAS A unused ITZ 2\nVISIBLE y\nKTHXBYE\n";
+    let config = LintConfig::from_json(r#"{ "unused-variable": false }"#).unwrap();
+    let rules: Vec<Rule> = lint(source, &config)
+        .unwrap()
+        .into_iter()
+        .map(|warning| warning.rule)
+        .collect();
+    assert_eq!(rules, vec![Rule::UseBeforeDeclaration]);
+
+    let error = LintConfig::from_json(r#"{ "no-such-rule": false }"#).unwrap_err();
+    assert_eq!(error, "unknown lint rule \"no-such-rule\"");
+}
+
+#[test]
+fn objects_are_checked_like_the_rest_of_the_program() {
+    let source = "HAI 1.3\nCAN HAS STDIO?\nO RLY?\n  YA RLY\n    VISIBLE \"imported\"\nOIC\n\
+                  O HAI IM kitteh\n  I HAS A name ITZ \"tom\"\n  HOW IZ I meow\n    FOUND YR name\n    \
+                  VISIBLE \"never\"\n  IF U SAY SO\nKTHX\nkitteh IZ meow MKAY\nO RLY?\n  YA RLY\n    \
+                  VISIBLE \"meowed\"\nOIC\nKTHXBYE\n";
+    // The slot is not an unused variable, and the method call sets IT, but the import does not.
+    assert_eq!(
+        warnings(source),
+        vec![
+            (Rule::ORlyWithoutIt, "STDIO".to_string()),
+            (Rule::UnreachableCode, "\"never\"".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn parse_errors_keep_their_span() {
+    let source = "HAI 1.2\nI HAS A x ITZ 1 2\nKTHXBYE\n";
+    let error = lint(source, &LintConfig::default()).unwrap_err();
+    let span = error.span.expect("the error has a span");
+    assert_eq!(&source[span.start..span.end], "2");
+}
@@ -0,0 +1,71 @@
+//! What the language server knows about a document.
+
+use lolcode_interpreter::lsp::{analyze, SymbolKind};
+
+fn names(source: &str, kind: SymbolKind) -> Vec<String> {
+    analyze(source)
+        .symbols
+        .into_iter()
+        .filter(|symbol| symbol.kind == kind)
+        .map(|symbol| symbol.name)
+        .collect()
+}
+
+#[test]
+fn references_resolve_to_their_declaration() {
+    let source = "HAI 1.2\nI HAS A x ITZ 1\nVISIBLE x\nKTHXBYE\n";
+    let analysis = analyze(source);
+    assert!(analysis.diagnostics.is_empty());
+    let used = source.rfind('x').unwrap();
+    let symbol = analysis.symbol_at(used).expect("x is referenced");
+    assert_eq!(analysis.symbols[symbol].name, "x");
+    assert_eq!(analysis.symbols[symbol].types, vec!["NUMBR"]);
+}
+
+#[test]
+fn functions_can_reference_themselves() {
+    let source = "HAI 1.2\nHOW IZ I f YR n\n  I IZ f YR n MKAY\nIF U SAY SO\nKTHXBYE\n";
+    let analysis = analyze(source);
+    assert_eq!(names(source, SymbolKind::Function), vec!["f"]);
+    assert_eq!(names(source, SymbolKind::Parameter), vec!["n"]);
+    assert!(analysis.unresolved.is_empty());
+}
+
+#[test]
+fn parse_errors_are_reported_where_they_are() {
+    let source = "HAI 1.2\nI HAS A x ITZ 1 2\nVISIBLE x\nKTHXBYE\n";
+    let analysis = analyze(source);
+    assert_eq!(analysis.diagnostics.len(), 1);
+    let diagnostic = &analysis.diagnostics[0];
+    assert_eq!(diagnostic.message, "unexpected \"2\"");
+    assert_eq!(&source[diagnostic.span.start..diagnostic.span.end], "2");
+}
+
+#[test]
+fn imports_and_objects_are_understood() {
+    let source = "HAI 1.3\nCAN HAS STDIO?\nO HAI IM kitteh\n  I HAS A name ITZ \"tom\"\n  \
+                  HOW IZ I meow\n    VISIBLE ME'Z name\n  IF U SAY SO\nKTHX\n\
+                  HOW IZ kitteh purr\n  VISIBLE \"purr\"\nIF U SAY SO\nkitteh IZ meow MKAY\nKTHXBYE\n";
+    let analysis = analyze(source);
+    assert!(
+        analysis.diagnostics.is_empty(),
+        "{:?}",
+        analysis.diagnostics
+    );
+    assert!(analysis.unresolved.is_empty(), "{:?}", analysis.unresolved);
+
+    let kitteh = analysis
+        .symbols
+        .iter()
+        .position(|symbol| symbol.name == "kitteh")
+        .expect("the object is declared");
+    assert_eq!(analysis.symbols[kitteh].types, vec!["BUKKIT"]);
+    let references = analysis
+        .references
+        .iter()
+        .filter(|reference| reference.symbol == kitteh)
+        .count();
+    assert_eq!(references, 2);
+    // Methods are slots of the object, not functions of the scope.
+    assert_eq!(names(source, SymbolKind::Function), vec!["meow"]);
+}
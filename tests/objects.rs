@@ -0,0 +1,107 @@
+//! The LOLCODE 1.3 objects declared with `O HAI IM`, their methods and their parents.
+
+mod common;
+
+use common::evaluate;
+use lolcode_interpreter::error::RuntimeErrorKind;
+use lolcode_interpreter::{InterpreterError, Value};
+
+#[test]
+fn methods_see_the_object_as_me() {
+    let (printed, result) = evaluate(
+        "O HAI IM kitteh
+  I HAS A name ITZ \"tom\"
+  I HAS A lives ITZ 9
+  HOW IZ I lose YR count
+    ME'Z lives R DIFF OF ME'Z lives AN count
+    FOUND YR ME'Z lives
+  IF U SAY SO
+KTHX
+HOW IZ kitteh greet
+  FOUND YR SMOOSH \"i iz \" AN ME'Z name MKAY
+IF U SAY SO
+kitteh IZ lose YR 2 MKAY
+VISIBLE IT
+kitteh IZ greet MKAY
+VISIBLE IT
+VISIBLE kitteh'Z lives
+",
+    );
+    assert_eq!(printed, "7\ni iz tom\n7\n");
+    assert_eq!(result, Ok(Value::Yarn("i iz tom".to_string())));
+}
+
+#[test]
+fn slots_and_methods_are_looked_up_in_the_parent() {
+    let (printed, result) = evaluate(
+        "O HAI IM animal
+  I HAS A legs ITZ 4
+  HOW IZ I describe
+    FOUND YR SMOOSH ME'Z sound AN \" on \" AN ME'Z legs AN \" legs\" MKAY
+  IF U SAY SO
+KTHX
+O HAI IM kitteh IM LIEK animal
+  I HAS A sound ITZ \"meow\"
+KTHX
+O HAI IM birb IM LIEK animal
+  I HAS A sound ITZ \"tweet\"
+  I HAS A legs ITZ 2
+KTHX
+kitteh IZ describe MKAY
+VISIBLE IT
+birb IZ describe MKAY
+VISIBLE IT
+VISIBLE kitteh'Z legs
+",
+    );
+    assert_eq!(printed, "meow on 4 legs\ntweet on 2 legs\n4\n");
+    assert_eq!(result, Ok(Value::Yarn("tweet on 2 legs".to_string())));
+}
+
+#[test]
+fn missing_slots_are_filled_in_by_omgwtf() {
+    let (printed, result) = evaluate(
+        "O HAI IM lazy
+  I HAS A calls ITZ 0
+  HOW IZ I omgwtf
+    ME'Z calls R SUM OF ME'Z calls AN 1
+    FOUND YR 42
+  IF U SAY SO
+KTHX
+VISIBLE lazy'Z answer
+VISIBLE lazy'Z answer
+VISIBLE lazy'Z calls
+",
+    );
+    // The result is stored in the slot, so `omgwtf` is only called the first time.
+    assert_eq!(printed, "42\n42\n1\n");
+    assert_eq!(result, Ok(Value::Noob));
+
+    let (_, result) = evaluate("O HAI IM strict\nKTHX\nVISIBLE strict'Z answer\n");
+    assert!(matches!(
+        result,
+        Err(InterpreterError::Runtime(error))
+            if error.kind == RuntimeErrorKind::SlotNotFound("answer".to_string())
+    ));
+}
+
+#[test]
+fn nested_objects_are_slots() {
+    let (printed, result) = evaluate(
+        "O HAI IM zoo
+  I HAS A name ITZ \"city zoo\"
+  O HAI IM kitteh
+    I HAS A name ITZ \"tom\"
+  KTHX
+  O HAI IM birb, I HAS A name ITZ \"tweety\", KTHX
+KTHX
+O HAI IM empty
+KTHX
+VISIBLE zoo'Z name
+VISIBLE zoo'Z kitteh'Z name
+VISIBLE zoo'Z birb'Z name
+",
+    );
+    assert_eq!(printed, "city zoo\ntom\ntweety\n");
+    assert_eq!(result, Ok(Value::Noob));
+}
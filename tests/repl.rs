@@ -0,0 +1,100 @@
+//! Interactive sessions: inputs spanning several lines, and the echo of `IT`.
+
+mod common;
+
+use common::interpreter_runtime;
+use lolcode_interpreter::repl::{Reply, Session};
+use lolcode_interpreter::SharedBuffer;
+
+fn session() -> (Session, SharedBuffer) {
+    let buffer = SharedBuffer::new();
+    (Session::new(interpreter_runtime(&buffer)), buffer)
+}
+
+#[test]
+fn blocks_run_once_closed() {
+    let (mut session, buffer) = session();
+    assert_eq!(session.push_line("I HAS A n ITZ 0"), Reply::Ran(None));
+    assert_eq!(
+        session.push_line("IM IN YR loop UPPIN YR n TIL BOTH SAEM n AN 2"),
+        Reply::Incomplete
+    );
+    assert_eq!(
+        session.push_line("  BOTH SAEM n AN 0, O RLY?"),
+        Reply::Incomplete
+    );
+    assert_eq!(
+        session.push_line("    YA RLY, VISIBLE \"first\""),
+        Reply::Incomplete
+    );
+    assert_eq!(session.push_line("  OIC"), Reply::Incomplete);
+    assert_eq!(buffer.contents(), "");
+    assert_eq!(session.push_line("IM OUTTA YR loop"), Reply::Ran(None));
+    assert_eq!(buffer.contents(), "first\n");
+    assert_eq!(session.input(), "");
+}
+
+#[test]
+fn comments_and_continued_lines_are_incomplete() {
+    let (mut session, buffer) = session();
+    assert_eq!(session.push_line("OBTW a comment"), Reply::Incomplete);
+    assert_eq!(
+        session.push_line("HOW IZ I, still a comment"),
+        Reply::Incomplete
+    );
+    assert_eq!(session.push_line("TLDR"), Reply::Ran(None));
+    assert_eq!(buffer.contents(), "");
+    assert_eq!(session.push_line("VISIBLE \"a\" ..."), Reply::Incomplete);
+    assert_eq!(session.push_line("  \"b\" ..."), Reply::Incomplete);
+    assert_eq!(session.push_line("  \"c\""), Reply::Ran(None));
+    assert_eq!(buffer.contents(), "a b c\n");
+}
+
+#[test]
+fn block_keywords_in_yarns_and_comments_are_ignored() {
+    let (mut session, buffer) = session();
+    assert_eq!(
+        session.push_line("VISIBLE \"O RLY? IM IN YR\""),
+        Reply::Ran(None)
+    );
+    // `::` is an escaped colon, so the quote after it ends the YARN.
+    assert_eq!(
+        session.push_line("VISIBLE \"a::\" BTW WTF?"),
+        Reply::Ran(None)
+    );
+    assert_eq!(buffer.contents(), "O RLY? IM IN YR\na:\n");
+}
+
+#[test]
+fn expressions_echo_it() {
+    let (mut session, _) = session();
+    assert_eq!(
+        session.push_line("SUM OF 40 AN 2"),
+        Reply::Ran(Some("42".to_string()))
+    );
+    assert_eq!(session.push_line("I HAS A x ITZ IT"), Reply::Ran(None));
+    assert_eq!(
+        session.push_line("SMOOSH x AN \"!\" MKAY"),
+        Reply::Ran(Some("42!".to_string()))
+    );
+}
+
+#[test]
+fn errors_are_rendered_and_the_session_goes_on() {
+    let (mut session, _) = session();
+    let Reply::Failed(error) = session.push_line("VISIBLE missing") else {
+        panic!("the input fails");
+    };
+    assert!(error.contains("<repl>:1:9"), "{}", error);
+    assert_eq!(session.input(), "");
+    assert_eq!(session.push_line("1"), Reply::Ran(Some("1".to_string())));
+}
+
+#[test]
+fn interrupting_drops_the_input() {
+    let (mut session, _) = session();
+    assert_eq!(session.push_line("HOW IZ I f"), Reply::Incomplete);
+    session.clear();
+    assert_eq!(session.input(), "");
+    assert_eq!(session.push_line("2"), Reply::Ran(Some("2".to_string())));
+}
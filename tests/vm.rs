@@ -0,0 +1,55 @@
+//! Runs the corpus on the bytecode VM and compares it with the tree-walking interpreter.
+
+mod common;
+
+use std::rc::Rc;
+
+use common::{assert_matches_tree, Output};
+use lolcode_interpreter::{vm, Interpreter, Limits, Runtime, SharedBuffer};
+
+#[test]
+fn vm_matches_tree() {
+    assert_matches_tree("vm", |program| {
+        let buffer = SharedBuffer::new();
+        let runtime = Runtime::new(Box::new(buffer.clone()), Box::new(std::io::empty()));
+        let failed = vm::run(&program.source, &program.name, Rc::new(runtime)).is_err();
+        Output {
+            printed: buffer.contents(),
+            failed,
+        }
+    });
+}
+
+fn runtime(limits: Limits) -> Runtime {
+    Runtime::new(Box::new(SharedBuffer::new()), Box::new(std::io::empty())).with_limits(limits)
+}
+
+#[test]
+fn arguments_are_charged_while_the_function_runs() {
+    let source = "HAI 1.2
+I HAS A kept ITZ \"kitteh\"
+HOW IZ I ignore YR text
+  FOUND YR 0
+IF U SAY SO
+I IZ ignore YR \"abcdefgh\" MKAY
+I IZ ignore YR \"abcdefgh\" MKAY
+KTHXBYE
+";
+    let vm_runtime = Rc::new(runtime(Limits::default()));
+    vm::run(source, "test.lol", vm_runtime.clone()).unwrap();
+    let mut interpreter = Interpreter::with_runtime(runtime(Limits::default()));
+    interpreter.evaluate(source).unwrap();
+    // Returning releases the arguments, so only `kept` is left.
+    assert_eq!(vm_runtime.gc_stats().live_bytes, "kitteh".len());
+    assert_eq!(interpreter.runtime().gc_stats().live_bytes, "kitteh".len());
+
+    // Both engines fail when the argument does not fit.
+    let limits = Limits {
+        max_memory: Some(10),
+        ..Limits::default()
+    };
+    let error = vm::run(source, "test.lol", Rc::new(runtime(limits.clone()))).unwrap_err();
+    assert!(error.contains("memory limit"), "{}", error);
+    let mut interpreter = Interpreter::with_runtime(runtime(limits));
+    assert!(interpreter.evaluate(source).is_err());
+}
@@ -0,0 +1,84 @@
+//! YARN literals: escape sequences and `:{var}` interpolation.
+
+mod common;
+
+use common::evaluate;
+
+fn visible(yarn: &str) -> Result<String, String> {
+    let (printed, result) = evaluate(&format!("VISIBLE \"{}\"", yarn));
+    result
+        .map(|_| printed.trim_end_matches('\n').to_string())
+        .map_err(|error| error.to_string())
+}
+
+#[test]
+fn escape_sequences_are_resolved() {
+    assert_eq!(visible("a:)b").as_deref(), Ok("a\nb"));
+    assert_eq!(visible("a:>b").as_deref(), Ok("a\tb"));
+    assert_eq!(visible("a:ob").as_deref(), Ok("a\u{7}b"));
+    assert_eq!(visible("a:\"b").as_deref(), Ok("a\"b"));
+    assert_eq!(visible("a::b").as_deref(), Ok("a:b"));
+    assert_eq!(visible(":(1F63A):(41)").as_deref(), Ok("\u{1F63A}A"));
+    assert_eq!(visible(":[SNOWMAN]").as_deref(), Ok("\u{2603}"));
+}
+
+#[test]
+fn invalid_escape_sequences_are_errors() {
+    assert_eq!(
+        visible(":x"),
+        Err("invalid escape sequence \":x\" in YARN".to_string())
+    );
+    assert_eq!(
+        visible(":(ZZ)"),
+        Err("\"ZZ\" is not a valid unicode code point".to_string())
+    );
+    assert_eq!(
+        visible(":[NOT A CHARACTER]"),
+        Err("no unicode character is named \"NOT A CHARACTER\"".to_string())
+    );
+    assert_eq!(
+        visible(":(41"),
+        Err("invalid escape sequence \":(41\" in YARN".to_string())
+    );
+}
+
+#[test]
+fn variables_are_interpolated() {
+    let (printed, result) = evaluate(
+        "I HAS A name ITZ \"kitteh\"
+I HAS A lives ITZ 9
+I HAS A weight ITZ 4.5
+VISIBLE \"hai :{name}! :{lives} lives, :{weight} kg\"
+",
+    );
+    assert!(result.is_ok());
+    assert_eq!(printed, "hai kitteh! 9 lives, 4.50 kg\n");
+}
+
+#[test]
+fn it_is_interpolated() {
+    let (printed, result) = evaluate(
+        "SUM OF 40 AN 2
+VISIBLE \":{IT}\"
+HOW IZ I shout YR word
+  SMOOSH word AN \"!\" MKAY
+  FOUND YR \"-> :{IT}\"
+IF U SAY SO
+I IZ shout YR \"hai\" MKAY
+VISIBLE IT
+",
+    );
+    assert!(result.is_ok(), "{:?}", result);
+    assert_eq!(printed, "42\n-> hai!\n");
+}
+
+#[test]
+fn unknown_variables_are_errors() {
+    assert_eq!(
+        visible("hai :{nobody}"),
+        Err("variable \"nobody\" was not declared".to_string())
+    );
+    // NOOB cannot be implicitly cast to a YARN.
+    let (_, result) = evaluate("I HAS A nothing\nVISIBLE \":{nothing}\"");
+    assert!(result.is_err());
+}